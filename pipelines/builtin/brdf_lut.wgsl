@@ -0,0 +1,91 @@
+// Split sum BRDF integration, x: n.v, y: roughness => (scale, bias) applied to f0
+
+let PI: f32 = 3.14159265358979323846264338327950288;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+) -> VertexOutput {
+    var out: VertexOutput;
+    
+    out.tex_coords = vec2(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    out.clip_position = vec4(vec2(out.tex_coords.x, out.tex_coords.y) * 2.0 - 1.0, 0.0, 1.0);
+    out.tex_coords.y = 1.0 - out.tex_coords.y;
+
+    return out;
+}
+
+fn radical_inverse_vdc(bits_in: u32) -> f32 {
+    var bits = bits_in;
+    bits = (bits << 16u) | (bits >> 16u);
+    bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+    bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+    bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+    bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+    return f32(bits) * 2.3283064365386963e-10; // / 0x100000000
+}
+
+fn hammersley(i: u32, n: u32) -> vec2<f32> {
+    return vec2(f32(i) / f32(n), radical_inverse_vdc(i));
+}
+
+fn importance_sample_ggx(xi: vec2<f32>, n: vec3<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    
+    // n is always +z, tangent space is world space
+    return vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+}
+
+fn geometry_schlick_ggx(ndotv: f32, roughness: f32) -> f32 {
+    // k for ibl, analytic lights use (r + 1)^2 / 8
+    let k = (roughness * roughness) / 2.0;
+
+    return ndotv / (ndotv * (1.0 - k) + k);
+}
+
+fn geometry_smith(ndotv: f32, ndotl: f32, roughness: f32) -> f32 {
+    return geometry_schlick_ggx(ndotv, roughness) * geometry_schlick_ggx(ndotl, roughness);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let ndotv = max(in.tex_coords.x, 0.0001);
+    let roughness = in.tex_coords.y;
+
+    let v = vec3(sqrt(1.0 - ndotv * ndotv), 0.0, ndotv);
+    let n = vec3(0.0, 0.0, 1.0);
+
+    var scale = 0.0;
+    var bias = 0.0;
+
+    let sample_count = 1024u;
+    for (var i = 0u; i < sample_count; i++) {
+        let xi = hammersley(i, sample_count);
+        let h = importance_sample_ggx(xi, n, roughness);
+        let l = normalize(2.0 * dot(v, h) * h - v);
+
+        let ndotl = max(l.z, 0.0);
+        let ndoth = max(h.z, 0.0);
+        let vdoth = max(dot(v, h), 0.0);
+
+        if (ndotl > 0.0) {
+            let g = geometry_smith(ndotv, ndotl, roughness);
+            let g_vis = (g * vdoth) / (ndoth * ndotv);
+            let fc = pow(1.0 - vdoth, 5.0);
+
+            scale += (1.0 - fc) * g_vis;
+            bias += fc * g_vis;
+        }
+    }
+
+    return vec4(scale / f32(sample_count), bias / f32(sample_count), 0.0, 1.0);
+}
@@ -0,0 +1,63 @@
+// Inputs
+
+let PI: f32 = 3.14159265358979323846264338327950288;
+
+//!binding()
+var environment_map: texture_cube<f32>;
+//!binding()
+var environment_sampler: sampler;
+//!binding()
+var<uniform> view_proj: mat4x4<f32>;
+
+// Vertex Shader
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) position: vec3<f32>,
+};
+
+@vertex
+fn vs_main(
+    model: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    out.position = model.position;
+    out.clip_position = view_proj * vec4(model.position, 1.0);
+    
+    return out;
+}
+
+// Fragment Shader
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let normal = normalize(in.position);
+
+    var up = vec3(1.0, 0.0, 0.0);
+    if (abs(normal.y) < 0.999) {
+        up = vec3(0.0, 1.0, 0.0);
+    }
+    let right = normalize(cross(up, normal));
+    let up = cross(normal, right);
+
+    // uniformly step over the hemisphere, weighted by cos (lambert) and sin (smaller rings near the pole)
+    let sample_delta = 0.025;
+    var irradiance = vec3(0.0);
+    var sample_count = 0.0;
+    for (var phi = 0.0; phi < 2.0 * PI; phi += sample_delta) {
+        for (var theta = 0.0; theta < 0.5 * PI; theta += sample_delta) {
+            let tangent_sample = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            let sample_dir = tangent_sample.x * right + tangent_sample.y * up + tangent_sample.z * normal;
+
+            irradiance += textureSampleLevel(environment_map, environment_sampler, sample_dir, 0.0).rgb * cos(theta) * sin(theta);
+            sample_count += 1.0;
+        }
+    }
+
+    return vec4(PI * irradiance / sample_count, 1.0);
+}
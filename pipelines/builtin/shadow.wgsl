@@ -0,0 +1,109 @@
+// Inputs
+
+struct ShadowView {
+    view_proj: mat4x4<f32>,
+    bias: f32,
+    normal_bias: f32,
+    pcf_radius: f32,
+    count: u32,
+}
+
+// MorphDelta
+struct MorphDelta {
+    position: vec4<f32>,
+    normal: vec4<f32>,
+    tangent: vec4<f32>,
+}
+
+// the deltas of each vertex are adjacent, see MorphTargets
+struct MorphTargets {
+    count: u32,
+    deltas: array<MorphDelta>,
+}
+
+// MorphTargets::MAX_TARGETS, packed in fours
+struct MorphWeights {
+    weights: array<vec4<f32>, 16>,
+}
+
+// Skeleton::MAX_JOINTS
+struct Joints {
+    matrices: array<mat4x4<f32>, 128>,
+}
+
+//!binding()
+var<uniform> view: ShadowView;
+// only bound by shadow_morph.ron and shadow_skinned.ron
+//!binding()
+var<storage> morph_targets: MorphTargets;
+//!binding()
+var<uniform> morph_weights: MorphWeights;
+// only bound by shadow_skinned.ron
+//!binding()
+var<uniform> joints: Joints;
+
+// Vertex Shader
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+};
+
+// the model matrix of InstanceTransform
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+};
+
+// Depth only, there is no fragment shader
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> @builtin(position) vec4<f32> {
+    let model_matrix = mat4x4(instance.model_matrix_0, instance.model_matrix_1, instance.model_matrix_2, instance.model_matrix_3);
+    return view.view_proj * model_matrix * vec4(model.position, 1.0);
+}
+
+fn morph(position: vec3<f32>, vertex_index: u32) -> vec3<f32> {
+    var morphed = position;
+
+    for (var i = 0u; i < morph_targets.count; i = i + 1u) {
+        morphed = morphed + morph_targets.deltas[vertex_index * morph_targets.count + i].position.xyz * morph_weights.weights[i / 4u][i % 4u];
+    }
+
+    return morphed;
+}
+
+@vertex
+fn vs_morph(
+    model: VertexInput,
+    instance: InstanceInput,
+    @builtin(vertex_index) vertex_index: u32,
+) -> @builtin(position) vec4<f32> {
+    let model_matrix = mat4x4(instance.model_matrix_0, instance.model_matrix_1, instance.model_matrix_2, instance.model_matrix_3);
+    return view.view_proj * model_matrix * vec4(morph(model.position, vertex_index), 1.0);
+}
+
+// SkinnedVertex
+struct SkinnedVertexInput {
+    @location(0) position: vec3<f32>,
+    @location(12) joints: vec4<u32>,
+    @location(13) weights: vec4<f32>,
+};
+
+@vertex
+fn vs_skinned(
+    model: SkinnedVertexInput,
+    instance: InstanceInput,
+    @builtin(vertex_index) vertex_index: u32,
+) -> @builtin(position) vec4<f32> {
+    let skin_matrix = joints.matrices[model.joints.x] * model.weights.x
+        + joints.matrices[model.joints.y] * model.weights.y
+        + joints.matrices[model.joints.z] * model.weights.z
+        + joints.matrices[model.joints.w] * model.weights.w;
+
+    let model_matrix = mat4x4(instance.model_matrix_0, instance.model_matrix_1, instance.model_matrix_2, instance.model_matrix_3);
+    return view.view_proj * model_matrix * skin_matrix * vec4(morph(model.position, vertex_index), 1.0);
+}
@@ -0,0 +1,43 @@
+
+// Jorge Jimenez, "Next Generation Post Processing in Call of Duty: Advanced Warfare", 13 tap downsample
+fn bloom_downsample(source: texture_2d<f32>, source_sampler: sampler, uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+
+    let a = textureSample(source, source_sampler, uv + texel * vec2(-2.0, -2.0)).rgb;
+    let b = textureSample(source, source_sampler, uv + texel * vec2(0.0, -2.0)).rgb;
+    let c = textureSample(source, source_sampler, uv + texel * vec2(2.0, -2.0)).rgb;
+    let d = textureSample(source, source_sampler, uv + texel * vec2(-2.0, 0.0)).rgb;
+    let e = textureSample(source, source_sampler, uv).rgb;
+    let f = textureSample(source, source_sampler, uv + texel * vec2(2.0, 0.0)).rgb;
+    let g = textureSample(source, source_sampler, uv + texel * vec2(-2.0, 2.0)).rgb;
+    let h = textureSample(source, source_sampler, uv + texel * vec2(0.0, 2.0)).rgb;
+    let i = textureSample(source, source_sampler, uv + texel * vec2(2.0, 2.0)).rgb;
+    let j = textureSample(source, source_sampler, uv + texel * vec2(-1.0, -1.0)).rgb;
+    let k = textureSample(source, source_sampler, uv + texel * vec2(1.0, -1.0)).rgb;
+    let l = textureSample(source, source_sampler, uv + texel * vec2(-1.0, 1.0)).rgb;
+    let m = textureSample(source, source_sampler, uv + texel * vec2(1.0, 1.0)).rgb;
+
+    var colour = e * 0.125;
+    colour += (a + c + g + i) * 0.03125;
+    colour += (b + d + f + h) * 0.0625;
+    colour += (j + k + l + m) * 0.125;
+
+    return colour;
+}
+
+// 3x3 tent filter
+fn bloom_upsample(source: texture_2d<f32>, source_sampler: sampler, uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source));
+
+    var colour = textureSample(source, source_sampler, uv).rgb * 4.0;
+    colour += textureSample(source, source_sampler, uv + texel * vec2(0.0, -1.0)).rgb * 2.0;
+    colour += textureSample(source, source_sampler, uv + texel * vec2(-1.0, 0.0)).rgb * 2.0;
+    colour += textureSample(source, source_sampler, uv + texel * vec2(1.0, 0.0)).rgb * 2.0;
+    colour += textureSample(source, source_sampler, uv + texel * vec2(0.0, 1.0)).rgb * 2.0;
+    colour += textureSample(source, source_sampler, uv + texel * vec2(-1.0, -1.0)).rgb;
+    colour += textureSample(source, source_sampler, uv + texel * vec2(1.0, -1.0)).rgb;
+    colour += textureSample(source, source_sampler, uv + texel * vec2(-1.0, 1.0)).rgb;
+    colour += textureSample(source, source_sampler, uv + texel * vec2(1.0, 1.0)).rgb;
+
+    return colour / 16.0;
+}
@@ -1,56 +1,103 @@
-struct AlphaMode {
-    blended: u32,
+// MaterialUniform
+struct MaterialFactors {
+    albedo: vec4<f32>,
+    emissive: vec4<f32>,
+    specular_colour: vec4<f32>,
+    sheen_colour: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
     cutoff: f32,
+    blended: u32,
+    unlit: u32,
+    ior: f32,
+    clearcoat: f32,
+    clearcoat_roughness: f32,
+    clearcoat_normal_scale: f32,
+    transmission: f32,
+    specular: f32,
+    sheen_roughness: f32,
+    // UV set of each MaterialTexture, packed in fours
+    tex_coords: array<vec4<u32>, 3>,
+    // KHR_texture_transform of each MaterialTexture
+    transforms: array<mat3x3<f32>, 11>,
 }
 
+// MaterialTexture
+let ALBEDO_TEXTURE: u32 = 0u;
+let METALLIC_TEXTURE: u32 = 1u;
+let ROUGHNESS_TEXTURE: u32 = 2u;
+let NORMAL_TEXTURE: u32 = 3u;
+let OCCLUSION_TEXTURE: u32 = 4u;
+let EMISSIVE_TEXTURE: u32 = 5u;
+let CLEARCOAT_TEXTURE: u32 = 6u;
+let CLEARCOAT_NORMAL_TEXTURE: u32 = 7u;
+let TRANSMISSION_TEXTURE: u32 = 8u;
+let SPECULAR_TEXTURE: u32 = 9u;
+let SHEEN_TEXTURE: u32 = 10u;
+
 struct Material {
     albedo: vec4<f32>,
     metallic: f32,
     roughness: f32,
     occlusion: f32,
     emissive: vec4<f32>,
+    // reflectance of dielectrics at normal incidence, from the ior and KHR_materials_specular
+    specular_f0: vec3<f32>,
+    specular: f32,
+    clearcoat: f32,
+    clearcoat_roughness: f32,
+    clearcoat_normal: vec3<f32>,
+    transmission: f32,
+    sheen_colour: vec3<f32>,
+    sheen_roughness: f32,
 }
 
 //!binding()
-var<uniform> alpha_mode: AlphaMode;
+var<uniform> material_factors: MaterialFactors;
 
 //!binding()
 var albedo_texture: texture_2d<f32>;
 //!binding()
 var albedo_sampler: sampler;
-//!binding()
-var<uniform> albedo: vec4<f32>;
-
 //!binding()
 var metallic_texture: texture_2d<f32>;
 //!binding()
 var metallic_sampler: sampler;
 //!binding()
-var<uniform> metallic_factor: f32;
-//!binding()
 var roughness_texture: texture_2d<f32>;
 //!binding()
 var roughness_sampler: sampler;
-//!binding()
-var<uniform> roughness_factor: f32;
-
 //!binding()
 var normal_texture: texture_2d<f32>;
 //!binding()
 var normal_sampler: sampler;
-//!binding()
-var<uniform> normal_scale: f32;
-
 //!binding()
 var occlusion_texture: texture_2d<f32>;
 //!binding()
 var occlusion_sampler: sampler;
-//!binding()
-var<uniform> occlusion_strength: f32;
-
 //!binding()
 var emissive_texture: texture_2d<f32>;
 //!binding()
 var emissive_sampler: sampler;
 //!binding()
-var<uniform> emissive_factor: vec4<f32>;
+var clearcoat_texture: texture_2d<f32>;
+//!binding()
+var clearcoat_sampler: sampler;
+//!binding()
+var clearcoat_normal_texture: texture_2d<f32>;
+//!binding()
+var clearcoat_normal_sampler: sampler;
+//!binding()
+var transmission_texture: texture_2d<f32>;
+//!binding()
+var transmission_sampler: sampler;
+//!binding()
+var specular_texture: texture_2d<f32>;
+//!binding()
+var specular_sampler: sampler;
+//!binding()
+var sheen_texture: texture_2d<f32>;
+//!binding()
+var sheen_sampler: sampler;
@@ -0,0 +1,10 @@
+
+struct PostProcessing {
+    exposure: f32,
+    tonemapper: u32,
+    bloom_intensity: f32,
+    bloom_threshold: f32,
+    bloom_knee: f32,
+    colour_grading: f32,
+    fxaa: u32,
+};
@@ -1,11 +1,57 @@
 
-fn tonemap(colour: vec3<f32>) -> vec3<f32> {
-    // let exposure = 0.5;
-    // let constrast = 0.75;
-    // return pow(exposure * colour, vec3(constrast));
+fn tonemap_reinhard(colour: vec3<f32>) -> vec3<f32> {
     return colour / (colour + 1.0);
 }
 
-fn tonemap_rgba(colour: vec4<f32>) -> vec4<f32> {
-    return vec4(tonemap(colour.rgb), colour.a);
+// Krzysztof Narkowicz, "ACES Filmic Tone Mapping Curve"
+fn tonemap_aces(colour: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((colour * (a * colour + b)) / (colour * (c * colour + d) + e), vec3(0.0), vec3(1.0));
+}
+
+// Benjamin Wrensch, "Minimal AgX Implementation", with a polynomial fit of the default contrast curve
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+}
+
+fn tonemap_agx(colour: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104
+    );
+    let outset = mat3x3(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    var x = inset * max(colour, vec3(1.0e-10));
+    x = clamp(log2(x), vec3(min_ev), vec3(max_ev));
+    x = (x - min_ev) / (max_ev - min_ev);
+    x = agx_contrast(x);
+    x = outset * x;
+
+    // the curve outputs display encoded colours
+    return pow(max(x, vec3(0.0)), vec3(2.2));
+}
+
+// 0: none, 1: reinhard, 2: aces, 3: agx
+fn tonemap(colour: vec3<f32>, tonemapper: u32) -> vec3<f32> {
+    if (tonemapper == 1u) {
+        return tonemap_reinhard(colour);
+    } else if (tonemapper == 2u) {
+        return tonemap_aces(colour);
+    } else if (tonemapper == 3u) {
+        return tonemap_agx(colour);
+    }
+    return clamp(colour, vec3(0.0), vec3(1.0));
 }
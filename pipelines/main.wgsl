@@ -1,53 +1,93 @@
 // Inputs
 
 let PI: f32 = 3.14159265358979323846264338327950288;
-
-struct Transform {
-    model_matrix: mat4x4<f32>,
-    inv_model_matrix: mat4x4<f32>,
-}
+// CubeMap::PREFILTER_MIP_LEVELS - 1
+let MAX_REFLECTION_LOD: f32 = 4.0;
 
 struct Camera {
     position: vec4<f32>,
     view_proj: mat4x4<f32>,
 };
 
-// struct PointLight {
-//     position: vec3<f32>,
-//     colour: vec4<f32>,
-// }
+struct PointLight {
+    position: vec3<f32>,
+    // first shadow view, -1 without shadows
+    shadow: i32,
+    colour: vec4<f32>,
+    // 0 if unbounded
+    range: f32,
+}
 
-// struct DirectionalLight {
-//     direction: vec3<f32>,
-//     colour: vec4<f32>,
-// }
+struct DirectionalLight {
+    direction: vec3<f32>,
+    // first shadow view, -1 without shadows
+    shadow: i32,
+    colour: vec4<f32>,
+}
 
-struct Light {
-    pos_dir: vec3<f32>,
+struct SpotLight {
+    position: vec3<f32>,
+    // 0 if unbounded
+    range: f32,
+    direction: vec3<f32>,
+    angle_scale: f32,
     colour: vec4<f32>,
+    angle_offset: f32,
+}
+
+struct ShadowView {
+    view_proj: mat4x4<f32>,
+    bias: f32,
+    normal_bias: f32,
+    pcf_radius: f32,
+    count: u32,
 }
 
 struct AmbientLight {
     colour: vec4<f32>,
 }
 
+// MorphDelta, morph target offsets of a vertex
+struct MorphDelta {
+    position: vec4<f32>,
+    normal: vec4<f32>,
+    tangent: vec4<f32>,
+}
+
+// the deltas of each vertex are adjacent, see MorphTargets
+struct MorphTargets {
+    count: u32,
+    deltas: array<MorphDelta>,
+}
+
+// MorphTargets::MAX_TARGETS, packed in fours
+struct MorphWeights {
+    weights: array<vec4<f32>, 16>,
+}
+
+// Skeleton::MAX_JOINTS
+struct Joints {
+    matrices: array<mat4x4<f32>, 128>,
+}
+
 //!include("includes/colour_encodings.wgsl")
-//!include("includes/tonemapping.wgsl")
 //!include("includes/material_bindings.wgsl")
 
-//!binding()
-var<uniform> transform: Transform;
 //!binding()
 var<uniform> camera: Camera;
 //!binding()
-var<storage> point_lights: array<Light>;
+var<storage> point_lights: array<PointLight>;
 //!binding()
 var<uniform> num_point_lights: u32;
 //!binding()
-var<storage> directional_lights: array<Light>;
+var<storage> directional_lights: array<DirectionalLight>;
 //!binding()
 var<uniform> num_directional_lights: u32;
 //!binding()
+var<storage> spot_lights: array<SpotLight>;
+//!binding()
+var<uniform> num_spot_lights: u32;
+//!binding()
 var<storage> ambient_lights: array<AmbientLight>;
 //!binding()
 var<uniform> num_ambient_lights: u32;
@@ -55,19 +95,53 @@ var<uniform> num_ambient_lights: u32;
 var irradiance_map: texture_cube<f32>;
 //!binding()
 var irradiance_sampler: sampler;
-// //!binding()
-// var reflections_map: texture_cube<f32>;
-// //!binding()
-// var reflections_sampler: sampler;
+//!binding()
+var reflections_map: texture_cube<f32>;
+//!binding()
+var reflections_sampler: sampler;
+//!binding()
+var brdf_lut: texture_2d<f32>;
+//!binding()
+var brdf_lut_sampler: sampler;
+//!binding()
+var shadow_maps: texture_depth_2d_array;
+//!binding()
+var shadow_sampler: sampler_comparison;
+//!binding()
+var<storage> shadow_views: array<ShadowView>;
+//!binding()
+var<uniform> num_shadow_views: u32;
+// only bound by morph.ron and skinned.ron
+//!binding()
+var<storage> morph_targets: MorphTargets;
+//!binding()
+var<uniform> morph_weights: MorphWeights;
+// only bound by skinned.ron, the last binding so other variants can leave it out
+//!binding()
+var<uniform> joints: Joints;
 
 // Vertex Shader
 
+// ModelVertex
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
     @location(2) normal: vec3<f32>,
-    @location(3) tangent: vec3<f32>,
-    @location(4) bitangent: vec3<f32>,
+    // w is the handedness of the bitangent
+    @location(3) tangent: vec4<f32>,
+    @location(4) tex_coords_1: vec2<f32>,
+    @location(14) colour: vec4<f32>,
+};
+
+// InstanceTransform
+struct InstanceInput {
+    @location(5) model_matrix_0: vec4<f32>,
+    @location(6) model_matrix_1: vec4<f32>,
+    @location(7) model_matrix_2: vec4<f32>,
+    @location(8) model_matrix_3: vec4<f32>,
+    @location(9) normal_matrix_0: vec3<f32>,
+    @location(10) normal_matrix_1: vec3<f32>,
+    @location(11) normal_matrix_2: vec3<f32>,
 };
 
 struct VertexOutput {
@@ -77,26 +151,97 @@ struct VertexOutput {
     @location(2) normal: vec3<f32>,
     @location(3) tangent: vec3<f32>,
     @location(4) bitangent: vec3<f32>,
+    @location(5) tex_coords_1: vec2<f32>,
+    @location(6) colour: vec4<f32>,
 };
 
-@vertex
-fn vs_main(
-    model: VertexInput,
-) -> VertexOutput {
-    let pos = transform.model_matrix * vec4(model.position, 1.0);
+fn vertex_output(model: VertexInput, model_matrix: mat4x4<f32>, normal_matrix: mat3x3<f32>) -> VertexOutput {
+    let pos = model_matrix * vec4(model.position, 1.0);
     
     var out: VertexOutput;
     out.tex_coords = model.tex_coords;
+    out.tex_coords_1 = model.tex_coords_1;
+    out.colour = model.colour;
     out.position = pos.xyz;
-    out.normal = normalize(transpose(transform.inv_model_matrix) * vec4(model.normal, 0.0)).xyz;
-    out.tangent = normalize(transform.model_matrix * vec4(model.tangent, 0.0)).xyz;
-    out.bitangent = normalize(transform.model_matrix * vec4(model.bitangent, 0.0)).xyz;
+    out.normal = normalize(normal_matrix * model.normal);
+    out.tangent = normalize(model_matrix * vec4(model.tangent.xyz, 0.0)).xyz;
+    out.bitangent = cross(out.normal, out.tangent) * model.tangent.w;
 
     out.clip_position = camera.view_proj * pos;
     
     return out;
 }
 
+@vertex
+fn vs_main(
+    model: VertexInput,
+    instance: InstanceInput,
+) -> VertexOutput {
+    let model_matrix = mat4x4(instance.model_matrix_0, instance.model_matrix_1, instance.model_matrix_2, instance.model_matrix_3);
+    let normal_matrix = mat3x3(instance.normal_matrix_0, instance.normal_matrix_1, instance.normal_matrix_2);
+
+    return vertex_output(model, model_matrix, normal_matrix);
+}
+
+fn morph(model: VertexInput, vertex_index: u32) -> VertexInput {
+    var vertex = model;
+
+    for (var i = 0u; i < morph_targets.count; i = i + 1u) {
+        let weight = morph_weights.weights[i / 4u][i % 4u];
+        let delta = morph_targets.deltas[vertex_index * morph_targets.count + i];
+
+        vertex.position = vertex.position + delta.position.xyz * weight;
+        vertex.normal = vertex.normal + delta.normal.xyz * weight;
+        vertex.tangent = vec4(vertex.tangent.xyz + delta.tangent.xyz * weight, vertex.tangent.w);
+    }
+
+    return vertex;
+}
+
+@vertex
+fn vs_morph(
+    model: VertexInput,
+    instance: InstanceInput,
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    let model_matrix = mat4x4(instance.model_matrix_0, instance.model_matrix_1, instance.model_matrix_2, instance.model_matrix_3);
+    let normal_matrix = mat3x3(instance.normal_matrix_0, instance.normal_matrix_1, instance.normal_matrix_2);
+
+    return vertex_output(morph(model, vertex_index), model_matrix, normal_matrix);
+}
+
+// SkinnedVertex
+struct SkinnedVertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+    @location(3) tangent: vec4<f32>,
+    @location(4) tex_coords_1: vec2<f32>,
+    @location(14) colour: vec4<f32>,
+    @location(12) joints: vec4<u32>,
+    @location(13) weights: vec4<f32>,
+};
+
+@vertex
+fn vs_skinned(
+    model: SkinnedVertexInput,
+    instance: InstanceInput,
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    let skin_matrix = joints.matrices[model.joints.x] * model.weights.x
+        + joints.matrices[model.joints.y] * model.weights.y
+        + joints.matrices[model.joints.z] * model.weights.z
+        + joints.matrices[model.joints.w] * model.weights.w;
+
+    let model_matrix = mat4x4(instance.model_matrix_0, instance.model_matrix_1, instance.model_matrix_2, instance.model_matrix_3) * skin_matrix;
+    // assumes joints are scaled uniformly
+    let normal_matrix = mat3x3(instance.normal_matrix_0, instance.normal_matrix_1, instance.normal_matrix_2) * mat3x3(skin_matrix[0].xyz, skin_matrix[1].xyz, skin_matrix[2].xyz);
+
+    // morph targets are applied before skinning
+    let vertex = morph(VertexInput(model.position, model.tex_coords, model.normal, model.tangent, model.tex_coords_1, model.colour), vertex_index);
+    return vertex_output(vertex, model_matrix, normal_matrix);
+}
+
 // Fragment Shader
 
 fn distribution_ggx(normal: vec3<f32>, half: vec3<f32>, roughness: f32) -> f32 {
@@ -131,95 +276,266 @@ fn geometry_smith(normal: vec3<f32>, view: vec3<f32>, light_dir: vec3<f32>, roug
     return ggx_v * ggx_l;
 }
 
-fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
-    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+// f90 is less than 1 for dielectrics with KHR_materials_specular
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>, f90: vec3<f32>) -> vec3<f32> {
+    return f0 + (f90 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
 }
 
-fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
-    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, f90: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(f90 * (1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
 }
 
-fn calc_light(light: Light, directional: bool, material: Material, position: vec3<f32>, normal: vec3<f32>, view_dir: vec3<f32>) -> vec3<f32> {
-    let light_colour = light.colour.rgb * light.colour.a;
+// Cook-Torrance without the fresnel term
+fn specular_brdf(normal: vec3<f32>, view_dir: vec3<f32>, light_dir: vec3<f32>, half_dir: vec3<f32>, roughness: f32) -> f32 {
+    let ndf = distribution_ggx(normal, half_dir, roughness);
+    let g = geometry_smith(normal, view_dir, light_dir, roughness);
+    let denominator = 4.0 * max(dot(normal, view_dir), 0.0) * max(dot(normal, light_dir), 0.0) + 0.0001;
 
-    let light_vec = light.pos_dir - position;
-    
-    var light_dir = vec3(0.0);
-    if (directional) {
-        light_dir = light.pos_dir;
-    } else {
-        light_dir = normalize(light_vec);
-    };
-    
+    return ndf * g / denominator;
+}
+
+// Sheen distribution of KHR_materials_sheen ("Production Friendly Microfacet Sheen BRDF", Estevez & Kulla)
+fn distribution_charlie(ndoth: f32, roughness: f32) -> f32 {
+    let inv_alpha = 1.0 / max(roughness * roughness, 0.000001);
+    let sin2 = max(1.0 - ndoth * ndoth, 0.0);
+
+    return (2.0 + inv_alpha) * pow(sin2, inv_alpha * 0.5) / (2.0 * PI);
+}
+
+fn visibility_neubelt(ndotl: f32, ndotv: f32) -> f32 {
+    return 1.0 / (4.0 * (ndotl + ndotv - ndotl * ndotv) + 0.0001);
+}
+
+// Smoothly reaches zero at the range, as recommended by KHR_lights_punctual
+fn range_attenuation(distance: f32, range: f32) -> f32 {
+    if (range <= 0.0) {
+        return 1.0;
+    }
+
+    let ratio = distance / range;
+    return clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+}
+
+fn distance_attenuation(distance: f32, range: f32) -> f32 {
+    return range_attenuation(distance, range) / (distance * distance + 1.0);
+}
+
+// light_dir points towards the light
+fn calc_light(light_dir: vec3<f32>, radiance: vec3<f32>, material: Material, normal: vec3<f32>, view_dir: vec3<f32>) -> vec3<f32> {
     let half_dir = normalize(view_dir + light_dir);
+    let hdotv = max(dot(half_dir, view_dir), 0.0);
+    let ndotl = max(dot(normal, light_dir), 0.0);
     
-    var attenuation = 1.0;
-    if (!directional) {
-        let distance = length(light_vec);
-        attenuation /= distance * distance + 1.0;
-    };
-    
-    let radiance = light_colour * attenuation;    
-    
-    let f0 = vec3(0.04);
-    let f0 = mix(f0, material.albedo.rgb, material.metallic);
-    let f = fresnel_schlick(max(dot(half_dir, view_dir), 0.0), f0);
-    let ndf = distribution_ggx(normal, half_dir, material.roughness);
-    let g = geometry_smith(normal, view_dir, light_dir, material.roughness);
-
-    let ks = f;
-    let kd = (vec3(1.0) - ks) * (1.0 - material.metallic);
-    
-    let numerator = ndf * g * f;
-    let denominator = 4.0 * max(dot(normal, view_dir), 0.0) * max(dot(normal, light_dir), 0.0)  + 0.0001;
-    let specular = numerator / denominator;
-    
-    let ndotl = max(dot(normal, light_dir), 0.0);        
-    return (kd * material.albedo.rgb / PI + specular) * radiance * ndotl;
+    let f0 = mix(material.specular_f0, material.albedo.rgb, material.metallic);
+    let f90 = mix(vec3(material.specular), vec3(1.0), material.metallic);
+    let f = fresnel_schlick(hdotv, f0, f90);
+
+    // transmitted light is lost from the diffuse lobe
+    let kd = (vec3(1.0) - f) * (1.0 - material.metallic) * (1.0 - material.transmission);
+    let specular = f * specular_brdf(normal, view_dir, light_dir, half_dir, material.roughness);
+    let sheen = material.sheen_colour * distribution_charlie(max(dot(normal, half_dir), 0.0), material.sheen_roughness) * visibility_neubelt(ndotl, max(dot(normal, view_dir), 0.0));
+    let base = (kd * material.albedo.rgb / PI + specular + sheen) * radiance * ndotl;
+
+    // the clearcoat layer reflects some light before it reaches the base
+    let clearcoat_fresnel = fresnel_schlick(hdotv, vec3(0.04), vec3(1.0)).x * material.clearcoat;
+    let clearcoat_ndotl = max(dot(material.clearcoat_normal, light_dir), 0.0);
+    let clearcoat = clearcoat_fresnel * specular_brdf(material.clearcoat_normal, view_dir, light_dir, half_dir, material.clearcoat_roughness) * radiance * clearcoat_ndotl;
+
+    return base * (1.0 - clearcoat_fresnel) + clearcoat;
 }
 
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let map_normal = (textureSample(normal_texture, normal_sampler, in.tex_coords) * 2.0 - 1.0).rgb;
-    let map_normal = mix(vec3(0.0, 0.0, 1.0), map_normal, normal_scale);
-    // let map_normal = vec3(0.0, 0.0, 1.0);
+// 3x3 percentage closer filter, each tap is bilinearly filtered by the comparison sampler
+fn sample_shadow(view: ShadowView, layer: i32, position: vec3<f32>) -> f32 {
+    let clip = view.view_proj * vec4(position, 1.0);
+    let ndc = clip.xyz / clip.w;
+
+    // beyond the far plane
+    if (ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    let uv = ndc.xy * vec2(0.5, -0.5) + 0.5;
+    let depth = ndc.z - view.bias;
+    let texel_size = view.pcf_radius / vec2<f32>(textureDimensions(shadow_maps));
+
+    var visibility = 0.0;
+    for (var x = -1; x <= 1; x++) {
+        for (var y = -1; y <= 1; y++) {
+            let offset = vec2(f32(x), f32(y)) * texel_size;
+            visibility += textureSampleCompareLevel(shadow_maps, shadow_sampler, uv + offset, layer, depth);
+        }
+    }
+
+    return visibility / 9.0;
+}
+
+// Uses the first cascade containing the position
+fn directional_shadow(shadow: i32, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if (shadow < 0) {
+        return 1.0;
+    }
+
+    let count = i32(shadow_views[shadow].count);
+    for (var i = 0; i < count; i++) {
+        let layer = shadow + i;
+        let view = shadow_views[layer];
+        let offset_position = position + normal * view.normal_bias;
+
+        let clip = view.view_proj * vec4(offset_position, 1.0);
+        let ndc = clip.xyz / clip.w;
+        // leave room for the filter kernel at the edges
+        if (all(abs(ndc.xy) < vec2(0.98)) && ndc.z >= 0.0 && ndc.z <= 1.0) {
+            return sample_shadow(view, layer, offset_position);
+        }
+    }
+
+    return 1.0;
+}
+
+// Views are ordered +X, -X, +Y, -Y, +Z, -Z
+fn point_shadow(shadow: i32, light_position: vec3<f32>, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    if (shadow < 0) {
+        return 1.0;
+    }
+
+    let light_vec = position - light_position;
+    let abs_vec = abs(light_vec);
+
+    var face = 0;
+    if (abs_vec.x >= abs_vec.y && abs_vec.x >= abs_vec.z) {
+        face = select(1, 0, light_vec.x > 0.0);
+    } else if (abs_vec.y >= abs_vec.z) {
+        face = select(3, 2, light_vec.y > 0.0);
+    } else {
+        face = select(5, 4, light_vec.z > 0.0);
+    }
+
+    let layer = shadow + face;
+    let view = shadow_views[layer];
+    let offset_position = position + normal * view.normal_bias * length(light_vec);
+
+    return sample_shadow(view, layer, offset_position);
+}
+
+// refract(), reflecting on total internal reflection
+fn refract_dir(incident: vec3<f32>, normal: vec3<f32>, eta: f32) -> vec3<f32> {
+    let ndoti = dot(normal, incident);
+    let k = 1.0 - eta * eta * (1.0 - ndoti * ndoti);
+
+    return select(eta * incident - (eta * ndoti + sqrt(max(k, 0.0))) * normal, reflect(incident, normal), k < 0.0);
+}
+
+// the UV set and transform of a MaterialTexture
+fn uv(in: VertexOutput, texture: u32) -> vec2<f32> {
+    let tex_coord = material_factors.tex_coords[texture / 4u][texture % 4u];
+    let uv = select(in.tex_coords, in.tex_coords_1, tex_coord == 1u);
+
+    return (material_factors.transforms[texture] * vec3(uv, 1.0)).xy;
+}
+
+fn shade(in: VertexOutput) -> vec4<f32> {
+    let tbn = mat3x3(in.tangent, in.bitangent, in.normal);
 
     var material: Material;
-    material.albedo = textureSample(albedo_texture, albedo_sampler, in.tex_coords);
-    material.albedo = srgba_to_linear(material.albedo) * srgba_to_linear(albedo);
+    material.albedo = textureSample(albedo_texture, albedo_sampler, uv(in, ALBEDO_TEXTURE));
+    material.albedo = srgba_to_linear(material.albedo) * srgba_to_linear(material_factors.albedo) * in.colour;
     
-    material.metallic = textureSample(metallic_texture, metallic_sampler, in.tex_coords).b * metallic_factor;
-    material.roughness = textureSample(roughness_texture, roughness_sampler, in.tex_coords).g * roughness_factor;
-    material.occlusion = mix(1.0, textureSample(occlusion_texture, occlusion_sampler, in.tex_coords).r, occlusion_strength);
-    material.emissive = textureSample(emissive_texture, emissive_sampler, in.tex_coords) * emissive_factor;
+    material.metallic = textureSample(metallic_texture, metallic_sampler, uv(in, METALLIC_TEXTURE)).b * material_factors.metallic;
+    material.roughness = textureSample(roughness_texture, roughness_sampler, uv(in, ROUGHNESS_TEXTURE)).g * material_factors.roughness;
+    material.occlusion = mix(1.0, textureSample(occlusion_texture, occlusion_sampler, uv(in, OCCLUSION_TEXTURE)).r, material_factors.occlusion_strength);
+    material.emissive = textureSample(emissive_texture, emissive_sampler, uv(in, EMISSIVE_TEXTURE)) * material_factors.emissive;
 
-    let tbn = mat3x3(in.tangent, in.bitangent, in.normal);
+    let map_normal = (textureSample(normal_texture, normal_sampler, uv(in, NORMAL_TEXTURE)) * 2.0 - 1.0).rgb;
+    let map_normal = mix(vec3(0.0, 0.0, 1.0), map_normal, material_factors.normal_scale);
     let normal = normalize(tbn * map_normal);
+
+    // KHR_materials_ior and KHR_materials_specular
+    let specular_sample = textureSample(specular_texture, specular_sampler, uv(in, SPECULAR_TEXTURE));
+    let ior_ratio = (material_factors.ior - 1.0) / (material_factors.ior + 1.0);
+    material.specular = material_factors.specular * specular_sample.a;
+    material.specular_f0 = min(ior_ratio * ior_ratio * material_factors.specular_colour.rgb * srgb_to_linear(specular_sample.rgb), vec3(1.0)) * material.specular;
+
+    // KHR_materials_clearcoat
+    let clearcoat_sample = textureSample(clearcoat_texture, clearcoat_sampler, uv(in, CLEARCOAT_TEXTURE));
+    material.clearcoat = material_factors.clearcoat * clearcoat_sample.r;
+    material.clearcoat_roughness = material_factors.clearcoat_roughness * clearcoat_sample.g;
+    let clearcoat_map_normal = (textureSample(clearcoat_normal_texture, clearcoat_normal_sampler, uv(in, CLEARCOAT_NORMAL_TEXTURE)) * 2.0 - 1.0).rgb;
+    material.clearcoat_normal = normalize(tbn * mix(vec3(0.0, 0.0, 1.0), clearcoat_map_normal, material_factors.clearcoat_normal_scale));
+
+    // KHR_materials_transmission
+    material.transmission = material_factors.transmission * textureSample(transmission_texture, transmission_sampler, uv(in, TRANSMISSION_TEXTURE)).r;
+
+    // KHR_materials_sheen
+    let sheen_sample = textureSample(sheen_texture, sheen_sampler, uv(in, SHEEN_TEXTURE));
+    material.sheen_colour = material_factors.sheen_colour.rgb * srgb_to_linear(sheen_sample.rgb);
+    material.sheen_roughness = material_factors.sheen_roughness * sheen_sample.a;
+
     let view_dir = normalize(camera.position.xyz - in.position);
 
-    let f0 = vec3(0.04);
-    let f0 = mix(f0, material.albedo.rgb, material.metallic);
-    let ks = fresnel_schlick_roughness(max(dot(normal, view_dir), 0.0), f0, material.roughness); 
-    let kd = 1.0 - ks;
+    let f0 = mix(material.specular_f0, material.albedo.rgb, material.metallic);
+    let f90 = mix(vec3(material.specular), vec3(1.0), material.metallic);
+    let ndotv = max(dot(normal, view_dir), 0.0);
+    let ks = fresnel_schlick_roughness(ndotv, f0, f90, material.roughness); 
+    let kd = (1.0 - ks) * (1.0 - material.metallic);
     let irradiance = textureSample(irradiance_map, irradiance_sampler, normal).rgb;
     let diffuse = irradiance * material.albedo.rgb;
-    let ambient = (kd * diffuse) * material.occlusion;
 
-    if (material.albedo.a <= alpha_mode.cutoff) {
+    // transmission is approximated by refracting the environment, tinted by the albedo
+    let refraction_dir = refract_dir(-view_dir, normal, 1.0 / material_factors.ior);
+    let transmitted = textureSampleLevel(reflections_map, reflections_sampler, refraction_dir, material.roughness * MAX_REFLECTION_LOD).rgb * material.albedo.rgb;
+    let diffuse = mix(diffuse, transmitted, material.transmission);
+
+    let reflection_dir = reflect(-view_dir, normal);
+    let prefiltered = textureSampleLevel(reflections_map, reflections_sampler, reflection_dir, material.roughness * MAX_REFLECTION_LOD).rgb;
+    let env_brdf = textureSample(brdf_lut, brdf_lut_sampler, vec2(ndotv, material.roughness)).rg;
+    let specular = prefiltered * (ks * env_brdf.x + f90 * env_brdf.y);
+    
+    let ambient = (kd * diffuse + specular) * material.occlusion;
+
+    let clearcoat_ndotv = max(dot(material.clearcoat_normal, view_dir), 0.0);
+    let clearcoat_fresnel = fresnel_schlick(clearcoat_ndotv, vec3(0.04), vec3(1.0)).x * material.clearcoat;
+    let clearcoat_prefiltered = textureSampleLevel(reflections_map, reflections_sampler, reflect(-view_dir, material.clearcoat_normal), material.clearcoat_roughness * MAX_REFLECTION_LOD).rgb;
+    let clearcoat_env_brdf = textureSample(brdf_lut, brdf_lut_sampler, vec2(clearcoat_ndotv, material.clearcoat_roughness)).rg;
+    let clearcoat_ambient = clearcoat_prefiltered * (0.04 * clearcoat_env_brdf.x + clearcoat_env_brdf.y) * material.clearcoat * material.occlusion;
+
+    if (material.albedo.a <= material_factors.cutoff) {
         discard;
     }
 
-    if (alpha_mode.blended == 0u) {
+    if (material_factors.blended == 0u) {
         material.albedo.a = 1.0;
     }
 
+    // KHR_materials_unlit
+    if (material_factors.unlit == 1u) {
+        return material.albedo;
+    }
+
     var reflectance = vec3(0.0);
     for (var i = 0u; i < num_point_lights; i++) {
-        reflectance += calc_light(point_lights[i], false, material, in.position, normal, view_dir);
+        let light = point_lights[i];
+        let light_vec = light.position - in.position;
+        let distance = length(light_vec);
+        let shadow = point_shadow(light.shadow, light.position, in.position, in.normal);
+        let radiance = light.colour.rgb * light.colour.a * distance_attenuation(distance, light.range) * shadow;
+        reflectance += calc_light(light_vec / distance, radiance, material, normal, view_dir);
     }
     
     for (var i = 0u; i < num_directional_lights; i++) {
-        reflectance += calc_light(directional_lights[i], true, material, in.position, normal, view_dir);
+        let light = directional_lights[i];
+        let shadow = directional_shadow(light.shadow, in.position, in.normal);
+        let radiance = light.colour.rgb * light.colour.a * shadow;
+        reflectance += calc_light(light.direction, radiance, material, normal, view_dir);
+    }
+
+    for (var i = 0u; i < num_spot_lights; i++) {
+        let light = spot_lights[i];
+        let light_vec = light.position - in.position;
+        let distance = length(light_vec);
+        let light_dir = light_vec / distance;
+        let cone = clamp(dot(light.direction, light_dir) * light.angle_scale + light.angle_offset, 0.0, 1.0);
+        let radiance = light.colour.rgb * light.colour.a * distance_attenuation(distance, light.range) * cone * cone;
+        reflectance += calc_light(light_dir, radiance, material, normal, view_dir);
     }
     
     var ambient_light = vec3(0.0) + ambient;
@@ -227,12 +543,40 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         ambient_light += ambient_lights[i].colour.rgb * ambient_lights[i].colour.a * material.albedo.rgb;
     }
     ambient_light *= material.occlusion;
+    ambient_light = ambient_light * (1.0 - clearcoat_fresnel) + clearcoat_ambient;
 
+    // tonemapped by the render graph
     let final_colour = reflectance + ambient_light + material.emissive.rgb;
-    let final_colour = tonemap(final_colour);
     
     return vec4(final_colour, material.albedo.a);
     // return vec4(vec3(material.metallic), 1.0);
     // return vec4(pow((in.normal + 1.0) / 2.0, vec3(2.2)), 1.0);
     // return vec4(vec3(normal_scale / 2.0), 1.0);
 }
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return shade(in);
+}
+
+struct OitOutput {
+    @location(0) accumulation: vec4<f32>,
+    @location(1) weight: vec4<f32>,
+};
+
+// weighted blended order-independent transparency, see the WeightedBlended render graph pass
+@fragment
+fn fs_oit(in: VertexOutput) -> OitOutput {
+    let colour = shade(in);
+
+    // equation 7 of "Weighted Blended Order-Independent Transparency" (McGuire & Bavoil)
+    let distance = length(camera.position.xyz - in.position);
+    let weight = colour.a * clamp(10.0 / (1e-5 + pow(distance / 5.0, 2.0) + pow(distance / 200.0, 6.0)), 1e-2, 3e3);
+
+    var out: OitOutput;
+    // rgb is summed and alpha multiplies the revealage, see the blend state of OIT pipelines
+    out.accumulation = vec4(colour.rgb * weight, colour.a);
+    out.weight = vec4(weight, 0.0, 0.0, colour.a);
+
+    return out;
+}
@@ -0,0 +1,12 @@
+//!include("../includes/fullscreen.wgsl")
+//!include("../includes/bloom.wgsl")
+
+//!binding()
+var source: texture_2d<f32>;
+//!binding()
+var source_sampler: sampler;
+
+@fragment
+fn fs_main(vout: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4(bloom_downsample(source, source_sampler, vout.tex_coords), 1.0);
+}
@@ -0,0 +1,27 @@
+// First bloom downsample, removes pixels below the threshold
+
+//!include("../includes/fullscreen.wgsl")
+//!include("../includes/bloom.wgsl")
+//!include("../includes/post_processing.wgsl")
+
+//!binding()
+var source: texture_2d<f32>;
+//!binding()
+var source_sampler: sampler;
+
+//!binding()
+var<uniform> settings: PostProcessing;
+
+@fragment
+fn fs_main(vout: VertexOutput) -> @location(0) vec4<f32> {
+    // clamped so a few very bright pixels don't flicker
+    let colour = min(bloom_downsample(source, source_sampler, vout.tex_coords), vec3(65000.0));
+
+    let brightness = max(colour.r, max(colour.g, colour.b));
+    let knee = settings.bloom_knee;
+    var soft = clamp(brightness - settings.bloom_threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.00001);
+    let contribution = max(soft, brightness - settings.bloom_threshold) / max(brightness, 0.00001);
+
+    return vec4(colour * contribution, 1.0);
+}
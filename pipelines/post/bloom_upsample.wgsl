@@ -0,0 +1,22 @@
+// Blends the upsampled coarser mip with the current one, each level halves the weight of the coarser levels
+
+//!include("../includes/fullscreen.wgsl")
+//!include("../includes/bloom.wgsl")
+
+//!binding()
+var coarse: texture_2d<f32>;
+//!binding()
+var coarse_sampler: sampler;
+
+//!binding()
+var current: texture_2d<f32>;
+//!binding()
+var current_sampler: sampler;
+
+@fragment
+fn fs_main(vout: VertexOutput) -> @location(0) vec4<f32> {
+    let upsampled = bloom_upsample(coarse, coarse_sampler, vout.tex_coords);
+    let colour = textureSample(current, current_sampler, vout.tex_coords).rgb;
+
+    return vec4(mix(colour, upsampled, 0.5), 1.0);
+}
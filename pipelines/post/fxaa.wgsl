@@ -0,0 +1,65 @@
+// FXAA (the console variant), or a plain copy when disabled
+
+//!include("../includes/fullscreen.wgsl")
+//!include("../includes/colour_encodings.wgsl")
+//!include("../includes/post_processing.wgsl")
+
+//!binding()
+var ldr: texture_2d<f32>;
+//!binding()
+var ldr_sampler: sampler;
+
+//!binding()
+var<uniform> settings: PostProcessing;
+
+let REDUCE_MIN: f32 = 0.0078125;
+let REDUCE_MUL: f32 = 0.125;
+let SPAN_MAX: f32 = 8.0;
+
+fn sample_ldr(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(ldr, ldr_sampler, uv, 0.0).rgb;
+}
+
+// edges are found in display space, the target is sampled as linear
+fn luma(colour: vec3<f32>) -> f32 {
+    return dot(linear_to_srgb(colour), vec3(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(vout: VertexOutput) -> @location(0) vec4<f32> {
+    let centre = sample_ldr(vout.tex_coords);
+    if (settings.fxaa == 0u) {
+        return vec4(centre, 1.0);
+    }
+
+    let texel = 1.0 / vec2<f32>(textureDimensions(ldr));
+
+    let luma_nw = luma(sample_ldr(vout.tex_coords + vec2(-1.0, -1.0) * texel));
+    let luma_ne = luma(sample_ldr(vout.tex_coords + vec2(1.0, -1.0) * texel));
+    let luma_sw = luma(sample_ldr(vout.tex_coords + vec2(-1.0, 1.0) * texel));
+    let luma_se = luma(sample_ldr(vout.tex_coords + vec2(1.0, 1.0) * texel));
+    let luma_m = luma(centre);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    var dir = vec2(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let inverse_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * inverse_dir_min, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * texel;
+
+    let colour_a = 0.5 * (
+        sample_ldr(vout.tex_coords + dir * (1.0 / 3.0 - 0.5)) +
+        sample_ldr(vout.tex_coords + dir * (2.0 / 3.0 - 0.5))
+    );
+    let colour_b = colour_a * 0.5 + 0.25 * (
+        sample_ldr(vout.tex_coords + dir * -0.5) +
+        sample_ldr(vout.tex_coords + dir * 0.5)
+    );
+
+    let luma_b = luma(colour_b);
+    if (luma_b < luma_min || luma_b > luma_max) {
+        return vec4(colour_a, 1.0);
+    }
+    return vec4(colour_b, 1.0);
+}
@@ -0,0 +1,24 @@
+// Blends the average weighted blended OIT colour over the target, by the coverage of the transparent meshes
+
+//!include("../includes/fullscreen.wgsl")
+
+//!binding()
+var accumulation: texture_2d<f32>;
+//!binding()
+var accumulation_sampler: sampler;
+
+//!binding()
+var weight: texture_2d<f32>;
+//!binding()
+var weight_sampler: sampler;
+
+@fragment
+fn fs_main(vout: VertexOutput) -> @location(0) vec4<f32> {
+    let accumulated = textureSample(accumulation, accumulation_sampler, vout.tex_coords);
+    let weight = textureSample(weight, weight_sampler, vout.tex_coords).r;
+
+    // accumulated alpha is the revealage
+    let average = accumulated.rgb / clamp(weight, 1e-4, 5e4);
+
+    return vec4(average, 1.0 - accumulated.a);
+}
@@ -0,0 +1,54 @@
+// Applies bloom, exposure, tonemapping and colour grading, writing into a display (sRGB) target
+
+//!include("../includes/fullscreen.wgsl")
+//!include("../includes/tonemapping.wgsl")
+//!include("../includes/colour_encodings.wgsl")
+//!include("../includes/post_processing.wgsl")
+
+//!binding()
+var hdr: texture_2d<f32>;
+//!binding()
+var hdr_sampler: sampler;
+
+//!binding()
+var bloom: texture_2d<f32>;
+//!binding()
+var bloom_sampler: sampler;
+
+//!binding()
+var<uniform> settings: PostProcessing;
+
+//!binding()
+var lut: texture_2d<f32>;
+//!binding()
+var lut_sampler: sampler;
+
+fn colour_grade(colour: vec3<f32>) -> vec3<f32> {
+    let size = f32(textureDimensions(lut).y);
+    let scaled = clamp(colour, vec3(0.0), vec3(1.0)) * (size - 1.0);
+    let slice = floor(scaled.b);
+
+    let uv = vec2((slice * size + scaled.r + 0.5) / (size * size), (scaled.g + 0.5) / size);
+    let next_uv = uv + vec2(1.0 / size, 0.0);
+
+    let graded = textureSampleLevel(lut, lut_sampler, uv, 0.0).rgb;
+    let next_graded = textureSampleLevel(lut, lut_sampler, next_uv, 0.0).rgb;
+
+    return mix(graded, next_graded, scaled.b - slice);
+}
+
+@fragment
+fn fs_main(vout: VertexOutput) -> @location(0) vec4<f32> {
+    var colour = textureSample(hdr, hdr_sampler, vout.tex_coords).rgb;
+    // the bloom passes are skipped without bloom, leaving stale contents
+    if (settings.bloom_intensity > 0.0) {
+        let bloom_colour = textureSample(bloom, bloom_sampler, vout.tex_coords).rgb;
+        colour = mix(colour, bloom_colour, settings.bloom_intensity);
+    }
+
+    colour *= settings.exposure;
+    colour = linear_to_srgb(tonemap(colour, settings.tonemapper));
+    colour = mix(colour, colour_grade(colour), settings.colour_grading);
+
+    return vec4(srgb_to_linear(colour), 1.0);
+}
@@ -6,7 +6,6 @@ struct CameraUniform {
 };
 
 //!include("includes/colour_encodings.wgsl")
-//!include("includes/tonemapping.wgsl")
 
 //!binding()
 var skybox_texture: texture_cube<f32>;
@@ -52,9 +51,7 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     
     var colour = textureSample(skybox_texture, skybox_sampler, in.position).rgb;
 
-    // colour = tonemap(srgb_to_linear(colour));
-    colour = tonemap(colour).rgb;
-    // colour = linear_to_srgb(colour);
+    // colour = srgb_to_linear(colour);
 
     var out: FragmentOutput;
     
@@ -1,4 +1,4 @@
-use crate::{node::{Component, NodeDescriptor}, util::AsAny, renderer::{RenderInput, Renderable, Renderer, UniformBuffer}, resource::Resources, components::Transform};
+use crate::{node::{Component, NodeDescriptor}, util::AsAny, renderer::{RenderInput, Renderable, Renderer, UniformArena}, resource::Resources, components::Transform, engine::Frustum};
 
 #[rustfmt::skip]
 pub const OPENGL_TO_WGPU_MATRIX: glam::Mat4 = glam::Mat4::from_cols_array(&[
@@ -39,6 +39,41 @@ impl Camera {
 
         return OPENGL_TO_WGPU_MATRIX * proj * view;
     }
+
+    pub fn frustum(&self, transform: &Transform, aspect: f32) -> Frustum {
+        Frustum::from_view_projection(self.build_view_projection_matrix(transform, aspect))
+    }
+
+    /// Near and far planes, infinite perspective projections have an infinite far plane.
+    pub(crate) fn depth_range(&self) -> (f32, f32) {
+        match self {
+            Camera::Perspective { znear, zfar, .. } => (*znear, zfar.unwrap_or(f32::INFINITY)),
+            Camera::Orthographic { znear, zfar, .. } => (*znear, *zfar),
+        }
+    }
+
+    /// World space corners of the slice of the view frustum between `near` and `far`.
+    pub(crate) fn frustum_corners(&self, transform: &Transform, aspect: f32, near: f32, far: f32) -> [glam::Vec3; 8] {
+        let half_extents = |distance: f32| match self {
+            Camera::Perspective { fovy, .. } => {
+                let half_height = (fovy / 2.0).tan() * distance;
+                glam::vec2(half_height * aspect, half_height)
+            },
+            Camera::Orthographic { ymag, .. } => glam::vec2(ymag / 2.0 * aspect, ymag / 2.0),
+        };
+
+        let matrix = transform.global_matrix();
+        let mut corners = [glam::Vec3::ZERO; 8];
+
+        for (i, distance) in [near, far].into_iter().enumerate() {
+            let extents = half_extents(distance);
+            for (j, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].into_iter().enumerate() {
+                corners[i * 4 + j] = matrix.transform_point3(glam::vec3(x * extents.x, y * extents.y, -distance));
+            }
+        }
+
+        corners
+    }
 }
 
 impl AsAny for Camera {
@@ -54,15 +89,15 @@ impl Renderable for Camera {
     fn render_inputs(&self, node: &NodeDescriptor, renderer: &Renderer, resources: &mut Resources) -> Vec<RenderInput> {
         let transform = node.get_component::<Transform>().unwrap_or(&Transform::IDENTITY);
 
-        let uniform = UniformBuffer::from_value(
-            renderer, resources,
-            CameraUniform::new(self, transform, renderer.window.size.width as f32 / renderer.window.size.height as f32),
+        let uniform = UniformArena::write(
+            renderer, resources, node.id(), "camera",
+            CameraUniform::new(self, transform, renderer.aspect()),
         );
 
         // vec![RenderInput::new("camera", RenderInputStorage::BindGroup(uniform.bind_group()))]
         // vec![RenderInput::BindGroup("camera".into(), uniform.bind_group())]
         // vec![RenderInput::UniformBuffer("camera".into(), uniform)]
-        vec![RenderInput::BindingResources("camera".into(), vec![uniform.binding_resource()])]
+        vec![RenderInput::BindingResources("camera".into(), vec![uniform])]
     }
 }
 
@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{util::AsAny, engine::UpdateContext, resource::{Resources, Handle, AnimationClip, ChannelValue}, node::{Node, NodeId, Component, NodeDescriptor, NodeScript}, components::{Transform, MorphWeights}};
+
+/// Plays `AnimationClip`s on the `Transform`s and `MorphWeights` of the node's subtree, added by `load_gltf` to scenes with animations.
+pub struct AnimationPlayer {
+    /// Clips that can be played, i.e. the animations of a glTF scene
+    pub clips: Vec<Handle<AnimationClip>>,
+    /// Playback rate, negative plays backwards
+    pub speed: f32,
+    /// Restarts clips when they end, otherwise they hold their last keyframe
+    pub looping: bool,
+    paused: bool,
+    current: Option<PlayingClip>,
+    /// Faded out while `current` fades in
+    previous: Option<PlayingClip>,
+    fade_time: f32,
+    fade_duration: f32,
+    /// Poses of the subtree when the fade started, blended with properties only one of the clips animates
+    fade_from: Option<HashMap<NodeId, Pose>>,
+}
+
+struct PlayingClip {
+    clip: Handle<AnimationClip>,
+    time: f32,
+}
+
+/// Sampled properties of a node's transform and morph weights, `None` if no channel animates them.
+#[derive(Debug, Default, Clone)]
+struct Pose {
+    translation: Option<glam::Vec3>,
+    rotation: Option<glam::Quat>,
+    scale: Option<glam::Vec3>,
+    weights: Option<Vec<f32>>,
+}
+
+impl Pose {
+    /// The node's current transform and morph weights.
+    fn of(node: &Node) -> Pose {
+        let transform = node.get_component::<Transform>();
+
+        Pose {
+            translation: transform.map(|transform| transform.translation),
+            rotation: transform.map(|transform| transform.rotation),
+            scale: transform.map(|transform| transform.scale),
+            weights: node.get_component::<MorphWeights>().map(|morph_weights| morph_weights.weights.clone()),
+        }
+    }
+
+    /// Properties only one of the poses has are blended with those of `rest`, so they don't snap when the fade ends.
+    fn blend(self, other: Pose, rest: &Pose, t: f32) -> Pose {
+        fn blend<T: Clone>(a: Option<T>, b: Option<T>, rest: &Option<T>, interpolate: impl Fn(T, T) -> T) -> Option<T> {
+            if a.is_none() && b.is_none() {
+                return None;
+            }
+
+            match (a.or_else(|| rest.clone()), b.or_else(|| rest.clone())) {
+                (Some(a), Some(b)) => Some(interpolate(a, b)),
+                (a, b) => a.or(b),
+            }
+        }
+
+        Pose {
+            translation: blend(self.translation, other.translation, &rest.translation, |a, b| a.lerp(b, t)),
+            rotation: blend(self.rotation, other.rotation, &rest.rotation, |a, b| a.slerp(b, t)),
+            scale: blend(self.scale, other.scale, &rest.scale, |a, b| a.lerp(b, t)),
+            weights: blend(self.weights, other.weights, &rest.weights, |a, b| a.iter().zip(&b).map(|(a, b)| a + (b - a) * t).collect()),
+        }
+    }
+}
+
+impl AnimationPlayer {
+    pub fn new(clips: Vec<Handle<AnimationClip>>) -> AnimationPlayer {
+        AnimationPlayer {
+            clips,
+            speed: 1.0,
+            looping: true,
+            paused: false,
+            current: None,
+            previous: None,
+            fade_time: 0.0,
+            fade_duration: 0.0,
+            fade_from: None,
+        }
+    }
+
+    /// The first of `clips` named `name`.
+    pub fn clip(&self, resources: &Resources, name: &str) -> Option<Handle<AnimationClip>> {
+        self.clips.iter().find(|clip| clip.get(resources).name.as_deref() == Some(name)).cloned()
+    }
+
+    /// Plays `clip` from the start, replacing the current clip immediately.
+    pub fn play(&mut self, clip: Handle<AnimationClip>) {
+        self.current = Some(PlayingClip { clip, time: 0.0 });
+        self.previous = None;
+        self.paused = false;
+    }
+
+    /// Plays `clip` from the start, blending from the current clip to it over `duration` seconds.
+    pub fn cross_fade(&mut self, clip: Handle<AnimationClip>, duration: f32) {
+        if duration <= 0.0 {
+            return self.play(clip);
+        }
+
+        self.previous = self.current.take();
+        self.current = Some(PlayingClip { clip, time: 0.0 });
+        self.fade_time = 0.0;
+        self.fade_duration = duration;
+        self.fade_from = None;
+        self.paused = false;
+    }
+
+    /// Stops playing, leaving transforms in their current pose.
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.previous = None;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn current_clip(&self) -> Option<&Handle<AnimationClip>> {
+        self.current.as_ref().map(|current| &current.clip)
+    }
+
+    /// Seconds into the current clip.
+    pub fn time(&self) -> Option<f32> {
+        self.current.as_ref().map(|current| current.time)
+    }
+
+    pub fn seek(&mut self, time: f32) {
+        if let Some(current) = &mut self.current {
+            current.time = time;
+        }
+    }
+
+    fn advance(&mut self, resources: &Resources, delta_time: f32) {
+        if self.paused {
+            return;
+        }
+
+        for playing in self.current.iter_mut().chain(&mut self.previous) {
+            let duration = playing.clip.get(resources).duration;
+            let time = playing.time + delta_time * self.speed;
+
+            playing.time = if self.looping && duration > 0.0 {
+                time.rem_euclid(duration)
+            } else {
+                time.clamp(0.0, duration)
+            };
+        }
+
+        if self.previous.is_some() {
+            self.fade_time += delta_time;
+            if self.fade_time >= self.fade_duration {
+                self.previous = None;
+                self.fade_from = None;
+            }
+        }
+    }
+
+    fn sample(&self, resources: &Resources) -> HashMap<NodeId, Pose> {
+        fn sample_clip(playing: &PlayingClip, resources: &Resources) -> HashMap<NodeId, Pose> {
+            let mut poses: HashMap<NodeId, Pose> = hashmap!{};
+
+            for channel in &playing.clip.get(resources).channels {
+                let pose = poses.entry(channel.target).or_default();
+                match channel.sample(playing.time) {
+                    ChannelValue::Translation(translation) => pose.translation = Some(translation),
+                    ChannelValue::Rotation(rotation) => pose.rotation = Some(rotation),
+                    ChannelValue::Scale(scale) => pose.scale = Some(scale),
+                    ChannelValue::Weights(weights) => pose.weights = Some(weights),
+                }
+            }
+
+            poses
+        }
+
+        let Some(current) = &self.current else { return hashmap!{} };
+        let mut poses = sample_clip(current, resources);
+
+        if let Some(previous) = &self.previous {
+            let t = self.fade_time / self.fade_duration;
+
+            let mut previous_poses = sample_clip(previous, resources);
+            // nodes only one of the clips animates are blended too
+            let targets: HashSet<NodeId> = poses.keys().chain(previous_poses.keys()).copied().collect();
+            let no_rest = Pose::default();
+
+            for target in targets {
+                let rest = self.fade_from.as_ref().and_then(|fade_from| fade_from.get(&target)).unwrap_or(&no_rest);
+                let previous_pose = previous_poses.remove(&target).unwrap_or_default();
+                let pose = poses.entry(target).or_default();
+                *pose = previous_pose.blend(std::mem::take(pose), rest, t);
+            }
+        }
+
+        poses
+    }
+}
+
+impl AsAny for AnimationPlayer {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for AnimationPlayer {}
+
+/// Advances every `AnimationPlayer` and poses its subtree, before transforms are propagated.
+pub(crate) struct AnimationScript;
+
+impl AnimationScript {
+    fn update_players(&mut self, node: &mut NodeDescriptor, delta_time: f32, resources: &Resources) {
+        fn apply(node: &mut Node, poses: &HashMap<NodeId, Pose>) {
+            if let Some(pose) = poses.get(&node.id()) {
+                if let Some(transform) = node.get_component_mut::<Transform>() {
+                    if let Some(translation) = pose.translation {
+                        transform.translation = translation;
+                    }
+                    if let Some(rotation) = pose.rotation {
+                        transform.rotation = rotation;
+                    }
+                    if let Some(scale) = pose.scale {
+                        transform.scale = scale;
+                    }
+                }
+
+                if let (Some(weights), Some(morph_weights)) = (&pose.weights, node.get_component_mut::<MorphWeights>()) {
+                    morph_weights.weights.clone_from(weights);
+                }
+            }
+
+            for child in &mut node.desc.children {
+                apply(child, poses);
+            }
+        }
+
+        fn capture(node: &Node, poses: &mut HashMap<NodeId, Pose>) {
+            poses.insert(node.id(), Pose::of(node));
+
+            for child in &node.desc.children {
+                capture(child, poses);
+            }
+        }
+
+        for child in &mut node.children {
+            child.traverse_if_mut(&mut |node| node.has_component::<AnimationPlayer>(), &mut |node| {
+                // the subtree is captured before the first pose of a fade is applied
+                let player = node.get_component::<AnimationPlayer>().unwrap();
+                if player.previous.is_some() && player.fade_from.is_none() {
+                    let mut fade_from = hashmap!{};
+                    capture(node, &mut fade_from);
+                    node.get_component_mut::<AnimationPlayer>().unwrap().fade_from = Some(fade_from);
+                }
+
+                let player = node.get_component_mut::<AnimationPlayer>().unwrap();
+                player.advance(resources, delta_time);
+                let poses = player.sample(resources);
+
+                apply(node, &poses);
+            });
+        }
+    }
+}
+
+impl NodeScript for AnimationScript {
+    fn update(&mut self, node: &mut NodeDescriptor, context: &UpdateContext, resources: &mut Resources) {
+        self.update_players(node, context.delta_time, resources);
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{util::AsAny, renderer::{Renderable, RenderInput, Renderer}, resource::{Resources, Handle, Model}, node::{Component, NodeDescriptor}};
+
+/// Draws a model once per transform, relative to the node's `Transform`. Instances are batched into one draw per mesh,
+/// which requires a shader with the `InstancedModel` vertex type (i.e. `pipelines/main.ron`), other shaders draw the model once.
+pub struct InstancedModel {
+    pub model: Handle<Model>,
+    pub instances: Vec<glam::Mat4>,
+}
+
+impl InstancedModel {
+    pub fn new(model: Handle<Model>, instances: Vec<glam::Mat4>) -> InstancedModel {
+        InstancedModel {
+            model,
+            instances,
+        }
+    }
+}
+
+impl AsAny for InstancedModel {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for InstancedModel {
+    fn as_renderable(&self) -> Option<&dyn Renderable> { Some(self) }
+}
+
+impl Renderable for InstancedModel {
+    fn render_inputs(&self, node: &NodeDescriptor, renderer: &Renderer, resources: &mut Resources) -> Vec<RenderInput> {
+        let mut inputs = Renderable::render_inputs(&self.model, node, renderer, resources);
+        inputs.push(RenderInput::Instances(self.instances.clone()));
+        inputs
+    }
+}
@@ -1,12 +1,34 @@
-use crate::{engine::Rgba, util::AsAny, renderer::{Renderable, RenderInput, Renderer, SceneInputItem}, resource::Resources, node::{Component, NodeDescriptor}};
+use crate::{engine::Rgba, util::AsAny, renderer::{Renderable, RenderInput, Renderer, SceneInputItem, ShadowCaster, ShadowCasters}, resource::Resources, node::{Component, NodeDescriptor}};
 
-use super::Transform;
+use super::{Transform, DirectionalShadows, PointShadows};
 
-pub struct PointLight(pub Rgba);
+pub struct PointLight {
+    /// Alpha is intensity
+    pub colour: Rgba,
+    /// Distance at which the light's influence smoothly reaches zero, unbounded if `None`
+    pub range: Option<f32>,
+}
 
 impl PointLight {
     pub fn new(colour: Rgba) -> PointLight {
-        PointLight(colour)
+        PointLight {
+            colour,
+            range: None,
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> PointLight {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> PointLight {
+        self.colour.a = intensity;
+        self
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.colour.a
     }
 }
 
@@ -20,10 +42,17 @@ impl Component for PointLight {
 }
 
 impl Renderable for PointLight {
-    fn render_inputs(&self, node: &NodeDescriptor, _renderer: &Renderer, _resources: &mut Resources) -> Vec<RenderInput> {
+    fn render_inputs(&self, node: &NodeDescriptor, _renderer: &Renderer, resources: &mut Resources) -> Vec<RenderInput> {
         let transform = node.get_component::<Transform>().expect("Attempted to render point light without missing transform.");
 
-        let uniform = PointLightUniform::new(transform, self.0);
+        let mut uniform = PointLightUniform::new(transform, self.colour, self.range);
+
+        if let Some(settings) = node.get_component::<PointShadows>() {
+            uniform.shadow = ShadowCasters::reserve(resources, ShadowCaster::Point {
+                position: uniform.position.into(),
+                settings: *settings,
+            });
+        }
         
         vec![RenderInput::SceneInput("point_lights".into(), SceneInputItem::new(uniform))]
     }
@@ -33,16 +62,113 @@ impl Renderable for PointLight {
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct PointLightUniform {
     position: [f32; 3],
-    _padding0: u32,
+    /// Index of the first shadow map view, -1 without shadows
+    shadow: i32,
     colour: [f32; 4],
+    /// 0 if unbounded
+    range: f32,
+    _padding0: [u32; 3],
 }
 
 impl PointLightUniform {
-    pub fn new(transform: &Transform, colour: Rgba) -> PointLightUniform {
+    pub fn new(transform: &Transform, colour: Rgba, range: Option<f32>) -> PointLightUniform {
         PointLightUniform {
             position: transform.global_matrix().to_scale_rotation_translation().2.into(),
-            _padding0: 0,
+            shadow: -1,
             colour: colour.into(),
+            range: range.unwrap_or(0.0),
+            _padding0: [0; 3],
+        }
+    }
+}
+
+/// Like `DirectionalLight`, points directly downwards without a rotation. The cone angles are measured from the centre of the cone,
+/// intensity falls off between the inner and outer angle.
+pub struct SpotLight {
+    /// Alpha is intensity
+    pub colour: Rgba,
+    pub inner_cone_angle: f32,
+    pub outer_cone_angle: f32,
+    /// Distance at which the light's influence smoothly reaches zero, unbounded if `None`
+    pub range: Option<f32>,
+}
+
+impl SpotLight {
+    pub fn new(colour: Rgba, inner_cone_angle: f32, outer_cone_angle: f32) -> SpotLight {
+        SpotLight {
+            colour,
+            inner_cone_angle,
+            outer_cone_angle,
+            range: None,
+        }
+    }
+
+    pub fn with_range(mut self, range: f32) -> SpotLight {
+        self.range = Some(range);
+        self
+    }
+
+    pub fn with_intensity(mut self, intensity: f32) -> SpotLight {
+        self.colour.a = intensity;
+        self
+    }
+
+    pub fn intensity(&self) -> f32 {
+        self.colour.a
+    }
+}
+
+impl AsAny for SpotLight {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for SpotLight {
+    fn as_renderable(&self) -> Option<&dyn Renderable> { Some(self) }
+}
+
+impl Renderable for SpotLight {
+    fn render_inputs(&self, node: &NodeDescriptor, _renderer: &Renderer, _resources: &mut Resources) -> Vec<RenderInput> {
+        let transform = node.get_component::<Transform>().expect("Attempted to render spot light without missing transform.");
+
+        let uniform = SpotLightUniform::new(transform, self);
+        
+        vec![RenderInput::SceneInput("spot_lights".into(), SceneInputItem::new(uniform))]
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct SpotLightUniform {
+    position: [f32; 3],
+    /// 0 if unbounded
+    range: f32,
+    direction: [f32; 3],
+    angle_scale: f32,
+    colour: [f32; 4],
+    angle_offset: f32,
+    _padding0: [u32; 3],
+}
+
+impl SpotLightUniform {
+    pub fn new(transform: &Transform, light: &SpotLight) -> SpotLightUniform {
+        let (_, rotation, translation) = transform.global_matrix().to_scale_rotation_translation();
+        let direction = rotation * glam::Vec3::Y;
+
+        // cone falloff as recommended by KHR_lights_punctual, linear in the cosine of the angle
+        let cos_inner = light.inner_cone_angle.cos();
+        let cos_outer = light.outer_cone_angle.cos();
+        let angle_scale = 1.0 / (cos_inner - cos_outer).max(0.001);
+        let angle_offset = -cos_outer * angle_scale;
+
+        SpotLightUniform {
+            position: translation.into(),
+            range: light.range.unwrap_or(0.0),
+            direction: direction.into(),
+            angle_scale,
+            colour: light.colour.into(),
+            angle_offset,
+            _padding0: [0; 3],
         }
     }
 }
@@ -66,10 +192,17 @@ impl Component for DirectionalLight {
 }
 
 impl Renderable for DirectionalLight {
-    fn render_inputs(&self, node: &NodeDescriptor, _renderer: &Renderer, _resources: &mut Resources) -> Vec<RenderInput> {
+    fn render_inputs(&self, node: &NodeDescriptor, _renderer: &Renderer, resources: &mut Resources) -> Vec<RenderInput> {
         let transform = node.get_component::<Transform>().expect("Attempted to render point light without missing transform.");
 
-        let uniform = DirectionalLightUniform::new(transform, self.0);
+        let mut uniform = DirectionalLightUniform::new(transform, self.0);
+
+        if let Some(settings) = node.get_component::<DirectionalShadows>() {
+            uniform.shadow = ShadowCasters::reserve(resources, ShadowCaster::Directional {
+                direction: uniform.direction.into(),
+                settings: *settings,
+            });
+        }
         
         vec![RenderInput::SceneInput("directional_lights".into(), SceneInputItem::new(uniform))]
     }
@@ -79,7 +212,8 @@ impl Renderable for DirectionalLight {
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
 pub(crate) struct DirectionalLightUniform {
     direction: [f32; 3],
-    _padding0: u32,
+    /// Index of the first shadow map view, -1 without shadows
+    shadow: i32,
     colour: [f32; 4],
 }
 
@@ -89,7 +223,7 @@ impl DirectionalLightUniform {
         let direction = rotation * glam::Vec3::Y;
         DirectionalLightUniform {
             direction: direction.into(),
-            _padding0: 0,
+            shadow: -1,
             colour: colour.into(),
         }
     }
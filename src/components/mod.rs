@@ -1,5 +1,19 @@
 mod transform;
 mod light;
+mod shadow;
+mod post_processing;
+mod sorting;
+mod instancing;
+mod skinning;
+mod morphing;
+mod animation;
 
 pub use transform::*;
 pub use light::*;
+pub use shadow::*;
+pub use post_processing::*;
+pub use sorting::*;
+pub use instancing::*;
+pub use skinning::*;
+pub use morphing::*;
+pub use animation::*;
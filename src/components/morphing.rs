@@ -0,0 +1,57 @@
+use crate::{util::AsAny, resource::{Resources, MorphTargets}, node::{Component, NodeDescriptor}, renderer::{Renderable, RenderInput, Renderer, UniformArena}};
+
+/// Weight of each morph target of the node's meshes, which requires a shader with the `MeshMorphTargets` input (i.e. `pipelines/morph.ron`).
+/// Imported from glTF mesh weights, and driven by animations with weight channels.
+#[derive(Debug, Clone, Default)]
+pub struct MorphWeights {
+    pub weights: Vec<f32>,
+}
+
+impl MorphWeights {
+    pub fn new(weights: Vec<f32>) -> MorphWeights {
+        MorphWeights {
+            weights,
+        }
+    }
+}
+
+impl AsAny for MorphWeights {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for MorphWeights {
+    fn as_renderable(&self) -> Option<&dyn Renderable> { Some(self) }
+}
+
+impl Renderable for MorphWeights {
+    fn render_inputs(&self, node: &NodeDescriptor, renderer: &Renderer, resources: &mut Resources) -> Vec<RenderInput> {
+        let uniform = UniformArena::write(
+            renderer, resources, node.id(), "morph_weights",
+            MorphWeightsUniform::new(self),
+        );
+
+        vec![RenderInput::BindingResources("morph_weights".into(), vec![uniform])]
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MorphWeightsUniform {
+    /// Packed in fours, uniform array elements are 16 byte aligned
+    weights: [[f32; 4]; MorphTargets::MAX_TARGETS / 4],
+}
+
+impl MorphWeightsUniform {
+    pub fn new(morph_weights: &MorphWeights) -> MorphWeightsUniform {
+        let mut weights = [[0.0; 4]; MorphTargets::MAX_TARGETS / 4];
+
+        for (i, weight) in morph_weights.weights.iter().take(MorphTargets::MAX_TARGETS).enumerate() {
+            weights[i / 4][i % 4] = *weight;
+        }
+
+        MorphWeightsUniform {
+            weights,
+        }
+    }
+}
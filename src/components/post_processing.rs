@@ -0,0 +1,120 @@
+use crate::{util::AsAny, node::Component};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tonemapper {
+    /// Clamps, for debugging
+    None,
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve
+    Aces,
+    AgX,
+}
+
+/// Post-processing settings of the `Camera` on the same node, used by the passes of the default render graph.
+/// Cameras without it use `PostProcessing::default()`.
+///
+/// The colour grading LUT is the global `Sampler` resource `tritium::colour_grading_lut`, see `Texture::identity_lut` for its layout.
+/// It maps tonemapped sRGB colours to sRGB colours.
+#[derive(Debug, Clone, Copy)]
+pub struct PostProcessing {
+    /// Exposure compensation in stops.
+    pub exposure: f32,
+    pub tonemapper: Tonemapper,
+    /// Fraction of the image replaced with bloom, 0 to disable it.
+    pub bloom_intensity: f32,
+    /// Brightness above which pixels bloom, 0 for every pixel.
+    pub bloom_threshold: f32,
+    /// Softens the threshold, as a fraction of it.
+    pub bloom_knee: f32,
+    /// Blends between the tonemapped image (0) and the colour graded image (1).
+    pub colour_grading: f32,
+    /// Smooths edges after tonemapping, cheaper than MSAA but blurs some detail.
+    pub fxaa: bool,
+}
+
+impl PostProcessing {
+    pub fn new(tonemapper: Tonemapper) -> PostProcessing {
+        PostProcessing {
+            tonemapper,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_exposure(mut self, exposure: f32) -> PostProcessing {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn with_bloom(mut self, intensity: f32, threshold: f32) -> PostProcessing {
+        self.bloom_intensity = intensity;
+        self.bloom_threshold = threshold;
+        self
+    }
+
+    pub fn with_colour_grading(mut self, colour_grading: f32) -> PostProcessing {
+        self.colour_grading = colour_grading;
+        self
+    }
+
+    pub fn with_fxaa(mut self) -> PostProcessing {
+        self.fxaa = true;
+        self
+    }
+}
+
+impl Default for PostProcessing {
+    fn default() -> PostProcessing {
+        PostProcessing {
+            exposure: 0.0,
+            tonemapper: Tonemapper::Reinhard,
+            bloom_intensity: 0.04,
+            bloom_threshold: 0.0,
+            bloom_knee: 0.5,
+            colour_grading: 1.0,
+            fxaa: false,
+        }
+    }
+}
+
+impl AsAny for PostProcessing {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for PostProcessing {}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct PostProcessingUniform {
+    /// Linear multiplier
+    exposure: f32,
+    tonemapper: u32,
+    bloom_intensity: f32,
+    bloom_threshold: f32,
+    bloom_knee: f32,
+    colour_grading: f32,
+    fxaa: u32,
+    _padding0: u32,
+}
+
+impl PostProcessingUniform {
+    pub const KEY: &'static str = "tritium::post_processing";
+
+    pub fn new(settings: &PostProcessing) -> PostProcessingUniform {
+        PostProcessingUniform {
+            exposure: settings.exposure.exp2(),
+            tonemapper: match settings.tonemapper {
+                Tonemapper::None => 0,
+                Tonemapper::Reinhard => 1,
+                Tonemapper::Aces => 2,
+                Tonemapper::AgX => 3,
+            },
+            bloom_intensity: settings.bloom_intensity,
+            bloom_threshold: settings.bloom_threshold,
+            bloom_knee: settings.bloom_threshold * settings.bloom_knee,
+            colour_grading: settings.colour_grading,
+            fxaa: settings.fxaa as u32,
+            _padding0: 0,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use crate::{util::AsAny, node::Component};
+
+/// Enables cascaded shadow maps for a `DirectionalLight` on the same node.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectionalShadows {
+    /// Number of cascades the view frustum is split into, at most `DirectionalShadows::MAX_CASCADES`.
+    pub cascades: u32,
+    /// Distance from the camera shadows are rendered up to.
+    pub max_distance: f32,
+    /// Blends cascade splits between uniform (0) and logarithmic (1).
+    pub split_lambda: f32,
+    /// Constant depth bias, in shadow map depth units.
+    pub bias: f32,
+    /// Offset along the surface normal, in shadow map texels.
+    pub normal_bias: f32,
+    /// Percentage closer filtering radius in texels, 0 for hard shadows.
+    pub pcf_radius: f32,
+}
+
+impl DirectionalShadows {
+    pub const MAX_CASCADES: u32 = 4;
+
+    pub fn new(cascades: u32, max_distance: f32) -> DirectionalShadows {
+        DirectionalShadows {
+            cascades: cascades.clamp(1, DirectionalShadows::MAX_CASCADES),
+            max_distance,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_bias(mut self, bias: f32, normal_bias: f32) -> DirectionalShadows {
+        self.bias = bias;
+        self.normal_bias = normal_bias;
+        self
+    }
+
+    pub fn with_pcf_radius(mut self, pcf_radius: f32) -> DirectionalShadows {
+        self.pcf_radius = pcf_radius;
+        self
+    }
+}
+
+impl Default for DirectionalShadows {
+    fn default() -> DirectionalShadows {
+        DirectionalShadows {
+            cascades: 4,
+            max_distance: 50.0,
+            split_lambda: 0.75,
+            bias: 0.0005,
+            normal_bias: 1.5,
+            pcf_radius: 1.0,
+        }
+    }
+}
+
+impl AsAny for DirectionalShadows {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for DirectionalShadows {}
+
+/// Enables a cube shadow map for a `PointLight` on the same node.
+#[derive(Debug, Clone, Copy)]
+pub struct PointShadows {
+    pub znear: f32,
+    pub zfar: f32,
+    /// Constant depth bias, in shadow map depth units.
+    pub bias: f32,
+    /// Offset along the surface normal, in shadow map texels.
+    pub normal_bias: f32,
+    /// Percentage closer filtering radius in texels, 0 for hard shadows.
+    pub pcf_radius: f32,
+}
+
+impl PointShadows {
+    pub fn new(znear: f32, zfar: f32) -> PointShadows {
+        PointShadows {
+            znear,
+            zfar,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_bias(mut self, bias: f32, normal_bias: f32) -> PointShadows {
+        self.bias = bias;
+        self.normal_bias = normal_bias;
+        self
+    }
+
+    pub fn with_pcf_radius(mut self, pcf_radius: f32) -> PointShadows {
+        self.pcf_radius = pcf_radius;
+        self
+    }
+}
+
+impl Default for PointShadows {
+    fn default() -> PointShadows {
+        PointShadows {
+            znear: 0.1,
+            zfar: 50.0,
+            bias: 0.0002,
+            normal_bias: 1.5,
+            pcf_radius: 1.0,
+        }
+    }
+}
+
+impl AsAny for PointShadows {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for PointShadows {}
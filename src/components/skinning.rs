@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use crate::{util::AsAny, engine::UpdateContext, resource::Resources, node::{Node, NodeId, Component, NodeDescriptor, NodeScript}, renderer::{Renderable, RenderInput, Renderer, UniformArena}, components::Transform};
+
+/// Joints deforming the node's skinned meshes, which requires a shader with the `SkinnedModel` vertex type (i.e. `pipelines/skinned.ron`).
+/// Imported from glTF skins, joint matrices are computed from the joints' transforms each frame.
+pub struct Skeleton {
+    pub joints: Vec<NodeId>,
+    /// Transforms the mesh into the local space of each joint in its bind pose.
+    pub inverse_bind_matrices: Vec<glam::Mat4>,
+    joint_matrices: Vec<glam::Mat4>,
+}
+
+impl Skeleton {
+    /// Joints beyond this are ignored, see `SkeletonUniform`.
+    pub const MAX_JOINTS: usize = 128;
+
+    /// Missing inverse bind matrices are the identity.
+    pub fn new(joints: Vec<NodeId>, mut inverse_bind_matrices: Vec<glam::Mat4>) -> Skeleton {
+        if joints.len() > Skeleton::MAX_JOINTS {
+            log::warn!("Skeleton has {} joints, only the first {} are used.", joints.len(), Skeleton::MAX_JOINTS);
+        }
+
+        inverse_bind_matrices.resize(joints.len(), glam::Mat4::IDENTITY);
+
+        Skeleton {
+            joint_matrices: vec![glam::Mat4::IDENTITY; joints.len()],
+            joints,
+            inverse_bind_matrices,
+        }
+    }
+
+    /// Skinning matrix of each joint, relative to the node. Identity in the bind pose.
+    pub fn joint_matrices(&self) -> &[glam::Mat4] {
+        &self.joint_matrices
+    }
+
+    fn update_joint_matrices(&mut self, node_matrix: glam::Mat4, global_matrices: &HashMap<NodeId, glam::Mat4>) {
+        let inverse_node_matrix = node_matrix.inverse();
+
+        for ((joint, inverse_bind_matrix), joint_matrix) in self.joints.iter().zip(&self.inverse_bind_matrices).zip(&mut self.joint_matrices) {
+            // joints outside the scene stay in their bind pose
+            *joint_matrix = match global_matrices.get(joint) {
+                Some(global_matrix) => inverse_node_matrix * *global_matrix * *inverse_bind_matrix,
+                None => glam::Mat4::IDENTITY,
+            };
+        }
+    }
+}
+
+impl AsAny for Skeleton {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for Skeleton {
+    fn as_renderable(&self) -> Option<&dyn Renderable> { Some(self) }
+}
+
+impl Renderable for Skeleton {
+    fn render_inputs(&self, node: &NodeDescriptor, renderer: &Renderer, resources: &mut Resources) -> Vec<RenderInput> {
+        let uniform = UniformArena::write(
+            renderer, resources, node.id(), "joints",
+            SkeletonUniform::new(self),
+        );
+
+        vec![RenderInput::BindingResources("joints".into(), vec![uniform])]
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkeletonUniform {
+    joint_matrices: [[[f32; 4]; 4]; Skeleton::MAX_JOINTS],
+}
+
+impl SkeletonUniform {
+    pub fn new(skeleton: &Skeleton) -> SkeletonUniform {
+        let mut joint_matrices = [glam::Mat4::IDENTITY.to_cols_array_2d(); Skeleton::MAX_JOINTS];
+
+        for (uniform, matrix) in joint_matrices.iter_mut().zip(skeleton.joint_matrices()) {
+            *uniform = matrix.to_cols_array_2d();
+        }
+
+        SkeletonUniform {
+            joint_matrices,
+        }
+    }
+}
+
+/// Updates the joint matrices of every `Skeleton`, after `TransformPropagationScript`.
+pub(crate) struct SkeletonUpdateScript;
+
+impl SkeletonUpdateScript {
+    fn update_skeletons(&mut self, node: &mut NodeDescriptor) {
+        fn collect(node: &Node, global_matrices: &mut HashMap<NodeId, glam::Mat4>) {
+            if let Some(transform) = node.get_component::<Transform>() {
+                global_matrices.insert(node.id(), transform.global_matrix());
+            }
+
+            for child in &node.desc.children {
+                collect(child, global_matrices);
+            }
+        }
+
+        let mut global_matrices = hashmap!{};
+
+        for child in &node.children {
+            collect(child, &mut global_matrices);
+        }
+
+        for child in &mut node.children {
+            child.traverse_if_mut(&mut |node| node.has_component::<Skeleton>(), &mut |node| {
+                let node_matrix = node.get_component::<Transform>().map(|transform| transform.global_matrix()).unwrap_or(glam::Mat4::IDENTITY);
+                node.get_component_mut::<Skeleton>().unwrap().update_joint_matrices(node_matrix, &global_matrices);
+            });
+        }
+    }
+}
+
+impl NodeScript for SkeletonUpdateScript {
+    fn post_update(&mut self, node: &mut NodeDescriptor, _context: &UpdateContext, _resources: &mut Resources) {
+        self.update_skeletons(node);
+    }
+
+    fn post_tick(&mut self, node: &mut NodeDescriptor, _context: &UpdateContext, _resources: &mut Resources) {
+        self.update_skeletons(node);
+    }
+}
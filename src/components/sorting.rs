@@ -0,0 +1,13 @@
+use crate::{util::AsAny, node::Component};
+
+/// Offsets the view depth used to sort the meshes of the node, in world units.
+/// Positive values sort the node as if it were further from the camera, so transparent meshes are drawn behind others at the same depth.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SortBias(pub f32);
+
+impl AsAny for SortBias {
+    fn as_any(&self) -> &dyn std::any::Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any { self }
+}
+
+impl Component for SortBias {}
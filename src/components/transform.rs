@@ -1,4 +1,4 @@
-use crate::{util::AsAny, engine::UpdateContext, resource::Resources, node::{Node, Component, NodeDescriptor, NodeScript}, renderer::{Renderable, RenderInput, Renderer, UniformBuffer}};
+use crate::{util::AsAny, engine::UpdateContext, resource::Resources, node::{Node, Component, NodeDescriptor, NodeScript}, renderer::{Renderable, RenderInput, Renderer, UniformArena}};
 
 #[derive(Debug)]
 pub struct Transform {
@@ -79,16 +79,16 @@ impl Component for Transform {
 }
 
 impl Renderable for Transform {
-    fn render_inputs(&self, _node: &NodeDescriptor, renderer: &Renderer, resources: &mut Resources) -> Vec<RenderInput> {
-        let uniform = UniformBuffer::from_value(
-            renderer, resources,
+    fn render_inputs(&self, node: &NodeDescriptor, renderer: &Renderer, resources: &mut Resources) -> Vec<RenderInput> {
+        let uniform = UniformArena::write(
+            renderer, resources, node.id(), "transform",
             TransformUniform::new(self),
         );
 
         // vec![RenderInput::new("transform", RenderInputStorage::BindGroup(uniform.bind_group()))]
         // vec![RenderInput::BindGroup("transform".into(), uniform)]
         // vec![RenderInput::UniformBuffer("transform".into(), uniform)]
-        vec![RenderInput::BindingResources("transform".into(), vec![uniform.binding_resource()])]
+        vec![RenderInput::BindingResources("transform".into(), vec![uniform])]
     }
 }
 
@@ -0,0 +1,94 @@
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: glam::Vec3,
+    pub max: glam::Vec3,
+}
+
+impl Aabb {
+    /// Contains nothing, the identity of `Aabb::union`.
+    pub const EMPTY: Aabb = Aabb {
+        min: glam::Vec3::splat(f32::INFINITY),
+        max: glam::Vec3::splat(f32::NEG_INFINITY),
+    };
+
+    pub fn new(min: glam::Vec3, max: glam::Vec3) -> Aabb {
+        Aabb {
+            min,
+            max,
+        }
+    }
+
+    pub fn from_points<I: IntoIterator<Item = glam::Vec3>>(points: I) -> Aabb {
+        points.into_iter().fold(Aabb::EMPTY, |aabb, point| Aabb::new(aabb.min.min(point), aabb.max.max(point)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.cmpgt(self.max).any()
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn centre(&self) -> glam::Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> glam::Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Bounds of the transformed box, see "Transforming Axis-Aligned Bounding Boxes" (Arvo, Graphics Gems).
+    pub fn transform(&self, matrix: glam::Mat4) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let centre = matrix.transform_point3(self.centre());
+        let half_extents = self.half_extents();
+        let half_extents = matrix.x_axis.truncate().abs() * half_extents.x
+            + matrix.y_axis.truncate().abs() * half_extents.y
+            + matrix.z_axis.truncate().abs() * half_extents.z;
+
+        Aabb::new(centre - half_extents, centre + half_extents)
+    }
+}
+
+/// Planes of a view frustum, pointing inwards.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near, far. `xyz` is the normal, `w` the distance.
+    pub planes: [glam::Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a view projection matrix with a 0 to 1 depth range, see "Fast Extraction of Viewing Frustum Planes from the World-View-Projection Matrix" (Gribb & Hartmann).
+    pub fn from_view_projection(matrix: glam::Mat4) -> Frustum {
+        let (x, y, z, w) = (matrix.row(0), matrix.row(1), matrix.row(2), matrix.row(3));
+
+        let planes = [w + x, w - x, w + y, w - y, z, w - z].map(|plane| {
+            // the far plane of infinite projections has no normal
+            let length = plane.truncate().length();
+            if length > f32::EPSILON { plane / length } else { plane }
+        });
+
+        Frustum {
+            planes,
+        }
+    }
+
+    /// Conservative, boxes near the corners of the frustum may intersect while outside of it.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        if aabb.is_empty() {
+            return false;
+        }
+
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the normal
+            let normal = plane.truncate();
+            let corner = glam::Vec3::select(normal.cmpge(glam::Vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+}
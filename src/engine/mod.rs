@@ -1,8 +1,10 @@
 mod colour;
+mod bounds;
 
 pub use colour::*;
+pub use bounds::*;
 
-use crate::{resource::Handle, input::{KeyboardManager, MouseManager}};
+use crate::{resource::Handle, input::{KeyboardManager, MouseManager}, renderer::FrameCapture};
 
 pub struct FrameCounter {
     pub current_frame: usize,
@@ -34,4 +36,5 @@ pub struct UpdateContext {
     pub delta_time: f32,
     pub keyboard: Handle<KeyboardManager>,
     pub mouse: Handle<MouseManager>,
+    pub capture: Handle<FrameCapture>,
 }
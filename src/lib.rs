@@ -1,11 +1,11 @@
 #[macro_use] extern crate maplit;
 
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, path::Path, time::Duration};
 
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
-    window::{WindowBuilder, Window},
+    window::WindowBuilder,
 };
 
 use crate::renderer::SceneInputItem;
@@ -19,18 +19,26 @@ pub mod engine;
 pub mod camera;
 pub mod components;
 
-const CLEAR_COLOUR: wgpu::Color = wgpu::Color {
-    r: 0.1,
-    g: 0.05,
-    b: 0.15,
-    a: 1.0,
-};
-
-#[derive(Default, Debug, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 struct VideoConfig {
     vsync: bool,
+    /// MSAA samples, 1 disables MSAA
+    msaa_samples: u32,
+}
+
+impl Default for VideoConfig {
+    fn default() -> VideoConfig {
+        VideoConfig {
+            vsync: false,
+            msaa_samples: 4,
+        }
+    }
 }
 
+/// Shader, vertex buffer, index buffer, material and number of indices of a batch of instances, and its node if it can't be batched with other nodes.
+type BatchKey = (resource::HandleId, resource::HandleId, resource::HandleId, Option<resource::HandleId>, u32, Option<node::NodeId>);
+
 struct EngineState {
     renderer: renderer::Renderer,
     global_root: node::Node,
@@ -38,6 +46,20 @@ struct EngineState {
     tick_keyboard_manager: resource::Handle<input::KeyboardManager>,
     mouse_manager: resource::Handle<input::MouseManager>,
     tick_mouse_manager: resource::Handle<input::MouseManager>,
+    frame_capture: resource::Handle<renderer::FrameCapture>,
+    shadow_maps: renderer::ShadowMaps,
+    render_graph: renderer::RenderGraph,
+    /// Settings of the current camera, written to the post processing uniform and deciding which graph passes run
+    post_processing: components::PostProcessing,
+    /// Buffers and bind groups reused between frames
+    scene_buffers: HashMap<String, renderer::StorageBuffer>,
+    /// Weights of meshes with morph targets on nodes without `MorphWeights`
+    empty_morph_weights: renderer::UniformBuffer,
+    instance_buffers: HashMap<BatchKey, renderer::InstanceBuffer>,
+    bind_groups: renderer::BindGroupCache,
+    /// Window frames being copied back, and saved on other threads
+    pending_captures: Vec<renderer::PendingCapture>,
+    saving_captures: Vec<std::thread::JoinHandle<()>>,
     frame_counter: engine::FrameCounter,
     resources: resource::Resources,
     ticks_per_second: u32,
@@ -45,19 +67,42 @@ struct EngineState {
 }
 
 impl EngineState {
-    async fn new<F: FnMut(&renderer::Renderer, &mut resource::Resources) -> node::Node>(window: Window, mut scene_builder: F) -> EngineState {
-        let video_config: VideoConfig = confy::load("wgpu-game-engine", Some("video")).unwrap();
-
-        let renderer = renderer::Renderer::new(window, video_config.vsync).await;
+    fn new<F: FnMut(&renderer::Renderer, &mut resource::Resources) -> node::Node>(renderer: renderer::Renderer, mut scene_builder: F) -> EngineState {
         let mut resources = resource::Resources::new();
 
+        // Environment independent, shared by every shader using specular image based lighting
+        let brdf_lut = resource::Texture::brdf_lut(&renderer, &mut resources);
+        let brdf_lut = resources.store(brdf_lut);
+        let brdf_lut = resource::Sampler::new(&renderer, &mut resources, brdf_lut, wgpu::AddressMode::ClampToEdge, wgpu::AddressMode::ClampToEdge, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest);
+        resources.set_global("tritium::brdf_lut", brdf_lut);
+
+        // Lights with shadows enabled reserve shadow map views while being extracted
+        resources.set_engine_global(renderer::ShadowCasters::KEY, renderer::ShadowCasters::new());
+        // Node uniforms, i.e. transforms and cameras
+        resources.set_engine_global(renderer::UniformArena::KEY, renderer::UniformArena::new(&renderer));
+        let shadow_maps = renderer::ShadowMaps::new(&renderer, &mut resources, renderer::ShadowMaps::DEFAULT_RESOLUTION);
+        // Written each frame from the current camera's settings
+        let post_processing = renderer::UniformBuffer::from_value(&renderer, &mut resources, components::PostProcessingUniform::new(&components::PostProcessing::default()));
+        resources.set_global(components::PostProcessingUniform::KEY, post_processing);
+
+        let colour_grading_lut = resource::Texture::identity_lut(&renderer, &mut resources, 32);
+        let colour_grading_lut = resources.store(colour_grading_lut);
+        let colour_grading_lut = resource::Sampler::new(&renderer, &mut resources, colour_grading_lut, wgpu::AddressMode::ClampToEdge, wgpu::AddressMode::ClampToEdge, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, wgpu::FilterMode::Nearest);
+        resources.set_global("tritium::colour_grading_lut", colour_grading_lut);
+
+        let render_graph = renderer::RenderGraph::from_resource(&renderer, &mut resources, renderer::RenderGraph::DEFAULT_PATH).unwrap();
+
+        let empty_morph_weights = renderer::UniformBuffer::from_value(&renderer, &mut resources, components::MorphWeightsUniform::new(&components::MorphWeights::default()));
+
         log::info!("Building Scene");
         let current_scene = scene_builder(&renderer, &mut resources);
         log::info!("Scene Built");
 
         let global_root = node::Node::builder("global_root")
             // .add_script(camera::CameraUpdateScript)
+            .add_script(components::AnimationScript)
             .add_script(components::TransformPropagationScript)
+            .add_script(components::SkeletonUpdateScript)
             .add_child(current_scene)
             .build();
         
@@ -68,6 +113,16 @@ impl EngineState {
             tick_keyboard_manager: resources.store(input::KeyboardManager::new()),
             mouse_manager: resources.store(input::MouseManager::new()),
             tick_mouse_manager: resources.store(input::MouseManager::new()),
+            frame_capture: resources.store(renderer::FrameCapture::new()),
+            shadow_maps,
+            render_graph,
+            post_processing: components::PostProcessing::default(),
+            scene_buffers: hashmap!{},
+            empty_morph_weights,
+            instance_buffers: hashmap!{},
+            bind_groups: renderer::BindGroupCache::new(),
+            pending_captures: vec![],
+            saving_captures: vec![],
             frame_counter: engine::FrameCounter::new(),
             resources,
             ticks_per_second: 60,
@@ -79,21 +134,21 @@ impl EngineState {
         match event {
             Event::DeviceEvent { event, .. } => match event {
                 DeviceEvent::MouseMotion { delta } => {
-                    if self.renderer.window.focused {
+                    if self.renderer.focused() {
                         let delta = glam::vec2(delta.0 as f32, delta.1 as f32);
                         self.mouse_manager.get_mut(&mut self.resources).update_delta(delta);
                         self.tick_mouse_manager.get_mut(&mut self.resources).update_delta(delta);
                     }
                 },
                 DeviceEvent::MouseWheel { delta } => {
-                    if self.renderer.window.focused {
+                    if self.renderer.focused() {
                         self.mouse_manager.get_mut(&mut self.resources).update_scroll_delta(*delta);
                         self.tick_mouse_manager.get_mut(&mut self.resources).update_scroll_delta(*delta);
                     }
                 },
                 _ => {},
             },
-            Event::WindowEvent { window_id, event } => if *window_id == self.renderer.window.window.id() {
+            Event::WindowEvent { window_id, event } => if Some(*window_id) == self.renderer.window().map(|window| window.window.id()) {
                 match event {
                     WindowEvent::KeyboardInput { input, .. } => {
                         self.keyboard_manager.get_mut(&mut self.resources).input(input);
@@ -163,6 +218,51 @@ impl EngineState {
         });
     }
 
+    fn update_context(&self, delta_time: f32, keyboard: &resource::Handle<input::KeyboardManager>, mouse: &resource::Handle<input::MouseManager>) -> engine::UpdateContext {
+        let size = self.renderer.size();
+
+        engine::UpdateContext {
+            window_size: glam::vec2(size.width as f32, size.height as f32),
+            window_focused: self.renderer.focused(),
+            delta_time,
+            keyboard: keyboard.clone(),
+            mouse: mouse.clone(),
+            capture: self.frame_capture.clone(),
+        }
+    }
+
+    /// Uploads assets loaded in the background, reloads changed assets, runs the update stages of every script, then resets per-frame input.
+    fn run_update(&mut self, delta_time: f32) {
+        let context = self.update_context(delta_time, &self.keyboard_manager, &self.mouse_manager);
+
+        self.resources.update_assets(&self.renderer);
+
+        // reloaded shaders keep their handles but not their bind group layouts
+        if self.resources.reload_assets(&self.renderer) {
+            self.bind_groups = renderer::BindGroupCache::new();
+        }
+
+        // self.first_update(&context);
+        self.pre_update(&context);
+        self.update(&context);
+        self.post_update(&context);
+
+        self.keyboard_manager.get_mut(&mut self.resources).reset_input();
+        self.mouse_manager.get_mut(&mut self.resources).reset_input();
+    }
+
+    /// Runs the tick stages of every script, then resets per-tick input.
+    fn run_tick(&mut self, delta_time: f32) {
+        let context = self.update_context(delta_time, &self.tick_keyboard_manager, &self.tick_mouse_manager);
+
+        self.pre_tick(&context);
+        self.tick(&context);
+        self.post_tick(&context);
+
+        self.tick_keyboard_manager.get_mut(&mut self.resources).reset_input();
+        self.tick_mouse_manager.get_mut(&mut self.resources).reset_input();
+    }
+
     fn extract(&mut self) -> Vec<renderer::QueuedRenderObject> {
         
         struct MeshInput {
@@ -170,14 +270,23 @@ impl EngineState {
             index_buffer: resource::Handle<wgpu::Buffer>,
             material: Option<resource::Handle<resource::Material>>,
             num_elements: u32,
+            bounds: engine::Aabb,
+            morph_targets: Option<renderer::BindingHolder>,
         }
 
         struct ExtractedNode {
             shader: Option<resource::Handle<renderer::Shader>>,
             meshes: Vec<MeshInput>,
+            global_matrix: glam::Mat4,
+            /// Relative to `global_matrix`, see `components::InstancedModel`
+            instances: Option<Vec<glam::Mat4>>,
+            sort_bias: f32,
             binding_resources: HashMap<String, Vec<renderer::BindingHolder>>,
         }
         
+        self.resources.get_engine_global_mut::<renderer::UniformArena>(renderer::UniformArena::KEY).unwrap().begin_frame();
+        self.bind_groups.begin_frame();
+
         // Inputs associated with individual nodes, i.e. Transform
         let mut extracted_nodes = hashmap!{};
         // Inputs associated with the scene as a whole, i.e. All lights
@@ -202,6 +311,9 @@ impl EngineState {
                 let mut node_data = ExtractedNode {
                     shader: None,
                     meshes: vec![],
+                    global_matrix: node.get_component::<components::Transform>().map(|transform| transform.global_matrix()).unwrap_or(glam::Mat4::IDENTITY),
+                    instances: None,
+                    sort_bias: node.get_component::<components::SortBias>().map(|bias| bias.0).unwrap_or(0.0),
                     binding_resources: hashmap!{},
                 };
                 
@@ -213,12 +325,17 @@ impl EngineState {
                             index_buffer,
                             material,
                             num_elements,
+                            bounds,
+                            morph_targets,
                         } => node_data.meshes.push(MeshInput {
                             vertex_buffer,
                             index_buffer,
                             material,
                             num_elements,
+                            bounds,
+                            morph_targets,
                         }),
+                        renderer::RenderInput::Instances(instances) => node_data.instances.get_or_insert_with(Vec::new).extend(instances),
                         // renderer::RenderInput::BindGroup(name, bind_group) => {
                         //     node_data.bind_groups.insert(name.clone(), bind_group.clone());
                         // },
@@ -249,12 +366,27 @@ impl EngineState {
             visit(&mut self.global_root, &mut self.resources, &mut extracted_nodes, &mut scene_data, &self.renderer);
         }
 
-        let empty_storage_buffer = {
-            // 1 word, item size of 2 words => num items = 0
-            let buffer = renderer::StorageBuffer::from_bytes(&self.renderer, &mut self.resources, &[0; 32], 64);
-            buffer.binding_resources()
+        let (frustum, view) = {
+            // taking the casters leaves an empty list for the next frame
+            let casters = std::mem::take(self.resources.get_engine_global_mut::<renderer::ShadowCasters>(renderer::ShadowCasters::KEY).unwrap());
+
+            let camera_node = self.resources.get_global::<node::NodeId>("current_camera").and_then(|id| self.global_root.find_by_id(id));
+            let camera = camera_node.and_then(|node| Some((node.get_component::<camera::Camera>()?, node.get_component::<components::Transform>()?)));
+
+            self.shadow_maps.prepare(&self.renderer, &mut self.resources, &casters, camera, self.renderer.aspect());
+
+            self.post_processing = camera_node.and_then(|node| node.get_component::<components::PostProcessing>().copied()).unwrap_or_default();
+            let uniform = self.resources.get_global::<renderer::UniformBuffer>(components::PostProcessingUniform::KEY).unwrap();
+            uniform.write(&self.renderer, &self.resources, components::PostProcessingUniform::new(&self.post_processing));
+
+            let frustum = camera.map(|(camera, transform)| camera.frustum(transform, self.renderer.aspect()));
+            let view = camera.map(|(_, transform)| transform.global_matrix().inverse()).unwrap_or(glam::Mat4::IDENTITY);
+
+            (frustum, view)
         };
 
+        let empty_storage_buffer = renderer::StorageBuffer::empty(&self.renderer, &mut self.resources);
+
         let scene_data = {
             let mut new_scene_data = hashmap!{};
 
@@ -278,248 +410,325 @@ impl EngineState {
                 // let points: &[components::PointLightUniform] = bytemuck::cast_slice(&data);
                 // log::trace!("{points:?}");
                 
-                let buffer = renderer::StorageBuffer::from_bytes(&self.renderer, &mut self.resources, &data, data.len() / num_items);
+                let item_size = data.len() / num_items;
+                let binding_resources = match self.scene_buffers.get_mut(&collection) {
+                    Some(buffer) => {
+                        buffer.write_bytes(&self.renderer, &mut self.resources, &data, item_size);
+                        buffer.binding_resources()
+                    },
+                    None => {
+                        let buffer = renderer::StorageBuffer::from_bytes(&self.renderer, &mut self.resources, &data, item_size);
+                        let binding_resources = buffer.binding_resources();
+                        self.scene_buffers.insert(collection.clone(), buffer);
+                        binding_resources
+                    },
+                };
 
-                new_scene_data.insert(collection, buffer.binding_resources());
+                new_scene_data.insert(collection, binding_resources);
             }
 
+            // collections without items this frame use the empty buffer
+            self.scene_buffers.retain(|collection, _| new_scene_data.contains_key(collection));
+
             new_scene_data
         };
 
-        let mut opaque_queue = vec![];
-        let mut transparent_queue = vec![];
+        struct Batch<'a> {
+            shader: resource::Handle<renderer::Shader>,
+            mesh: &'a MeshInput,
+            binding_resources: &'a HashMap<String, Vec<renderer::BindingHolder>>,
+            transparent: bool,
+            double_sided: bool,
+            order_independent: bool,
+            /// Depth, visibility and matrix of each instance
+            instances: Vec<(f32, bool, glam::Mat4)>,
+        }
+
+        // meshes sharing a shader, buffers and material are drawn with one instanced draw
+        let mut batches: HashMap<BatchKey, Batch> = hashmap!{};
+        let mut stats = renderer::RenderStats::default();
 
         // let current_camera = self.resources.get_global::<world::NodeId>("current_camera").expect("Global resource 'current_camera' must be set to a valid NodeId with an attached Camera bundle.");
 
-        for (_, ExtractedNode { shader, meshes, binding_resources }) in &extracted_nodes {
+        for (node_id, ExtractedNode { shader, meshes, global_matrix, instances, sort_bias, binding_resources }) in &extracted_nodes {
             if meshes.len() > 0 {
                 let shader_handle = shader.clone().expect("Failed to render mesh, no shader specified.");
-                let inputs = {
-                    let shader = shader_handle.get(&self.resources);
-                    shader.inputs.clone()
+                let shader = shader_handle.get(&self.resources);
+
+                let instanced = shader.vertex_type().instanced();
+                let morphed = shader.inputs.contains(&renderer::ShaderInput::MeshMorphTargets);
+                // meshes placed without the node's transform (i.e. skyboxes) are never culled, nor are skinned or morphed meshes, which move outside their bounds
+                let transformed = instanced || shader.inputs.iter().any(|input| matches!(input, renderer::ShaderInput::Node { res, .. } if res == "transform"));
+                let culled = transformed && !morphed && shader.vertex_type() != renderer::VertexLayoutType::SkinnedModel;
+                // morph weights are per node too
+                let node_inputs = morphed || shader.inputs.iter().any(|input| matches!(input, renderer::ShaderInput::Node { .. }));
+                let has_material = shader.inputs.contains(&renderer::ShaderInput::MeshMaterial);
+
+                // shaders without instancing only have the node's transform
+                let matrices = match instances {
+                    Some(instances) if instanced => instances.iter().map(|instance| *global_matrix * *instance).collect(),
+                    _ => vec![*global_matrix],
                 };
 
-                let mut double_sided = false;
-                let mut transparent = false;
-
                 for mesh in meshes {
-                    // todo: a bit inefficient to recalculate order for each mesh
-                    let mut ordered_binding_resources = vec![];
-
-                    for input in &inputs {
-                        let mut resources = vec![];
-                        match input {
-                            renderer::ShaderInput::MeshMaterial => {
-                                let resource = mesh.material.as_ref().expect("Shader MeshMaterial input not present, mesh does not have material.");
-                                let material = resource.get(&self.resources);
-
-                                double_sided = material.double_sided;
-                                transparent = material.alpha_mode == resource::AlphaMode::Blend;
-                                
-                                resources.extend(material.binding_resources(&self.resources))
-                            }
-                            renderer::ShaderInput::Node { res, .. } => {
-                                let resource = binding_resources.get(res).expect(&format!("Shader input '{res}' not present in node."));
-                                
-                                resources.extend(resource.clone())
-                            },
-                            renderer::ShaderInput::GlobalNode { node, res, .. } => {
-                                let node_id = self.resources.get_global::<node::NodeId>(node).expect(&format!("Failed to get global shader input '{node}.{res}'. No such global NodeId resource '{node}' exists."));
-                                let node_data = extracted_nodes.get(node_id).expect(&format!("Failed to get global shader input '{node}.{res}'. No Node exists with NodeId as specified in global resource '{node}'."));
-                                let resource = node_data.binding_resources.get(res).expect(&format!("Failed to get global shader input '{node}.{res}'. The node at '{node}' does not have binding resource '{res}'."));
-                                
-                                resources.extend(resource.clone())
-                            },
-                            renderer::ShaderInput::Scene { collection, .. } => {
-                                // let bind_group = scene_data.get(collection).unwrap_or(&empty_storage_buffer);
-                                // let resource = scene_data.get(collection).expect(&format!("Failed to find element of shader scene input '{collection}'. Empty or non-existant."));
-                                let resource = scene_data.get(collection).unwrap_or(&empty_storage_buffer);
-                                
-                                resources.extend(resource.clone());
-                            },
-                            renderer::ShaderInput::Resource { ty, res } => {
-                                match ty {
-                                    renderer::BindingResourceType::Material => {
-                                        let resource = self.resources.get_global::<resource::Material>(res).expect(&format!("Material resource '{res}' could not be found, required by shader."));
-                                        resources.extend(resource.binding_resources(&self.resources));
-                                    },
-                                    renderer::BindingResourceType::Texture => {
-                                        let resource = self.resources.get_global::<resource::Texture>(res).expect(&format!("Texture resource '{res}' could not be found, required by shader."));
-                                        resources.push(resource.binding_resource());
-                                    },
-                                    renderer::BindingResourceType::Sampler => {
-                                        let resource = self.resources.get_global::<resource::Sampler>(res).expect(&format!("Sampler resource '{res}' could not be found, required by shader."));
-                                        resources.extend(resource.binding_resources(&self.resources));
-                                    },
-                                    renderer::BindingResourceType::CubeMap => {
-                                        let resource = self.resources.get_global::<resource::CubeMap>(res).expect(&format!("Cubemap resource '{res}' could not be found, required by shader."));
-                                        resources.push(resource.binding_resource());
-                                    },
-                                    renderer::BindingResourceType::CubeSampler => {
-                                        let resource = self.resources.get_global::<resource::CubeSampler>(res).expect(&format!("Cube sampler resource '{res}' could not be found, required by shader."));
-                                        resources.extend(resource.binding_resources(&self.resources));
-                                    },
-                                    renderer::BindingResourceType::Uniform => {
-                                        let resource = self.resources.get_global::<renderer::UniformBuffer>(res).expect(&format!("Uniform buffer resource '{res}' could not be found, required by shader."));
-                                        resources.push(resource.binding_resource());
-                                    },
-                                    renderer::BindingResourceType::Storage => {
-                                        let resource = self.resources.get_global::<renderer::StorageBuffer>(res).expect(&format!("Storage buffer resource '{res}' could not be found, required by shader."));
-                                        resources.extend(resource.binding_resources());
-                                    },
-                                }
-                            },
-                            renderer::ShaderInput::Manual(_) => panic!("Manual shader inputs are not permitted in standard shaders."),
+                    let material = mesh.material.as_ref().filter(|_| has_material).map(|material| material.get(&self.resources));
+                    let transparent = material.map_or(false, |material| material.alpha_mode == resource::AlphaMode::Blend);
+                    let double_sided = material.map_or(false, |material| material.double_sided);
+                    let order_independent = transparent && shader.order_independent && material.map_or(false, |material| material.transparency == resource::Transparency::WeightedBlended);
+
+                    let key = (
+                        resource::HandlesResource::id(&shader_handle),
+                        resource::HandlesResource::id(&mesh.vertex_buffer),
+                        resource::HandlesResource::id(&mesh.index_buffer),
+                        mesh.material.as_ref().map(resource::HandlesResource::id),
+                        mesh.num_elements,
+                        // per node inputs differ between nodes, and sorted transparent meshes have to be drawn in order
+                        (!instanced || node_inputs || (transparent && !order_independent)).then_some(*node_id),
+                    );
+
+                    let batch = batches.entry(key).or_insert_with(|| Batch {
+                        shader: shader_handle.clone(),
+                        mesh,
+                        binding_resources,
+                        transparent,
+                        double_sided,
+                        order_independent,
+                        instances: vec![],
+                    });
+
+                    for matrix in &matrices {
+                        let bounds = mesh.bounds.transform(*matrix);
+                        let visible = match &frustum {
+                            Some(frustum) if culled => frustum.intersects_aabb(&bounds),
+                            _ => true,
                         };
-        
-                        ordered_binding_resources.extend(resources);
-                    }
-                    
-                    let bind_group_entries = {
-                        let mut bind_group_entries = vec![];
-                        let mut current_binding = 0;
-                        
-                        for resource in ordered_binding_resources {
-                            match resource {
-                                renderer::BindingHolder::Buffer(buffer) => {
-                                    let binding = buffer.get(&self.resources);
-                                    bind_group_entries.push(wgpu::BindGroupEntry {
-                                        binding: current_binding,
-                                        resource: binding.as_entire_binding(),
-                                    });
-                                    current_binding += 1;
-                                },
-                                renderer::BindingHolder::Texture(view) => {
-                                    let view = view.get(&self.resources);
-                                    bind_group_entries.push(wgpu::BindGroupEntry {
-                                        binding: current_binding,
-                                        resource: wgpu::BindingResource::TextureView(view),
-                                    });
-                                    current_binding += 1;
-                                },
-                                renderer::BindingHolder::Sampler(sampler) => {
-                                    let sampler = sampler.get(&self.resources);
-                                    bind_group_entries.push(wgpu::BindGroupEntry {
-                                        binding: current_binding,
-                                        resource: wgpu::BindingResource::Sampler(sampler),
-                                    });
-                                    current_binding += 1;
-                                },
-                            }
-                        }
-            
-                        bind_group_entries
-                    };
+                        // distance in front of the camera
+                        let depth = -view.transform_point3(bounds.centre()).z + sort_bias;
 
-                    let bind_group = self.renderer.device.create_bind_group(&wgpu::BindGroupDescriptor {
-                        label: None,
-                        layout: &shader_handle.get(&self.resources).bind_group_layout,
-                        entries: bind_group_entries.as_slice(),
-                    });
+                        stats.objects += 1;
+                        if !visible {
+                            stats.culled += 1;
+                        }
 
-                    if transparent {
-                        transparent_queue.push(renderer::QueuedRenderObject {
-                            shader: shader_handle.clone(),
-                            vertex_buffer: mesh.vertex_buffer.clone(),
-                            index_buffer: mesh.index_buffer.clone(),
-                            bind_group,
-                            num_indices: mesh.num_elements,
-                            transparent,
-                            double_sided,
-                        });
-                    } else {
-                        opaque_queue.push(renderer::QueuedRenderObject {
-                            shader: shader_handle.clone(),
-                            vertex_buffer: mesh.vertex_buffer.clone(),
-                            index_buffer: mesh.index_buffer.clone(),
-                            bind_group,
-                            num_indices: mesh.num_elements,
-                            transparent,
-                            double_sided,
-                        });
+                        batch.instances.push((depth, visible, *matrix));
                     }
                 }
             }
         }
 
-        opaque_queue.extend(transparent_queue);
+        let mut opaque_queue = vec![];
+        let mut transparent_queue = vec![];
 
-        opaque_queue
-    }
+        // i.e. an `InstancedModel` without instances
+        batches.retain(|_, batch| !batch.instances.is_empty());
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let mut render_objects = self.extract();
+        // batches of previous frames that no longer exist
+        self.instance_buffers.retain(|key, _| batches.contains_key(key));
 
-        let output = self.renderer.window.surface.get_current_texture()?;
+        for (key, Batch { shader, mesh, binding_resources, transparent, double_sided, order_independent, mut instances }) in batches {
+            // visible instances first, culled ones are only drawn into shadow maps
+            instances.sort_by(|(a_depth, a_visible, _), (b_depth, b_visible, _)| {
+                let order = if transparent { b_depth.total_cmp(a_depth) } else { a_depth.total_cmp(b_depth) };
+                b_visible.cmp(a_visible).then(order)
+            });
 
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        
-        let mut encoder = self.renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Render Encoder"),
-        });
+            let depth = instances[0].0;
+            let num_visible_instances = instances.iter().filter(|(_, visible, _)| *visible).count() as u32;
+            if num_visible_instances > 0 {
+                stats.draws += 1;
+            }
 
-        {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(CLEAR_COLOUR),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.renderer.window.depth_texture,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: true,
-                    }),
-                    stencil_ops: None,
-                }),
-            });
+            // also bound by shadow maps
+            let mut morph_targets = None;
+
+            let ordered_binding_resources = {
+                let mut ordered_binding_resources = vec![];
+
+                for input in &shader.get(&self.resources).inputs {
+                    let mut resources = vec![];
+                    match input {
+                        renderer::ShaderInput::MeshMaterial => {
+                            let resource = mesh.material.as_ref().expect("Shader MeshMaterial input not present, mesh does not have material.");
+                            let material = resource.get(&self.resources);
+                            
+                            resources.extend(material.binding_resources(&self.resources))
+                        }
+                        renderer::ShaderInput::MeshMorphTargets => {
+                            // the empty buffer has zero targets
+                            resources.push(mesh.morph_targets.clone().unwrap_or_else(|| empty_storage_buffer[0].clone()));
+                            resources.push(binding_resources.get("morph_weights").and_then(|weights| weights.first()).cloned().unwrap_or_else(|| self.empty_morph_weights.binding_resource()));
 
-            {
-                for render_object in &mut render_objects {
-                    let index = renderer::PipelineProperties {
-                        transparent: render_object.transparent,
-                        double_sided: render_object.double_sided,
-                        colour_format: self.renderer.window.config.format,
-                        depth_format: Some(resource::Texture::DEPTH_FORMAT),
+                            morph_targets = Some(resources.clone());
+                        },
+                        renderer::ShaderInput::Node { res, .. } => {
+                            let resource = binding_resources.get(res).unwrap_or_else(|| panic!("Shader input '{res}' not present in node."));
+                            
+                            resources.extend(resource.clone())
+                        },
+                        renderer::ShaderInput::GlobalNode { node, res, .. } => {
+                            let node_id = self.resources.get_global::<node::NodeId>(node).unwrap_or_else(|| panic!("Failed to get global shader input '{node}.{res}'. No such global NodeId resource '{node}' exists."));
+                            let node_data = extracted_nodes.get(node_id).unwrap_or_else(|| panic!("Failed to get global shader input '{node}.{res}'. No Node exists with NodeId as specified in global resource '{node}'."));
+                            let resource = node_data.binding_resources.get(res).unwrap_or_else(|| panic!("Failed to get global shader input '{node}.{res}'. The node at '{node}' does not have binding resource '{res}'."));
+                            
+                            resources.extend(resource.clone())
+                        },
+                        renderer::ShaderInput::Scene { collection, .. } => {
+                            // let bind_group = scene_data.get(collection).unwrap_or(&empty_storage_buffer);
+                            // let resource = scene_data.get(collection).expect(&format!("Failed to find element of shader scene input '{collection}'. Empty or non-existant."));
+                            let resource = scene_data.get(collection).unwrap_or(&empty_storage_buffer);
+                            
+                            resources.extend(resource.clone());
+                        },
+                        renderer::ShaderInput::Resource { ty, res } => resources.extend(ty.global_binding_resources(&self.resources, res)),
+                        renderer::ShaderInput::Shadows => resources.extend(self.shadow_maps.binding_resources()),
+                        renderer::ShaderInput::Manual(_) => panic!("Manual shader inputs are not permitted in standard shaders."),
                     };
-                    
-                    let shader = render_object.shader.get_mut(&mut self.resources);
-                    shader.prepare_pipeline(&self.renderer, index);
+    
+                    ordered_binding_resources.extend(resources);
                 }
-                for render_object in &render_objects {
-                    let pipeline = {
-                        let shader = render_object.shader.get(&self.resources);
-                        shader.get_pipeline(renderer::PipelineProperties {
-                            transparent: render_object.transparent,
-                            double_sided: render_object.double_sided,
-                            colour_format: self.renderer.window.config.format,
-                            depth_format: Some(resource::Texture::DEPTH_FORMAT),
-                        }).unwrap()
-                    };
 
-                    let vertex_buffer = render_object.vertex_buffer.get(&self.resources);
-                    let index_buffer = render_object.index_buffer.get(&self.resources);
-                    
-                    render_pass.set_pipeline(pipeline);
-                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                    render_pass.set_bind_group(0, &render_object.bind_group, &[]);
-                    
-                    render_pass.draw_indexed(0..render_object.num_indices, 0, 0..1);
-                }
+                ordered_binding_resources
+            };
+            
+            let joints = if shader.get(&self.resources).vertex_type() == renderer::VertexLayoutType::SkinnedModel {
+                binding_resources.get("joints").and_then(|joints| joints.first()).cloned()
+            } else {
+                None
+            };
+            let bind_group = self.bind_groups.get_or_create(&self.renderer, &mut self.resources, &shader, ordered_binding_resources);
+
+            let transforms: Vec<_> = instances.iter().map(|(_, _, matrix)| renderer::InstanceTransform::new(*matrix)).collect();
+            let instance_buffer = match self.instance_buffers.get_mut(&key) {
+                Some(buffer) => {
+                    buffer.write(&self.renderer, &mut self.resources, &transforms);
+                    buffer.buffer.clone()
+                },
+                None => {
+                    let buffer = renderer::InstanceBuffer::new(&self.renderer, &mut self.resources, &transforms);
+                    let handle = buffer.buffer.clone();
+                    self.instance_buffers.insert(key, buffer);
+                    handle
+                },
+            };
+
+            let render_object = renderer::QueuedRenderObject {
+                shader,
+                vertex_buffer: mesh.vertex_buffer.clone(),
+                index_buffer: mesh.index_buffer.clone(),
+                bind_group,
+                instances: instance_buffer,
+                num_instances: instances.len() as u32,
+                num_visible_instances,
+                num_indices: mesh.num_elements,
+                transparent,
+                double_sided,
+                order_independent,
+                joints,
+                morph_targets,
+            };
+
+            if transparent {
+                transparent_queue.push((depth, render_object));
+            } else {
+                opaque_queue.push((depth, render_object));
             }
         }
 
-        self.renderer.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
+        // opaque by pipeline to reduce state changes, then front to back to reduce overdraw
+        opaque_queue.sort_by(|(a_depth, a), (b_depth, b)| {
+            resource::HandlesResource::id(&a.shader).cmp(&resource::HandlesResource::id(&b.shader))
+                .then(a.double_sided.cmp(&b.double_sided))
+                .then(a_depth.total_cmp(b_depth))
+        });
+        // transparent back to front, so they blend over everything behind them
+        transparent_queue.sort_by(|(a_depth, _), (b_depth, _)| b_depth.total_cmp(a_depth));
+
+        log::trace!("Culled {} of {} objects, {} draws.", stats.culled, stats.objects, stats.draws);
+        self.resources.set_global(renderer::RenderStats::KEY, stats);
+
+        opaque_queue.into_iter().chain(transparent_queue).map(|(_, render_object)| render_object).collect()
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.save_captures(false);
+
+        let render_objects = self.extract();
+        self.shadow_maps.render(&self.renderer, &mut self.resources, &mut self.bind_groups, &render_objects);
+        let capture_paths = self.frame_capture.get_mut(&mut self.resources).take_pending(self.frame_counter.current_frame);
+
+        match &self.renderer.surface {
+            renderer::RenderSurface::Window(window) => {
+                let output = window.surface.get_current_texture()?;
+                let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+                if capture_paths.is_empty() {
+                    self.draw(&view, &render_objects);
+                } else {
+                    let size = self.renderer.size();
+                    let colour_format = self.renderer.colour_format();
+                    let texture = renderer::create_capture_texture(&self.renderer.device, colour_format, size.width, size.height);
+
+                    self.draw(&texture.create_view(&wgpu::TextureViewDescriptor::default()), &render_objects);
+                    renderer::blit_to_surface(&self.renderer, &mut self.resources, &texture, &view);
+
+                    match renderer::PendingCapture::new(&self.renderer, &texture, colour_format, size.width, size.height, capture_paths) {
+                        Ok(capture) => self.pending_captures.push(capture),
+                        Err(err) => log::error!("Failed to capture frame: {err}"),
+                    }
+                }
+
+                output.present();
+            },
+            renderer::RenderSurface::Offscreen(offscreen) => {
+                let view = offscreen.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+                self.draw(&view, &render_objects);
+
+                if !capture_paths.is_empty() {
+                    renderer::save_captures(self.renderer.capture_frame(), &capture_paths);
+                }
+            },
+        }
+
+        // shadow and fullscreen passes create their bind groups after extraction
+        if let Some(&stats) = self.resources.get_global::<renderer::RenderStats>(renderer::RenderStats::KEY) {
+            self.resources.set_global(renderer::RenderStats::KEY, renderer::RenderStats {
+                bind_groups_created: self.bind_groups.created(),
+                ..stats
+            });
+        }
         
         Ok(())
     }
+
+    /// Saves window captures whose copies finished, if `wait` is set it blocks until every capture is copied and saved.
+    fn save_captures(&mut self, wait: bool) {
+        if self.pending_captures.is_empty() && self.saving_captures.is_empty() {
+            return;
+        }
+
+        self.renderer.device.poll(if wait { wgpu::Maintain::Wait } else { wgpu::Maintain::Poll });
+
+        for mut capture in std::mem::take(&mut self.pending_captures) {
+            if capture.copied() {
+                self.saving_captures.push(capture.save());
+            } else {
+                self.pending_captures.push(capture);
+            }
+        }
+
+        if wait {
+            for thread in self.saving_captures.drain(..) {
+                let _ = thread.join();
+            }
+        } else {
+            self.saving_captures.retain(|thread| !thread.is_finished());
+        }
+    }
+
+    fn draw(&mut self, view: &wgpu::TextureView, render_objects: &[renderer::QueuedRenderObject]) {
+        self.render_graph.execute(&self.renderer, &mut self.resources, &mut self.bind_groups, view, render_objects, &self.post_processing);
+    }
 }
 
 pub struct App {
@@ -534,8 +743,11 @@ impl App {
         let event_loop = EventLoop::new();
         
         let state = {
+            let video_config: VideoConfig = confy::load("wgpu-game-engine", Some("video")).unwrap();
+
             let window = WindowBuilder::new().build(&event_loop).unwrap();
-            EngineState::new(window, scene_builder).await
+            let renderer = renderer::Renderer::new(window, video_config.vsync, video_config.msaa_samples).await;
+            EngineState::new(renderer, scene_builder)
         };
         
         App {
@@ -543,15 +755,36 @@ impl App {
             event_loop,
         }
     }
+
+    pub fn frame_capture_mut(&mut self) -> &mut renderer::FrameCapture {
+        self.state.frame_capture.get_mut(&mut self.state.resources)
+    }
+
+    pub fn renderer(&self) -> &renderer::Renderer {
+        &self.state.renderer
+    }
+
+    /// Replaces the render graph, `RenderGraph::DEFAULT_PATH` is used unless set.
+    pub fn set_render_graph(&mut self, render_graph: renderer::RenderGraph) {
+        self.state.render_graph = render_graph;
+    }
+
+    /// Loads a render graph from RON and replaces the current one with it.
+    pub fn load_render_graph<P: AsRef<Path>>(&mut self, path: P) -> Result<(), renderer::RenderGraphError> {
+        self.state.render_graph = renderer::RenderGraph::from_resource(&self.state.renderer, &mut self.state.resources, path)?;
+        Ok(())
+    }
 }
 
 pub fn run(app: App) {
     let App { mut state, event_loop } = app;
+
+    let window_id = state.renderer.window().expect("App renderer must have a window.").window.id();
     
     event_loop.run(move |event, _, control_flow| 
         if !state.input(&event) {
             match event {
-                Event::WindowEvent { window_id, event } if window_id == state.renderer.window.window.id() => match event {
+                Event::WindowEvent { window_id: id, event } if id == window_id => match event {
                     WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                     WindowEvent::Resized(new_size) => {
                         state.renderer.on_resize(new_size);
@@ -560,36 +793,20 @@ pub fn run(app: App) {
                         state.renderer.on_resize(*new_inner_size);
                     },
                     WindowEvent::Focused(focused) => {
-                        state.renderer.window.focused = focused;
+                        if let Some(window) = state.renderer.window_mut() {
+                            window.focused = focused;
+                        }
                     }
                     _ => {}
                 },
-                Event::RedrawRequested(window_id) if window_id == state.renderer.window.window.id() => {
+                Event::RedrawRequested(id) if id == window_id => {
                     let mut current_time = std::time::Instant::now();
                     let delta_time = (current_time - state.frame_counter.last_frame_time).as_secs_f32();
                     let tick_delta_time = 1.0 / state.ticks_per_second as f32;
     
                     let tick_time = state.frame_counter.last_tick_time + std::time::Duration::from_secs_f32(tick_delta_time);
-    
-                    let window_size = glam::vec2(state.renderer.window.size.width as f32, state.renderer.window.size.height as f32);
-    
-                    let context = engine::UpdateContext {
-                        window_size,
-                        window_focused: state.renderer.window.focused,
-                        delta_time,
-                        keyboard: state.keyboard_manager.clone(),
-                        mouse: state.mouse_manager.clone(),
-                    };
                     
-                    // state.first_update(&context);
-                    state.pre_update(&context);
-                    state.update(&context);
-                    state.post_update(&context);
-    
-                    {
-                        state.keyboard_manager.get_mut(&mut state.resources).reset_input();
-                        state.mouse_manager.get_mut(&mut state.resources).reset_input();
-                    }
+                    state.run_update(delta_time);
                     
                     // Almost at a tick, rendering a frame would be a waste, wait for tick
                     if (tick_time - current_time).as_secs_f32() < delta_time {
@@ -600,22 +817,7 @@ pub fn run(app: App) {
                     }
     
                     if current_time >= tick_time {
-                        let context = engine::UpdateContext {
-                            window_size,
-                            window_focused: state.renderer.window.focused,
-                            delta_time: tick_delta_time,
-                            keyboard: state.tick_keyboard_manager.clone(),
-                            mouse: state.tick_mouse_manager.clone(),
-                        };
-                        
-                        state.pre_tick(&context);
-                        state.tick(&context);
-                        state.post_tick(&context);
-    
-                        {
-                            state.tick_keyboard_manager.get_mut(&mut state.resources).reset_input();
-                            state.tick_mouse_manager.get_mut(&mut state.resources).reset_input();
-                        }
+                        state.run_tick(tick_delta_time);
     
                         if state.frame_counter.current_tick % (state.ticks_per_second * 2) as usize == 0 {
                             log::debug!("MS: {:.2}, FPS: {:.2}, TPS (target {:.2}): {:.2}", delta_time * 1000.0, 1.0 / delta_time, 1.0 / tick_delta_time, 1.0 / (current_time - state.frame_counter.last_tick_time).as_secs_f32());
@@ -641,8 +843,12 @@ pub fn run(app: App) {
                     state.frame_counter.current_frame = state.frame_counter.current_frame.wrapping_add(1);
                     state.frame_counter.last_frame_time = current_time;
                 },
+                // captures of the last frames are still being saved
+                Event::LoopDestroyed => state.save_captures(true),
                 Event::MainEventsCleared => {
-                    state.renderer.window.window.request_redraw();
+                    if let Some(window) = state.renderer.window() {
+                        window.window.request_redraw();
+                    }
                 },
                 // Event::NewEvents(cause) => {
                 //     match cause {
@@ -655,3 +861,105 @@ pub fn run(app: App) {
         }
     );
 }
+
+/// Runs a scene without opening a window, rendering each frame into an offscreen texture.
+/// Time advances by exactly one tick per frame, so the output is independent of how fast frames are rendered.
+pub struct HeadlessApp {
+    state: EngineState,
+}
+
+impl HeadlessApp {
+    pub async fn new<F: FnMut(&renderer::Renderer, &mut resource::Resources) -> node::Node>(width: u32, height: u32, scene_builder: F) -> Result<HeadlessApp, renderer::RendererError> {
+        let _ = env_logger::try_init();
+
+        let renderer = renderer::Renderer::new_headless(width, height).await?;
+        let state = EngineState::new(renderer, scene_builder);
+
+        Ok(HeadlessApp {
+            state,
+        })
+    }
+
+    pub fn renderer(&self) -> &renderer::Renderer {
+        &self.state.renderer
+    }
+
+    pub fn resources(&self) -> &resource::Resources {
+        &self.state.resources
+    }
+
+    pub fn resources_mut(&mut self) -> &mut resource::Resources {
+        &mut self.state.resources
+    }
+
+    pub fn root(&self) -> &node::Node {
+        &self.state.global_root
+    }
+
+    pub fn root_mut(&mut self) -> &mut node::Node {
+        &mut self.state.global_root
+    }
+
+    pub fn current_frame(&self) -> usize {
+        self.state.frame_counter.current_frame
+    }
+
+    /// Updates and ticks the node tree once, then renders a single frame.
+    pub fn step(&mut self) {
+        let delta_time = 1.0 / self.state.ticks_per_second as f32;
+
+        self.state.run_update(delta_time);
+        self.state.run_tick(delta_time);
+        
+        self.state.frame_counter.current_tick = self.state.frame_counter.current_tick.wrapping_add(1);
+
+        // offscreen rendering cannot fail with a surface error
+        self.state.render().expect("Failed to render offscreen frame.");
+        
+        self.state.frame_counter.current_frame = self.state.frame_counter.current_frame.wrapping_add(1);
+    }
+
+    pub fn run_frames(&mut self, frames: usize) {
+        for _ in 0..frames {
+            self.step();
+        }
+    }
+
+    /// Blocks until every asset loading in the background is uploaded or failed.
+    pub fn finish_loading(&mut self) {
+        self.state.resources.finish_loading(&self.state.renderer);
+    }
+
+    pub fn frame_capture_mut(&mut self) -> &mut renderer::FrameCapture {
+        self.state.frame_capture.get_mut(&mut self.state.resources)
+    }
+
+    /// Replaces the render graph, `RenderGraph::DEFAULT_PATH` is used unless set.
+    pub fn set_render_graph(&mut self, render_graph: renderer::RenderGraph) {
+        self.state.render_graph = render_graph;
+    }
+
+    /// Loads a render graph from RON and replaces the current one with it.
+    pub fn load_render_graph<P: AsRef<Path>>(&mut self, path: P) -> Result<(), renderer::RenderGraphError> {
+        self.state.render_graph = renderer::RenderGraph::from_resource(&self.state.renderer, &mut self.state.resources, path)?;
+        Ok(())
+    }
+
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        self.state.renderer.set_sample_count(sample_count);
+    }
+
+    /// Statistics of the most recently rendered frame.
+    pub fn render_stats(&self) -> renderer::RenderStats {
+        self.state.resources.get_global::<renderer::RenderStats>(renderer::RenderStats::KEY).copied().unwrap_or_default()
+    }
+
+    /// Reads back the most recently rendered frame.
+    pub fn capture_frame(&self) -> Result<image::DynamicImage, renderer::CaptureError> {
+        self.state.renderer.capture_frame()
+    }
+
+    pub fn save_frame<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), renderer::CaptureError> {
+        renderer::save_image(&self.capture_frame()?, path)
+    }
+}
@@ -1,6 +1,5 @@
-use std::{io::BufReader, path::Path};
 
-use tritium::{renderer::Shader, resource::{self, Model, CubeMap, Material, Sampler, CubeSampler, Handle}, node::{Node, ClosureScript}, camera::Camera, engine::Rgba, components::{Transform, DirectionalLight, AmbientLight}};
+use tritium::{renderer::{Renderer, Shader}, resource::{self, Resources, Model, CubeMap, CubeError, CubeSampler, Handle, SpawnScene}, node::{Node, ClosureScript}, camera::Camera, engine::Rgba, components::{Transform, DirectionalLight, DirectionalShadows, Skeleton, AnimationPlayer, MorphWeights}};
 use winit::event::VirtualKeyCode;
 
 #[tokio::main]
@@ -15,105 +14,33 @@ async fn main() {
         ).unwrap();
         let main_render_pipeline = resources.store(main_render_pipeline);
 
+        let skinned_render_pipeline = Shader::from_resource(
+            renderer,
+            "pipelines/skinned.ron",
+        ).unwrap();
+        let skinned_render_pipeline = resources.store(skinned_render_pipeline);
+
+        let morph_render_pipeline = Shader::from_resource(
+            renderer,
+            "pipelines/morph.ron",
+        ).unwrap();
+        let morph_render_pipeline = resources.store(morph_render_pipeline);
+
         let skybox_render_pipeline = Shader::from_resource(
             renderer,
             "pipelines/skybox.ron",
         ).unwrap();
         let skybox_render_pipeline = resources.store(skybox_render_pipeline);
 
-        // Resources
+        resources.watch_shader(&main_render_pipeline, "pipelines/main.ron");
+        resources.watch_shader(&skinned_render_pipeline, "pipelines/skinned.ron");
+        resources.watch_shader(&morph_render_pipeline, "pipelines/morph.ron");
+        resources.watch_shader(&skybox_render_pipeline, "pipelines/skybox.ron");
 
-        let scene_node = {
-            // let paths = [
-            //     Path::new("res/materials/antique_veneer1_bl/"),
-            //     Path::new("res/materials/chipping-painted-wall-bl/"),
-            //     Path::new("res/materials/dirt-red-bricks-bl/"),
-            //     Path::new("res/materials/gray-granite-flecks-bl/"),
-            //     Path::new("res/materials/painted-metal-shed-bl/"),
-            //     // Path::new("res/materials/rustediron1-alt2-bl/"),
-            //     // Path::new("res/materials/sharp-boulder-layered-bl/"),
-            //     Path::new("res/materials/soft-blanket-bl/"),
-            //     Path::new("res/materials/mirror/"),
-            // ];
-
-            // let mut scene = Node::builder("Scene");
-            
-            // for (i, path) in paths.into_iter().enumerate() {
-            //     let material = {
-            //         let albedo_texture = resource::load_texture(renderer, resources, path.join("albedo.png"), false).unwrap();
-            //         let albedo_sampler = Sampler::new_default(renderer, resources, albedo_texture);
-            //         let albedo_sampler = resources.store(albedo_sampler);
-                    
-            //         let normal_texture = resource::load_texture(renderer, resources, path.join("normal.png"), false).unwrap();
-            //         let normal_sampler = Sampler::new_default(renderer, resources, normal_texture);
-            //         let normal_sampler = resources.store(normal_sampler);
-                    
-            //         let metallic_texture = resource::load_texture(renderer, resources, path.join("metallic.png"), false).unwrap();
-            //         let metallic_sampler = Sampler::new_default(renderer, resources, metallic_texture);
-            //         let metallic_sampler = resources.store(metallic_sampler);
-                    
-            //         let roughness_texture = resource::load_texture(renderer, resources, path.join("roughness.png"), false).unwrap();
-            //         let roughness_sampler = Sampler::new_default(renderer, resources, roughness_texture);
-            //         let roughness_sampler = resources.store(roughness_sampler);
-                    
-            //         let occlusion_texture = resource::load_texture(renderer, resources, path.join("occlusion.png"), false).unwrap();
-            //         let occlusion_sampler = Sampler::new_default(renderer, resources, occlusion_texture);
-            //         let occlusion_sampler = resources.store(occlusion_sampler);
-                    
-            //         let material = Material::builder()
-            //             .albedo_sampler(albedo_sampler)
-            //             .normal_sampler(normal_sampler)
-            //             .metallic_sampler(metallic_sampler)
-            //             .roughness_sampler(roughness_sampler)
-            //             .occlusion_sampler(occlusion_sampler)
-            //             .build(renderer, resources);
-                    
-            //         resources.store(material)
-            //     };
-                
-            //     let model = Model::new_sphere(renderer, resources, Some(material), 255, resource::SphereUV::Equirectangular2X);
-            //     let model = resources.store(model);
-    
-            //     scene = scene.add_child(
-            //         Node::builder(&path.file_name().unwrap().to_string_lossy())
-            //         .add_component(Transform::from_translation_scale(glam::vec3(i as f32 - 2.0, 0.0, 0.0), glam::Vec3::splat(0.5)))
-            //         .add_component(model)
-            //         .add_component(main_render_pipeline.clone())
-            //         .build()
-            //     );
-            // }
-
-            // scene.build()
-
-            // let mut scene = Node::builder("scene");
-
-            // for y in 0..7 {
-            //     for x in 0..7 {
-            //         let material = Material::builder()
-            //             .albedo(Rgba::RED)
-            //             .metallic_factor(y as f32 / 6.0)
-            //             .roughness_factor(x as f32 / 6.0)
-            //             .build(renderer, resources);
-            //         let material = resources.store(material);
-
-            //         let sphere = Model::new_sphere(renderer, resources, Some(material), 32, resource::SphereUV::Cube);
-            //         let sphere = resources.store(sphere);
-
-            //         scene = scene.add_child(
-            //             Node::builder(&format!("node ({x}, {y})"))
-            //             .add_component(Transform::from_translation_scale(glam::vec3(x as f32 - 3.0, y as f32 - 3.0, 0.0) * 1.5, glam::Vec3::splat(0.5)))
-            //             .add_component(sphere)
-            //             .add_component(main_render_pipeline.clone())
-            //             .build()
-            //         );
-            //     }
-            // }
-
-            // scene.build()
-        };
+        // Resources
 
         let scene_node = {
-            let mut node = resource::load_gltf(renderer, resources, "res/WaterBottle.glb", None).unwrap();
+            let scene = resource::load_gltf_async(resources, "res/WaterBottle.glb", None);
             // let mut node = resource::load_gltf(renderer, resources, "res/tests/EmissiveStrengthTest.glb", None).unwrap();
             
             // let mut node = resource::load_gltf(renderer, resources, "res/tests/AlphaBlendModeTest.glb", None).unwrap();
@@ -126,10 +53,25 @@ async fn main() {
             // let mut node = resource::load_gltf(renderer, resources, "res/tests/TextureLinearInterpolationTest.glb", None).unwrap();
             // let mut node = resource::load_gltf(renderer, resources, "res/tests/TextureSettingsTest.glb", None).unwrap();
             
-            node.traverse_if_mut(&mut |node| node.has_component::<Handle<Model>>(), &mut |node| {
-                node.add_component(main_render_pipeline.clone());
+            let spawn_scene = SpawnScene::new(scene).on_spawn(move |node, resources| {
+                resources.watch_gltf(node, "res/WaterBottle.glb", None);
+
+                node.traverse_if_mut(&mut |node| node.has_component::<Handle<Model>>(), &mut |node| {
+                    if node.has_component::<Skeleton>() {
+                        node.add_component(skinned_render_pipeline.clone());
+                    } else if node.has_component::<MorphWeights>() {
+                        node.add_component(morph_render_pipeline.clone());
+                    } else {
+                        node.add_component(main_render_pipeline.clone());
+                    }
+                });
+                if let Some(player) = node.get_component_mut::<AnimationPlayer>() {
+                    let clip = player.clips[0].clone();
+                    player.play(clip);
+                }
             });
-            node
+
+            Node::builder("Scene").add_script(spawn_scene).build()
         };
         
         let skybox_model = {
@@ -160,10 +102,17 @@ async fn main() {
 
             // From equirectangular jpeg
             // let image = image::load(BufReader::new(std::fs::File::open("res/skyboxes/milkyway/Milkyway_BG.jpg").unwrap()), image::ImageFormat::Jpeg).unwrap();
-            let image = image::load(BufReader::new(std::fs::File::open("res/skyboxes/grand_canyon/GCanyon_C_YumaPoint_8k.jpg").unwrap()), image::ImageFormat::Jpeg).unwrap();
-            let cubemap = CubeMap::from_image_equirectangular(renderer, resources, &image, None).unwrap();
-            let cubemap = resources.store(cubemap);
-            let cubemap = CubeSampler::new_default(renderer, resources, cubemap);
+            // grey until the 8k image is decoded
+            let placeholder = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(64, 32, image::Rgb([128, 128, 128])));
+            set_environment(renderer, resources, &placeholder).unwrap();
+
+            resources.load_async("skybox", || {
+                Ok(image::open("res/skyboxes/grand_canyon/GCanyon_C_YumaPoint_8k.jpg")?)
+            }, |renderer, resources, image| {
+                set_environment(renderer, resources, &image)?;
+                log::trace!("Skybox Loaded");
+                Ok(())
+            });
             
             // From equirectangular hdr
             // let decoder = image::codecs::hdr::HdrDecoder::new(BufReader::new(std::fs::File::open("res/skyboxes/grand_canyon/equirectangular.hdr").unwrap())).unwrap();
@@ -175,35 +124,8 @@ async fn main() {
             // let cubemap = CubeMap::from_equirectangular(renderer, resources, &image, None, true).unwrap();
             
             // resources.store(cubemap)
-            resources.set_global("tritium::skybox", cubemap);
-            
-            // let decoder = image::codecs::hdr::HdrDecoder::new(BufReader::new(std::fs::File::open("res/skyboxes/milkyway/Milkyway_Light.hdr").unwrap())).unwrap();
-            let decoder = image::codecs::hdr::HdrDecoder::new(BufReader::new(std::fs::File::open("res/skyboxes/grand_canyon/GCanyon_C_YumaPoint_Env.hdr").unwrap())).unwrap();
-            let width = decoder.metadata().width;
-            let height = decoder.metadata().height;
-            let pixels = decoder.read_image_hdr().unwrap().iter().flat_map(|p| p.0).collect::<Vec<_>>();
-            let image = image::DynamicImage::ImageRgb32F(image::Rgb32FImage::from_vec(width, height, pixels).unwrap());
-
-            let cubemap = CubeMap::from_image_equirectangular(renderer, resources, &image, None).unwrap();
-            let cubemap = resources.store(cubemap);
-            let cubemap = CubeSampler::new_default(renderer, resources, cubemap);
-
-            resources.set_global("tritium::irradiance", cubemap);
             
-            // let decoder = image::codecs::hdr::HdrDecoder::new(BufReader::new(std::fs::File::open("res/skyboxes/milkyway/Milkyway_small.hdr").unwrap())).unwrap();
-            // let decoder = image::codecs::hdr::HdrDecoder::new(BufReader::new(std::fs::File::open("res/skyboxes/grand_canyon/GCanyon_C_YumaPoint_3k.hdr").unwrap())).unwrap();
-            // let width = decoder.metadata().width;
-            // let height = decoder.metadata().height;
-            // let pixels = decoder.read_image_hdr().unwrap().iter().flat_map(|p| p.0).collect::<Vec<_>>();
-            // let image = image::DynamicImage::ImageRgb32F(image::Rgb32FImage::from_vec(width, height, pixels).unwrap());
-
-            // let cubemap = CubeMap::from_image_equirectangular(renderer, resources, &image, None).unwrap();
-            // let cubemap = resources.store(cubemap);
-            // let cubemap = CubeSampler::new_default(renderer, resources, cubemap);
-
-            // resources.set_global("tritium::reflections", cubemap);
         // };
-        log::trace!("Skybox Loaded");
 
         // Scene
 
@@ -275,6 +197,7 @@ async fn main() {
                 Node::builder("sun")
                 .add_component(Transform::from_rotation(glam::Quat::from_axis_angle(glam::vec3(-2.0, 0.0, -1.0).normalize(), -std::f32::consts::PI / 2.9)))
                 .add_component(DirectionalLight::new(Rgba::new(1.0, 1.0, 1.0, 5.0)))
+                .add_component(DirectionalShadows::default())
                 // .add_component(AmbientLight::new(Rgba::new(1.0, 1.0, 1.0, 0.25)))
                 .build()
             )
@@ -304,3 +227,24 @@ async fn main() {
     }).await;
     tritium::run(app);
 }
+
+/// Sets the skybox, reflections and irradiance from a 2x1 equirectangular image.
+fn set_environment(renderer: &Renderer, resources: &mut Resources, image: &image::DynamicImage) -> Result<(), CubeError> {
+    let cubemap = CubeMap::from_image_equirectangular(renderer, resources, image, None)?;
+    let cubemap = resources.store(cubemap);
+
+    let reflections = CubeMap::prefilter_specular(renderer, resources, &cubemap, Some("Skybox Reflections"));
+    let reflections = resources.store(reflections);
+    let reflections = CubeSampler::new(renderer, resources, reflections, wgpu::AddressMode::ClampToEdge, wgpu::AddressMode::ClampToEdge, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear);
+    resources.set_global("tritium::reflections", reflections);
+
+    let irradiance = CubeMap::convolve_irradiance(renderer, resources, &cubemap, Some("Skybox Irradiance"));
+    let irradiance = resources.store(irradiance);
+    let irradiance = CubeSampler::new_default(renderer, resources, irradiance);
+    resources.set_global("tritium::irradiance", irradiance);
+
+    let cubemap = CubeSampler::new_default(renderer, resources, cubemap);
+    resources.set_global("tritium::skybox", cubemap);
+
+    Ok(())
+}
@@ -256,6 +256,10 @@ impl NodeBuilder {
     //     }
     // }
 
+    pub fn id(&self) -> NodeId {
+        self.node.id()
+    }
+
     pub fn add_component<T: Component + 'static>(mut self, component: T) -> NodeBuilder {
         self.node.add_component(component);
         self
@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use crate::{node::NodeId, resource::{Handle, HandleId, HandlesResource, Resources}};
+
+use super::{Renderer, BindingHolder, Shader};
+
+/// Uniforms of nodes (i.e. transforms and cameras), kept in persistent pages of slots instead of a new buffer per frame.
+/// A slot is only written when its contents change, and is recycled after a frame without being written.
+///
+/// Slots are bound as static buffer slices rather than with dynamic offsets, so every node still has bind groups of its own.
+/// They're cached by `BindGroupCache` and only recreated when a slot moves. Sharing one bind group per shader and page would
+/// need dynamic offsets in the layouts of node inputs and at every draw.
+pub struct UniformArena {
+    pages: Vec<Handle<wgpu::Buffer>>,
+    slots: HashMap<(NodeId, &'static str), UniformSlot>,
+    /// Recycled (page, offset) pairs by slot size
+    free: HashMap<u64, Vec<(usize, u64)>>,
+    /// End of the allocated part of the last page
+    next_offset: u64,
+    alignment: u64,
+}
+
+struct UniformSlot {
+    page: usize,
+    offset: u64,
+    size: u64,
+    contents: Vec<u8>,
+    written: bool,
+}
+
+impl UniformArena {
+    pub(crate) const KEY: &'static str = "tritium::uniform_arena";
+    const PAGE_SIZE: u64 = 64 * 1024;
+
+    pub(crate) fn new(renderer: &Renderer) -> UniformArena {
+        UniformArena {
+            pages: vec![],
+            slots: hashmap!{},
+            free: hashmap!{},
+            next_offset: UniformArena::PAGE_SIZE,
+            alignment: renderer.device.limits().min_uniform_buffer_offset_alignment as u64,
+        }
+    }
+
+    /// Writes the uniform named `name` of a node, returning its binding resource. Every node uniform has to be written each frame it's used.
+    pub fn write<T: bytemuck::Pod + bytemuck::Zeroable>(renderer: &Renderer, resources: &mut Resources, node: NodeId, name: &'static str, uniform: T) -> BindingHolder {
+        let contents = bytemuck::bytes_of(&uniform);
+        let size = (contents.len() as u64).max(1);
+
+        let arena = resources.get_engine_global_mut::<UniformArena>(UniformArena::KEY).expect("Uniform arena not present.");
+        // a uniform with a different type needs a different slot
+        let reuse = arena.slots.get(&(node, name)).map_or(false, |slot| slot.size == size);
+
+        if !reuse && !arena.has_room(size) {
+            // pages are stored as resources, so the arena can't be borrowed while allocating one
+            let page_size = arena.slot_size(size).max(UniformArena::PAGE_SIZE);
+            let page = resources.store(renderer.device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Uniform Arena Page"),
+                size: page_size,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+
+            let arena = resources.get_engine_global_mut::<UniformArena>(UniformArena::KEY).unwrap();
+            arena.pages.push(page);
+            arena.next_offset = 0;
+        }
+
+        let arena = resources.get_engine_global_mut::<UniformArena>(UniformArena::KEY).unwrap();
+        if !reuse {
+            if let Some(slot) = arena.slots.remove(&(node, name)) {
+                arena.recycle(&slot);
+            }
+
+            let (page, offset) = arena.allocate(arena.slot_size(size));
+            arena.slots.insert((node, name), UniformSlot {
+                page,
+                offset,
+                size,
+                contents: vec![],
+                written: false,
+            });
+        }
+
+        let slot = arena.slots.get_mut(&(node, name)).unwrap();
+        slot.written = true;
+        let offset = slot.offset;
+        let dirty = slot.contents != contents;
+        if dirty {
+            slot.contents = contents.to_vec();
+        }
+
+        let buffer = arena.pages[slot.page].clone();
+        if dirty {
+            renderer.queue.write_buffer(buffer.get(resources), offset, contents);
+        }
+
+        BindingHolder::BufferSlice { buffer, offset, size }
+    }
+
+    fn slot_size(&self, size: u64) -> u64 {
+        (size + self.alignment - 1) / self.alignment * self.alignment
+    }
+
+    fn has_room(&self, size: u64) -> bool {
+        let slot_size = self.slot_size(size);
+        self.free.get(&slot_size).map_or(false, |free| !free.is_empty())
+            || (!self.pages.is_empty() && self.next_offset + slot_size <= UniformArena::PAGE_SIZE)
+    }
+
+    fn recycle(&mut self, slot: &UniformSlot) {
+        let slot_size = self.slot_size(slot.size);
+        self.free.entry(slot_size).or_default().push((slot.page, slot.offset));
+    }
+
+    /// Requires `has_room`
+    fn allocate(&mut self, slot_size: u64) -> (usize, u64) {
+        if let Some(slot) = self.free.get_mut(&slot_size).and_then(|free| free.pop()) {
+            return slot;
+        }
+
+        let offset = self.next_offset;
+        self.next_offset += slot_size;
+        (self.pages.len() - 1, offset)
+    }
+
+    /// Recycles the slots of uniforms that weren't written since the last call.
+    pub(crate) fn begin_frame(&mut self) {
+        let unused: Vec<_> = self.slots.iter().filter(|(_, slot)| !slot.written).map(|(key, _)| *key).collect();
+        for key in unused {
+            let slot = self.slots.remove(&key).unwrap();
+            self.recycle(&slot);
+        }
+
+        for slot in self.slots.values_mut() {
+            slot.written = false;
+        }
+    }
+}
+
+/// Bind groups reused between frames, keyed by the shader and the resources bound to it.
+/// Entries are evicted after a frame without being used.
+pub(crate) struct BindGroupCache {
+    bind_groups: HashMap<(HandleId, Vec<BindingHolder>), (Handle<wgpu::BindGroup>, bool)>,
+    /// Bind groups created since the last `begin_frame`
+    created: usize,
+}
+
+impl BindGroupCache {
+    pub fn new() -> BindGroupCache {
+        BindGroupCache {
+            bind_groups: hashmap!{},
+            created: 0,
+        }
+    }
+
+    pub fn get_or_create(&mut self, renderer: &Renderer, resources: &mut Resources, shader: &Handle<Shader>, binding_resources: Vec<BindingHolder>) -> Handle<wgpu::BindGroup> {
+        let key = (shader.id(), binding_resources);
+        if let Some((bind_group, used)) = self.bind_groups.get_mut(&key) {
+            *used = true;
+            return bind_group.clone();
+        }
+
+        let entries: Vec<_> = key.1.iter().enumerate().map(|(binding, resource)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: resource.binding_resource(resources),
+        }).collect();
+
+        let bind_group = renderer.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &shader.get(resources).bind_group_layout,
+            entries: entries.as_slice(),
+        });
+        let bind_group = resources.store(bind_group);
+
+        self.bind_groups.insert(key, (bind_group.clone(), true));
+        self.created += 1;
+        bind_group
+    }
+
+    /// Evicts bind groups that weren't used since the last call, releasing the resources they hold.
+    pub fn begin_frame(&mut self) {
+        self.bind_groups.retain(|_, (_, used)| std::mem::replace(used, false));
+        self.created = 0;
+    }
+
+    pub fn created(&self) -> usize {
+        self.created
+    }
+}
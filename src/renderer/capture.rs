@@ -0,0 +1,454 @@
+use std::{path::{Path, PathBuf}, sync::mpsc};
+
+use crate::resource::Resources;
+
+use super::{Renderer, Shader, PipelineProperties};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptureFormat {
+    Png,
+    Hdr,
+    Tiff,
+}
+
+impl CaptureFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CaptureFormat::Png => "png",
+            CaptureFormat::Hdr => "hdr",
+            CaptureFormat::Tiff => "tiff",
+        }
+    }
+}
+
+/// Writes every `every_nth` rendered frame to `directory`, numbered from zero, i.e. `{prefix}00000.png`.
+#[derive(Debug, Clone)]
+pub struct ImageSequence {
+    pub directory: PathBuf,
+    pub prefix: String,
+    pub format: CaptureFormat,
+    pub every_nth: usize,
+    next_index: usize,
+}
+
+impl ImageSequence {
+    pub fn new<P: AsRef<Path>>(directory: P, format: CaptureFormat, every_nth: usize) -> ImageSequence {
+        ImageSequence {
+            directory: directory.as_ref().to_owned(),
+            prefix: "frame_".into(),
+            format,
+            every_nth: every_nth.max(1),
+            next_index: 0,
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> ImageSequence {
+        self.prefix = prefix.to_owned();
+        self
+    }
+
+    fn next_path(&mut self) -> PathBuf {
+        let path = self.directory.join(format!("{}{:05}.{}", self.prefix, self.next_index, self.format.extension()));
+        self.next_index += 1;
+        path
+    }
+}
+
+/// Requests captures of rendered frames. Available to scripts through `UpdateContext::capture`.
+#[derive(Debug, Default)]
+pub struct FrameCapture {
+    screenshots: Vec<PathBuf>,
+    sequence: Option<ImageSequence>,
+}
+
+impl FrameCapture {
+    pub fn new() -> FrameCapture {
+        FrameCapture {
+            screenshots: vec![],
+            sequence: None,
+        }
+    }
+
+    /// Saves the next rendered frame, the image format is determined by the file extension.
+    pub fn screenshot<P: AsRef<Path>>(&mut self, path: P) {
+        self.screenshots.push(path.as_ref().to_owned());
+    }
+
+    pub fn start_sequence(&mut self, sequence: ImageSequence) {
+        if let Err(err) = std::fs::create_dir_all(&sequence.directory) {
+            log::error!("Failed to create image sequence directory {:?}: {err}", sequence.directory);
+        }
+        self.sequence = Some(sequence);
+    }
+
+    pub fn stop_sequence(&mut self) -> Option<ImageSequence> {
+        self.sequence.take()
+    }
+
+    pub fn sequence(&self) -> Option<&ImageSequence> {
+        self.sequence.as_ref()
+    }
+
+    /// Paths the given frame should be written to, clears pending screenshots.
+    pub(crate) fn take_pending(&mut self, frame: usize) -> Vec<PathBuf> {
+        let mut paths: Vec<_> = self.screenshots.drain(..).collect();
+
+        if let Some(sequence) = &mut self.sequence {
+            if frame % sequence.every_nth == 0 {
+                paths.push(sequence.next_path());
+            }
+        }
+
+        paths
+    }
+}
+
+/// Copies an array layer of a single sample texture's first mip level back to the cpu.
+pub(crate) fn read_texture(renderer: &Renderer, texture: &wgpu::Texture, format: wgpu::TextureFormat, width: u32, height: u32, layer: u32) -> Result<image::DynamicImage, CaptureError> {
+    let copy = TextureCopy::new(renderer, texture, format, width, height, layer)?;
+
+    let mapped = copy.map();
+    renderer.device.poll(wgpu::Maintain::Wait);
+    mapped.recv().expect("Capture buffer mapping callback was dropped.").map_err(CaptureError::BufferAsyncError)?;
+
+    Ok(copy.read().into_image())
+}
+
+/// A texture copied into a buffer the cpu can map.
+struct TextureCopy {
+    buffer: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    unpadded_bytes_per_row: u32,
+    padded_bytes_per_row: u32,
+}
+
+impl TextureCopy {
+    /// Records and submits the copy, it has to be mapped before it can be read.
+    fn new(renderer: &Renderer, texture: &wgpu::Texture, format: wgpu::TextureFormat, width: u32, height: u32, layer: u32) -> Result<TextureCopy, CaptureError> {
+        let bytes_per_pixel = match format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb
+            | wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => 4,
+            wgpu::TextureFormat::Rgba16Float => 8,
+            _ => return Err(CaptureError::UnsupportedFormat(format)),
+        };
+
+        let unpadded_bytes_per_row = bytes_per_pixel * width;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = (unpadded_bytes_per_row + align - 1) / align * align;
+
+        let buffer = renderer.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Capture Buffer"),
+            size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Capture Encoder"),
+        });
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+
+        Ok(TextureCopy {
+            buffer,
+            format,
+            width,
+            height,
+            unpadded_bytes_per_row,
+            padded_bytes_per_row,
+        })
+    }
+
+    /// Receives the result once the device is polled after the copy finished.
+    fn map(&self) -> mpsc::Receiver<Result<(), wgpu::BufferAsyncError>> {
+        let (sender, receiver) = mpsc::channel();
+        self.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        receiver
+    }
+
+    /// Copies the pixels out of the mapped buffer.
+    fn read(self) -> CapturedPixels {
+        let mut pixels = Vec::with_capacity((self.unpadded_bytes_per_row * self.height) as usize);
+        {
+            let data = self.buffer.slice(..).get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..self.unpadded_bytes_per_row as usize]);
+            }
+        }
+        self.buffer.unmap();
+
+        CapturedPixels {
+            pixels,
+            format: self.format,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Tightly packed pixels of a `TextureCopy`.
+struct CapturedPixels {
+    pixels: Vec<u8>,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+}
+
+impl CapturedPixels {
+    fn into_image(self) -> image::DynamicImage {
+        let CapturedPixels { pixels, format, width, height } = self;
+
+        match format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {
+                image::DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, pixels).unwrap())
+            },
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+                let pixels = pixels.chunks(4).flat_map(|p| [p[2], p[1], p[0], p[3]]).collect();
+                image::DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, pixels).unwrap())
+            },
+            wgpu::TextureFormat::Rgba16Float => {
+                let pixels = pixels.chunks(2).map(|p| half::f16::from_ne_bytes([p[0], p[1]]).to_f32()).collect();
+                image::DynamicImage::ImageRgba32F(image::Rgba32FImage::from_raw(width, height, pixels).unwrap())
+            },
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// A window frame being copied back to the cpu, it's saved on another thread once the copy finishes so rendering doesn't wait on it.
+pub(crate) struct PendingCapture {
+    copy: TextureCopy,
+    mapped: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+    /// `Some` once the copy finished
+    result: Option<Result<(), CaptureError>>,
+    paths: Vec<PathBuf>,
+}
+
+impl PendingCapture {
+    pub(crate) fn new(renderer: &Renderer, texture: &wgpu::Texture, format: wgpu::TextureFormat, width: u32, height: u32, paths: Vec<PathBuf>) -> Result<PendingCapture, CaptureError> {
+        let copy = TextureCopy::new(renderer, texture, format, width, height, 0)?;
+        let mapped = copy.map();
+
+        Ok(PendingCapture {
+            copy,
+            mapped,
+            result: None,
+            paths,
+        })
+    }
+
+    /// Whether the copy finished, the device has to be polled for it to finish.
+    pub(crate) fn copied(&mut self) -> bool {
+        if self.result.is_none() {
+            self.result = match self.mapped.try_recv() {
+                Ok(result) => Some(result.map_err(CaptureError::BufferAsyncError)),
+                Err(mpsc::TryRecvError::Empty) => None,
+                Err(mpsc::TryRecvError::Disconnected) => panic!("Capture buffer mapping callback was dropped."),
+            };
+        }
+
+        self.result.is_some()
+    }
+
+    /// Saves the finished capture on another thread.
+    pub(crate) fn save(self) -> std::thread::JoinHandle<()> {
+        let PendingCapture { copy, result, paths, .. } = self;
+        let pixels = result.expect("Capture saved before it was copied.").map(|()| copy.read());
+
+        std::thread::spawn(move || save_captures(pixels.map(CapturedPixels::into_image), &paths))
+    }
+}
+
+/// Texture frames are drawn into when they need to be captured from a window, as swapchain textures can't be copied from on every backend.
+/// It's blitted to the swapchain afterwards, see `blit_to_surface`.
+pub(crate) fn create_capture_texture(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Capture Texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::TEXTURE_BINDING,
+    })
+}
+
+/// Draws a capture texture into the window's swapchain texture.
+pub(crate) fn blit_to_surface(renderer: &Renderer, resources: &mut Resources, texture: &wgpu::Texture, view: &wgpu::TextureView) {
+    let properties = PipelineProperties {
+        transparent: false, double_sided: false,
+        colour_format: Some(renderer.colour_format()), depth_format: None, sample_count: 1, weight_format: None,
+    };
+
+    let shader = if let Some(shader) = resources.get_engine_global_mut::<Shader>("capture::blit_pipeline") {
+        shader.prepare_pipeline(renderer, properties);
+        &*shader
+    } else {
+        let mut shader = Shader::from_resource(renderer, "pipelines/builtin/blit.ron").expect("Blit shader not present.");
+        shader.prepare_pipeline(renderer, properties);
+        resources.set_engine_global("capture::blit_pipeline", shader);
+        resources.get_engine_global::<Shader>("capture::blit_pipeline").expect("unreachable")
+    };
+
+    // same size, so nearest sampling copies the texture exactly
+    let sampler = renderer.device.create_sampler(&wgpu::SamplerDescriptor::default());
+    let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let bind_group = renderer.device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Capture Blit Bind Group"),
+        layout: &shader.bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+    });
+
+    let mut encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Capture Blit Encoder"),
+    });
+
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Capture Blit"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(shader.get_pipeline(properties).unwrap());
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    renderer.queue.submit(std::iter::once(encoder.finish()));
+}
+
+pub(crate) fn save_captures(image: Result<image::DynamicImage, CaptureError>, paths: &[PathBuf]) {
+    match image {
+        Ok(image) => for path in paths {
+            match save_image(&image, path) {
+                Ok(_) => log::debug!("Saved frame capture {path:?}"),
+                Err(err) => log::error!("Failed to save frame capture {path:?}: {err}"),
+            }
+        },
+        Err(err) => log::error!("Failed to capture frame: {err}"),
+    }
+}
+
+/// Saves an image, converting it to a pixel format the encoder supports (i.e. Radiance HDR only encodes 32-bit float rgb).
+pub fn save_image<P: AsRef<Path>>(image: &image::DynamicImage, path: P) -> Result<(), CaptureError> {
+    let path = path.as_ref();
+
+    let extension = path.extension().map(|ext| ext.to_string_lossy().to_lowercase());
+
+    match extension.as_deref() {
+        Some("hdr") => {
+            let mut float_image = image.to_rgb32f();
+            // 8 and 16 bit images are srgb encoded, radiance files are linear
+            if !is_float(image) {
+                for channel in float_image.iter_mut() {
+                    *channel = srgb_to_linear(*channel);
+                }
+            }
+            let image = float_image;
+            let file = std::io::BufWriter::new(std::fs::File::create(path).map_err(|err| CaptureError::ImageError(err.into()))?);
+            let pixels: Vec<_> = image.pixels().copied().collect();
+            image::codecs::hdr::HdrEncoder::new(file).encode(&pixels, image.width() as usize, image.height() as usize)
+        },
+        // float images are linear, integer formats are srgb encoded
+        Some("png") | Some("tif") | Some("tiff") if is_float(image) => image::DynamicImage::ImageRgba16(encode_srgb(image).to_rgba16()).save(path),
+        _ if is_float(image) => image::DynamicImage::ImageRgba8(encode_srgb(image).to_rgba8()).save(path),
+        _ => image.save(path),
+    }.map_err(CaptureError::ImageError)
+}
+
+fn is_float(image: &image::DynamicImage) -> bool {
+    matches!(image, image::DynamicImage::ImageRgba32F(_) | image::DynamicImage::ImageRgb32F(_))
+}
+
+/// Applies the srgb transfer function to the colour of a linear image, clamped to [0, 1].
+fn encode_srgb(image: &image::DynamicImage) -> image::DynamicImage {
+    let mut image = image.to_rgba32f();
+
+    for pixel in image.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = linear_to_srgb(channel.clamp(0.0, 1.0));
+        }
+    }
+
+    image::DynamicImage::ImageRgba32F(image)
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 }
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    NotOffscreen,
+    UnsupportedFormat(wgpu::TextureFormat),
+    BufferAsyncError(wgpu::BufferAsyncError),
+    ImageError(image::ImageError),
+}
+
+impl std::fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::NotOffscreen => write!(f, "Only offscreen renderers keep their colour target, use FrameCapture to capture window frames."),
+            CaptureError::UnsupportedFormat(format) => write!(f, "Capturing textures of format {format:?} is not supported."),
+            CaptureError::BufferAsyncError(err) => err.fmt(f),
+            CaptureError::ImageError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
@@ -0,0 +1,738 @@
+use std::{collections::{BTreeSet, HashMap, HashSet}, path::{Path, PathBuf}};
+
+use crate::{components::PostProcessing, resource::{Handle, Resources, Texture}};
+
+use super::{Renderer, Shader, ShaderInput, ShaderLoadError, BindingResourceType, BindingHolder, BindGroupCache, PipelineProperties, QueuedRenderObject};
+
+/// Declarative description of how a frame is rendered, loaded from RON like `ShaderResource`.
+///
+/// Passes draw into render targets, and may sample targets written by earlier passes. Passes are scheduled by their
+/// dependencies rather than declaration order, passes which don't contribute to `output` are skipped. The targets
+/// `output` (the window or offscreen colour texture) and `depth` (the renderer's depth texture, multisampled) always exist.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RenderGraphResource {
+    pub name: String,
+    #[serde(default)]
+    pub targets: Vec<TargetDescriptor>,
+    pub passes: Vec<PassDescriptor>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TargetDescriptor {
+    pub name: String,
+    pub format: TargetFormat,
+    #[serde(default)]
+    pub size: TargetSize,
+    /// Has `Renderer::sample_count()` samples, passes drawing into it must resolve it before it can be sampled.
+    #[serde(default)]
+    pub multisampled: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum TargetFormat {
+    /// Same format as `output`
+    Surface,
+    Rgba8Unorm,
+    Rgba8UnormSrgb,
+    Rgba16Float,
+    Rg16Float,
+    R16Float,
+    R8Unorm,
+    Depth32Float,
+}
+
+impl TargetFormat {
+    pub fn is_depth(&self) -> bool {
+        matches!(self, TargetFormat::Depth32Float)
+    }
+
+    fn to_wgpu(self, surface_format: wgpu::TextureFormat) -> wgpu::TextureFormat {
+        match self {
+            TargetFormat::Surface => surface_format,
+            TargetFormat::Rgba8Unorm => wgpu::TextureFormat::Rgba8Unorm,
+            TargetFormat::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            TargetFormat::Rgba16Float => wgpu::TextureFormat::Rgba16Float,
+            TargetFormat::Rg16Float => wgpu::TextureFormat::Rg16Float,
+            TargetFormat::R16Float => wgpu::TextureFormat::R16Float,
+            TargetFormat::R8Unorm => wgpu::TextureFormat::R8Unorm,
+            TargetFormat::Depth32Float => wgpu::TextureFormat::Depth32Float,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum TargetSize {
+    /// Same size as `output`
+    #[default]
+    Window,
+    /// `output` size multiplied by a factor, i.e. 0.5 for half resolution
+    Scaled(f32),
+    Fixed(u32, u32),
+}
+
+impl TargetSize {
+    fn resolve(&self, window: winit::dpi::PhysicalSize<u32>) -> (u32, u32) {
+        match self {
+            TargetSize::Window => (window.width, window.height),
+            TargetSize::Scaled(scale) => (((window.width as f32 * scale) as u32).max(1), ((window.height as f32 * scale) as u32).max(1)),
+            TargetSize::Fixed(width, height) => (*width, *height),
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PassDescriptor {
+    pub name: String,
+    pub kind: PassKind,
+    /// Targets sampled by the pass, bound in order to the `Manual(Sampler)` inputs of fullscreen shaders.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(default)]
+    pub colour: Option<String>,
+    #[serde(default)]
+    pub depth: Option<String>,
+    /// Single sample target the multisampled colour target is resolved into. Without MSAA the pass draws into it directly.
+    #[serde(default)]
+    pub resolve: Option<String>,
+    /// Loads the previous contents if `None`
+    #[serde(default)]
+    pub clear_colour: Option<(f64, f64, f64, f64)>,
+    /// Loads the previous contents if `None`
+    #[serde(default)]
+    pub clear_depth: Option<f32>,
+    /// Skips the pass on frames where the condition doesn't hold, it always runs if `None`.
+    #[serde(default)]
+    pub condition: Option<PassCondition>,
+}
+
+impl PassDescriptor {
+    fn writes(&self) -> impl Iterator<Item = &String> {
+        let weight: Vec<_> = match &self.kind {
+            PassKind::WeightedBlended { weight, weight_resolve } => std::iter::once(weight).chain(weight_resolve.iter()).collect(),
+            _ => vec![],
+        };
+
+        self.colour.iter().chain(self.depth.iter()).chain(self.resolve.iter()).chain(weight)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub enum PassKind {
+    /// Draws the extracted meshes with their own shaders.
+    Scene(SceneQueue),
+    /// Draws a fullscreen triangle, the shader (relative to the graph file) has no vertex buffers.
+    Fullscreen {
+        shader: String,
+    },
+    /// Draws `Transparency::WeightedBlended` meshes, see "Weighted Blended Order-Independent Transparency" (McGuire & Bavoil).
+    /// Accumulates weighted premultiplied colours into the rgb of `colour`, the product of their transparency into its alpha and
+    /// their weights into `weight` (a float target). Composite them over the opaque image with a fullscreen pass, i.e. `pipelines/post/oit_composite.ron`.
+    /// Without such a pass the meshes are sorted and blended like other transparent meshes.
+    WeightedBlended {
+        weight: String,
+        /// Like `PassDescriptor::resolve`, for `weight`.
+        #[serde(default)]
+        weight_resolve: Option<String>,
+    },
+}
+
+impl PassKind {
+    fn draws(&self, render_object: &QueuedRenderObject, order_independent: bool) -> bool {
+        match self {
+            PassKind::Scene(queue) => queue.contains(render_object) && !(order_independent && render_object.order_independent),
+            PassKind::Fullscreen { .. } => false,
+            PassKind::WeightedBlended { .. } => render_object.order_independent,
+        }
+    }
+}
+
+/// Checked each frame before the pass is executed, so passes of disabled effects cost nothing.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum PassCondition {
+    /// `PostProcessing::bloom_intensity` is above 0
+    Bloom,
+    /// `PostProcessing::fxaa` is set
+    Fxaa,
+    /// `Transparency::WeightedBlended` meshes are visible
+    OrderIndependent,
+    Not(Box<PassCondition>),
+}
+
+impl PassCondition {
+    fn holds(&self, post_processing: &PostProcessing, render_objects: &[QueuedRenderObject]) -> bool {
+        match self {
+            PassCondition::Bloom => post_processing.bloom_intensity > 0.0,
+            PassCondition::Fxaa => post_processing.fxaa,
+            PassCondition::OrderIndependent => render_objects.iter().any(|render_object| render_object.order_independent && render_object.num_visible_instances > 0),
+            PassCondition::Not(condition) => !condition.holds(post_processing, render_objects),
+        }
+    }
+}
+
+/// Meshes drawn by a scene pass. `Transparency::WeightedBlended` meshes are left to the `WeightedBlended` pass if the graph has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum SceneQueue {
+    All,
+    Opaque,
+    Transparent,
+}
+
+impl SceneQueue {
+    fn contains(&self, render_object: &QueuedRenderObject) -> bool {
+        match self {
+            SceneQueue::All => true,
+            SceneQueue::Opaque => !render_object.transparent,
+            SceneQueue::Transparent => render_object.transparent,
+        }
+    }
+}
+
+impl RenderGraphResource {
+    pub const OUTPUT: &'static str = "output";
+    pub const DEPTH: &'static str = "depth";
+
+    fn target_format(&self, name: &str) -> Option<TargetFormat> {
+        match name {
+            RenderGraphResource::OUTPUT => Some(TargetFormat::Surface),
+            RenderGraphResource::DEPTH => Some(TargetFormat::Depth32Float),
+            _ => self.targets.iter().find(|target| target.name == name).map(|target| target.format),
+        }
+    }
+
+    fn target_multisampled(&self, name: &str) -> bool {
+        match name {
+            RenderGraphResource::OUTPUT => false,
+            RenderGraphResource::DEPTH => true,
+            _ => self.targets.iter().any(|target| target.name == name && target.multisampled),
+        }
+    }
+
+    /// Validates the graph and returns the indices of the passes to execute, in order.
+    pub fn schedule(&self) -> Result<Vec<usize>, RenderGraphError> {
+        let mut names = HashSet::new();
+        for target in &self.targets {
+            if target.name == RenderGraphResource::OUTPUT || target.name == RenderGraphResource::DEPTH || !names.insert(&target.name) {
+                return Err(RenderGraphError::DuplicateTarget(target.name.clone()));
+            }
+        }
+
+        for pass in &self.passes {
+            let format = |target: &String| self.target_format(target).ok_or_else(|| RenderGraphError::UnknownTarget {
+                pass: pass.name.clone(),
+                target: target.clone(),
+            });
+            let invalid = |target: &String| RenderGraphError::InvalidAttachment {
+                pass: pass.name.clone(),
+                target: target.clone(),
+            };
+
+            if let Some(target) = &pass.colour {
+                if format(target)?.is_depth() {
+                    return Err(invalid(target));
+                }
+            }
+            if let Some(target) = &pass.depth {
+                if !format(target)?.is_depth() {
+                    return Err(invalid(target));
+                }
+            }
+            let check_resolve = |source: Option<&String>, target: Option<&String>| -> Result<(), RenderGraphError> {
+                if let Some(target) = target {
+                    let resolvable = match source {
+                        Some(source) => self.target_multisampled(source) && format(source)? == format(target)?,
+                        None => false,
+                    };
+                    if !resolvable || self.target_multisampled(target) {
+                        return Err(invalid(target));
+                    }
+                }
+                Ok(())
+            };
+            check_resolve(pass.colour.as_ref(), pass.resolve.as_ref())?;
+
+            if let (Some(colour), Some(depth)) = (&pass.colour, &pass.depth) {
+                if self.target_multisampled(colour) != self.target_multisampled(depth) {
+                    return Err(RenderGraphError::SampleCountMismatch(pass.name.clone()));
+                }
+            }
+            if let PassKind::WeightedBlended { weight, weight_resolve } = &pass.kind {
+                // both targets are resolved, or neither
+                if format(weight)?.is_depth() || pass.resolve.is_some() != weight_resolve.is_some() {
+                    return Err(invalid(weight));
+                }
+                check_resolve(Some(weight), weight_resolve.as_ref())?;
+
+                if pass.colour.as_ref().map(|colour| self.target_multisampled(colour)) != Some(self.target_multisampled(weight)) {
+                    return Err(RenderGraphError::SampleCountMismatch(pass.name.clone()));
+                }
+            }
+            for target in &pass.inputs {
+                // depth textures need a different binding type, only colour targets can be sampled
+                if format(target)?.is_depth() || self.target_multisampled(target) || target == RenderGraphResource::OUTPUT {
+                    return Err(invalid(target));
+                }
+                if pass.writes().any(|written| written == target) {
+                    return Err(RenderGraphError::Feedback {
+                        pass: pass.name.clone(),
+                        target: target.clone(),
+                    });
+                }
+            }
+
+            let has_attachment = match pass.kind {
+                PassKind::Scene(_) => pass.colour.is_some() || pass.depth.is_some(),
+                PassKind::Fullscreen { .. } | PassKind::WeightedBlended { .. } => pass.colour.is_some(),
+            };
+            if !has_attachment {
+                return Err(RenderGraphError::MissingAttachment(pass.name.clone()));
+            }
+        }
+
+        // dependencies[j] contains every pass which must run before pass j
+        let mut dependencies = vec![BTreeSet::new(); self.passes.len()];
+        for (j, pass) in self.passes.iter().enumerate() {
+            for (i, other) in self.passes.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                // read after write
+                let reads = pass.inputs.iter().any(|target| other.writes().any(|written| written == target));
+                // writes to the same target happen in declaration order, i.e. depth prepass then colour pass
+                let overwrites = i < j && pass.writes().any(|target| other.writes().any(|written| written == target));
+
+                if reads || overwrites {
+                    dependencies[j].insert(i);
+                }
+            }
+        }
+
+        // topological sort, preferring declaration order
+        let mut order = vec![];
+        let mut scheduled = vec![false; self.passes.len()];
+        while order.len() < self.passes.len() {
+            let next = (0..self.passes.len()).find(|&i| !scheduled[i] && dependencies[i].iter().all(|&dependency| scheduled[dependency]));
+
+            match next {
+                Some(i) => {
+                    scheduled[i] = true;
+                    order.push(i);
+                },
+                None => {
+                    let pass = (0..self.passes.len()).find(|&i| !scheduled[i]).unwrap();
+                    return Err(RenderGraphError::Cycle(self.passes[pass].name.clone()));
+                },
+            }
+        }
+
+        // cull passes which don't contribute to the output
+        let mut live: Vec<_> = self.passes.iter().map(|pass| pass.writes().any(|target| target == RenderGraphResource::OUTPUT)).collect();
+        if !live.iter().any(|live| *live) {
+            return Err(RenderGraphError::NoOutput);
+        }
+        for &i in order.iter().rev() {
+            if live[i] {
+                for &dependency in &dependencies[i] {
+                    live[dependency] = true;
+                }
+            }
+        }
+
+        Ok(order.into_iter().filter(|&i| live[i]).collect())
+    }
+}
+
+impl std::str::FromStr for RenderGraphResource {
+    type Err = RenderGraphError;
+
+    fn from_str(source: &str) -> Result<RenderGraphResource, RenderGraphError> {
+        ron::from_str(source).map_err(RenderGraphError::ParseError)
+    }
+}
+
+struct GraphTarget {
+    view: Handle<wgpu::TextureView>,
+    format: wgpu::TextureFormat,
+}
+
+/// A validated `RenderGraphResource` with its render targets and fullscreen shaders.
+pub struct RenderGraph {
+    resource: RenderGraphResource,
+    order: Vec<usize>,
+    targets: HashMap<String, GraphTarget>,
+    target_size: winit::dpi::PhysicalSize<u32>,
+    target_sample_count: u32,
+    fullscreen_shaders: HashMap<usize, Handle<Shader>>,
+    sampler: Handle<wgpu::Sampler>,
+    /// Has a `WeightedBlended` pass.
+    order_independent: bool,
+}
+
+impl RenderGraph {
+    pub const DEFAULT_PATH: &'static str = "pipelines/graphs/default.ron";
+
+    /// Relative shader paths are resolved from `directory`.
+    pub fn new<P: AsRef<Path>>(renderer: &Renderer, resources: &mut Resources, resource: RenderGraphResource, directory: P) -> Result<RenderGraph, RenderGraphError> {
+        let order = resource.schedule()?;
+
+        let mut fullscreen_shaders = hashmap!{};
+        for &index in &order {
+            if let PassKind::Fullscreen { shader } = &resource.passes[index].kind {
+                let shader = Shader::from_resource(renderer, directory.as_ref().join(shader)).map_err(RenderGraphError::ShaderLoadError)?;
+
+                let samplers = shader.inputs.iter().filter(|input| matches!(input, ShaderInput::Manual(BindingResourceType::Sampler))).count();
+                let inputs_match = samplers == resource.passes[index].inputs.len()
+                    && shader.inputs.iter().all(|input| matches!(input, ShaderInput::Manual(BindingResourceType::Sampler) | ShaderInput::Resource { .. }));
+                if !inputs_match {
+                    return Err(RenderGraphError::InputMismatch(resource.passes[index].name.clone()));
+                }
+
+                fullscreen_shaders.insert(index, resources.store(shader));
+            }
+        }
+
+        let sampler = renderer.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Render Graph Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let sampler = resources.store(sampler);
+
+        log::debug!("Render graph '{}' pass order: {:?}", resource.name, order.iter().map(|&i| &resource.passes[i].name).collect::<Vec<_>>());
+
+        let order_independent = order.iter().any(|&index| matches!(resource.passes[index].kind, PassKind::WeightedBlended { .. }));
+
+        Ok(RenderGraph {
+            resource,
+            order,
+            targets: hashmap!{},
+            target_size: winit::dpi::PhysicalSize::new(0, 0),
+            target_sample_count: 1,
+            fullscreen_shaders,
+            sampler,
+            order_independent,
+        })
+    }
+
+    pub fn from_resource<P: AsRef<Path>>(renderer: &Renderer, resources: &mut Resources, path: P) -> Result<RenderGraph, RenderGraphError> {
+        log::trace!("Loading Render Graph {}", path.as_ref().to_string_lossy());
+
+        let source = std::fs::read_to_string(&path).map_err(RenderGraphError::IoError)?;
+        let resource: RenderGraphResource = source.parse()?;
+        let directory = path.as_ref().parent().map(Path::to_owned).unwrap_or_else(|| PathBuf::from("./"));
+
+        RenderGraph::new(renderer, resources, resource, directory)
+    }
+
+    pub fn resource(&self) -> &RenderGraphResource {
+        &self.resource
+    }
+
+    /// Names of the passes that are executed, in order, including those skipped by their `PassCondition`.
+    pub fn pass_order(&self) -> Vec<&str> {
+        self.order.iter().map(|&i| self.resource.passes[i].name.as_str()).collect()
+    }
+
+    /// Creates the graph's targets, or recreates them if the output was resized or the sample count changed.
+    fn prepare_targets(&mut self, renderer: &Renderer, resources: &mut Resources) {
+        let size = renderer.size();
+        let sample_count = renderer.sample_count();
+        if size == self.target_size && sample_count == self.target_sample_count && self.targets.len() == self.resource.targets.len() {
+            return;
+        }
+
+        self.target_size = size;
+        self.target_sample_count = sample_count;
+        self.targets.clear();
+
+        for target in &self.resource.targets {
+            let (width, height) = target.size.resolve(size);
+            let format = target.format.to_wgpu(renderer.colour_format());
+            let sample_count = if target.multisampled { sample_count } else { 1 };
+            // multisampled textures can only be resolved
+            let usage = if sample_count > 1 {
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+            } else {
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC
+            };
+
+            let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some(&target.name),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage,
+            });
+            let view = resources.store(texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+            self.targets.insert(target.name.clone(), GraphTarget {
+                view,
+                format,
+            });
+        }
+    }
+
+    fn target_view<'a>(&self, renderer: &'a Renderer, resources: &'a Resources, output: &'a wgpu::TextureView, name: &str) -> &'a wgpu::TextureView {
+        match name {
+            RenderGraphResource::OUTPUT => output,
+            RenderGraphResource::DEPTH => renderer.depth_view(),
+            _ => self.targets[name].view.get(resources),
+        }
+    }
+
+    fn target_format(&self, renderer: &Renderer, name: &str) -> wgpu::TextureFormat {
+        match name {
+            RenderGraphResource::OUTPUT => renderer.colour_format(),
+            RenderGraphResource::DEPTH => Texture::DEPTH_FORMAT,
+            _ => self.targets[name].format,
+        }
+    }
+
+    fn pipeline_properties(&self, renderer: &Renderer, pass: &PassDescriptor) -> PipelineProperties {
+        let multisampled = pass.colour.iter().chain(pass.depth.iter()).any(|name| self.resource.target_multisampled(name));
+
+        PipelineProperties {
+            transparent: false,
+            double_sided: false,
+            colour_format: pass.colour.as_ref().map(|name| self.target_format(renderer, name)),
+            depth_format: pass.depth.as_ref().map(|name| self.target_format(renderer, name)),
+            sample_count: if multisampled { renderer.sample_count() } else { 1 },
+            weight_format: match &pass.kind {
+                PassKind::WeightedBlended { weight, .. } => Some(self.target_format(renderer, weight)),
+                _ => None,
+            },
+        }
+    }
+
+    pub(crate) fn execute(&mut self, renderer: &Renderer, resources: &mut Resources, bind_groups: &mut BindGroupCache, output: &wgpu::TextureView, render_objects: &[QueuedRenderObject], post_processing: &PostProcessing) {
+        self.prepare_targets(renderer, resources);
+
+        let order: Vec<usize> = self.order.iter().copied()
+            .filter(|&index| self.resource.passes[index].condition.as_ref().map_or(true, |condition| condition.holds(post_processing, render_objects)))
+            .collect();
+
+        // pipelines and fullscreen input bind groups have to be created before the render passes borrow them
+        let mut fullscreen_bind_groups = hashmap!{};
+        for &index in &order {
+            let pass = &self.resource.passes[index];
+            let properties = self.pipeline_properties(renderer, pass);
+
+            match &pass.kind {
+                PassKind::Scene(_) | PassKind::WeightedBlended { .. } => for render_object in render_objects.iter().filter(|render_object| render_object.num_visible_instances > 0 && pass.kind.draws(render_object, self.order_independent)) {
+                    let properties = PipelineProperties {
+                        transparent: render_object.transparent,
+                        double_sided: render_object.double_sided,
+                        ..properties
+                    };
+
+                    let mut shader_handle = render_object.shader.clone();
+                    let shader = shader_handle.get_mut(resources);
+                    if shader.get_pipeline(properties).is_none() {
+                        shader.prepare_pipeline(renderer, properties);
+                    }
+                },
+                PassKind::Fullscreen { .. } => {
+                    let properties = PipelineProperties {
+                        double_sided: true,
+                        ..properties
+                    };
+
+                    let mut shader_handle = self.fullscreen_shaders[&index].clone();
+                    let shader = shader_handle.get_mut(resources);
+                    if shader.get_pipeline(properties).is_none() {
+                        shader.prepare_pipeline(renderer, properties);
+                    }
+
+                    let mut binding_resources = vec![];
+                    let mut targets = pass.inputs.iter();
+
+                    for input in &shader_handle.get(resources).inputs {
+                        match input {
+                            ShaderInput::Resource { ty, res } => binding_resources.extend(ty.global_binding_resources(resources, res)),
+                            _ => {
+                                binding_resources.push(BindingHolder::Texture(self.targets[targets.next().unwrap()].view.clone()));
+                                binding_resources.push(BindingHolder::Sampler(self.sampler.clone()));
+                            },
+                        }
+                    }
+
+                    fullscreen_bind_groups.insert(index, bind_groups.get_or_create(renderer, resources, &shader_handle, binding_resources));
+                },
+            }
+        }
+
+        let resources = &*resources;
+
+        let mut encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder"),
+        });
+
+        for &index in &order {
+            let pass = &self.resource.passes[index];
+            let properties = self.pipeline_properties(renderer, pass);
+
+            // without MSAA there's nothing to resolve, the resolve target is drawn into instead
+            let (colour, resolve) = match &pass.resolve {
+                Some(resolve) if renderer.sample_count() == 1 => (Some(resolve), None),
+                resolve => (pass.colour.as_ref(), resolve.as_ref()),
+            };
+
+            let clear_colour = match pass.kind {
+                PassKind::WeightedBlended { .. } => Some((0.0, 0.0, 0.0, 1.0)),
+                _ => pass.clear_colour,
+            };
+
+            let colour_attachment = colour.map(|name| wgpu::RenderPassColorAttachment {
+                view: self.target_view(renderer, resources, output, name),
+                resolve_target: resolve.map(|name| self.target_view(renderer, resources, output, name)),
+                ops: wgpu::Operations {
+                    load: match clear_colour {
+                        Some((r, g, b, a)) => wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a }),
+                        None => wgpu::LoadOp::Load,
+                    },
+                    store: true,
+                },
+            });
+
+            let weight_attachment = match &pass.kind {
+                PassKind::WeightedBlended { weight, weight_resolve } => {
+                    let (weight, resolve) = match weight_resolve {
+                        Some(resolve) if renderer.sample_count() == 1 => (resolve, None),
+                        resolve => (weight, resolve.as_ref()),
+                    };
+
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: self.target_view(renderer, resources, output, weight),
+                        resolve_target: resolve.map(|name| self.target_view(renderer, resources, output, name)),
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    })
+                },
+                _ => None,
+            };
+
+            let depth_attachment = pass.depth.as_ref().map(|name| wgpu::RenderPassDepthStencilAttachment {
+                view: self.target_view(renderer, resources, output, name),
+                depth_ops: Some(wgpu::Operations {
+                    load: match pass.clear_depth {
+                        Some(depth) => wgpu::LoadOp::Clear(depth),
+                        None => wgpu::LoadOp::Load,
+                    },
+                    store: true,
+                }),
+                stencil_ops: None,
+            });
+
+            // depth only pipelines have no colour targets at all, rather than an empty one
+            let colour_attachments: Vec<_> = colour_attachment.into_iter().chain(weight_attachment).map(Some).collect();
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(&pass.name),
+                color_attachments: &colour_attachments,
+                depth_stencil_attachment: depth_attachment,
+            });
+
+            // opaque objects are sorted by pipeline, so consecutive objects mostly share one
+            let mut current_pipeline: Option<&wgpu::RenderPipeline> = None;
+
+            match &pass.kind {
+                PassKind::Scene(_) | PassKind::WeightedBlended { .. } => for render_object in render_objects.iter().filter(|render_object| render_object.num_visible_instances > 0 && pass.kind.draws(render_object, self.order_independent)) {
+                    let shader = render_object.shader.get(resources);
+                    let pipeline = shader.get_pipeline(PipelineProperties {
+                        transparent: render_object.transparent,
+                        double_sided: render_object.double_sided,
+                        ..properties
+                    }).unwrap();
+
+                    let vertex_buffer = render_object.vertex_buffer.get(resources);
+                    let index_buffer = render_object.index_buffer.get(resources);
+
+                    if !current_pipeline.map_or(false, |current| std::ptr::eq(current, pipeline)) {
+                        render_pass.set_pipeline(pipeline);
+                        current_pipeline = Some(pipeline);
+                    }
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    render_pass.set_bind_group(0, render_object.bind_group.get(resources), &[]);
+
+                    if shader.vertex_type().instanced() {
+                        render_pass.set_vertex_buffer(1, render_object.instances.get(resources).slice(..));
+                        render_pass.draw_indexed(0..render_object.num_indices, 0, 0..render_object.num_visible_instances);
+                    } else {
+                        render_pass.draw_indexed(0..render_object.num_indices, 0, 0..1);
+                    }
+                },
+                PassKind::Fullscreen { .. } => {
+                    let pipeline = self.fullscreen_shaders[&index].get(resources).get_pipeline(PipelineProperties {
+                        double_sided: true,
+                        ..properties
+                    }).unwrap();
+
+                    render_pass.set_pipeline(pipeline);
+                    render_pass.set_bind_group(0, fullscreen_bind_groups[&index].get(resources), &[]);
+                    render_pass.draw(0..3, 0..1);
+                },
+            }
+        }
+
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+    }
+}
+
+#[derive(Debug)]
+pub enum RenderGraphError {
+    IoError(std::io::Error),
+    ParseError(ron::error::SpannedError),
+    ShaderLoadError(ShaderLoadError),
+    DuplicateTarget(String),
+    UnknownTarget {
+        pass: String,
+        target: String,
+    },
+    /// A depth target used as a colour attachment or input, a multisampled input, or an invalid resolve or weight target
+    InvalidAttachment {
+        pass: String,
+        target: String,
+    },
+    /// A pass samples a target it also draws into
+    Feedback {
+        pass: String,
+        target: String,
+    },
+    MissingAttachment(String),
+    /// The colour, depth and weight targets of a pass aren't all multisampled
+    SampleCountMismatch(String),
+    /// A fullscreen shader's inputs don't match the pass inputs, or aren't global resources
+    InputMismatch(String),
+    Cycle(String),
+    NoOutput,
+}
+
+impl std::fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderGraphError::IoError(err) => err.fmt(f),
+            RenderGraphError::ParseError(err) => err.fmt(f),
+            RenderGraphError::ShaderLoadError(err) => err.fmt(f),
+            RenderGraphError::DuplicateTarget(target) => write!(f, "Render target '{target}' is declared more than once, or uses a reserved name."),
+            RenderGraphError::UnknownTarget { pass, target } => write!(f, "Pass '{pass}' uses undeclared render target '{target}'."),
+            RenderGraphError::InvalidAttachment { pass, target } => write!(f, "Pass '{pass}' can't use render target '{target}' that way, check its format."),
+            RenderGraphError::Feedback { pass, target } => write!(f, "Pass '{pass}' samples render target '{target}' while drawing into it."),
+            RenderGraphError::MissingAttachment(pass) => write!(f, "Pass '{pass}' has no target to draw into."),
+            RenderGraphError::SampleCountMismatch(pass) => write!(f, "Pass '{pass}' draws into multisampled and single sample targets."),
+            RenderGraphError::InputMismatch(pass) => write!(f, "The shader of pass '{pass}' must have one Manual(Sampler) input per pass input, other inputs must be global Resources."),
+            RenderGraphError::Cycle(pass) => write!(f, "Pass '{pass}' is part of a dependency cycle."),
+            RenderGraphError::NoOutput => write!(f, "No pass draws into '{}'.", RenderGraphResource::OUTPUT),
+        }
+    }
+}
+
+impl std::error::Error for RenderGraphError {}
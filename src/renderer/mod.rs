@@ -4,26 +4,45 @@ use winit::window::Window;
 
 mod pipeline;
 mod window;
+mod offscreen;
+mod capture;
+mod graph;
+mod shadow;
 mod uniform;
 mod vertex;
+mod arena;
 
 pub use pipeline::*;
 pub use window::*;
+pub use offscreen::*;
+pub use capture::*;
+pub use graph::*;
+pub use shadow::*;
 pub use uniform::*;
 pub use vertex::*;
+pub use arena::*;
 
-use crate::{resource::{Material, Handle, Texture, Resources}, node::{NodeDescriptor, Component}, util::AsAny};
+use crate::{resource::{Material, Handle, Texture, Resources}, node::{NodeDescriptor, Component}, util::AsAny, engine::Aabb};
 
 pub struct Renderer {
     pub(crate) device: wgpu::Device,
     pub(crate) queue: wgpu::Queue,
-    pub(crate) window: WindowAdapter,
+    pub(crate) surface: RenderSurface,
+    msaa_supported: bool,
+    pub(crate) backend: wgpu::Backend,
     // pub(crate) bind_group_layouts: HashMap<BindGroupLayoutType, wgpu::BindGroupLayout>,
     // pub(crate) vertex_layouts: HashMap<VertexLayoutType, wgpu::VertexBufferLayout<'a>>,
 }
 
+/// Where the renderer draws each frame, either the swapchain of a window or an offscreen texture.
+pub enum RenderSurface {
+    Window(WindowAdapter),
+    Offscreen(OffscreenAdapter),
+}
+
 impl Renderer {
-    pub async fn new(window: Window, vsync: bool) -> Renderer {
+    /// `sample_count` is the number of MSAA samples, see `Renderer::set_sample_count`.
+    pub async fn new(window: Window, vsync: bool, sample_count: u32) -> Renderer {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::Backends::all());
@@ -37,13 +56,17 @@ impl Renderer {
         let (device, queue) = adapter.request_device(
         &wgpu::DeviceDescriptor {
             label: Some("device"),
-            features: wgpu::Features::empty(),
+            features: adapter.features() & Renderer::OPTIONAL_FEATURES,
             limits: wgpu::Limits::default(),
         }, None).await.unwrap();
 
+        let format = surface.get_supported_formats(&adapter)[0];
+        let msaa_supported = Renderer::msaa_supported(&adapter, format);
+        let sample_count = Renderer::supported_sample_count(msaa_supported, sample_count);
+
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface.get_supported_formats(&adapter)[0],
+            format,
             width: size.width,
             height: size.height,
             present_mode: if vsync { wgpu::PresentMode::AutoVsync } else { wgpu::PresentMode::AutoNoVsync },
@@ -51,7 +74,7 @@ impl Renderer {
         };
         surface.configure(&device, &config);
 
-        let depth_texture = Texture::create_depth_texture(&device, &config, "Depth Texture");
+        let depth_texture = Texture::create_depth_texture(&device, config.width, config.height, sample_count, "Depth Texture");
 
         let window = WindowAdapter {
             window,
@@ -61,26 +84,218 @@ impl Renderer {
             depth_texture,
             focused: false,
             vsync,
+            sample_count,
         };
 
         Renderer {
             device,
             queue,
-            window,
+            surface: RenderSurface::Window(window),
+            msaa_supported,
+            backend: adapter.get_info().backend,
             // bind_group_layouts,
             // vertex_layouts,
         }
     }
 
+    /// Renders into an offscreen texture instead of a window. Falls back to a software adapter if no hardware adapter is available.
+    pub async fn new_headless(width: u32, height: u32) -> Result<Renderer, RendererError> {
+        let instance = wgpu::Instance::new(wgpu::Backends::all());
+
+        let adapter = match instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }).await {
+            Some(adapter) => adapter,
+            None => instance.request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::LowPower,
+                force_fallback_adapter: true,
+                compatible_surface: None,
+            }).await.ok_or(RendererError::AdapterNotFound)?,
+        };
+
+        log::info!("Using headless adapter {:?}", adapter.get_info());
+
+        let (device, queue) = adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: Some("device"),
+            features: adapter.features() & Renderer::OPTIONAL_FEATURES,
+            // software adapters do not necessarily meet the default limits
+            limits: adapter.limits(),
+        }, None).await.map_err(RendererError::RequestDeviceError)?;
+
+        let offscreen = OffscreenAdapter::new(&device, winit::dpi::PhysicalSize::new(width.max(1), height.max(1)));
+
+        Ok(Renderer {
+            device,
+            queue,
+            surface: RenderSurface::Offscreen(offscreen),
+            msaa_supported: Renderer::msaa_supported(&adapter, OffscreenAdapter::COLOUR_FORMAT),
+            backend: adapter.get_info().backend,
+        })
+    }
+
     pub fn on_resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        self.window.resize(&self.device, new_size);
+        match &mut self.surface {
+            RenderSurface::Window(window) => window.resize(&self.device, new_size),
+            RenderSurface::Offscreen(offscreen) => offscreen.resize(&self.device, new_size),
+        }
     }
 
     pub fn reconfigure_surface(&mut self) {
-        self.window.reconfigure(&self.device);
+        if let RenderSurface::Window(window) = &mut self.surface {
+            window.reconfigure(&self.device);
+        }
+    }
+
+    /// Features used when the adapter has them.
+    const OPTIONAL_FEATURES: wgpu::Features = wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+        .union(wgpu::Features::TEXTURE_COMPRESSION_BC)
+        .union(wgpu::Features::TEXTURE_COMPRESSION_ETC2)
+        .union(wgpu::Features::TEXTURE_COMPRESSION_ASTC_LDR);
+
+    /// Multisampling the float formats the default render graph draws into depends on the adapter.
+    fn msaa_supported(adapter: &wgpu::Adapter, colour_format: wgpu::TextureFormat) -> bool {
+        let supports = |format, flags| adapter.get_texture_format_features(format).flags.contains(flags);
+        let resolvable = wgpu::TextureFormatFeatureFlags::MULTISAMPLE | wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE;
+
+        adapter.features().contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            && [wgpu::TextureFormat::Rgba16Float, wgpu::TextureFormat::R16Float, colour_format].into_iter().all(|format| supports(format, resolvable))
+            && supports(Texture::DEPTH_FORMAT, wgpu::TextureFormatFeatureFlags::MULTISAMPLE)
+    }
+
+    /// Without extra features only 1 (disabled) and 4 samples are guaranteed to be supported.
+    fn supported_sample_count(msaa_supported: bool, sample_count: u32) -> u32 {
+        match sample_count {
+            0 | 1 => 1,
+            _ if !msaa_supported => {
+                log::warn!("MSAA isn't supported by the adapter, disabling it.");
+                1
+            },
+            4 => 4,
+            _ => {
+                log::warn!("Unsupported MSAA sample count {sample_count}, using 4.");
+                4
+            },
+        }
+    }
+
+    /// Sets the number of MSAA samples used by multisampled render graph targets and the depth texture, 1 disables MSAA.
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        let sample_count = Renderer::supported_sample_count(self.msaa_supported, sample_count);
+
+        match &mut self.surface {
+            RenderSurface::Window(window) => {
+                window.sample_count = sample_count;
+                window.reconfigure(&self.device);
+            },
+            RenderSurface::Offscreen(offscreen) => {
+                offscreen.sample_count = sample_count;
+                offscreen.resize(&self.device, offscreen.size);
+            },
+        }
+    }
+
+    /// Optional features enabled on the device, e.g. which compressed texture formats can be uploaded as is.
+    pub fn features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
+    /// Runs `f`, returning the validation error it raised instead of panicking (i.e. a shader that doesn't compile).
+    pub(crate) fn validate<T>(&self, f: impl FnOnce() -> T) -> Result<T, wgpu::Error> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let value = f();
+
+        // some backends only resolve error scopes once the device is polled
+        self.device.poll(wgpu::Maintain::Wait);
+        match pollster::block_on(self.device.pop_error_scope()) {
+            None => Ok(value),
+            Some(err) => Err(err),
+        }
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        match &self.surface {
+            RenderSurface::Window(window) => window.sample_count,
+            RenderSurface::Offscreen(offscreen) => offscreen.sample_count,
+        }
+    }
+
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        match &self.surface {
+            RenderSurface::Window(window) => window.size,
+            RenderSurface::Offscreen(offscreen) => offscreen.size,
+        }
+    }
+
+    pub fn aspect(&self) -> f32 {
+        let size = self.size();
+        size.width as f32 / size.height as f32
+    }
+
+    /// Offscreen renderers are always considered focused.
+    pub fn focused(&self) -> bool {
+        match &self.surface {
+            RenderSurface::Window(window) => window.focused,
+            RenderSurface::Offscreen(_) => true,
+        }
+    }
+
+    pub fn window(&self) -> Option<&WindowAdapter> {
+        match &self.surface {
+            RenderSurface::Window(window) => Some(window),
+            RenderSurface::Offscreen(_) => None,
+        }
+    }
+
+    pub fn window_mut(&mut self) -> Option<&mut WindowAdapter> {
+        match &mut self.surface {
+            RenderSurface::Window(window) => Some(window),
+            RenderSurface::Offscreen(_) => None,
+        }
+    }
+
+    pub(crate) fn colour_format(&self) -> wgpu::TextureFormat {
+        match &self.surface {
+            RenderSurface::Window(window) => window.config.format,
+            RenderSurface::Offscreen(offscreen) => offscreen.format,
+        }
+    }
+
+    pub(crate) fn depth_view(&self) -> &wgpu::TextureView {
+        match &self.surface {
+            RenderSurface::Window(window) => &window.depth_texture,
+            RenderSurface::Offscreen(offscreen) => &offscreen.depth_texture,
+        }
     }
+
+    /// Reads back the last frame rendered offscreen.
+    pub fn capture_frame(&self) -> Result<image::DynamicImage, CaptureError> {
+        match &self.surface {
+            RenderSurface::Window(_) => Err(CaptureError::NotOffscreen),
+            RenderSurface::Offscreen(offscreen) => read_texture(self, &offscreen.texture, offscreen.format, offscreen.size.width, offscreen.size.height, 0),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RendererError {
+    AdapterNotFound,
+    RequestDeviceError(wgpu::RequestDeviceError),
 }
 
+impl std::fmt::Display for RendererError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RendererError::AdapterNotFound => write!(f, "No compatible graphics adapter found."),
+            RendererError::RequestDeviceError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RendererError {}
+
 pub trait RenderTarget {
     fn view(&self) -> Handle<wgpu::TextureView>;
 }
@@ -90,10 +305,38 @@ pub(crate) struct QueuedRenderObject {
     // pub target: Handle<Box<dyn RenderTarget>>,
     pub vertex_buffer: Handle<wgpu::Buffer>,
     pub index_buffer: Handle<wgpu::Buffer>,
-    pub bind_group: wgpu::BindGroup,
+    pub bind_group: Handle<wgpu::BindGroup>,
+    /// `InstanceTransform`s of every instance, visible ones first. Shaders without the `InstancedModel` vertex type draw only one.
+    pub instances: Handle<wgpu::Buffer>,
+    pub num_instances: u32,
+    /// Instances inside the camera frustum, culled instances are still drawn into shadow maps.
+    pub num_visible_instances: u32,
     pub num_indices: u32,
     pub transparent: bool,
     pub double_sided: bool,
+    /// Transparent with `Transparency::WeightedBlended`, and the shader supports it.
+    pub order_independent: bool,
+    /// Joint matrices of meshes with the `SkinnedModel` vertex type, for shadow maps.
+    pub joints: Option<BindingHolder>,
+    /// Bindings of the `MeshMorphTargets` input of shaders with one, for shadow maps.
+    pub morph_targets: Option<Vec<BindingHolder>>,
+}
+
+/// Statistics of the last extracted frame, stored in the global resource `tritium::render_stats`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderStats {
+    /// Mesh instances queued for rendering, including culled ones.
+    pub objects: usize,
+    /// Mesh instances outside the camera frustum.
+    pub culled: usize,
+    /// Draw calls of each scene pass, after batching instances.
+    pub draws: usize,
+    /// Bind groups created this frame, others were reused from previous frames.
+    pub bind_groups_created: usize,
+}
+
+impl RenderStats {
+    pub const KEY: &'static str = "tritium::render_stats";
 }
 
 // #[derive(Debug, Clone)]
@@ -104,7 +347,13 @@ pub enum RenderInput {
         index_buffer: Handle<wgpu::Buffer>,
         material: Option<Handle<Material>>,
         num_elements: u32,
+        /// Object space bounds, transformed by the node's `Transform`.
+        bounds: Aabb,
+        /// Deltas of the mesh's morph targets, see `MorphTargets`.
+        morph_targets: Option<BindingHolder>,
     },
+    /// Draws the node's meshes once per transform, relative to the node, see `InstancedModel`.
+    Instances(Vec<glam::Mat4>),
     BindingResources(String, Vec<BindingHolder>),
     SceneInput(String, SceneInputItem),
 }
@@ -0,0 +1,51 @@
+use crate::resource::Texture;
+
+pub struct OffscreenAdapter {
+    pub(crate) texture: wgpu::Texture,
+    pub(crate) format: wgpu::TextureFormat,
+    pub(crate) size: winit::dpi::PhysicalSize<u32>,
+    pub(crate) depth_texture: wgpu::TextureView,
+    pub(crate) sample_count: u32,
+}
+
+impl OffscreenAdapter {
+    /// Matches the srgb swapchain formats used for windows, so shaders produce identical output in both modes.
+    pub const COLOUR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    pub fn new(device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) -> OffscreenAdapter {
+        let texture = OffscreenAdapter::create_colour_texture(device, size);
+        let depth_texture = Texture::create_depth_texture(device, size.width, size.height, 1, "Offscreen Depth Texture");
+
+        OffscreenAdapter {
+            texture,
+            format: OffscreenAdapter::COLOUR_FORMAT,
+            size,
+            depth_texture,
+            sample_count: 1,
+        }
+    }
+
+    fn create_colour_texture(device: &wgpu::Device, size: winit::dpi::PhysicalSize<u32>) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Colour Texture"),
+            size: wgpu::Extent3d {
+                width: size.width,
+                height: size.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: OffscreenAdapter::COLOUR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        })
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = new_size;
+            self.texture = OffscreenAdapter::create_colour_texture(device, new_size);
+            self.depth_texture = Texture::create_depth_texture(device, new_size.width, new_size.height, self.sample_count, "Offscreen Depth Texture");
+        }
+    }
+}
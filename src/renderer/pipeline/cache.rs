@@ -1,25 +1,34 @@
 use std::collections::HashMap;
 
-use crate::renderer::{VertexLayoutType, Renderer, PositionVertex, ModelVertex, Vertex};
+use crate::renderer::{VertexLayoutType, Renderer, PositionVertex, ModelVertex, SkinnedVertex, Vertex, InstanceTransform};
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub(crate) struct PipelineProperties {
     pub transparent: bool,
     pub double_sided: bool,
-    pub colour_format: wgpu::TextureFormat,
+    /// Depth-only pipelines (i.e. shadow maps) have no colour target and no fragment stage.
+    pub colour_format: Option<wgpu::TextureFormat>,
     pub depth_format: Option<wgpu::TextureFormat>,
+    /// MSAA samples of the targets, 1 without multisampling.
+    pub sample_count: u32,
+    /// Weighted blended OIT pipelines use the entry point `fs_oit`, accumulating into the colour target and a second target of this format.
+    pub weight_format: Option<wgpu::TextureFormat>,
 }
 
 pub(crate) struct PipelineCache {
     pipelines: HashMap<PipelineProperties, wgpu::RenderPipeline>,
     shader: wgpu::ShaderModule,
     vertex_type: VertexLayoutType,
+    vertex_entry_point: String,
+    blended: bool,
 }
 
 impl PipelineCache {
     pub fn new<'a>(
         renderer: &Renderer,
         vertex_type: VertexLayoutType,
+        vertex_entry_point: &str,
+        blended: bool,
         shader: wgpu::ShaderModuleDescriptor,
     ) -> PipelineCache {
         let shader = renderer.device.create_shader_module(shader);
@@ -28,9 +37,15 @@ impl PipelineCache {
             pipelines: hashmap!{},
             shader,
             vertex_type,
+            vertex_entry_point: vertex_entry_point.into(),
+            blended,
         }
     }
 
+    pub fn prepared(&self) -> impl Iterator<Item = PipelineProperties> + '_ {
+        self.pipelines.keys().copied()
+    }
+
     pub fn prepare_pipeline(&mut self, renderer: &Renderer, index: PipelineProperties, bind_group_layout: &wgpu::BindGroupLayout) {
         let cull_mode = if index.double_sided {
             None
@@ -46,26 +61,60 @@ impl PipelineCache {
             push_constant_ranges: &[],
         });
         
+        // both OIT targets share a blend state, GL backends may not support independent blending
+        let oit_blend = wgpu::BlendState {
+            // sum of weighted premultiplied colours, and of weights in the weight target
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            // product of (1 - alpha), the revealage
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+
+        let (fragment_entry_point, targets) = match index.weight_format {
+            Some(weight_format) => ("fs_oit", vec![
+                index.colour_format.map(|format| wgpu::ColorTargetState {
+                    format,
+                    blend: Some(oit_blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+                Some(wgpu::ColorTargetState {
+                    format: weight_format,
+                    blend: Some(oit_blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                }),
+            ]),
+            None => ("fs_main", vec![index.colour_format.map(|format| wgpu::ColorTargetState {
+                format,
+                blend: self.blended.then_some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })]),
+        };
+
         let pipeline = renderer.device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
             label: None,
             layout: Some(&layout),
             vertex: wgpu::VertexState {
                 module: &self.shader,
-                entry_point: "vs_main",
+                entry_point: &self.vertex_entry_point,
                 buffers: &match self.vertex_type {
                     VertexLayoutType::Position => vec![PositionVertex::desc()],
                     VertexLayoutType::Model => vec![ModelVertex::desc()],
+                    VertexLayoutType::InstancedModel => vec![ModelVertex::desc(), InstanceTransform::desc()],
+                    VertexLayoutType::SkinnedModel => vec![SkinnedVertex::desc(), InstanceTransform::desc()],
                     VertexLayoutType::None => vec![],
                 },
             },
-            fragment: Some(wgpu::FragmentState {
+            fragment: index.colour_format.map(|_| wgpu::FragmentState {
                 module: &self.shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: index.colour_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
+                entry_point: fragment_entry_point,
+                targets: &targets,
             }),
             primitive: wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
@@ -87,7 +136,7 @@ impl PipelineCache {
                 bias: wgpu::DepthBiasState::default(),
             }),
             multisample: wgpu::MultisampleState {
-                count: 1,
+                count: index.sample_count,
                 mask: !0,
                 alpha_to_coverage_enabled: false,
             },
@@ -100,4 +149,8 @@ impl PipelineCache {
     pub fn get_pipeline(&self, index: PipelineProperties) -> Option<&wgpu::RenderPipeline> {
         self.pipelines.get(&index)
     }
+
+    pub fn vertex_type(&self) -> VertexLayoutType {
+        self.vertex_type
+    }
 }
@@ -1,8 +1,8 @@
-use std::{path::Path, io::{BufReader, BufRead}};
+use std::{path::{Path, PathBuf}, io::{BufReader, BufRead}};
 
-use crate::{resource::{Texture, CubeMap, Material, Handle, Sampler, CubeSampler}};
+use crate::{resource::{Texture, CubeMap, Material, MorphTargets, Handle, Sampler, CubeSampler, Resources}};
 
-use super::{Renderer, VertexLayoutType, UniformBuffer, StorageBuffer};
+use super::{Renderer, VertexLayoutType, UniformBuffer, StorageBuffer, ShadowMaps};
 
 mod cache;
 
@@ -12,16 +12,20 @@ pub struct Shader {
     pipelines: PipelineCache,
     pub(crate) inputs: Vec<ShaderInput>,
     pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    /// Has an `fs_oit` entry point for weighted blended OIT.
+    pub(crate) order_independent: bool,
+    /// The resource file, shader file and its includes
+    pub(crate) sources: Vec<PathBuf>,
 }
 
 impl Shader {
     fn new(
         renderer: &Renderer,
-        name: &str,
-        shader_inputs: Vec<ShaderInput>,
-        vertex_type: VertexLayoutType,
+        resource: ShaderResource,
         shader: wgpu::ShaderModuleDescriptor,
+        sources: Vec<PathBuf>,
     ) -> Shader {
+        let ShaderResource { name, inputs: shader_inputs, vertex_type, vertex_entry_point, order_independent, blended, .. } = resource;
 
         let bind_group_layout_entries = {
             let mut bind_group_layout_entries = vec![];
@@ -36,6 +40,8 @@ impl Shader {
                     BindingResourceType::CubeSampler => CubeSampler::binding_types(),
                     BindingResourceType::Uniform => UniformBuffer::binding_types(),
                     BindingResourceType::Storage => StorageBuffer::binding_types(),
+                    BindingResourceType::ShadowMaps => ShadowMaps::binding_types(),
+                    BindingResourceType::MorphTargets => MorphTargets::binding_types(),
                 };
                 
                 for binding_type in binding_types {
@@ -59,9 +65,11 @@ impl Shader {
         });
 
         Shader {
-            pipelines: PipelineCache::new(renderer, vertex_type, shader),
+            pipelines: PipelineCache::new(renderer, vertex_type, &vertex_entry_point, blended, shader),
             inputs: shader_inputs,
             bind_group_layout,
+            order_independent,
+            sources,
         }
     }
 
@@ -69,26 +77,47 @@ impl Shader {
         renderer: &Renderer,
         path: P,
     ) -> Result<Shader, ShaderLoadError> {
-        log::trace!("Loading Pipeline {}", path.as_ref().to_str().unwrap());
+        Shader::load(renderer, path.as_ref(), None)
+    }
+
+    /// Loads the shader at `path` again, with the pipelines prepared so far, keeping this one if it doesn't compile.
+    pub(crate) fn reload<P: AsRef<Path>>(&self, renderer: &Renderer, path: P) -> Result<Shader, ShaderLoadError> {
+        Shader::load(renderer, path.as_ref(), Some(self.pipelines.prepared().collect()))
+    }
+
+    /// If `pipelines` is set they're prepared, and compile errors of the shader and them are returned rather than panicking.
+    /// Validating waits for the device to be idle, so it's only done when reloading.
+    pub(crate) fn load(renderer: &Renderer, path: &Path, pipelines: Option<Vec<PipelineProperties>>) -> Result<Shader, ShaderLoadError> {
+        log::trace!("Loading Pipeline {}", path.to_str().unwrap());
         
-        let resource: ShaderResource = ron::from_str(&std::fs::read_to_string(&path)
+        let resource: ShaderResource = ron::from_str(&std::fs::read_to_string(path)
             .map_err(|err| ShaderLoadError::IoError(err))?)
             .map_err(|err| ShaderLoadError::ParseError(err))?;
 
         let name = format!("{} Shader Module", &resource.name);
 
-        let shader_path = path.as_ref().parent().unwrap_or(&Path::new("./")).join(&resource.shader_file);
+        let shader_path = path.parent().unwrap_or(&Path::new("./")).join(&resource.shader_file);
 
         log::trace!("Loading Shader {}", shader_path.to_str().unwrap());
 
+        let mut sources = vec![path.to_owned()];
+
         let shader = wgpu::ShaderModuleDescriptor {
             label: Some(&name),
             source: wgpu::ShaderSource::Wgsl(
-                preprocess_wgsl(shader_path)?.into()
+                preprocess_wgsl(shader_path, &mut sources)?.into()
             ),
         };
 
-        Ok(Shader::new(renderer, &resource.name, resource.inputs, resource.vertex_type, shader))
+        let Some(pipelines) = pipelines else { return Ok(Shader::new(renderer, resource, shader, sources)) };
+
+        renderer.validate(|| {
+            let mut shader = Shader::new(renderer, resource, shader, sources);
+            for index in pipelines {
+                shader.prepare_pipeline(renderer, index);
+            }
+            shader
+        }).map_err(|err| ShaderLoadError::CompileError(err.to_string()))
     }
 
     pub(crate) fn prepare_pipeline(&mut self, renderer: &Renderer, index: PipelineProperties) {
@@ -98,6 +127,10 @@ impl Shader {
     pub(crate) fn get_pipeline(&self, index: PipelineProperties) -> Option<&wgpu::RenderPipeline> {
         self.pipelines.get_pipeline(index)
     }
+
+    pub(crate) fn vertex_type(&self) -> VertexLayoutType {
+        self.pipelines.vertex_type()
+    }
 }
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -108,6 +141,25 @@ pub struct ShaderResource {
     inputs: Vec<ShaderInput>,
     vertex_type: VertexLayoutType,
     shader_file: String,
+    /// Lets variants of a shader share a file, i.e. `skinned.ron` uses `vs_skinned` of `main.wgsl`.
+    #[serde(default = "ShaderResource::default_vertex_entry_point")]
+    vertex_entry_point: String,
+    /// The shader has an `fs_oit` entry point, used for `Transparency::WeightedBlended` materials.
+    #[serde(default)]
+    order_independent: bool,
+    /// Alpha blends into the colour target, otherwise the output replaces it (i.e. mip levels keep the alpha of their texture).
+    #[serde(default = "ShaderResource::default_blended")]
+    blended: bool,
+}
+
+impl ShaderResource {
+    fn default_vertex_entry_point() -> String {
+        "vs_main".into()
+    }
+
+    fn default_blended() -> bool {
+        true
+    }
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -119,6 +171,46 @@ pub enum BindingResourceType {
     CubeSampler,
     Uniform, // any type
     Storage, // { len: u32, data: array<T> }
+    ShadowMaps, // depth array, comparison sampler, views storage
+    MorphTargets, // number of targets and deltas storage, weights uniform
+}
+
+impl BindingResourceType {
+    /// Binding resources of the global resource `res`, for `ShaderInput::Resource` inputs.
+    pub(crate) fn global_binding_resources(&self, resources: &Resources, res: &str) -> Vec<BindingHolder> {
+        match self {
+            BindingResourceType::Material => {
+                let resource = resources.get_global::<Material>(res).unwrap_or_else(|| panic!("Material resource '{res}' could not be found, required by shader."));
+                resource.binding_resources(resources)
+            },
+            BindingResourceType::Texture => {
+                let resource = resources.get_global::<Texture>(res).unwrap_or_else(|| panic!("Texture resource '{res}' could not be found, required by shader."));
+                vec![resource.binding_resource()]
+            },
+            BindingResourceType::Sampler => {
+                let resource = resources.get_global::<Sampler>(res).unwrap_or_else(|| panic!("Sampler resource '{res}' could not be found, required by shader."));
+                resource.binding_resources(resources).to_vec()
+            },
+            BindingResourceType::CubeMap => {
+                let resource = resources.get_global::<CubeMap>(res).unwrap_or_else(|| panic!("Cubemap resource '{res}' could not be found, required by shader."));
+                vec![resource.binding_resource()]
+            },
+            BindingResourceType::CubeSampler => {
+                let resource = resources.get_global::<CubeSampler>(res).unwrap_or_else(|| panic!("Cube sampler resource '{res}' could not be found, required by shader."));
+                resource.binding_resources(resources).to_vec()
+            },
+            BindingResourceType::Uniform => {
+                let resource = resources.get_global::<UniformBuffer>(res).unwrap_or_else(|| panic!("Uniform buffer resource '{res}' could not be found, required by shader."));
+                vec![resource.binding_resource()]
+            },
+            BindingResourceType::Storage => {
+                let resource = resources.get_global::<StorageBuffer>(res).unwrap_or_else(|| panic!("Storage buffer resource '{res}' could not be found, required by shader."));
+                resource.binding_resources().to_vec()
+            },
+            BindingResourceType::ShadowMaps => panic!("Shadow maps are not a global resource, use the Shadows shader input."),
+            BindingResourceType::MorphTargets => panic!("Morph targets are not a global resource, use the MeshMorphTargets shader input."),
+        }
+    }
 }
 
 // #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -131,6 +223,8 @@ pub enum BindingResourceType {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub(crate) enum ShaderInput {
     MeshMaterial,
+    /// Morph targets of the mesh, weighted by the node's `MorphWeights`. Meshes without targets are drawn unchanged.
+    MeshMorphTargets,
     Node {
         ty: BindingResourceType,
         res: String,
@@ -148,6 +242,8 @@ pub(crate) enum ShaderInput {
         ty: BindingResourceType,
         res: String,
     },
+    /// Shadow maps of every light with shadows enabled this frame, see `ShadowMaps`.
+    Shadows,
     Manual(BindingResourceType),
 }
 
@@ -155,10 +251,12 @@ impl ShaderInput {
     pub fn layout(&self) -> BindingResourceType {
         match self {
             ShaderInput::MeshMaterial => BindingResourceType::Material,
+            ShaderInput::MeshMorphTargets => BindingResourceType::MorphTargets,
             ShaderInput::Node { ty, .. } => *ty,
             ShaderInput::GlobalNode { ty, .. } => *ty,
             ShaderInput::Scene { .. } => BindingResourceType::Storage,
             ShaderInput::Resource { ty, .. } => *ty,
+            ShaderInput::Shadows => BindingResourceType::ShadowMaps,
             ShaderInput::Manual(ty) => *ty,
             // ShaderInput::Scene { layout, .. } => *layout,
         }
@@ -169,6 +267,10 @@ impl ShaderInput {
 pub enum ShaderLoadError {
     IoError(std::io::Error),
     ParseError(ron::error::SpannedError),
+    /// Invalid `//!` preprocessor macro
+    MacroError(String),
+    /// The shader or one of its pipelines failed validation
+    CompileError(String),
 }
 
 impl std::fmt::Display for ShaderLoadError {
@@ -176,6 +278,8 @@ impl std::fmt::Display for ShaderLoadError {
         match self {
             ShaderLoadError::IoError(err) => err.fmt(f),
             ShaderLoadError::ParseError(err) => err.fmt(f),
+            ShaderLoadError::MacroError(err) => err.fmt(f),
+            ShaderLoadError::CompileError(err) => err.fmt(f),
         }
     }
 }
@@ -186,15 +290,37 @@ impl std::error::Error for ShaderLoadError {}
 //     fn binding_resources(&self, renderer: &Renderer, resources: &mut Resources) -> Vec<wgpu::BindingResource>;
 // }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub enum BindingHolder {
     Buffer(Handle<wgpu::Buffer>),
+    /// Part of a buffer, i.e. a slot of the `UniformArena`
+    BufferSlice {
+        buffer: Handle<wgpu::Buffer>,
+        offset: u64,
+        size: u64,
+    },
     Texture(Handle<wgpu::TextureView>),
     Sampler(Handle<wgpu::Sampler>),
 }
 
-fn preprocess_wgsl<P: AsRef<Path>>(path: P) -> Result<String, ShaderLoadError> {
-    fn preprocess_internal<P: AsRef<Path>>(path: P, current_binding: &mut u32, collapse: bool) -> Result<String, ShaderLoadError> {
+impl BindingHolder {
+    pub(crate) fn binding_resource<'a>(&self, resources: &'a Resources) -> wgpu::BindingResource<'a> {
+        match self {
+            BindingHolder::Buffer(buffer) => buffer.get(resources).as_entire_binding(),
+            BindingHolder::BufferSlice { buffer, offset, size } => wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: buffer.get(resources),
+                offset: *offset,
+                size: wgpu::BufferSize::new(*size),
+            }),
+            BindingHolder::Texture(view) => wgpu::BindingResource::TextureView(view.get(resources)),
+            BindingHolder::Sampler(sampler) => wgpu::BindingResource::Sampler(sampler.get(resources)),
+        }
+    }
+}
+
+/// Resolves `//!` macros, every file read is added to `sources`.
+fn preprocess_wgsl<P: AsRef<Path>>(path: P, sources: &mut Vec<PathBuf>) -> Result<String, ShaderLoadError> {
+    fn preprocess_internal<P: AsRef<Path>>(path: P, current_binding: &mut u32, collapse: bool, sources: &mut Vec<PathBuf>) -> Result<String, ShaderLoadError> {
         fn get_args<'a, T: serde::Deserialize<'a>>(line: &'a str, command: &str) -> Result<Option<T>, ()> {
             if !line.starts_with("//!") {
                 return Ok(None);
@@ -218,23 +344,25 @@ fn preprocess_wgsl<P: AsRef<Path>>(path: P) -> Result<String, ShaderLoadError> {
             }
         }
     
+        sources.push(path.as_ref().to_owned());
+
         let file = std::fs::File::open(path.as_ref()).map_err(|err| ShaderLoadError::IoError(err))?;
         let reader = BufReader::new(file);
     
         let mut lines = vec![];
     
         for (i, line) in reader.lines().enumerate() {
-            let line = line.unwrap().trim().to_owned();
+            let line = line.map_err(ShaderLoadError::IoError)?.trim().to_owned();
 
-            let error_msg = format!("Invalid macro args on line {} of file '{}'.", i+1, path.as_ref().to_string_lossy());
+            let macro_error = |_| ShaderLoadError::MacroError(format!("Invalid macro args on line {} of file '{}'.", i+1, path.as_ref().to_string_lossy()));
     
-            let line = if let Some((include_path,)) = get_args::<(String,)>(&line, "include").expect(&error_msg) {
+            let line = if let Some((include_path,)) = get_args::<(String,)>(&line, "include").map_err(macro_error)? {
                 let path = Path::new(path.as_ref()).parent().unwrap_or(&Path::new("./")).join(include_path);
                 
-                let line = preprocess_internal(path, current_binding, true)?;
+                let line = preprocess_internal(path, current_binding, true, sources)?;
 
                 line
-            } else if let Some(_) = get_args::<()>(&line, "binding").expect(&error_msg) {
+            } else if get_args::<()>(&line, "binding").map_err(macro_error)?.is_some() {
                 let line = format!("@group(0) @binding({})", current_binding);
                 *current_binding += 1;
                 line
@@ -250,5 +378,5 @@ fn preprocess_wgsl<P: AsRef<Path>>(path: P) -> Result<String, ShaderLoadError> {
         Ok(lines.join(if collapse { " " } else { "\n" }))
     }
 
-    preprocess_internal(path, &mut 0, false)
+    preprocess_internal(path, &mut 0, false, sources)
 }
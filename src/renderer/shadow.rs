@@ -0,0 +1,411 @@
+use bytemuck::Zeroable;
+
+use crate::{camera::Camera, components::{DirectionalShadows, PointShadows, Transform}, resource::{Handle, Resources}};
+
+use super::{Renderer, Shader, PipelineProperties, UniformBuffer, StorageBuffer, BindingHolder, BindGroupCache, QueuedRenderObject, VertexLayoutType};
+
+/// A single shadow map, i.e. one cascade of a directional light or one face of a point light.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct ShadowViewUniform {
+    view_proj: glam::Mat4,
+    bias: f32,
+    /// World units for directional lights, per unit of distance from the light for point lights.
+    normal_bias: f32,
+    pcf_radius: f32,
+    /// Number of cascades of the light, 6 for point lights.
+    count: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ShadowCaster {
+    Directional {
+        direction: glam::Vec3,
+        settings: DirectionalShadows,
+    },
+    Point {
+        position: glam::Vec3,
+        settings: PointShadows,
+    },
+}
+
+impl ShadowCaster {
+    fn num_views(&self) -> u32 {
+        match self {
+            ShadowCaster::Directional { settings, .. } => settings.cascades.clamp(1, DirectionalShadows::MAX_CASCADES),
+            ShadowCaster::Point { .. } => 6,
+        }
+    }
+}
+
+/// Shadow casting lights found while extracting a frame. Each light reserves consecutive views of the shadow maps,
+/// and stores the index of the first in its uniform so shaders can find them.
+#[derive(Debug, Default)]
+pub(crate) struct ShadowCasters {
+    casters: Vec<ShadowCaster>,
+    num_views: u32,
+}
+
+impl ShadowCasters {
+    pub const KEY: &'static str = "tritium::shadow_casters";
+
+    pub fn new() -> ShadowCasters {
+        ShadowCasters {
+            casters: vec![],
+            num_views: 0,
+        }
+    }
+
+    /// Returns the index of the caster's first view, or -1 if there is no room left for its views.
+    pub fn reserve(resources: &mut Resources, caster: ShadowCaster) -> i32 {
+        let Some(casters) = resources.get_engine_global_mut::<ShadowCasters>(ShadowCasters::KEY) else { return -1 };
+
+        let first_view = casters.num_views;
+        if first_view + caster.num_views() > ShadowMaps::MAX_VIEWS {
+            log::warn!("Shadow map views exhausted, light will not cast shadows.");
+            return -1;
+        }
+
+        casters.num_views += caster.num_views();
+        casters.casters.push(caster);
+
+        first_view as i32
+    }
+}
+
+/// Depth maps of every shadow casting light, stored as layers of a single array texture and sampled with percentage closer filtering.
+pub struct ShadowMaps {
+    resolution: u32,
+    num_layers: u32,
+    texture: wgpu::Texture,
+    view: Handle<wgpu::TextureView>,
+    layer_views: Vec<wgpu::TextureView>,
+    sampler: Handle<wgpu::Sampler>,
+    views: StorageBuffer,
+    /// Kept between frames, the first `num_views` are used this frame
+    view_uniforms: Vec<(UniformBuffer, wgpu::BindGroup)>,
+    num_views: usize,
+    shader: Shader,
+    /// Skinned and morphed meshes also bind their joints or morph targets, so need a bind group per mesh
+    skinned_shader: Handle<Shader>,
+    morph_shader: Handle<Shader>,
+}
+
+impl ShadowMaps {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    pub const DEFAULT_RESOLUTION: u32 = 1024;
+    pub const MAX_VIEWS: u32 = 32;
+
+    const PIPELINE: PipelineProperties = PipelineProperties {
+        transparent: false,
+        // thin geometry such as planes should still cast shadows from behind
+        double_sided: true,
+        colour_format: None,
+        depth_format: Some(ShadowMaps::FORMAT),
+        sample_count: 1,
+        weight_format: None,
+    };
+
+    pub fn new(renderer: &Renderer, resources: &mut Resources, resolution: u32) -> ShadowMaps {
+        let mut shader = Shader::from_resource(renderer, "pipelines/builtin/shadow.ron").expect("Shadow shader not found.");
+        shader.prepare_pipeline(renderer, ShadowMaps::PIPELINE);
+        let mut skinned_shader = Shader::from_resource(renderer, "pipelines/builtin/shadow_skinned.ron").expect("Skinned shadow shader not found.");
+        skinned_shader.prepare_pipeline(renderer, ShadowMaps::PIPELINE);
+        let mut morph_shader = Shader::from_resource(renderer, "pipelines/builtin/shadow_morph.ron").expect("Morph shadow shader not found.");
+        morph_shader.prepare_pipeline(renderer, ShadowMaps::PIPELINE);
+        let skinned_shader = resources.store(skinned_shader);
+        let morph_shader = resources.store(morph_shader);
+
+        let sampler = renderer.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let sampler = resources.store(sampler);
+
+        // a single layer texture is not an array on every backend
+        let num_layers = 2;
+        let (texture, view, layer_views) = ShadowMaps::create_texture(renderer, resolution, num_layers);
+        let view = resources.store(view);
+
+        let views = StorageBuffer::from_slice(renderer, resources, &[ShadowViewUniform::zeroed()]);
+
+        ShadowMaps {
+            resolution,
+            num_layers,
+            texture,
+            view,
+            layer_views,
+            sampler,
+            views,
+            view_uniforms: vec![],
+            num_views: 0,
+            shader,
+            skinned_shader,
+            morph_shader,
+        }
+    }
+
+    fn create_texture(renderer: &Renderer, resolution: u32, num_layers: u32) -> (wgpu::Texture, wgpu::TextureView, Vec<wgpu::TextureView>) {
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Maps"),
+            size: wgpu::Extent3d {
+                width: resolution,
+                height: resolution,
+                depth_or_array_layers: num_layers,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ShadowMaps::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Shadow Maps View"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        let layer_views = (0..num_layers).map(|layer| texture.create_view(&wgpu::TextureViewDescriptor {
+            label: None,
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: layer,
+            array_layer_count: std::num::NonZeroU32::new(1),
+            ..Default::default()
+        })).collect();
+
+        (texture, view, layer_views)
+    }
+
+    pub fn resolution(&self) -> u32 {
+        self.resolution
+    }
+
+    /// Calculates the views of this frame's shadow casters, growing the shadow map texture if required.
+    pub(crate) fn prepare(&mut self, renderer: &Renderer, resources: &mut Resources, casters: &ShadowCasters, camera: Option<(&Camera, &Transform)>, aspect: f32) {
+        // powers of two, as a square texture with 6 layers is treated as a cube map on some backends
+        let num_layers = casters.num_views.max(2).next_power_of_two();
+        if num_layers > self.num_layers {
+            let (texture, view, layer_views) = ShadowMaps::create_texture(renderer, self.resolution, num_layers);
+            self.texture = texture;
+            self.view = resources.store(view);
+            self.layer_views = layer_views;
+            self.num_layers = num_layers;
+        }
+
+        let mut views = vec![];
+        for caster in &casters.casters {
+            match caster {
+                ShadowCaster::Directional { direction, settings } => views.extend(self.directional_views(*direction, settings, camera, aspect)),
+                ShadowCaster::Point { position, settings } => views.extend(self.point_views(*position, settings)),
+            }
+        }
+
+        while self.view_uniforms.len() < views.len() {
+            let uniform = UniformBuffer::from_value(renderer, resources, ShadowViewUniform::zeroed());
+            let bind_group = renderer.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.shader.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform.buffer.get(resources).as_entire_binding(),
+                    },
+                ],
+            });
+            self.view_uniforms.push((uniform, bind_group));
+        }
+
+        for ((uniform, _), view) in self.view_uniforms.iter().zip(&views) {
+            uniform.write(renderer, resources, *view);
+        }
+        self.num_views = views.len();
+
+        if views.is_empty() {
+            views.push(ShadowViewUniform::zeroed());
+        }
+        self.views.write_slice(renderer, resources, &views);
+    }
+
+    /// Splits the camera frustum into cascades, each covered by an orthographic projection along the light direction.
+    fn directional_views(&self, direction: glam::Vec3, settings: &DirectionalShadows, camera: Option<(&Camera, &Transform)>, aspect: f32) -> Vec<ShadowViewUniform> {
+        let cascades = settings.cascades.clamp(1, DirectionalShadows::MAX_CASCADES);
+        let direction = direction.normalize();
+
+        let (znear, zfar) = camera.map(|(camera, _)| camera.depth_range()).unwrap_or((0.1, f32::INFINITY));
+        let zfar = zfar.min(settings.max_distance).max(znear);
+
+        // practical split scheme, blending logarithmic and uniform splits
+        let split = |i: u32| {
+            let t = i as f32 / cascades as f32;
+            let logarithmic = znear * (zfar / znear).powf(t);
+            let uniform = znear + (zfar - znear) * t;
+            settings.split_lambda * logarithmic + (1.0 - settings.split_lambda) * uniform
+        };
+
+        let up = if direction.y.abs() > 0.99 { glam::Vec3::Z } else { glam::Vec3::Y };
+
+        (0..cascades).map(|i| {
+            // bounding sphere of the cascade, so the projection doesn't change size as the camera rotates
+            let (centre, radius) = match camera {
+                Some((camera, transform)) => {
+                    let corners = camera.frustum_corners(transform, aspect, split(i), split(i + 1));
+                    let centre = corners.iter().copied().sum::<glam::Vec3>() / 8.0;
+                    let radius = corners.iter().map(|corner| corner.distance(centre)).fold(0.0, f32::max);
+                    (centre, radius)
+                },
+                None => (glam::Vec3::ZERO, split(i + 1)),
+            };
+            let radius = (radius * 16.0).ceil() / 16.0;
+
+            // casters outside the cascade, up to max_distance towards the light, still cast into it
+            let depth = 2.0 * radius + settings.max_distance;
+            let view = glam::Mat4::look_at_rh(centre + direction * (radius + settings.max_distance), centre, up);
+            let mut proj = glam::Mat4::orthographic_rh(-radius, radius, -radius, radius, 0.0, depth);
+
+            // snap to whole texels, so shadow edges don't shimmer as the camera moves
+            let texels = self.resolution as f32 / 2.0;
+            let origin = (proj * view).project_point3(glam::Vec3::ZERO).truncate() * texels;
+            let offset = (origin.round() - origin) / texels;
+            proj.w_axis.x += offset.x;
+            proj.w_axis.y += offset.y;
+
+            ShadowViewUniform {
+                view_proj: proj * view,
+                bias: settings.bias,
+                normal_bias: settings.normal_bias * 2.0 * radius / self.resolution as f32,
+                pcf_radius: settings.pcf_radius,
+                count: cascades,
+            }
+        }).collect()
+    }
+
+    /// Perspective views of each cube face, ordered +X, -X, +Y, -Y, +Z, -Z.
+    fn point_views(&self, position: glam::Vec3, settings: &PointShadows) -> Vec<ShadowViewUniform> {
+        let proj = glam::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, settings.znear, settings.zfar);
+
+        let faces = [
+            (glam::Vec3::X, glam::Vec3::NEG_Y),
+            (glam::Vec3::NEG_X, glam::Vec3::NEG_Y),
+            (glam::Vec3::Y, glam::Vec3::Z),
+            (glam::Vec3::NEG_Y, glam::Vec3::NEG_Z),
+            (glam::Vec3::Z, glam::Vec3::NEG_Y),
+            (glam::Vec3::NEG_Z, glam::Vec3::NEG_Y),
+        ];
+
+        faces.into_iter().map(|(direction, up)| ShadowViewUniform {
+            view_proj: proj * glam::Mat4::look_to_rh(position, direction, up),
+            bias: settings.bias,
+            // texel size at a distance of 1 from the light
+            normal_bias: settings.normal_bias * 2.0 / self.resolution as f32,
+            pcf_radius: settings.pcf_radius,
+            count: 6,
+        }).collect()
+    }
+
+    /// Draws the depth of every opaque mesh into each view prepared this frame, skinned and morphed meshes with their joints and morph targets.
+    pub(crate) fn render(&self, renderer: &Renderer, resources: &mut Resources, bind_groups: &mut BindGroupCache, render_objects: &[QueuedRenderObject]) {
+        if self.num_views == 0 {
+            return;
+        }
+
+        let casters: Vec<_> = render_objects.iter()
+            .filter(|render_object| !render_object.transparent && render_object.morph_targets.is_none())
+            .filter(|render_object| matches!(render_object.shader.get(resources).vertex_type(), VertexLayoutType::Model | VertexLayoutType::InstancedModel))
+            .collect();
+        // skinned meshes have morph targets too, bound even if empty
+        let deformed_casters: Vec<_> = render_objects.iter()
+            .filter(|render_object| !render_object.transparent && render_object.morph_targets.is_some())
+            .filter(|render_object| render_object.joints.is_some() || render_object.shader.get(resources).vertex_type() == VertexLayoutType::InstancedModel)
+            .map(|render_object| (render_object, if render_object.joints.is_some() { &self.skinned_shader } else { &self.morph_shader }))
+            .collect();
+
+        // joints and morph targets differ between meshes, so deformed meshes have a bind group per view and mesh
+        let deformed_bind_groups: Vec<Vec<_>> = self.view_uniforms[..self.num_views].iter().map(|(uniform, _)| {
+            deformed_casters.iter().map(|(render_object, shader)| {
+                let binding_resources: Vec<_> = std::iter::once(uniform.binding_resource())
+                    .chain(render_object.morph_targets.iter().flatten().cloned())
+                    .chain(render_object.joints.clone())
+                    .collect();
+
+                bind_groups.get_or_create(renderer, resources, shader, binding_resources)
+            }).collect()
+        }).collect();
+
+        let pipeline = self.shader.get_pipeline(ShadowMaps::PIPELINE).unwrap();
+
+        let mut encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Shadow Encoder"),
+        });
+
+        for (((_, bind_group), layer_view), deformed_bind_groups) in self.view_uniforms[..self.num_views].iter().zip(&self.layer_views).zip(&deformed_bind_groups) {
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: layer_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, bind_group, &[]);
+
+            // culled instances are drawn too, they may still cast shadows into view
+            for render_object in &casters {
+                render_pass.set_vertex_buffer(0, render_object.vertex_buffer.get(resources).slice(..));
+                render_pass.set_vertex_buffer(1, render_object.instances.get(resources).slice(..));
+                render_pass.set_index_buffer(render_object.index_buffer.get(resources).slice(..), wgpu::IndexFormat::Uint32);
+
+                render_pass.draw_indexed(0..render_object.num_indices, 0, 0..render_object.num_instances);
+            }
+
+            for ((render_object, shader), bind_group) in deformed_casters.iter().zip(deformed_bind_groups) {
+                render_pass.set_pipeline(shader.get(resources).get_pipeline(ShadowMaps::PIPELINE).unwrap());
+                render_pass.set_bind_group(0, bind_group.get(resources), &[]);
+                render_pass.set_vertex_buffer(0, render_object.vertex_buffer.get(resources).slice(..));
+                render_pass.set_vertex_buffer(1, render_object.instances.get(resources).slice(..));
+                render_pass.set_index_buffer(render_object.index_buffer.get(resources).slice(..), wgpu::IndexFormat::Uint32);
+
+                render_pass.draw_indexed(0..render_object.num_indices, 0, 0..render_object.num_instances);
+            }
+        }
+
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    pub(crate) fn binding_types() -> Vec<wgpu::BindingType> {
+        let mut binding_types = vec![
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Depth,
+                view_dimension: wgpu::TextureViewDimension::D2Array,
+                multisampled: false,
+            },
+            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+        ];
+        binding_types.extend(StorageBuffer::binding_types());
+        binding_types
+    }
+
+    pub(crate) fn binding_resources(&self) -> [BindingHolder; 4] {
+        let [views, num_views] = self.views.binding_resources();
+        [
+            BindingHolder::Texture(self.view.clone()),
+            BindingHolder::Sampler(self.sampler.clone()),
+            views,
+            num_views,
+        ]
+    }
+}
@@ -36,6 +36,10 @@ impl UniformBuffer {
         }
     }
 
+    pub fn write<T: bytemuck::Pod + bytemuck::Zeroable>(&self, renderer: &Renderer, resources: &Resources, uniform: T) {
+        renderer.queue.write_buffer(self.buffer.get(resources), 0, bytemuck::cast_slice(&[uniform]));
+    }
+
     pub(crate) fn binding_types() -> Vec<wgpu::BindingType> {
         vec![
             wgpu::BindingType::Buffer {
@@ -63,9 +67,16 @@ impl UniformBuffer {
 pub struct StorageBuffer {
     buffer: Handle<wgpu::Buffer>,
     len_buffer: Handle<wgpu::Buffer>,
+    /// Size of `buffer` in bytes
+    capacity: u64,
+    /// Last written contents, writing the same contents again is skipped
+    contents: Vec<u8>,
+    num_items: u32,
 }
 
 impl StorageBuffer {
+    const EMPTY_KEY: &'static str = "tritium::empty_storage_buffer";
+
     pub fn from_slice<T: bytemuck::Pod + bytemuck::Zeroable>(renderer: &Renderer, resources: &mut Resources, values: &[T]) -> StorageBuffer {
         let num_items = values.len() as u32;
         
@@ -96,6 +107,7 @@ impl StorageBuffer {
         //     label: None,
         // }));
 
+        let capacity = buffer.size();
         let buffer = resources.store(buffer);
         let len_buffer = resources.store(len_buffer);
 
@@ -103,6 +115,9 @@ impl StorageBuffer {
             // uniforms,
             buffer,
             len_buffer,
+            capacity,
+            contents: bytemuck::cast_slice(values).to_vec(),
+            num_items,
             // bind_group,
             // marker: PhantomData,
         }
@@ -138,6 +153,7 @@ impl StorageBuffer {
         //     label: None,
         // }));
 
+        let capacity = buffer.size();
         let buffer = resources.store(buffer);
         let len_buffer = resources.store(len_buffer);
 
@@ -145,11 +161,58 @@ impl StorageBuffer {
             // uniforms,
             buffer,
             len_buffer,
+            capacity,
+            contents: values.to_vec(),
+            num_items,
             // bind_group,
             // marker: PhantomData,
         }
     }
 
+    /// Binding resources of a buffer without items, created on first use and shared.
+    pub(crate) fn empty(renderer: &Renderer, resources: &mut Resources) -> [BindingHolder; 2] {
+        if let Some(buffer) = resources.get_engine_global::<StorageBuffer>(StorageBuffer::EMPTY_KEY) {
+            return buffer.binding_resources();
+        }
+
+        // large enough to bind as an array of any light, item size of twice that => num items = 0
+        let buffer = StorageBuffer::from_bytes(renderer, resources, &[0; 128], 256);
+        let binding_resources = buffer.binding_resources();
+        resources.set_engine_global(StorageBuffer::EMPTY_KEY, buffer);
+        binding_resources
+    }
+
+    pub fn write_slice<T: bytemuck::Pod + bytemuck::Zeroable>(&mut self, renderer: &Renderer, resources: &mut Resources, values: &[T]) {
+        self.write_bytes(renderer, resources, bytemuck::cast_slice(values), std::mem::size_of::<T>())
+    }
+
+    /// Replaces the contents, the buffer is only reallocated (doubling its size) if they don't fit and only written if they changed.
+    pub fn write_bytes(&mut self, renderer: &Renderer, resources: &mut Resources, values: &[u8], item_size: usize) {
+        let num_items = (values.len() / item_size) as u32;
+
+        if num_items != self.num_items {
+            renderer.queue.write_buffer(self.len_buffer.get(resources), 0, bytemuck::cast_slice(&[num_items]));
+            self.num_items = num_items;
+        }
+
+        if values == self.contents.as_slice() {
+            return;
+        }
+
+        if values.len() as u64 > self.capacity {
+            self.capacity = (values.len() as u64).next_power_of_two();
+            self.buffer = resources.store(renderer.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: self.capacity,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+
+        renderer.queue.write_buffer(self.buffer.get(resources), 0, values);
+        self.contents = values.to_vec();
+    }
+
     pub fn binding_types() -> Vec<wgpu::BindingType> {
         vec![
             wgpu::BindingType::Buffer {
@@ -1,13 +1,29 @@
+use crate::resource::{Handle, Resources};
+
+use super::Renderer;
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum VertexLayoutType {
     None,
     Position,
     Model,
+    /// `ModelVertex` and an `InstanceTransform` per instance, meshes are batched into instanced draws.
+    InstancedModel,
+    /// `SkinnedVertex` and an `InstanceTransform` per instance, deformed by the node's `Skeleton`.
+    SkinnedModel,
+}
+
+impl VertexLayoutType {
+    /// Has a second vertex buffer of `InstanceTransform`s.
+    pub fn instanced(&self) -> bool {
+        matches!(self, VertexLayoutType::InstancedModel | VertexLayoutType::SkinnedModel)
+    }
 }
 
 pub trait Vertex: bytemuck::Pod {
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a>;
+    /// Object space position, used for bounds.
+    fn position(&self) -> glam::Vec3;
 }
 
 #[repr(C)]
@@ -17,6 +33,10 @@ pub struct PositionVertex {
 }
 
 impl Vertex for PositionVertex {
+    fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<PositionVertex>() as wgpu::BufferAddress,
@@ -38,42 +58,152 @@ pub struct ModelVertex {
     pub position: glam::Vec3,
     pub tex_coords: glam::Vec2,
     pub normal: glam::Vec3,
-    pub tangent: glam::Vec3,
-    pub bitangent: glam::Vec3,
+    /// xyz is the tangent, w (1 or -1) the handedness of the bitangent `cross(normal, tangent) * w`
+    pub tangent: [f32; 4],
+    /// Second UV set, i.e. for occlusion maps
+    pub tex_coords_1: glam::Vec2,
+    /// Linear, multiplies the base colour
+    pub colour: [f32; 4],
+}
+
+impl Default for ModelVertex {
+    fn default() -> ModelVertex {
+        ModelVertex {
+            position: glam::Vec3::ZERO,
+            tex_coords: glam::Vec2::ZERO,
+            normal: glam::Vec3::ZERO,
+            tangent: [1.0, 0.0, 0.0, 1.0],
+            tex_coords_1: glam::Vec2::ZERO,
+            colour: [1.0; 4],
+        }
+    }
 }
 
 impl Vertex for ModelVertex {
+    fn position(&self) -> glam::Vec3 {
+        self.position
+    }
+
+    /// Colour is at location 14, after `InstanceTransform` and the joints of `SkinnedVertex`.
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+            0 => Float32x3, 1 => Float32x2, 2 => Float32x3, 3 => Float32x4, 4 => Float32x2, 14 => Float32x4,
+        ];
+
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<ModelVertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: 0,
-                    shader_location: 0,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x2,
-                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: std::mem::size_of::<[f32; 5]>() as wgpu::BufferAddress,
-                    shader_location: 2,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: std::mem::size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                    shader_location: 3,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x3,
-                    offset: std::mem::size_of::<[f32; 11]>() as wgpu::BufferAddress,
-                    shader_location: 4,
-                },
-            ],
+            attributes: &ATTRIBUTES,
         }
     }
 }
+
+/// `ModelVertex` with up to four joints of the node's `Skeleton`, at locations 12 and 13 (after `InstanceTransform`).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinnedVertex {
+    pub vertex: ModelVertex,
+    pub joints: [u32; 4],
+    /// Weights of `joints`, summing to 1
+    pub weights: [f32; 4],
+}
+
+impl SkinnedVertex {
+    pub fn new(vertex: ModelVertex, joints: [u32; 4], weights: [f32; 4]) -> SkinnedVertex {
+        SkinnedVertex {
+            vertex,
+            joints,
+            weights,
+        }
+    }
+}
+
+impl Vertex for SkinnedVertex {
+    fn position(&self) -> glam::Vec3 {
+        self.vertex.position
+    }
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 8] = wgpu::vertex_attr_array![
+            0 => Float32x3, 1 => Float32x2, 2 => Float32x3, 3 => Float32x4, 4 => Float32x2, 14 => Float32x4,
+            12 => Uint32x4, 13 => Float32x4,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SkinnedVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+/// Per instance vertex data of `VertexLayoutType::InstancedModel` and `VertexLayoutType::SkinnedModel`, at locations 5 to 11.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceTransform {
+    pub model_matrix: [[f32; 4]; 4],
+    /// Inverse transpose of the model matrix.
+    pub normal_matrix: [[f32; 3]; 3],
+}
+
+impl InstanceTransform {
+    pub fn new(matrix: glam::Mat4) -> InstanceTransform {
+        InstanceTransform {
+            model_matrix: matrix.to_cols_array_2d(),
+            normal_matrix: glam::Mat3::from_mat4(matrix).inverse().transpose().to_cols_array_2d(),
+        }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
+            5 => Float32x4, 6 => Float32x4, 7 => Float32x4, 8 => Float32x4,
+            9 => Float32x3, 10 => Float32x3, 11 => Float32x3,
+        ];
+
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceTransform>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+/// Instances of a batch, kept between frames and only reallocated (doubling its size) when they don't fit.
+pub(crate) struct InstanceBuffer {
+    pub buffer: Handle<wgpu::Buffer>,
+    capacity: u64,
+}
+
+impl InstanceBuffer {
+    pub fn new(renderer: &Renderer, resources: &mut Resources, instances: &[InstanceTransform]) -> InstanceBuffer {
+        // at least one instance, buffers can't be empty
+        let capacity = (std::mem::size_of_val(instances).max(std::mem::size_of::<InstanceTransform>()) as u64).next_power_of_two();
+
+        let mut buffer = InstanceBuffer {
+            buffer: resources.store(InstanceBuffer::create_buffer(renderer, capacity)),
+            capacity,
+        };
+        buffer.write(renderer, resources, instances);
+        buffer
+    }
+
+    fn create_buffer(renderer: &Renderer, size: u64) -> wgpu::Buffer {
+        renderer.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn write(&mut self, renderer: &Renderer, resources: &mut Resources, instances: &[InstanceTransform]) {
+        let contents: &[u8] = bytemuck::cast_slice(instances);
+
+        if contents.len() as u64 > self.capacity {
+            self.capacity = (contents.len() as u64).next_power_of_two();
+            self.buffer = resources.store(InstanceBuffer::create_buffer(renderer, self.capacity));
+        }
+
+        renderer.queue.write_buffer(self.buffer.get(resources), 0, contents);
+    }
+}
@@ -8,6 +8,7 @@ pub struct WindowAdapter {
     // pub(crate) depth_texture: resource::Texture,
     pub(crate) depth_texture: wgpu::TextureView,
     pub(crate) vsync: bool,
+    pub(crate) sample_count: u32,
     pub(crate) focused: bool,
 }
 
@@ -20,7 +21,7 @@ impl WindowAdapter {
             self.config.present_mode = if self.vsync { wgpu::PresentMode::AutoVsync } else { wgpu::PresentMode::AutoNoVsync };
             self.surface.configure(device, &self.config);
 
-            self.depth_texture = Texture::create_depth_texture(device, &self.config, "Depth Texture");
+            self.depth_texture = Texture::create_depth_texture(device, new_size.width, new_size.height, self.sample_count, "Depth Texture");
         }
     }
     
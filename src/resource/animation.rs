@@ -0,0 +1,147 @@
+use crate::node::NodeId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    /// Holds each keyframe until the next
+    Step,
+    /// Hermite spline, keyframes have an in tangent, a value and an out tangent
+    CubicSpline,
+}
+
+/// Keyframe values of a channel, three per keyframe with `Interpolation::CubicSpline`.
+#[derive(Debug, Clone)]
+pub enum Keyframes {
+    Translation(Vec<glam::Vec3>),
+    Rotation(Vec<glam::Quat>),
+    Scale(Vec<glam::Vec3>),
+    /// One weight per morph target for each keyframe (and tangent)
+    Weights(Vec<f32>),
+}
+
+/// A property of a node's `Transform`, or its `MorphWeights`, at some time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChannelValue {
+    Translation(glam::Vec3),
+    Rotation(glam::Quat),
+    Scale(glam::Vec3),
+    Weights(Vec<f32>),
+}
+
+/// Animates one property of the `Transform` or the `MorphWeights` of the node `target`.
+#[derive(Debug, Clone)]
+pub struct AnimationChannel {
+    pub target: NodeId,
+    pub interpolation: Interpolation,
+    /// Seconds, ascending
+    pub times: Vec<f32>,
+    pub keyframes: Keyframes,
+}
+
+impl AnimationChannel {
+    /// Clamped to the first and last keyframes.
+    pub fn sample(&self, time: f32) -> ChannelValue {
+        match &self.keyframes {
+            Keyframes::Translation(values) => ChannelValue::Translation(sample(&self.times, values, self.interpolation, time)),
+            Keyframes::Rotation(values) => ChannelValue::Rotation(sample(&self.times, values, self.interpolation, time).normalize()),
+            Keyframes::Scale(values) => ChannelValue::Scale(sample(&self.times, values, self.interpolation, time)),
+            Keyframes::Weights(values) => {
+                let num_values = match self.interpolation {
+                    Interpolation::CubicSpline => self.times.len() * 3,
+                    _ => self.times.len(),
+                };
+                let num_weights = values.len() / num_values.max(1);
+
+                // each weight is sampled on its own, its values are every `num_weights`th
+                ChannelValue::Weights((0..num_weights).map(|i| {
+                    let values: Vec<_> = values.iter().skip(i).step_by(num_weights).copied().collect();
+                    sample(&self.times, &values, self.interpolation, time)
+                }).collect())
+            },
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.0)
+    }
+}
+
+trait Keyframe: Copy + std::ops::Add<Output = Self> + std::ops::Mul<f32, Output = Self> {
+    fn interpolate(self, other: Self, t: f32) -> Self;
+}
+
+impl Keyframe for f32 {
+    fn interpolate(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Keyframe for glam::Vec3 {
+    fn interpolate(self, other: Self, t: f32) -> Self {
+        self.lerp(other, t)
+    }
+}
+
+impl Keyframe for glam::Quat {
+    fn interpolate(self, other: Self, t: f32) -> Self {
+        self.slerp(other, t)
+    }
+}
+
+fn sample<T: Keyframe>(times: &[f32], values: &[T], interpolation: Interpolation, time: f32) -> T {
+    // the value of keyframe i, skipping the tangents of cubic splines
+    let value = |i: usize| match interpolation {
+        Interpolation::CubicSpline => values[i * 3 + 1],
+        _ => values[i],
+    };
+
+    let next = times.partition_point(|keyframe_time| *keyframe_time <= time);
+    if next == 0 {
+        return value(0);
+    }
+    if next == times.len() {
+        return value(times.len() - 1);
+    }
+
+    let previous = next - 1;
+    let delta = times[next] - times[previous];
+    let t = (time - times[previous]) / delta;
+
+    match interpolation {
+        Interpolation::Step => value(previous),
+        Interpolation::Linear => value(previous).interpolate(value(next), t),
+        Interpolation::CubicSpline => {
+            let out_tangent = values[previous * 3 + 2] * delta;
+            let in_tangent = values[next * 3] * delta;
+
+            let t2 = t * t;
+            let t3 = t2 * t;
+
+            value(previous) * (2.0 * t3 - 3.0 * t2 + 1.0)
+                + out_tangent * (t3 - 2.0 * t2 + t)
+                + value(next) * (-2.0 * t3 + 3.0 * t2)
+                + in_tangent * (t3 - t2)
+        },
+    }
+}
+
+/// Keyframed transforms and morph weights of nodes, i.e. a glTF animation. Played by an `AnimationPlayer`.
+#[derive(Debug, Clone)]
+pub struct AnimationClip {
+    pub name: Option<String>,
+    pub channels: Vec<AnimationChannel>,
+    /// Time of the last keyframe of any channel
+    pub duration: f32,
+}
+
+impl AnimationClip {
+    pub fn new(name: Option<&str>, channels: Vec<AnimationChannel>) -> AnimationClip {
+        let duration = channels.iter().map(AnimationChannel::duration).fold(0.0, f32::max);
+
+        AnimationClip {
+            name: name.map(|name| name.into()),
+            channels,
+            duration,
+        }
+    }
+}
@@ -1,18 +1,27 @@
 use image::GenericImageView;
 
-use crate::{renderer::{Renderer, RenderInput, RenderableResource, BindingHolder, Shader, PipelineProperties, UniformBuffer}, node::NodeDescriptor, resource::{Texture, Model}};
+use crate::{renderer::{Renderer, RenderInput, RenderableResource, BindingHolder, Shader, PipelineProperties, UniformBuffer, CaptureError, read_texture}, node::NodeDescriptor, resource::{Texture, Model}};
 
-use super::{Resources, Handle, Sampler};
+use super::{Resources, Handle, Sampler, CubeSampler};
 
 pub struct CubeMap {
     pub texture: wgpu::Texture,
     pub view: Handle<wgpu::TextureView>,
+    /// Size of each face
+    pub(crate) size: glam::UVec2,
     // pub sampler: Handle<wgpu::Sampler>,
     // bind_group: Handle<wgpu::BindGroup>,
 }
 
 impl CubeMap {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    /// Specular reflections don't need the full resolution of the environment
+    pub const PREFILTER_MAX_SIZE: u32 = 256;
+    /// Irradiance is low frequency, so a small cube map is enough
+    pub const IRRADIANCE_SIZE: u32 = 32;
+    pub const SPHERICAL_HARMONICS_SIZE: u32 = 64;
+    /// Must match `MAX_REFLECTION_LOD` + 1 in shaders sampling prefiltered environments
+    pub const PREFILTER_MIP_LEVELS: u32 = 5;
 
     /// From 2x1 equirectangular image, 2048x1024 texture will result in 512x512x6 cubemap. (cubemap width = source width / 4, cubemap height = source height / 2)
     pub fn from_image_equirectangular(
@@ -84,22 +93,15 @@ impl CubeMap {
             usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
         });
 
-        // (dir, tangent, bitangent)
-        let cubemap_proj = glam::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 10.0);
-        let cubemap_viewproj = [
-            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::X, glam::Vec3::Y),
-            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::NEG_X, glam::Vec3::Y),
-            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::Y, glam::Vec3::Z),
-            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::NEG_Y, glam::Vec3::NEG_Z),
-            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::NEG_Z, glam::Vec3::Y),
-            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::Z, glam::Vec3::Y),
-        ];
+        let cubemap_viewproj = CubeMap::face_view_projections();
 
         let index = PipelineProperties {
             transparent: false,
             double_sided: false,
-            colour_format: format,
+            colour_format: Some(format),
             depth_format: None,
+            sample_count: 1,
+            weight_format: None,
         };
 
         let mut pipeline = Shader::from_resource(renderer, "pipelines/builtin/cubemap_equirectangular.ron").expect("Cubemap equirectangular shader not found.");
@@ -221,6 +223,7 @@ impl CubeMap {
         Ok(CubeMap {
             texture: cubemap_texture,
             view,
+            size: glam::uvec2(size.width, size.height),
             // sampler,
             // bind_group,
         })
@@ -395,11 +398,350 @@ impl CubeMap {
         Ok(CubeMap {
             texture,
             view,
+            size: glam::uvec2(dimensions.0, dimensions.1),
             // sampler,
             // bind_group,
         })
     }
 
+    /// Prefilters an environment for specular image based lighting. Each mip level is convolved with the GGX distribution, roughness = mip / (mip count - 1).
+    pub fn prefilter_specular(
+        renderer: &Renderer,
+        resources: &mut Resources,
+        source: &Handle<CubeMap>,
+        label: Option<&str>,
+    ) -> CubeMap {
+        let format = wgpu::TextureFormat::Rgba16Float;
+        // mip level count is fixed, shaders can't query it on every backend
+        let mip_level_count = CubeMap::PREFILTER_MIP_LEVELS;
+        let size = source.get(resources).size.x.clamp(1 << (mip_level_count - 1), CubeMap::PREFILTER_MAX_SIZE);
+
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+
+        let index = PipelineProperties {
+            transparent: false,
+            double_sided: false,
+            colour_format: Some(format),
+            depth_format: None,
+            sample_count: 1,
+            weight_format: None,
+        };
+
+        let mut pipeline = Shader::from_resource(renderer, "pipelines/builtin/prefilter.ron").expect("Prefilter shader not found.");
+        pipeline.prepare_pipeline(renderer, index);
+
+        let cube_model = Model::new_inverted_cube(renderer, resources, None);
+        let source = CubeSampler::new(renderer, resources, source.clone(), wgpu::AddressMode::ClampToEdge, wgpu::AddressMode::ClampToEdge, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear);
+
+        for mip in 0..mip_level_count {
+            let roughness = mip as f32 / (mip_level_count - 1) as f32;
+
+            let uniforms = CubeMap::face_view_projections().map(|view_proj| UniformBuffer::from_value(renderer, resources, PrefilterUniform {
+                view_proj,
+                roughness,
+                _padding: [0.0; 3],
+            }));
+
+            let bind_groups = uniforms.map(|uniform| renderer.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source.texture.get(resources).view.get(resources)),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(source.sampler.get(resources)),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform.buffer.get(resources).as_entire_binding(),
+                    },
+                ],
+            }));
+
+            CubeMap::render_faces(renderer, resources, pipeline.get_pipeline(index).unwrap(), &cube_model, &texture, mip, &bind_groups);
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label,
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let view = resources.store(view);
+
+        CubeMap {
+            texture,
+            view,
+            size: glam::uvec2(size, size),
+        }
+    }
+
+    /// Convolves an environment into diffuse irradiance, the cosine weighted integral over the hemisphere around each direction.
+    /// Stored divided by PI (the lambertian normalisation), so shaders use `irradiance * albedo`.
+    pub fn convolve_irradiance(
+        renderer: &Renderer,
+        resources: &mut Resources,
+        source: &Handle<CubeMap>,
+        label: Option<&str>,
+    ) -> CubeMap {
+        let format = wgpu::TextureFormat::Rgba16Float;
+        let size = CubeMap::IRRADIANCE_SIZE;
+
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+
+        let index = PipelineProperties {
+            transparent: false,
+            double_sided: false,
+            colour_format: Some(format),
+            depth_format: None,
+            sample_count: 1,
+            weight_format: None,
+        };
+
+        let mut pipeline = Shader::from_resource(renderer, "pipelines/builtin/irradiance.ron").expect("Irradiance shader not found.");
+        pipeline.prepare_pipeline(renderer, index);
+
+        let cube_model = Model::new_inverted_cube(renderer, resources, None);
+        let source = CubeSampler::new(renderer, resources, source.clone(), wgpu::AddressMode::ClampToEdge, wgpu::AddressMode::ClampToEdge, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear);
+
+        let uniforms = CubeMap::face_view_projections().map(|view_proj| UniformBuffer::from_value(renderer, resources, view_proj));
+
+        let bind_groups = uniforms.map(|uniform| renderer.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &pipeline.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source.texture.get(resources).view.get(resources)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(source.sampler.get(resources)),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: uniform.buffer.get(resources).as_entire_binding(),
+                },
+            ],
+        }));
+
+        CubeMap::render_faces(renderer, resources, pipeline.get_pipeline(index).unwrap(), &cube_model, &texture, 0, &bind_groups);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label,
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let view = resources.store(view);
+
+        CubeMap {
+            texture,
+            view,
+            size: glam::uvec2(size, size),
+        }
+    }
+
+    /// Projects the cube map onto the first 3 bands of spherical harmonics, by rendering each face and reading it back from the gpu.
+    /// Projecting an irradiance map gives irradiance directly, a radiance environment must be convolved first (see `SphericalHarmonics::convolve_cosine`).
+    pub fn spherical_harmonics(
+        renderer: &Renderer,
+        resources: &mut Resources,
+        source: &Handle<CubeMap>,
+    ) -> Result<SphericalHarmonics, CaptureError> {
+        // cube textures can't be copied from on every backend, so faces are drawn into a 2d texture
+        let format = wgpu::TextureFormat::Rgba16Float;
+        let size = source.get(resources).size.x.min(CubeMap::SPHERICAL_HARMONICS_SIZE);
+
+        let face_texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Spherical Harmonics Face"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let face_view = face_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let index = PipelineProperties {
+            transparent: false,
+            double_sided: false,
+            colour_format: Some(format),
+            depth_format: None,
+            sample_count: 1,
+            weight_format: None,
+        };
+
+        // prefiltering with zero roughness samples the environment directly
+        let mut pipeline = Shader::from_resource(renderer, "pipelines/builtin/prefilter.ron").expect("Prefilter shader not found.");
+        pipeline.prepare_pipeline(renderer, index);
+
+        let cube_model = Model::new_inverted_cube(renderer, resources, None);
+        let source = CubeSampler::new(renderer, resources, source.clone(), wgpu::AddressMode::ClampToEdge, wgpu::AddressMode::ClampToEdge, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear);
+
+        let mut coefficients = [glam::Vec3::ZERO; 9];
+        let mut total_weight = 0.0;
+
+        for view_proj in CubeMap::face_view_projections() {
+            let uniform = UniformBuffer::from_value(renderer, resources, PrefilterUniform {
+                view_proj,
+                roughness: 0.0,
+                _padding: [0.0; 3],
+            });
+
+            let bind_group = renderer.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &pipeline.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(source.texture.get(resources).view.get(resources)),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(source.sampler.get(resources)),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: uniform.buffer.get(resources).as_entire_binding(),
+                    },
+                ],
+            });
+
+            CubeMap::render_face(renderer, resources, pipeline.get_pipeline(index).unwrap(), &cube_model, &face_view, &bind_group);
+
+            let image = read_texture(renderer, &face_texture, format, size, size, 0)?.to_rgb32f();
+            let inv_view_proj = view_proj.inverse();
+
+            for (x, y, pixel) in image.enumerate_pixels() {
+                // texel centre in normalised device coordinates, the face is a plane at distance 1
+                let ndc = glam::vec2(2.0 * (x as f32 + 0.5) / size as f32 - 1.0, 1.0 - 2.0 * (y as f32 + 0.5) / size as f32);
+                let direction = inv_view_proj.project_point3(ndc.extend(1.0)).normalize();
+
+                // solid angle of the texel, up to a constant factor
+                let weight = 1.0 / (1.0 + ndc.length_squared()).powf(1.5);
+                let colour = glam::Vec3::from(pixel.0);
+
+                for (coefficient, basis) in coefficients.iter_mut().zip(SphericalHarmonics::basis(direction)) {
+                    *coefficient += colour * basis * weight;
+                }
+
+                total_weight += weight;
+            }
+        }
+
+        let normalisation = 4.0 * std::f32::consts::PI / total_weight;
+
+        Ok(SphericalHarmonics {
+            coefficients: coefficients.map(|coefficient| coefficient * normalisation),
+        })
+    }
+
+    /// View projections looking at each face from the centre of the cube, in array layer order.
+    pub(crate) fn face_view_projections() -> [glam::Mat4; 6] {
+        let cubemap_proj = glam::Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1.0, 0.1, 10.0);
+        [
+            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::X, glam::Vec3::Y),
+            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::NEG_X, glam::Vec3::Y),
+            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::Y, glam::Vec3::Z),
+            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::NEG_Y, glam::Vec3::NEG_Z),
+            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::NEG_Z, glam::Vec3::Y),
+            cubemap_proj * glam::Mat4::look_at_rh(glam::Vec3::ZERO, glam::Vec3::Z, glam::Vec3::Y),
+        ]
+    }
+
+    /// Draws the inverted cube into each face of a cube texture's mip level, one bind group per face.
+    pub(crate) fn render_faces(
+        renderer: &Renderer,
+        resources: &Resources,
+        pipeline: &wgpu::RenderPipeline,
+        cube_model: &Model,
+        target: &wgpu::Texture,
+        mip_level: u32,
+        bind_groups: &[wgpu::BindGroup; 6],
+    ) {
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            let face_view = target.create_view(&wgpu::TextureViewDescriptor {
+                label: None,
+                dimension: Some(wgpu::TextureViewDimension::D2),
+                base_mip_level: mip_level,
+                mip_level_count: std::num::NonZeroU32::new(1),
+                base_array_layer: i as u32,
+                array_layer_count: std::num::NonZeroU32::new(1),
+                ..Default::default()
+            });
+
+            CubeMap::render_face(renderer, resources, pipeline, cube_model, &face_view, bind_group);
+        }
+    }
+
+    pub(crate) fn render_face(
+        renderer: &Renderer,
+        resources: &Resources,
+        pipeline: &wgpu::RenderPipeline,
+        cube_model: &Model,
+        view: &wgpu::TextureView,
+        bind_group: &wgpu::BindGroup,
+    ) {
+        let mut encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_vertex_buffer(0, cube_model.meshes[0].vertex_buffer.get(resources).slice(..));
+            render_pass.set_index_buffer(cube_model.meshes[0].index_buffer.get(resources).slice(..), wgpu::IndexFormat::Uint32);
+            render_pass.set_bind_group(0, bind_group, &[]);
+
+            render_pass.draw_indexed(0..cube_model.meshes[0].num_elements, 0, 0..1);
+        }
+
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+    }
+
     pub(crate) fn binding_types() -> Vec<wgpu::BindingType> {
         vec![
             wgpu::BindingType::Texture {
@@ -423,6 +765,64 @@ impl RenderableResource for CubeMap {
     }
 }
 
+/// Real spherical harmonics coefficients for bands 0..=2, ordered (l, m): (0, 0), (1, -1), (1, 0), (1, 1), (2, -2), (2, -1), (2, 0), (2, 1), (2, 2).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SphericalHarmonics {
+    pub coefficients: [glam::Vec3; 9],
+}
+
+impl SphericalHarmonics {
+    pub fn basis(direction: glam::Vec3) -> [f32; 9] {
+        let glam::Vec3 { x, y, z } = direction;
+        [
+            0.282095,
+            0.488603 * y,
+            0.488603 * z,
+            0.488603 * x,
+            1.092548 * x * y,
+            1.092548 * y * z,
+            0.315392 * (3.0 * z * z - 1.0),
+            1.092548 * x * z,
+            0.546274 * (x * x - y * y),
+        ]
+    }
+
+    pub fn evaluate(&self, direction: glam::Vec3) -> glam::Vec3 {
+        self.coefficients.iter()
+            .zip(SphericalHarmonics::basis(direction.normalize()))
+            .map(|(coefficient, basis)| *coefficient * basis)
+            .sum()
+    }
+
+    /// Convolves radiance with the clamped cosine lobe (Ramamoorthi & Hanrahan), giving irradiance divided by PI like `CubeMap::convolve_irradiance`.
+    pub fn convolve_cosine(&self) -> SphericalHarmonics {
+        // A_l / PI
+        let bands = [1.0, 2.0 / 3.0, 1.0 / 4.0];
+
+        let mut coefficients = self.coefficients;
+        for (i, coefficient) in coefficients.iter_mut().enumerate() {
+            let band = match i {
+                0 => 0,
+                1..=3 => 1,
+                _ => 2,
+            };
+            *coefficient *= bands[band];
+        }
+
+        SphericalHarmonics {
+            coefficients,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct PrefilterUniform {
+    view_proj: glam::Mat4,
+    roughness: f32,
+    _padding: [f32; 3],
+}
+
 pub struct Cube<T> {
     pub pos_x: T,
     pub neg_x: T,
@@ -475,3 +875,14 @@ pub enum CubeError {
     DimensionMismatch,
     TextureFormatMismatch,
 }
+
+impl std::fmt::Display for CubeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CubeError::DimensionMismatch => write!(f, "Cube faces have different dimensions."),
+            CubeError::TextureFormatMismatch => write!(f, "Cube faces have different texture formats."),
+        }
+    }
+}
+
+impl std::error::Error for CubeError {}
@@ -9,279 +9,234 @@ pub enum AlphaMode {
     Blend,
 }
 
+/// How `AlphaMode::Blend` materials are composited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transparency {
+    /// Blended in back to front order, wrong where transparent meshes intersect.
+    #[default]
+    Sorted,
+    /// Weighted blended order-independent transparency, approximate but independent of draw order.
+    /// Needs a shader with an `fs_oit` entry point and a render graph with a `WeightedBlended` pass, otherwise falls back to `Sorted`.
+    WeightedBlended,
+}
+
+/// The textures of a `Material`, see `MaterialBuilder::tex_coord` and `MaterialBuilder::texture_transform`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialTexture {
+    Albedo,
+    Metallic,
+    Roughness,
+    Normal,
+    Occlusion,
+    Emissive,
+    /// Clearcoat intensity in red, clearcoat roughness in green
+    Clearcoat,
+    ClearcoatNormal,
+    /// Transmission in red
+    Transmission,
+    /// Specular colour in rgb, specular strength in alpha
+    Specular,
+    /// Sheen colour in rgb, sheen roughness in alpha
+    Sheen,
+}
+
+impl MaterialTexture {
+    const COUNT: usize = 11;
+
+    const ALL: [MaterialTexture; MaterialTexture::COUNT] = [
+        MaterialTexture::Albedo,
+        MaterialTexture::Metallic,
+        MaterialTexture::Roughness,
+        MaterialTexture::Normal,
+        MaterialTexture::Occlusion,
+        MaterialTexture::Emissive,
+        MaterialTexture::Clearcoat,
+        MaterialTexture::ClearcoatNormal,
+        MaterialTexture::Transmission,
+        MaterialTexture::Specular,
+        MaterialTexture::Sheen,
+    ];
+
+    /// Used when the texture isn't set, leaves the factors unchanged
+    fn default_pixel(&self) -> [u8; 4] {
+        match self {
+            // default: metallic: 1.0, i.e. defer to roughness factor
+            MaterialTexture::Metallic => [0, 0, 255, 255],
+            MaterialTexture::Roughness => [0, 255, 0, 255],
+            MaterialTexture::Normal | MaterialTexture::ClearcoatNormal => [128, 128, 255, 255],
+            _ => [255, 255, 255, 255],
+        }
+    }
+}
+
+/// `KHR_texture_transform`, applied to the UVs of a texture as translation * rotation * scale.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureTransform {
+    pub offset: glam::Vec2,
+    /// Radians, counter-clockwise in UV space
+    pub rotation: f32,
+    pub scale: glam::Vec2,
+}
+
+impl Default for TextureTransform {
+    fn default() -> TextureTransform {
+        TextureTransform {
+            offset: glam::Vec2::ZERO,
+            rotation: 0.0,
+            scale: glam::Vec2::ONE,
+        }
+    }
+}
+
+impl TextureTransform {
+    pub fn matrix(&self) -> glam::Mat3 {
+        // UV space has v pointing down, so rotations are clockwise in glam's convention
+        glam::Mat3::from_translation(self.offset) * glam::Mat3::from_angle(-self.rotation) * glam::Mat3::from_scale(self.scale)
+    }
+}
+
+/// All factors of a `Material`, see `material_bindings.wgsl`.
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
-struct AlphaModeUniform {
-    blended: u32,
+struct MaterialUniform {
+    albedo: Rgba,
+    emissive: Rgba,
+    specular_colour: [f32; 4],
+    sheen_colour: [f32; 4],
+    metallic: f32,
+    roughness: f32,
+    normal_scale: f32,
+    occlusion_strength: f32,
     alpha_cutoff: f32,
+    blended: u32,
+    unlit: u32,
+    ior: f32,
+    clearcoat: f32,
+    clearcoat_roughness: f32,
+    clearcoat_normal_scale: f32,
+    transmission: f32,
+    specular: f32,
+    sheen_roughness: f32,
+    _padding: [f32; 2],
+    /// UV set of each `MaterialTexture`, packed in fours
+    tex_coords: [[u32; 4]; 3],
+    /// Texture transform of each `MaterialTexture`, mat3x3 columns are padded to vec4
+    transforms: [[[f32; 4]; 3]; MaterialTexture::COUNT],
 }
 
-impl AlphaModeUniform {
-    pub fn new(alpha_mode: AlphaMode) -> AlphaModeUniform {
-        AlphaModeUniform {
-            blended: if alpha_mode == AlphaMode::Blend { 1 } else { 0 },
-            alpha_cutoff: match alpha_mode {
+impl MaterialUniform {
+    fn new(builder: &MaterialBuilder) -> MaterialUniform {
+        let mut tex_coords = [[0; 4]; 3];
+        for (i, tex_coord) in builder.tex_coords.iter().enumerate() {
+            tex_coords[i / 4][i % 4] = *tex_coord;
+        }
+
+        let transforms = builder.transforms.map(|transform| {
+            let matrix = transform.matrix();
+            [matrix.x_axis.extend(0.0).to_array(), matrix.y_axis.extend(0.0).to_array(), matrix.z_axis.extend(0.0).to_array()]
+        });
+
+        MaterialUniform {
+            albedo: builder.albedo,
+            emissive: builder.emissive_factor,
+            specular_colour: builder.specular_colour_factor.extend(0.0).to_array(),
+            sheen_colour: builder.sheen_colour_factor.extend(0.0).to_array(),
+            metallic: builder.metallic_factor,
+            roughness: builder.roughness_factor,
+            normal_scale: builder.normal_scale,
+            occlusion_strength: builder.occlusion_strength,
+            alpha_cutoff: match builder.alpha_mode {
                 AlphaMode::Opaque => -1.0,
                 AlphaMode::Mask { cutoff } => cutoff,
                 AlphaMode::Blend => -1.0,
             },
+            blended: if builder.alpha_mode == AlphaMode::Blend { 1 } else { 0 },
+            unlit: if builder.unlit { 1 } else { 0 },
+            ior: builder.ior,
+            clearcoat: builder.clearcoat_factor,
+            clearcoat_roughness: builder.clearcoat_roughness_factor,
+            clearcoat_normal_scale: builder.clearcoat_normal_scale,
+            transmission: builder.transmission_factor,
+            specular: builder.specular_factor,
+            sheen_roughness: builder.sheen_roughness_factor,
+            _padding: [0.0; 2],
+            tex_coords,
+            transforms,
         }
     }
 }
 
+/// Surface properties of a mesh, bound as a uniform of factors and a texture per `MaterialTexture`.
+///
+/// Clearcoat and clearcoat roughness, specular strength and colour, and sheen colour and roughness each share one texture,
+/// as the shader has no samplers left for more. `load_gltf` only uses the first of such a pair if they're different textures.
 pub struct Material {
     name: Option<String>,
     pub(crate) double_sided: bool,
     pub(crate) alpha_mode: AlphaMode,
-    alpha_mode_buffer: UniformBuffer,
-    // albedo_texture: Handle<Texture>,
-    albedo_sampler: Handle<Sampler>,
-    albedo_buffer: UniformBuffer, // diffuse_colour: Rgba,
-    // metallic_texture: Handle<Texture>,
-    metallic_sampler: Handle<Sampler>,
-    metallic_factor_buffer: UniformBuffer, // metallic_factor: f32,
-    // roughness_texture: Handle<Texture>,
-    roughness_sampler: Handle<Sampler>,
-    roughness_factor_buffer: UniformBuffer, // roughness_factor: f32,
-    // normal_texture: Handle<Texture>,
-    normal_sampler: Handle<Sampler>,
-    normal_scale_buffer: UniformBuffer, // normal_scale: f32,
-    // occlusion_texture: Handle<Texture>,
-    occlusion_sampler: Handle<Sampler>,
-    occlusion_strength_buffer: UniformBuffer, // normal_strength: f32,
-    // emissive_texture: Handle<Texture>,
-    emissive_sampler: Handle<Sampler>,
-    emissive_factor_buffer: UniformBuffer, // emissive_factor: Rgba,
+    pub(crate) transparency: Transparency,
+    uniform: UniformBuffer,
+    /// Indexed by `MaterialTexture`
+    samplers: Vec<Handle<Sampler>>,
 }
 
 impl Material {
-    fn new(
-        renderer: &Renderer,
-        resources: &mut Resources,
-        name: Option<String>,
-        double_sided: bool,
-        alpha_mode: AlphaMode,
-        // albedo_texture: Option<Handle<Texture>>,
-        albedo_sampler: Option<Handle<Sampler>>,
-        albedo: Rgba,
-        // metallic_texture: Option<Handle<Texture>>,
-        metallic_sampler: Option<Handle<Sampler>>,
-        metallic_factor: f32,
-        // roughness_texture: Option<Handle<Texture>>,
-        roughness_sampler: Option<Handle<Sampler>>,
-        roughness_factor: f32,
-        // normal_texture: Option<Handle<Texture>>,
-        normal_sampler: Option<Handle<Sampler>>,
-        normal_scale: f32,
-        // occlusion_texture: Option<Handle<Texture>>,
-        occlusion_sampler: Option<Handle<Sampler>>,
-        occlusion_strength: f32,
-        // emissive_texture: Option<Handle<Texture>>,
-        emissive_sampler: Option<Handle<Sampler>>,
-        emissive_factor: Rgba,
-    ) -> Material {
-        let alpha_mode_buffer = UniformBuffer::from_value(renderer, resources, AlphaModeUniform::new(alpha_mode));
-        
-        let albedo_sampler = if let Some(albedo_sampler) = albedo_sampler {
-            albedo_sampler
-        } else {
-            let texture = Texture::from_pixel(renderer, resources, &[255, 255, 255, 255], None);
-            let texture = resources.store(texture);
-            let sampler = Sampler::new_default(renderer, resources, texture);
-            resources.store(sampler)
-        };
-
-        let albedo_buffer = UniformBuffer::from_value(renderer, resources, albedo);
-
-        let metallic_sampler = if let Some(metallic_sampler) = metallic_sampler {
-            metallic_sampler
-        } else {
-            // default: metallic: 1.0, i.e. defer to roughness factor
-            let texture = Texture::from_pixel(renderer, resources, &[0, 0, 255, 255], None);
-            let texture = resources.store(texture);
-            let sampler = Sampler::new_default(renderer, resources, texture);
-            resources.store(sampler)
-        };
-
-        let metallic_factor_buffer = UniformBuffer::from_value(renderer, resources, metallic_factor);
-
-        let roughness_sampler = if let Some(roughness_sampler) = roughness_sampler {
-            roughness_sampler
-        } else {
-            let texture = Texture::from_pixel(renderer, resources, &[0, 255, 0, 255], None);
-            let texture = resources.store(texture);
-            let sampler = Sampler::new_default(renderer, resources, texture);
-            resources.store(sampler)
-        };
-        
-        let roughness_factor_buffer = UniformBuffer::from_value(renderer, resources, roughness_factor);
-
-        let normal_sampler = if let Some(normal_sampler) = normal_sampler {
-            normal_sampler
-        } else {
-            let texture = Texture::from_pixel(renderer, resources, &[128, 128, 255, 255], None);
-            let texture = resources.store(texture);
-            let sampler = Sampler::new_default(renderer, resources, texture);
-            resources.store(sampler)
-        };
-
-        let normal_scale_buffer = UniformBuffer::from_value(renderer, resources, normal_scale);
-
-        let occlusion_sampler = if let Some(occlusion_sampler) = occlusion_sampler {
-            occlusion_sampler
-        } else {
-            let texture = Texture::from_pixel(renderer, resources, &[255, 255, 255, 255], None);
-            let texture = resources.store(texture);
-            let sampler = Sampler::new_default(renderer, resources, texture);
-            resources.store(sampler)
-        };
-
-        let occlusion_strength_buffer = UniformBuffer::from_value(renderer, resources, occlusion_strength);
-
-        let emissive_sampler = if let Some(emissive_sampler) = emissive_sampler {
-            emissive_sampler
-        } else {
-            let texture = Texture::from_pixel(renderer, resources, &[255, 255, 255, 255], None);
-            let texture = resources.store(texture);
-            let sampler = Sampler::new_default(renderer, resources, texture);
-            resources.store(sampler)
-        };
-
-        let emissive_factor_buffer = UniformBuffer::from_value(renderer, resources, emissive_factor);
+    fn new(renderer: &Renderer, resources: &mut Resources, builder: MaterialBuilder) -> Material {
+        let uniform = UniformBuffer::from_value(renderer, resources, MaterialUniform::new(&builder));
+
+        let samplers = MaterialTexture::ALL.iter().zip(builder.samplers).map(|(texture, sampler)| {
+            sampler.unwrap_or_else(|| {
+                let texture = Texture::from_pixel(renderer, resources, &texture.default_pixel(), None);
+                let texture = resources.store(texture);
+                let sampler = Sampler::new_default(renderer, resources, texture);
+                resources.store(sampler)
+            })
+        }).collect();
 
         Material {
-            name,
-            double_sided,
-            alpha_mode,
-            alpha_mode_buffer,
-            // albedo_texture,
-            albedo_sampler,
-            albedo_buffer,
-            // metallic_texture,
-            metallic_sampler,
-            metallic_factor_buffer,
-            // roughness_texture,
-            roughness_sampler,
-            roughness_factor_buffer,
-            // normal_texture,
-            normal_sampler,
-            normal_scale_buffer,
-            // occlusion_texture,
-            occlusion_sampler,
-            occlusion_strength_buffer,
-            // emissive_texture,
-            emissive_sampler,
-            emissive_factor_buffer,
+            name: builder.name,
+            double_sided: builder.double_sided,
+            alpha_mode: builder.alpha_mode,
+            transparency: builder.transparency,
+            uniform,
+            samplers,
         }
     }
 
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
     pub(crate) fn binding_types() -> Vec<wgpu::BindingType> {
-        vec![
-            // Alpha Mode
-            wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            // Base Colour Texture
-            wgpu::BindingType::Texture {
-                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                view_dimension: wgpu::TextureViewDimension::D2,
-                multisampled: false,
-            },
-            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-            wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            // Metallic Texture
-            wgpu::BindingType::Texture {
-                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                view_dimension: wgpu::TextureViewDimension::D2,
-                multisampled: false,
-            },
-            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-            wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            // Roughness Texture
-            wgpu::BindingType::Texture {
-                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                view_dimension: wgpu::TextureViewDimension::D2,
-                multisampled: false,
-            },
-            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-            wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            // Normal Texture
-            wgpu::BindingType::Texture {
-                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                view_dimension: wgpu::TextureViewDimension::D2,
-                multisampled: false,
-            },
-            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        let mut binding_types = vec![
+            // Factors
             wgpu::BindingType::Buffer {
                 ty: wgpu::BufferBindingType::Uniform,
                 has_dynamic_offset: false,
                 min_binding_size: None,
             },
-            // Occlusion Texture
-            wgpu::BindingType::Texture {
-                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                view_dimension: wgpu::TextureViewDimension::D2,
-                multisampled: false,
-            },
-            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-            wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-            // Emissive Texture
-            wgpu::BindingType::Texture {
+        ];
+
+        // Texture and sampler of each MaterialTexture
+        for _ in MaterialTexture::ALL {
+            binding_types.push(wgpu::BindingType::Texture {
                 sample_type: wgpu::TextureSampleType::Float { filterable: true },
                 view_dimension: wgpu::TextureViewDimension::D2,
                 multisampled: false,
-            },
-            wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-            wgpu::BindingType::Buffer {
-                ty: wgpu::BufferBindingType::Uniform,
-                has_dynamic_offset: false,
-                min_binding_size: None,
-            },
-        ]
+            });
+            binding_types.push(wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering));
+        }
+
+        binding_types
     }
 
     pub(crate) fn binding_resources(&self, resources: &Resources) -> Vec<BindingHolder> {
-        let albedo_sampler = self.albedo_sampler.get(resources);
-        let metallic_sampler = self.metallic_sampler.get(resources);
-        let roughness_sampler = self.roughness_sampler.get(resources);
-        let normal_sampler = self.normal_sampler.get(resources);
-        let occlusion_sampler= self.occlusion_sampler.get(resources);
-        let emissive_sampler = self.emissive_sampler.get(resources);
-        
-        let mut binding_resources = vec![];
-        
-        binding_resources.push(self.alpha_mode_buffer.binding_resource());
-        binding_resources.extend(albedo_sampler.binding_resources(resources));
-        binding_resources.push(self.albedo_buffer.binding_resource());
-        binding_resources.extend(metallic_sampler.binding_resources(resources));
-        binding_resources.push(self.metallic_factor_buffer.binding_resource());
-        binding_resources.extend(roughness_sampler.binding_resources(resources));
-        binding_resources.push(self.roughness_factor_buffer.binding_resource());
-        binding_resources.extend(normal_sampler.binding_resources(resources));
-        binding_resources.push(self.normal_scale_buffer.binding_resource());
-        binding_resources.extend(occlusion_sampler.binding_resources(resources));
-        binding_resources.push(self.occlusion_strength_buffer.binding_resource());
-        binding_resources.extend(emissive_sampler.binding_resources(resources));
-        binding_resources.push(self.emissive_factor_buffer.binding_resource());
-        
+        let mut binding_resources = vec![self.uniform.binding_resource()];
+
+        for sampler in &self.samplers {
+            binding_resources.extend(sampler.get(resources).binding_resources(resources));
+        }
+
         binding_resources
     }
 
@@ -294,27 +249,30 @@ pub struct MaterialBuilder {
     name: Option<String>,
     double_sided: bool,
     alpha_mode: AlphaMode,
-    // albedo_texture: Option<Handle<Texture>>,
-    albedo_sampler: Option<Handle<Sampler>>,
+    transparency: Transparency,
+    unlit: bool,
+    /// Indexed by `MaterialTexture`
+    samplers: [Option<Handle<Sampler>>; MaterialTexture::COUNT],
+    tex_coords: [u32; MaterialTexture::COUNT],
+    transforms: [TextureTransform; MaterialTexture::COUNT],
     albedo: Rgba,
-    // metallic_texture: Option<Handle<Texture>>,
-    metallic_sampler: Option<Handle<Sampler>>,
     metallic_factor: f32,
     metallic_set: bool,
-    // roughness_texture: Option<Handle<Texture>>,
-    roughness_sampler: Option<Handle<Sampler>>,
     roughness_factor: f32,
     roughness_set: bool,
-    // normal_texture: Option<Handle<Texture>>,
-    normal_sampler: Option<Handle<Sampler>>,
     normal_scale: f32,
-    // occlusion_texture: Option<Handle<Texture>>,
-    occlusion_sampler: Option<Handle<Sampler>>,
     occlusion_strength: f32,
-    // emissive_texture: Option<Handle<Texture>>,
-    emissive_sampler: Option<Handle<Sampler>>,
     emissive_factor: Rgba,
     emissive_set: bool,
+    ior: f32,
+    clearcoat_factor: f32,
+    clearcoat_roughness_factor: f32,
+    clearcoat_normal_scale: f32,
+    transmission_factor: f32,
+    specular_factor: f32,
+    specular_colour_factor: glam::Vec3,
+    sheen_colour_factor: glam::Vec3,
+    sheen_roughness_factor: f32,
 }
 
 impl MaterialBuilder {
@@ -323,32 +281,32 @@ impl MaterialBuilder {
             name: None,
             double_sided: false,
             alpha_mode: AlphaMode::Mask { cutoff: 0.5 },
-            // albedo_texture: None,
-            albedo_sampler: None,
+            transparency: Transparency::Sorted,
+            unlit: false,
+            samplers: Default::default(),
+            tex_coords: [0; MaterialTexture::COUNT],
+            transforms: [TextureTransform::default(); MaterialTexture::COUNT],
             albedo: Rgba::WHITE,
-            // metallic_texture: None,
-            metallic_sampler: None,
             metallic_factor: 1.0,
             metallic_set: false,
-            // roughness_texture: None,
-            roughness_sampler: None,
             roughness_factor: 1.0,
             roughness_set: false,
-            // normal_texture: None,
-            normal_sampler: None,
             normal_scale: 1.0,
-            // occlusion_texture: None,
-            occlusion_sampler: None,
             occlusion_strength: 1.0,
-            // emissive_texture: None,
-            emissive_sampler: None,
             emissive_factor: Rgba::WHITE,
             emissive_set: false,
-            // height_texture: None,
-            // height_scale: 1.0,
+            ior: 1.5,
+            clearcoat_factor: 0.0,
+            clearcoat_roughness_factor: 0.0,
+            clearcoat_normal_scale: 1.0,
+            transmission_factor: 0.0,
+            specular_factor: 1.0,
+            specular_colour_factor: glam::Vec3::ONE,
+            sheen_colour_factor: glam::Vec3::ZERO,
+            sheen_roughness_factor: 0.0,
         }
     }
-    
+
     pub fn name(mut self, name: &str) -> MaterialBuilder {
         self.name = Some(name.to_owned());
         self
@@ -364,33 +322,51 @@ impl MaterialBuilder {
         self
     }
 
-    // pub fn albedo_texture(mut self, albedo_texture: Handle<Texture>) -> MaterialBuilder {
-    //     self.albedo_texture = Some(albedo_texture);
-    //     self
-    // }
+    pub fn transparency(mut self, transparency: Transparency) -> MaterialBuilder {
+        self.transparency = transparency;
+        self
+    }
+
+    /// Outputs the albedo without lighting (`KHR_materials_unlit`)
+    pub fn unlit(mut self, unlit: bool) -> MaterialBuilder {
+        self.unlit = unlit;
+        self
+    }
+
+    /// Samples `texture` with UV set `set`, 0 (the default) or 1. Other sets fall back to 0.
+    pub fn tex_coord(mut self, texture: MaterialTexture, set: u32) -> MaterialBuilder {
+        self.tex_coords[texture as usize] = if set <= 1 {
+            set
+        } else {
+            log::warn!("UV set {} of {:?} texture is not supported, using set 0.", set, texture);
+            0
+        };
+        self
+    }
+
+    pub fn texture_transform(mut self, texture: MaterialTexture, transform: TextureTransform) -> MaterialBuilder {
+        self.transforms[texture as usize] = transform;
+        self
+    }
 
-    pub fn albedo_sampler(mut self, albedo_sampler: Handle<Sampler>) -> MaterialBuilder {
-        self.albedo_sampler = Some(albedo_sampler);
+    /// The sampler of any `MaterialTexture`, see the named methods for which channels are used.
+    pub fn sampler(mut self, texture: MaterialTexture, sampler: Handle<Sampler>) -> MaterialBuilder {
+        self.samplers[texture as usize] = Some(sampler);
         self
     }
 
+    pub fn albedo_sampler(self, albedo_sampler: Handle<Sampler>) -> MaterialBuilder {
+        self.sampler(MaterialTexture::Albedo, albedo_sampler)
+    }
+
     pub fn albedo(mut self, albedo: Rgba) -> MaterialBuilder {
         self.albedo = albedo;
         self
     }
 
     /// Metallic value sampled from blue channel
-    // pub fn metallic_texture(mut self, metallic_texture: Handle<Texture>) -> MaterialBuilder {
-    //     self.metallic_texture = Some(metallic_texture);
-    //     self.metallic_set = true;
-    //     self.roughness_set = true;
-    //     self
-    // }
-
-    /// Metallic value sampled from blue channel
-    pub fn metallic_sampler(mut self, metallic_sampler: Handle<Sampler>) -> MaterialBuilder {
-        self.metallic_sampler = Some(metallic_sampler);
-        self
+    pub fn metallic_sampler(self, metallic_sampler: Handle<Sampler>) -> MaterialBuilder {
+        self.sampler(MaterialTexture::Metallic, metallic_sampler)
     }
 
     pub fn metallic_factor(mut self, metallic_factor: f32) -> MaterialBuilder {
@@ -400,17 +376,8 @@ impl MaterialBuilder {
     }
 
     /// Roughness sampled from green channel
-    // pub fn roughness_texture(mut self, roughness_texture: Handle<Texture>) -> MaterialBuilder {
-    //     self.roughness_texture = Some(roughness_texture);
-    //     self.metallic_set = true;
-    //     self.roughness_set = true;
-    //     self
-    // }
-
-    /// Roughness sampled from green channel
-    pub fn roughness_sampler(mut self, roughness_sampler: Handle<Sampler>) -> MaterialBuilder {
-        self.roughness_sampler = Some(roughness_sampler);
-        self
+    pub fn roughness_sampler(self, roughness_sampler: Handle<Sampler>) -> MaterialBuilder {
+        self.sampler(MaterialTexture::Roughness, roughness_sampler)
     }
 
     pub fn roughness_factor(mut self, roughness_factor: f32) -> MaterialBuilder {
@@ -419,14 +386,8 @@ impl MaterialBuilder {
         self
     }
 
-    // pub fn normal_texture(mut self, normal_texture: Handle<Texture>) -> MaterialBuilder {
-    //     self.normal_texture = Some(normal_texture);
-    //     self
-    // }
-
-    pub fn normal_sampler(mut self, normal_sampler: Handle<Sampler>) -> MaterialBuilder {
-        self.normal_sampler = Some(normal_sampler);
-        self
+    pub fn normal_sampler(self, normal_sampler: Handle<Sampler>) -> MaterialBuilder {
+        self.sampler(MaterialTexture::Normal, normal_sampler)
     }
 
     pub fn normal_scale(mut self, normal_scale: f32) -> MaterialBuilder {
@@ -435,15 +396,8 @@ impl MaterialBuilder {
     }
 
     /// Occlusion sampled from red channel
-    // pub fn occlusion_texture(mut self, occlusion_texture: Handle<Texture>) -> MaterialBuilder {
-    //     self.occlusion_texture = Some(occlusion_texture);
-    //     self
-    // }
-
-    /// Occlusion sampled from red channel
-    pub fn occlusion_sampler(mut self, occlusion_sampler: Handle<Sampler>) -> MaterialBuilder {
-        self.occlusion_sampler = Some(occlusion_sampler);
-        self
+    pub fn occlusion_sampler(self, occlusion_sampler: Handle<Sampler>) -> MaterialBuilder {
+        self.sampler(MaterialTexture::Occlusion, occlusion_sampler)
     }
 
     pub fn occlusion_strength(mut self, occlusion_strength: f32) -> MaterialBuilder {
@@ -451,23 +405,68 @@ impl MaterialBuilder {
         self
     }
 
-    // pub fn emissive_texture(mut self, emissive_texture: Handle<Texture>) -> MaterialBuilder {
-    //     self.emissive_texture = Some(emissive_texture);
-    //     self.emissive_set = true;
-    //     self
-    // }
-
-    pub fn emissive_sampler(mut self, emissive_sampler: Handle<Sampler>) -> MaterialBuilder {
-        self.emissive_sampler = Some(emissive_sampler);
-        self
+    pub fn emissive_sampler(self, emissive_sampler: Handle<Sampler>) -> MaterialBuilder {
+        self.sampler(MaterialTexture::Emissive, emissive_sampler)
     }
 
+    /// Linear, may exceed 1 (`KHR_materials_emissive_strength`)
     pub fn emissive_factor(mut self, emissive_factor: Rgba) -> MaterialBuilder {
         self.emissive_factor = emissive_factor;
         self.emissive_set = true;
         self
     }
 
+    /// Index of refraction of dielectrics (`KHR_materials_ior`), 1.5 by default
+    pub fn ior(mut self, ior: f32) -> MaterialBuilder {
+        self.ior = ior;
+        self
+    }
+
+    /// Strength of a second specular layer over the material (`KHR_materials_clearcoat`)
+    pub fn clearcoat_factor(mut self, clearcoat_factor: f32) -> MaterialBuilder {
+        self.clearcoat_factor = clearcoat_factor;
+        self
+    }
+
+    pub fn clearcoat_roughness_factor(mut self, clearcoat_roughness_factor: f32) -> MaterialBuilder {
+        self.clearcoat_roughness_factor = clearcoat_roughness_factor;
+        self
+    }
+
+    pub fn clearcoat_normal_scale(mut self, clearcoat_normal_scale: f32) -> MaterialBuilder {
+        self.clearcoat_normal_scale = clearcoat_normal_scale;
+        self
+    }
+
+    /// Fraction of light transmitted through the surface (`KHR_materials_transmission`), refracted by the ior
+    pub fn transmission_factor(mut self, transmission_factor: f32) -> MaterialBuilder {
+        self.transmission_factor = transmission_factor;
+        self
+    }
+
+    /// Strength of dielectric reflections (`KHR_materials_specular`)
+    pub fn specular_factor(mut self, specular_factor: f32) -> MaterialBuilder {
+        self.specular_factor = specular_factor;
+        self
+    }
+
+    /// Linear tint of dielectric reflections at normal incidence
+    pub fn specular_colour_factor(mut self, specular_colour_factor: glam::Vec3) -> MaterialBuilder {
+        self.specular_colour_factor = specular_colour_factor;
+        self
+    }
+
+    /// Linear colour of the retroreflective sheen of cloth (`KHR_materials_sheen`), black disables it
+    pub fn sheen_colour_factor(mut self, sheen_colour_factor: glam::Vec3) -> MaterialBuilder {
+        self.sheen_colour_factor = sheen_colour_factor;
+        self
+    }
+
+    pub fn sheen_roughness_factor(mut self, sheen_roughness_factor: f32) -> MaterialBuilder {
+        self.sheen_roughness_factor = sheen_roughness_factor;
+        self
+    }
+
     pub fn build(mut self, renderer: &Renderer, resources: &mut Resources) -> Material {
         if !self.metallic_set {
             self.metallic_factor = 0.0;
@@ -479,30 +478,6 @@ impl MaterialBuilder {
             self.emissive_factor = Rgba::TRANSPARENT_BLACK;
         }
 
-        Material::new(
-            renderer,
-            resources,
-            self.name,
-            self.double_sided,
-            self.alpha_mode,
-            // self.albedo_texture,
-            self.albedo_sampler,
-            self.albedo,
-            // self.metallic_texture,
-            self.metallic_sampler,
-            self.metallic_factor,
-            // self.roughness_texture,
-            self.roughness_sampler,
-            self.roughness_factor,
-            // self.normal_texture,
-            self.normal_sampler,
-            self.normal_scale,
-            // self.occlusion_texture,
-            self.occlusion_sampler,
-            self.occlusion_strength,
-            // self.emissive_texture,
-            self.emissive_sampler,
-            self.emissive_factor,
-        )
+        Material::new(renderer, resources, self)
     }
 }
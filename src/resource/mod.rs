@@ -4,6 +4,7 @@ mod material;
 mod server;
 mod cubemap;
 mod sampler;
+mod animation;
 
 pub use model::*;
 pub use texture::*;
@@ -11,3 +12,4 @@ pub use material::*;
 pub use server::*;
 pub use cubemap::*;
 pub use sampler::*;
+pub use animation::*;
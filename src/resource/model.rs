@@ -1,6 +1,6 @@
 use wgpu::util::DeviceExt;
 
-use crate::{renderer::{Vertex, Renderer, RenderInput, RenderableResource, PositionVertex, ModelVertex}, node::NodeDescriptor, resource::compute_tangents};
+use crate::{renderer::{Vertex, Renderer, RenderInput, RenderableResource, PositionVertex, ModelVertex, BindingHolder}, node::NodeDescriptor, resource::compute_tangents, engine::Aabb};
 
 use super::{Material, Handle, Resources};
 
@@ -32,29 +32,29 @@ impl Model {
                     position: glam::vec3(-1.0,0.0, -1.0),
                     tex_coords: glam::vec2(0.0, 0.0),
                     normal: glam::vec3(0.0, 1.0, 0.0),
-                    tangent: glam::vec3(1.0, 0.0, 0.0),
-                    bitangent: glam::vec3(0.0, 0.0, -1.0),
+                    tangent: [1.0, 0.0, 0.0, 1.0],
+                    ..Default::default()
                 },
                 ModelVertex {
                     position: glam::vec3( 1.0, 0.0, -1.0),
                     tex_coords: glam::vec2(1.0, 0.0),
                     normal: glam::vec3(0.0, 1.0, 0.0),
-                    tangent: glam::vec3(1.0, 0.0, 0.0),
-                    bitangent: glam::vec3(0.0, 0.0, -1.0),
+                    tangent: [1.0, 0.0, 0.0, 1.0],
+                    ..Default::default()
                 },
                 ModelVertex {
                     position: glam::vec3( 1.0, 0.0,  1.0),
                     tex_coords: glam::vec2(1.0, 1.0),
                     normal: glam::vec3(0.0, 1.0, 0.0),
-                    tangent: glam::vec3(1.0, 0.0, 0.0),
-                    bitangent: glam::vec3(0.0, 0.0, -1.0),
+                    tangent: [1.0, 0.0, 0.0, 1.0],
+                    ..Default::default()
                 },
                 ModelVertex {
                     position: glam::vec3(-1.0, 0.0,  1.0),
                     tex_coords: glam::vec2(0.0, 1.0),
                     normal: glam::vec3(0.0, 1.0, 0.0),
-                    tangent: glam::vec3(1.0, 0.0, 0.0),
-                    bitangent: glam::vec3(0.0, 0.0, -1.0),
+                    tangent: [1.0, 0.0, 0.0, 1.0],
+                    ..Default::default()
                 },
             ], indices, material)
         } else {
@@ -198,8 +198,7 @@ impl Model {
                                 SphereUV::Cube => glam::vec2(x as f32 / subdivisions as f32, y as f32 / subdivisions as f32),
                             },
                             normal: pos,
-                            tangent: glam::Vec3::ZERO,
-                            bitangent: glam::Vec3::ZERO,
+                            ..Default::default()
                         });
                     } else {
                         position_vertices.push(PositionVertex {
@@ -243,6 +242,18 @@ impl Model {
             meshes: vec![mesh],
         }
     }
+
+    /// Object space bounds of all meshes.
+    pub fn bounds(&self) -> Aabb {
+        self.meshes.iter().fold(Aabb::EMPTY, |bounds, mesh| bounds.union(&mesh.bounds))
+    }
+
+    /// Bytes of the vertex, index and morph target buffers, materials aren't included.
+    pub fn gpu_memory(&self, resources: &Resources) -> u64 {
+        self.meshes.iter().flat_map(|mesh| {
+            [Some(&mesh.vertex_buffer), Some(&mesh.index_buffer), mesh.morph_targets.as_ref().map(|targets| &targets.buffer)]
+        }).flatten().map(|buffer| buffer.get(resources).size()).sum()
+    }
 }
 
 pub struct Mesh {
@@ -251,11 +262,15 @@ pub struct Mesh {
     pub(crate) index_buffer: Handle<wgpu::Buffer>,
     pub num_elements: u32,
     pub material: Option<Handle<Material>>,
+    /// Object space bounds of the vertices.
+    pub bounds: Aabb,
+    pub morph_targets: Option<MorphTargets>,
 }
 
 impl Mesh {
     pub fn new<T: Vertex>(renderer: &Renderer, resources: &mut Resources, name: Option<&str>, vertices: Vec<T>, indices: Vec<u32>, material: Option<Handle<Material>>) -> Mesh {
         let num_elements = indices.len() as u32;
+        let bounds = Aabb::from_points(vertices.iter().map(Vertex::position));
 
         let vertex_buffer = resources.store(renderer.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some(&format!("Mesh Vertex Buffer")),
@@ -275,8 +290,108 @@ impl Mesh {
             index_buffer,
             num_elements,
             material,
+            bounds,
+            morph_targets: None,
+        }
+    }
+
+    /// A mesh with morph targets, blended by the `MorphWeights` of its node in shaders with the `MeshMorphTargets` input.
+    pub fn new_morphed<T: Vertex>(renderer: &Renderer, resources: &mut Resources, name: Option<&str>, vertices: Vec<T>, indices: Vec<u32>, material: Option<Handle<Material>>, targets: &[MorphTarget]) -> Mesh {
+        let num_vertices = vertices.len();
+        let mut mesh = Mesh::new(renderer, resources, name, vertices, indices, material);
+
+        if !targets.is_empty() {
+            mesh.morph_targets = Some(MorphTargets::new(renderer, resources, num_vertices, targets));
+        }
+
+        mesh
+    }
+}
+
+/// Offsets of each vertex of a mesh, missing offsets are zero.
+#[derive(Debug, Clone, Default)]
+pub struct MorphTarget {
+    pub positions: Vec<glam::Vec3>,
+    pub normals: Vec<glam::Vec3>,
+    pub tangents: Vec<glam::Vec3>,
+}
+
+/// Morph targets (blend shapes) of a mesh, stored on the GPU.
+pub struct MorphTargets {
+    /// The number of targets followed by the deltas, so it only takes one binding
+    buffer: Handle<wgpu::Buffer>,
+    pub num_targets: u32,
+}
+
+impl MorphTargets {
+    /// Targets beyond this are ignored, see `MorphWeights`.
+    pub const MAX_TARGETS: usize = 64;
+
+    pub fn new(renderer: &Renderer, resources: &mut Resources, num_vertices: usize, targets: &[MorphTarget]) -> MorphTargets {
+        if targets.len() > MorphTargets::MAX_TARGETS {
+            log::warn!("Mesh has {} morph targets, only the first {} are used.", targets.len(), MorphTargets::MAX_TARGETS);
         }
+
+        let targets = &targets[..targets.len().min(MorphTargets::MAX_TARGETS)];
+
+        // the targets of each vertex are adjacent, so shaders only need the number of targets to index them
+        let mut deltas = Vec::with_capacity(num_vertices * targets.len());
+        for i in 0..num_vertices {
+            for target in targets {
+                let delta = |values: &Vec<glam::Vec3>| values.get(i).copied().unwrap_or(glam::Vec3::ZERO).extend(0.0).to_array();
+
+                deltas.push(MorphDelta {
+                    position: delta(&target.positions),
+                    normal: delta(&target.normals),
+                    tangent: delta(&target.tangents),
+                });
+            }
+        }
+
+        let num_targets = targets.len() as u32;
+        // padded to the alignment of the deltas
+        let mut contents = bytemuck::bytes_of(&[num_targets, 0, 0, 0]).to_vec();
+        contents.extend_from_slice(bytemuck::cast_slice(&deltas));
+
+        let buffer = resources.store(renderer.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mesh Morph Targets Buffer"),
+            contents: &contents,
+            usage: wgpu::BufferUsages::STORAGE,
+        }));
+
+        MorphTargets {
+            buffer,
+            num_targets,
+        }
+    }
+
+    /// The targets, and the weights of the node's `MorphWeights`.
+    pub(crate) fn binding_types() -> Vec<wgpu::BindingType> {
+        vec![
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+        ]
     }
+
+    pub(crate) fn binding_resource(&self) -> BindingHolder {
+        BindingHolder::Buffer(self.buffer.clone())
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MorphDelta {
+    position: [f32; 4],
+    normal: [f32; 4],
+    tangent: [f32; 4],
 }
 
 impl RenderableResource for Model {
@@ -290,6 +405,8 @@ impl RenderableResource for Model {
                 index_buffer: mesh.index_buffer.clone(),
                 material: mesh.material.clone(),
                 num_elements: mesh.num_elements,
+                bounds: mesh.bounds,
+                morph_targets: mesh.morph_targets.as_ref().map(MorphTargets::binding_resource),
             });
         }
 
@@ -0,0 +1,301 @@
+use gltf::accessor::{DataType, Dimensions};
+
+/// Reads each element of `accessor` as `N` floats. Normalized integers are mapped to [0, 1] or [-1, 1], other integers are converted as is.
+pub(crate) fn read_accessor_f32<const N: usize>(accessor: &gltf::Accessor, buffers: &[gltf::buffer::Data]) -> Result<Vec<[f32; N]>, AccessorError> {
+    let normalized = accessor.normalized();
+
+    read_accessor(accessor, buffers, |bytes, data_type| Ok(match data_type {
+        DataType::F32 => f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        DataType::I8 if normalized => (bytes[0] as i8 as f32 / i8::MAX as f32).max(-1.0),
+        DataType::U8 if normalized => bytes[0] as f32 / u8::MAX as f32,
+        DataType::I16 if normalized => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32 / i16::MAX as f32).max(-1.0),
+        DataType::U16 if normalized => u16::from_le_bytes([bytes[0], bytes[1]]) as f32 / u16::MAX as f32,
+        DataType::U32 if normalized => (u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64 / u32::MAX as f64) as f32,
+        DataType::I8 => bytes[0] as i8 as f32,
+        DataType::U8 => bytes[0] as f32,
+        DataType::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+        DataType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as f32,
+        DataType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32,
+    }))
+}
+
+/// Reads each element of `accessor` as `N` unsigned integers, i.e. indices and joints. Signed and float components are an error.
+pub(crate) fn read_accessor_u32<const N: usize>(accessor: &gltf::Accessor, buffers: &[gltf::buffer::Data]) -> Result<Vec<[u32; N]>, AccessorError> {
+    let index = accessor.index();
+
+    read_accessor(accessor, buffers, |bytes, data_type| match data_type {
+        DataType::U8 => Ok(bytes[0] as u32),
+        DataType::U16 => Ok(u16::from_le_bytes([bytes[0], bytes[1]]) as u32),
+        DataType::U32 => Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+        _ => Err(AccessorError::ComponentType { accessor: index, found: data_type }),
+    })
+}
+
+/// Elements are read from the accessor's buffer view (zero without one), then replaced by its sparse values.
+fn read_accessor<T: Copy + Default, const N: usize>(
+    accessor: &gltf::Accessor,
+    buffers: &[gltf::buffer::Data],
+    component: impl Fn(&[u8], DataType) -> Result<T, AccessorError>,
+) -> Result<Vec<[T; N]>, AccessorError> {
+    let index = accessor.index();
+    let dimensions = accessor.dimensions();
+    if dimensions.multiplicity() != N {
+        return Err(AccessorError::Dimensions { accessor: index, expected: N, found: dimensions });
+    }
+
+    let data_type = accessor.data_type();
+    let (offsets, element_size) = element_layout(dimensions, data_type.size());
+
+    let element = |bytes: Option<&[u8]>| -> Result<[T; N], AccessorError> {
+        let bytes = bytes.ok_or(AccessorError::OutOfBounds { accessor: index })?;
+        let mut element = [T::default(); N];
+        for (value, offset) in element.iter_mut().zip(&offsets) {
+            *value = component(&bytes[*offset..], data_type)?;
+        }
+        Ok(element)
+    };
+
+    let mut elements = vec![[T::default(); N]; accessor.count()];
+
+    if let Some(view) = accessor.view() {
+        let data = view_data(&view, buffers, index)?;
+        let stride = view.stride().unwrap_or(element_size);
+
+        for (i, value) in elements.iter_mut().enumerate() {
+            let start = accessor.offset() + i * stride;
+            *value = element(data.get(start..start + element_size))?;
+        }
+    }
+
+    if let Some(sparse) = accessor.sparse() {
+        let indices = sparse.indices();
+        let index_data = view_data(&indices.view(), buffers, index)?;
+        let index_size = indices.index_type().size();
+
+        let values = sparse.values();
+        let value_data = view_data(&values.view(), buffers, index)?;
+
+        for i in 0..sparse.count() as usize {
+            let start = indices.offset() as usize + i * index_size;
+            let bytes = index_data.get(start..start + index_size).ok_or(AccessorError::OutOfBounds { accessor: index })?;
+            let target = match indices.index_type() {
+                gltf::accessor::sparse::IndexType::U8 => bytes[0] as u32,
+                gltf::accessor::sparse::IndexType::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+                gltf::accessor::sparse::IndexType::U32 => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            };
+
+            // values are tightly packed
+            let start = values.offset() as usize + i * element_size;
+            let value = element(value_data.get(start..start + element_size))?;
+
+            *elements.get_mut(target as usize).ok_or(AccessorError::SparseIndex { accessor: index, index: target })? = value;
+        }
+    }
+
+    Ok(elements)
+}
+
+/// Offset of each component of an element and the size of the element. Matrix columns are aligned to 4 bytes.
+fn element_layout(dimensions: Dimensions, component_size: usize) -> (Vec<usize>, usize) {
+    let (columns, rows) = match dimensions {
+        Dimensions::Mat2 => (2, 2),
+        Dimensions::Mat3 => (3, 3),
+        Dimensions::Mat4 => (4, 4),
+        dimensions => (1, dimensions.multiplicity()),
+    };
+
+    let column_size = rows * component_size;
+    let column_stride = if columns > 1 { (column_size + 3) / 4 * 4 } else { column_size };
+
+    let offsets = (0..columns).flat_map(|column| (0..rows).map(move |row| column * column_stride + row * component_size)).collect();
+    (offsets, (columns - 1) * column_stride + column_size)
+}
+
+fn view_data<'a>(view: &gltf::buffer::View, buffers: &'a [gltf::buffer::Data], accessor: usize) -> Result<&'a [u8], AccessorError> {
+    let buffer = buffers.get(view.buffer().index()).ok_or(AccessorError::MissingBuffer(view.buffer().index()))?;
+    buffer.0.get(view.offset()..view.offset() + view.length()).ok_or(AccessorError::OutOfBounds { accessor })
+}
+
+#[derive(Debug)]
+pub enum AccessorError {
+    /// The accessor's type doesn't have the expected number of components
+    Dimensions {
+        accessor: usize,
+        expected: usize,
+        found: Dimensions,
+    },
+    /// i.e. float indices
+    ComponentType {
+        accessor: usize,
+        found: DataType,
+    },
+    /// An element is outside of its buffer view, or the view is outside of its buffer
+    OutOfBounds {
+        accessor: usize,
+    },
+    /// A sparse index is not less than the accessor's count
+    SparseIndex {
+        accessor: usize,
+        index: u32,
+    },
+    /// The buffer wasn't loaded
+    MissingBuffer(usize),
+}
+
+impl std::fmt::Display for AccessorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessorError::Dimensions { accessor, expected, found } => write!(f, "Accessor {accessor} has type {found:?}, expected {expected} components."),
+            AccessorError::ComponentType { accessor, found } => write!(f, "Accessor {accessor} has unsupported component type {found:?}."),
+            AccessorError::OutOfBounds { accessor } => write!(f, "Accessor {accessor} reads outside of its buffer."),
+            AccessorError::SparseIndex { accessor, index } => write!(f, "Accessor {accessor} has out of range sparse index {index}."),
+            AccessorError::MissingBuffer(buffer) => write!(f, "Buffer {buffer} is not loaded."),
+        }
+    }
+}
+
+impl std::error::Error for AccessorError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A document with one buffer of `bytes`, `views` as `(offset, length, stride)` and the accessors `accessors` (JSON objects).
+    fn document(bytes: &[u8], views: &[(usize, usize, Option<usize>)], accessors: &[&str]) -> (gltf::Document, Vec<gltf::buffer::Data>) {
+        let views: Vec<_> = views.iter().map(|(offset, length, stride)| match stride {
+            Some(stride) => format!(r#"{{"buffer": 0, "byteOffset": {offset}, "byteLength": {length}, "byteStride": {stride}}}"#),
+            None => format!(r#"{{"buffer": 0, "byteOffset": {offset}, "byteLength": {length}}}"#),
+        }).collect();
+
+        let json = format!(
+            r#"{{"asset": {{"version": "2.0"}}, "buffers": [{{"byteLength": {}}}], "bufferViews": [{}], "accessors": [{}]}}"#,
+            bytes.len(), views.join(", "), accessors.join(", "),
+        );
+
+        let gltf = gltf::Gltf::from_slice(json.as_bytes()).unwrap();
+        (gltf.document, vec![gltf::buffer::Data(bytes.to_vec())])
+    }
+
+    fn bytes<T: bytemuck::Pod>(values: &[T]) -> Vec<u8> {
+        bytemuck::cast_slice(values).to_vec()
+    }
+
+    #[test]
+    fn floats() {
+        let (document, buffers) = document(&bytes(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0]), &[(0, 24, None)], &[
+            r#"{"bufferView": 0, "componentType": 5126, "count": 2, "type": "VEC3"}"#,
+        ]);
+
+        let values = read_accessor_f32::<3>(&document.accessors().next().unwrap(), &buffers).unwrap();
+        assert_eq!(values, [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+    }
+
+    #[test]
+    fn normalized_integers() {
+        let mut data = bytes(&[-128i8, -127, 0, 127]);
+        data.extend(bytes(&[0u16, 32768, 65535, 0]));
+
+        let (document, buffers) = document(&data, &[(0, 4, None), (4, 8, None)], &[
+            r#"{"bufferView": 0, "componentType": 5120, "normalized": true, "count": 4, "type": "SCALAR"}"#,
+            r#"{"bufferView": 1, "componentType": 5123, "normalized": true, "count": 1, "type": "VEC3"}"#,
+            r#"{"bufferView": 1, "componentType": 5123, "count": 1, "type": "VEC3"}"#,
+        ]);
+        let accessors: Vec<_> = document.accessors().collect();
+
+        // -128 and -127 both map to -1
+        let signed = read_accessor_f32::<1>(&accessors[0], &buffers).unwrap();
+        assert_eq!(signed, [[-1.0], [-1.0], [0.0], [1.0]]);
+
+        let unsigned = read_accessor_f32::<3>(&accessors[1], &buffers).unwrap();
+        assert_eq!(unsigned[0][0], 0.0);
+        assert!((unsigned[0][1] - 0.5).abs() < 1e-4);
+        assert_eq!(unsigned[0][2], 1.0);
+
+        let raw = read_accessor_f32::<3>(&accessors[2], &buffers).unwrap();
+        assert_eq!(raw, [[0.0, 32768.0, 65535.0]]);
+    }
+
+    #[test]
+    fn indices_of_every_unsigned_type() {
+        let mut data = bytes(&[0u8, 1, 2, 255]);
+        data.extend(bytes(&[3u16, 65535]));
+        data.extend(bytes(&[70000u32]));
+
+        let (document, buffers) = document(&data, &[(0, 4, None), (4, 4, None), (8, 4, None)], &[
+            r#"{"bufferView": 0, "componentType": 5121, "count": 4, "type": "SCALAR"}"#,
+            r#"{"bufferView": 1, "componentType": 5123, "count": 2, "type": "SCALAR"}"#,
+            r#"{"bufferView": 2, "componentType": 5125, "count": 1, "type": "SCALAR"}"#,
+        ]);
+        let accessors: Vec<_> = document.accessors().collect();
+
+        assert_eq!(read_accessor_u32::<1>(&accessors[0], &buffers).unwrap(), [[0], [1], [2], [255]]);
+        assert_eq!(read_accessor_u32::<1>(&accessors[1], &buffers).unwrap(), [[3], [65535]]);
+        assert_eq!(read_accessor_u32::<1>(&accessors[2], &buffers).unwrap(), [[70000]]);
+    }
+
+    #[test]
+    fn interleaved_with_byte_stride() {
+        // position (3 floats) followed by a u8 joint and 3 bytes of padding
+        let mut data = vec![];
+        for i in 0..3 {
+            data.extend(bytes(&[i as f32, 10.0 * i as f32, 100.0 * i as f32]));
+            data.extend([i as u8 + 1, 0, 0, 0]);
+        }
+
+        let (document, buffers) = document(&data, &[(0, 48, Some(16))], &[
+            r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"}"#,
+            r#"{"bufferView": 0, "byteOffset": 12, "componentType": 5121, "count": 3, "type": "SCALAR"}"#,
+        ]);
+        let accessors: Vec<_> = document.accessors().collect();
+
+        assert_eq!(read_accessor_f32::<3>(&accessors[0], &buffers).unwrap(), [[0.0, 0.0, 0.0], [1.0, 10.0, 100.0], [2.0, 20.0, 200.0]]);
+        assert_eq!(read_accessor_u32::<1>(&accessors[1], &buffers).unwrap(), [[1], [2], [3]]);
+    }
+
+    #[test]
+    fn sparse_substitution() {
+        let mut data = bytes(&[1.0f32, 2.0, 3.0, 4.0]);
+        data.extend(bytes(&[3u16, 1]));
+        data.extend(bytes(&[40.0f32, 20.0]));
+
+        let (document, buffers) = document(&data, &[(0, 16, None), (16, 4, None), (20, 8, None)], &[
+            r#"{"bufferView": 0, "componentType": 5126, "count": 4, "type": "SCALAR",
+                "sparse": {"count": 2, "indices": {"bufferView": 1, "componentType": 5123}, "values": {"bufferView": 2}}}"#,
+            r#"{"componentType": 5126, "count": 3, "type": "SCALAR",
+                "sparse": {"count": 1, "indices": {"bufferView": 1, "byteOffset": 2, "componentType": 5123}, "values": {"bufferView": 2, "byteOffset": 4}}}"#,
+        ]);
+        let accessors: Vec<_> = document.accessors().collect();
+
+        assert_eq!(read_accessor_f32::<1>(&accessors[0], &buffers).unwrap(), [[1.0], [20.0], [3.0], [40.0]]);
+        // without a buffer view elements start as zero
+        assert_eq!(read_accessor_f32::<1>(&accessors[1], &buffers).unwrap(), [[0.0], [20.0], [0.0]]);
+    }
+
+    #[test]
+    fn matrix_columns_are_padded() {
+        // MAT2 of u8: each 2 byte column is padded to 4 bytes
+        let (document, buffers) = document(&[1, 2, 0, 0, 3, 4, 0, 0], &[(0, 8, None)], &[
+            r#"{"bufferView": 0, "componentType": 5121, "count": 1, "type": "MAT2"}"#,
+        ]);
+
+        assert_eq!(read_accessor_f32::<4>(&document.accessors().next().unwrap(), &buffers).unwrap(), [[1.0, 2.0, 3.0, 4.0]]);
+    }
+
+    #[test]
+    fn errors() {
+        let (document, buffers) = document(&bytes(&[1.0f32, 2.0]), &[(0, 8, None)], &[
+            r#"{"bufferView": 0, "componentType": 5126, "count": 2, "type": "SCALAR"}"#,
+            r#"{"bufferView": 0, "componentType": 5126, "count": 3, "type": "SCALAR"}"#,
+            r#"{"componentType": 5126, "count": 1, "type": "SCALAR",
+                "sparse": {"count": 1, "indices": {"bufferView": 0, "componentType": 5125}, "values": {"bufferView": 0}}}"#,
+        ]);
+        let accessors: Vec<_> = document.accessors().collect();
+
+        assert!(matches!(read_accessor_f32::<3>(&accessors[0], &buffers), Err(AccessorError::Dimensions { accessor: 0, expected: 3, .. })));
+        assert!(matches!(read_accessor_u32::<1>(&accessors[0], &buffers), Err(AccessorError::ComponentType { accessor: 0, .. })));
+        assert!(matches!(read_accessor_f32::<1>(&accessors[1], &buffers), Err(AccessorError::OutOfBounds { accessor: 1 })));
+        // the bits of 1.0 as a u32 index
+        assert!(matches!(read_accessor_f32::<1>(&accessors[2], &buffers), Err(AccessorError::SparseIndex { accessor: 2, index: 0x3f80_0000 })));
+        assert!(matches!(read_accessor_f32::<1>(&accessors[0], &[]), Err(AccessorError::MissingBuffer(0))));
+    }
+}
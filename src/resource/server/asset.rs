@@ -0,0 +1,384 @@
+use std::{any::Any, collections::HashMap, future::Future, panic::AssertUnwindSafe, path::Path, sync::{Arc, mpsc}};
+
+use tokio::sync::watch;
+
+use crate::{renderer::Renderer, node::{Node, NodeDescriptor, NodeScript}, engine::UpdateContext, resource::{Texture, Model, CubeMap, CubeError}};
+
+use super::{Resources, Handle, WeakHandle, HandleId, HandlesResource, TextureLoadError, ModelLoadError, SceneLoadError, DecodedTexture, ObjData, GltfData, build_gltf, cached_gltf_images, expand_greyscale, texture_options, register_texture, file_size};
+
+#[derive(Debug, Clone)]
+pub enum LoadState {
+    Loading,
+    Loaded,
+    Failed(Arc<AssetLoadError>),
+}
+
+impl LoadState {
+    pub fn is_loading(&self) -> bool {
+        matches!(self, LoadState::Loading)
+    }
+}
+
+/// Decoded data of a finished load.
+type Decoded = Result<Box<dyn Any + Send>, AssetLoadError>;
+
+/// Uploads decoded data on the main thread, storing the asset in its handle.
+type Upload = Box<dyn FnOnce(&Renderer, &mut Resources, Box<dyn Any + Send>) -> Result<(), AssetLoadError>>;
+
+struct AssetLoad {
+    label: String,
+    state: watch::Sender<LoadState>,
+    /// `None` once uploaded
+    upload: Option<Upload>,
+    alive: Box<dyn Fn() -> bool>,
+}
+
+/// Decodes assets on worker threads, the results are uploaded between frames by `Resources::update_assets`.
+pub(crate) struct AssetServer {
+    /// Created on the first load
+    runtime: Option<tokio::runtime::Runtime>,
+    sender: mpsc::Sender<(HandleId, Decoded)>,
+    receiver: mpsc::Receiver<(HandleId, Decoded)>,
+    loads: HashMap<HandleId, AssetLoad>,
+}
+
+impl AssetServer {
+    pub(crate) fn new() -> AssetServer {
+        let (sender, receiver) = mpsc::channel();
+
+        AssetServer {
+            runtime: None,
+            sender,
+            receiver,
+            loads: hashmap!{},
+        }
+    }
+
+    fn spawn<D: Send + 'static, F: FnOnce() -> Result<D, AssetLoadError> + Send + 'static>(&mut self, id: HandleId, decode: F) {
+        let runtime = self.runtime.get_or_insert_with(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("tritium-assets")
+                .build()
+                .expect("Failed to create asset loading runtime.")
+        });
+
+        let sender = self.sender.clone();
+
+        runtime.spawn_blocking(move || {
+            let decoded = std::panic::catch_unwind(AssertUnwindSafe(decode))
+                .unwrap_or(Err(AssetLoadError::Panicked))
+                .map(|data| Box::new(data) as Box<dyn Any + Send>);
+
+            // the server is gone if resources were dropped while loading
+            let _ = sender.send((id, decoded));
+        });
+    }
+
+    /// Drops finished loads of dropped handles.
+    pub(crate) fn drop_invalid(&mut self) {
+        self.loads.retain(|_, load| load.upload.is_some() || (load.alive)());
+    }
+
+    fn pending(&self) -> usize {
+        self.loads.values().filter(|load| load.upload.is_some()).count()
+    }
+}
+
+impl Drop for AssetServer {
+    fn drop(&mut self) {
+        // dropping a runtime blocks, which panics inside of an async context
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl Resources {
+    /// Runs `decode` on a worker thread, then `upload` on the main thread before the next update.
+    /// The handle is returned immediately, it has no resource until the asset is loaded (see `load_state`).
+    pub fn load_async<T, D, F, U>(&mut self, label: &str, decode: F, upload: U) -> Handle<T>
+    where
+        T: 'static,
+        D: Send + 'static,
+        F: FnOnce() -> Result<D, AssetLoadError> + Send + 'static,
+        U: FnOnce(&Renderer, &mut Resources, D) -> Result<T, AssetLoadError> + 'static,
+    {
+        self.load_async_into(label, decode, move |renderer, resources, data, handle| {
+            let asset = upload(renderer, resources, data)?;
+            handle.set(resources, asset);
+            Ok(())
+        })
+    }
+
+    /// Like `load_async`, but `upload` stores the asset in the handle itself.
+    pub(crate) fn load_async_into<T, D, F, U>(&mut self, label: &str, decode: F, upload: U) -> Handle<T>
+    where
+        T: 'static,
+        D: Send + 'static,
+        F: FnOnce() -> Result<D, AssetLoadError> + Send + 'static,
+        U: FnOnce(&Renderer, &mut Resources, D, &WeakHandle<T>) -> Result<(), AssetLoadError> + 'static,
+    {
+        self.register_manager::<T>();
+
+        let handle = Handle::new();
+
+        log::debug!("Loading {label:?} in the background");
+
+        let weak = handle.downgrade();
+        let upload: Upload = Box::new(move |renderer, resources, data| {
+            let data = data.downcast::<D>().expect("Decoded asset has the wrong type, this is a bug.");
+            upload(renderer, resources, *data, &weak)
+        });
+
+        let weak = handle.downgrade();
+
+        self.assets.loads.insert(handle.id(), AssetLoad {
+            label: label.to_owned(),
+            state: watch::channel(LoadState::Loading).0,
+            upload: Some(upload),
+            alive: Box::new(move || weak.valid()),
+        });
+        self.assets.spawn(handle.id(), decode);
+
+        handle
+    }
+
+    /// Resources that weren't loaded with `load_async` are always `Loaded`.
+    pub fn load_state<T: 'static>(&self, handle: &Handle<T>) -> LoadState {
+        match self.assets.loads.get(&handle.id()) {
+            Some(load) => load.state.borrow().clone(),
+            None => LoadState::Loaded,
+        }
+    }
+
+    /// Resolves once the asset is loaded or failed.
+    /// Uploads happen on the main thread between frames, so it mustn't be blocked on there.
+    pub fn loaded<T: 'static>(&self, handle: &Handle<T>) -> impl Future<Output = LoadState> + Send + 'static {
+        let receiver = self.assets.loads.get(&handle.id()).map(|load| load.state.subscribe());
+
+        async move {
+            let Some(mut receiver) = receiver else { return LoadState::Loaded };
+
+            loop {
+                let state = receiver.borrow().clone();
+                // a closed channel means resources were dropped
+                if !state.is_loading() || receiver.changed().await.is_err() {
+                    return state;
+                }
+            }
+        }
+    }
+
+    /// Number of assets still being decoded or waiting to be uploaded.
+    pub fn assets_loading(&self) -> usize {
+        self.assets.pending()
+    }
+
+    /// Uploads assets that finished decoding.
+    pub(crate) fn update_assets(&mut self, renderer: &Renderer) {
+        while let Ok((id, decoded)) = self.assets.receiver.try_recv() {
+            self.finish_load(renderer, id, decoded);
+        }
+    }
+
+    /// Blocks until every asset is loaded or failed.
+    pub(crate) fn finish_loading(&mut self, renderer: &Renderer) {
+        while self.assets.pending() > 0 {
+            // the server holds a sender, so this can't disconnect
+            let (id, decoded) = self.assets.receiver.recv().unwrap();
+            self.finish_load(renderer, id, decoded);
+        }
+    }
+
+    fn finish_load(&mut self, renderer: &Renderer, id: HandleId, decoded: Decoded) {
+        let Some(upload) = self.assets.loads.get_mut(&id).and_then(|load| load.upload.take()) else { return };
+
+        let state = match decoded.and_then(|data| upload(renderer, self, data)) {
+            Ok(()) => LoadState::Loaded,
+            Err(err) => LoadState::Failed(Arc::new(err)),
+        };
+
+        let load = &self.assets.loads[&id];
+
+        match &state {
+            LoadState::Failed(err) => log::error!("Failed to load {:?}: {err}", load.label),
+            _ => log::debug!("Loaded {:?}", load.label),
+        }
+
+        load.state.send_replace(state);
+        self.registry.finish_loading(id);
+    }
+}
+
+/// Returns the texture if it's already loaded or loading from `path`, see `Resources::cached_asset`.
+pub fn load_texture_async<P: AsRef<Path>>(resources: &mut Resources, path: P, flip_y: bool) -> Handle<Texture> {
+    let path = path.as_ref().to_owned();
+    let label = path.to_string_lossy().to_string();
+    let options = texture_options(flip_y);
+
+    if let Some(texture) = resources.cached_or_loading_asset(&path, &options) {
+        return texture;
+    }
+
+    let decode_path = path.clone();
+    let upload_path = path.clone();
+
+    let handle = resources.load_async_into(&label.clone(), move || Ok(DecodedTexture::read(&decode_path)?), move |renderer, resources, texture: DecodedTexture, handle| {
+        let texture = texture.upload(renderer, resources, Some(&label), flip_y)?;
+        handle.set(resources, texture);
+        register_texture(resources, handle, &upload_path, flip_y);
+        Ok(())
+    });
+    resources.register_loading(&handle, &path, &options);
+
+    handle
+}
+
+/// Returns the model if it's already loaded or loading from `path` by `load_obj_async`, see `Resources::cached_asset`.
+pub fn load_obj_async<P: AsRef<Path>>(resources: &mut Resources, path: P) -> Handle<Model> {
+    let path = path.as_ref().to_owned();
+    let label = path.to_string_lossy().to_string();
+
+    if let Some(model) = resources.cached_or_loading_asset(&path, "") {
+        return model;
+    }
+
+    let decode_path = path.clone();
+    let upload_path = path.clone();
+
+    let handle = resources.load_async_into(&label, move || Ok(ObjData::read(&decode_path)?), move |renderer, resources, obj: ObjData, handle| {
+        let model = obj.build(renderer, resources, true)?;
+        let gpu_memory = model.gpu_memory(resources);
+        handle.set(resources, model);
+        resources.register_asset(handle, &upload_path, "", file_size(&upload_path), gpu_memory);
+        Ok(())
+    });
+    resources.register_loading(&handle, &path, "");
+
+    handle
+}
+
+/// Add the scene to the node tree with `SpawnScene`. If scene is None, use default scene.
+/// Images already loaded from the same file aren't decoded again, see `Resources::cached_asset`.
+pub fn load_gltf_async<P: AsRef<Path>>(resources: &mut Resources, path: P, scene: Option<usize>) -> Handle<Node> {
+    let path = path.as_ref().to_owned();
+    let label = path.to_string_lossy().to_string();
+
+    // held until the scene is built, so they're still cached then
+    let cached_images = cached_gltf_images(resources, &path);
+    let skip_images = cached_images.iter().map(|(index, _)| *index).collect();
+
+    resources.load_async(&label, move || Ok(GltfData::read(&path, &skip_images)?), move |renderer, resources, gltf| {
+        let scene = build_gltf(renderer, resources, gltf, scene, true);
+        drop(cached_images);
+        Ok(scene?)
+    })
+}
+
+/// Loads a 2x1 equirectangular image, see `CubeMap::from_image_equirectangular`.
+pub fn load_cubemap_equirectangular_async<P: AsRef<Path>>(resources: &mut Resources, path: P) -> Handle<CubeMap> {
+    let path = path.as_ref().to_owned();
+    let label = path.to_string_lossy().to_string();
+
+    resources.load_async(&label.clone(), move || Ok(expand_greyscale(image::open(&path)?)), move |renderer, resources, image| {
+        Ok(CubeMap::from_image_equirectangular(renderer, resources, &image, Some(&label))?)
+    })
+}
+
+type OnSpawn = Box<dyn FnOnce(&mut Node, &mut Resources)>;
+
+/// Adds a scene loaded by `load_gltf_async` as a child of its node once loaded.
+pub struct SpawnScene {
+    scene: Option<Handle<Node>>,
+    on_spawn: Option<OnSpawn>,
+}
+
+impl SpawnScene {
+    pub fn new(scene: Handle<Node>) -> SpawnScene {
+        SpawnScene {
+            scene: Some(scene),
+            on_spawn: None,
+        }
+    }
+
+    /// Called with the scene before it's added, i.e. to add pipelines to its models.
+    pub fn on_spawn<F: FnOnce(&mut Node, &mut Resources) + 'static>(mut self, on_spawn: F) -> SpawnScene {
+        self.on_spawn = Some(Box::new(on_spawn));
+        self
+    }
+}
+
+impl NodeScript for SpawnScene {
+    fn pre_update(&mut self, node: &mut NodeDescriptor, _context: &UpdateContext, resources: &mut Resources) {
+        let Some(handle) = &self.scene else { return };
+
+        if resources.load_state(handle).is_loading() {
+            return;
+        }
+
+        // failures are logged when uploading
+        let handle = self.scene.take().unwrap();
+        let Some(mut scene) = resources.remove(&handle) else { return };
+
+        if let Some(on_spawn) = self.on_spawn.take() {
+            on_spawn(&mut scene, resources);
+        }
+
+        node.children.push(scene);
+    }
+}
+
+#[derive(Debug)]
+pub enum AssetLoadError {
+    TextureLoadError(TextureLoadError),
+    ModelLoadError(ModelLoadError),
+    SceneLoadError(SceneLoadError),
+    CubeError(CubeError),
+    /// Decoding panicked on the worker thread
+    Panicked,
+}
+
+impl From<TextureLoadError> for AssetLoadError {
+    fn from(err: TextureLoadError) -> Self {
+        AssetLoadError::TextureLoadError(err)
+    }
+}
+
+impl From<image::ImageError> for AssetLoadError {
+    fn from(err: image::ImageError) -> Self {
+        AssetLoadError::TextureLoadError(TextureLoadError::ImageError(err))
+    }
+}
+
+impl From<ModelLoadError> for AssetLoadError {
+    fn from(err: ModelLoadError) -> Self {
+        AssetLoadError::ModelLoadError(err)
+    }
+}
+
+impl From<SceneLoadError> for AssetLoadError {
+    fn from(err: SceneLoadError) -> Self {
+        AssetLoadError::SceneLoadError(err)
+    }
+}
+
+impl From<CubeError> for AssetLoadError {
+    fn from(err: CubeError) -> Self {
+        AssetLoadError::CubeError(err)
+    }
+}
+
+impl std::fmt::Display for AssetLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssetLoadError::TextureLoadError(err) => err.fmt(f),
+            AssetLoadError::ModelLoadError(err) => err.fmt(f),
+            AssetLoadError::SceneLoadError(err) => err.fmt(f),
+            AssetLoadError::CubeError(err) => err.fmt(f),
+            AssetLoadError::Panicked => write!(f, "Decoding panicked."),
+        }
+    }
+}
+
+impl std::error::Error for AssetLoadError {}
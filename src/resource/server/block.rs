@@ -0,0 +1,459 @@
+//! CPU decoders for 4x4 compressed blocks, used when the device can't sample a format.
+//! Texels are returned row by row as they'd be sampled: unorm channels in [0, 1], snorm channels in [-1, 1].
+
+pub(crate) type Texels = [[f32; 4]; 16];
+
+/// Decodes one block, `None` if there's no CPU decoder for `format`.
+pub(crate) fn decode_block(format: wgpu::TextureFormat, block: &[u8]) -> Option<Texels> {
+    use wgpu::TextureFormat::*;
+
+    let texels = match format {
+        Bc1RgbaUnorm | Bc1RgbaUnormSrgb => unorm(bc1(block, false)),
+        Bc2RgbaUnorm | Bc2RgbaUnormSrgb => {
+            let mut texels = bc1(&block[8..], true);
+            for (i, texel) in texels.iter_mut().enumerate() {
+                let alpha = (block[i / 2] >> (4 * (i % 2))) & 0xf;
+                texel[3] = alpha * 17;
+            }
+            unorm(texels)
+        },
+        Bc3RgbaUnorm | Bc3RgbaUnormSrgb => {
+            let mut texels = unorm(bc1(&block[8..], true));
+            for (texel, alpha) in texels.iter_mut().zip(bc4(&block[..8], false)) {
+                texel[3] = alpha;
+            }
+            texels
+        },
+        Bc4RUnorm | Bc4RSnorm => {
+            let red = bc4(block, format == Bc4RSnorm);
+            red.map(|r| [r, 0.0, 0.0, 1.0])
+        },
+        Bc5RgUnorm | Bc5RgSnorm => {
+            let red = bc4(&block[..8], format == Bc5RgSnorm);
+            let green = bc4(&block[8..], format == Bc5RgSnorm);
+            std::array::from_fn(|i| [red[i], green[i], 0.0, 1.0])
+        },
+        Bc7RgbaUnorm | Bc7RgbaUnormSrgb => unorm(bc7(block)),
+        Etc2Rgb8Unorm | Etc2Rgb8UnormSrgb => unorm(etc2(block, false)),
+        Etc2Rgb8A1Unorm | Etc2Rgb8A1UnormSrgb => unorm(etc2(block, true)),
+        Etc2Rgba8Unorm | Etc2Rgba8UnormSrgb => {
+            let mut texels = unorm(etc2(&block[8..], false));
+            for (texel, alpha) in texels.iter_mut().zip(eac_alpha(&block[..8])) {
+                texel[3] = alpha as f32 / 255.0;
+            }
+            texels
+        },
+        EacR11Unorm | EacR11Snorm => {
+            let red = eac_r11(block, format == EacR11Snorm);
+            red.map(|r| [r, 0.0, 0.0, 1.0])
+        },
+        EacRg11Unorm | EacRg11Snorm => {
+            let red = eac_r11(&block[..8], format == EacRg11Snorm);
+            let green = eac_r11(&block[8..], format == EacRg11Snorm);
+            std::array::from_fn(|i| [red[i], green[i], 0.0, 1.0])
+        },
+        _ => return None,
+    };
+
+    Some(texels)
+}
+
+fn unorm(texels: [[u8; 4]; 16]) -> Texels {
+    texels.map(|texel| texel.map(|channel| channel as f32 / 255.0))
+}
+
+fn rgb565(colour: u16) -> [u8; 4] {
+    let r = ((colour >> 11) & 0x1f) as u8;
+    let g = ((colour >> 5) & 0x3f) as u8;
+    let b = (colour & 0x1f) as u8;
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2), 255]
+}
+
+fn mix(a: [u8; 4], b: [u8; 4], weight_a: u32, weight_b: u32) -> [u8; 4] {
+    let total = weight_a + weight_b;
+    std::array::from_fn(|c| ((a[c] as u32 * weight_a + b[c] as u32 * weight_b + total / 2) / total) as u8)
+}
+
+/// BC1 colours, BC2 and BC3 always use the four colour mode.
+fn bc1(block: &[u8], four_colours: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+    let (a, b) = (rgb565(c0), rgb565(c1));
+    let palette = if c0 > c1 || four_colours {
+        [a, b, mix(a, b, 2, 1), mix(a, b, 1, 2)]
+    } else {
+        [a, b, mix(a, b, 1, 1), [0, 0, 0, 0]]
+    };
+
+    std::array::from_fn(|i| palette[((indices >> (2 * i)) & 3) as usize])
+}
+
+/// BC4 channel, also BC3 alpha.
+fn bc4(block: &[u8], signed: bool) -> [f32; 16] {
+    let indices = u64::from_le_bytes([block[2], block[3], block[4], block[5], block[6], block[7], 0, 0]);
+    let index = |i: usize| ((indices >> (3 * i)) & 7) as usize;
+
+    if signed {
+        // -128 is treated as -127
+        let (e0, e1) = ((block[0] as i8).max(-127) as f32, (block[1] as i8).max(-127) as f32);
+        let palette = bc4_palette(e0, e1, -127.0, 127.0);
+        std::array::from_fn(|i| palette[index(i)] / 127.0)
+    } else {
+        let palette = bc4_palette(block[0] as f32, block[1] as f32, 0.0, 255.0);
+        std::array::from_fn(|i| palette[index(i)] / 255.0)
+    }
+}
+
+fn bc4_palette(e0: f32, e1: f32, min: f32, max: f32) -> [f32; 8] {
+    let mut palette = [e0, e1, 0.0, 0.0, 0.0, 0.0, min, max];
+    if e0 > e1 {
+        for i in 1..7 {
+            palette[i + 1] = ((7 - i) as f32 * e0 + i as f32 * e1) / 7.0;
+        }
+    } else {
+        for i in 1..5 {
+            palette[i + 1] = ((5 - i) as f32 * e0 + i as f32 * e1) / 5.0;
+        }
+    }
+    palette.map(f32::round)
+}
+
+/// Reads a block's bits from least to most significant.
+struct Bits {
+    bits: u128,
+}
+
+impl Bits {
+    fn read(&mut self, count: u32) -> u32 {
+        let value = (self.bits & ((1 << count) - 1)) as u32;
+        self.bits >>= count;
+        value
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    colour_bits: u32,
+    alpha_bits: u32,
+    endpoint_p_bits: bool,
+    shared_p_bits: bool,
+    index_bits: u32,
+    secondary_index_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, colour_bits: 4, alpha_bits: 0, endpoint_p_bits: true, shared_p_bits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, colour_bits: 6, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: true, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, colour_bits: 5, alpha_bits: 0, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, colour_bits: 7, alpha_bits: 0, endpoint_p_bits: true, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, colour_bits: 5, alpha_bits: 6, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, colour_bits: 7, alpha_bits: 8, endpoint_p_bits: false, shared_p_bits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, colour_bits: 7, alpha_bits: 7, endpoint_p_bits: true, shared_p_bits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, colour_bits: 5, alpha_bits: 5, endpoint_p_bits: true, shared_p_bits: false, index_bits: 2, secondary_index_bits: 0 },
+];
+
+/// Texels in the second subset of each two subset partition.
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80,
+    0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000,
+    0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c,
+    0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a,
+    0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c,
+    0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+/// Subset of each texel of the three subset partitions, two bits per texel.
+const BC7_PARTITIONS_3: [u32; 64] = [
+    0xaa685050, 0x6a5a5040, 0x5a5a4200, 0x5450a0a8, 0xa5a50000, 0xa0a05050, 0x5555a0a0, 0x5a5a5050,
+    0xaa550000, 0xaa555500, 0xaaaa5500, 0x90909090, 0x94949494, 0xa4a4a4a4, 0xa9a59450, 0x2a0a4250,
+    0xa5945040, 0x0a425054, 0xa5a5a500, 0x55a0a0a0, 0xa8a85454, 0x6a6a4040, 0xa4a45000, 0x1a1a0500,
+    0x0050a4a4, 0xaaa59090, 0x14696914, 0x69691400, 0xa08585a0, 0xaa821414, 0x50a4a450, 0x6a5a0200,
+    0xa9a58000, 0x5090a0a8, 0xa8a09050, 0x24242424, 0x00aa5500, 0x24924924, 0x24499224, 0x50a50a50,
+    0x500aa550, 0xaaaa4444, 0x66660000, 0xa5a0a5a0, 0x50a050a0, 0x69286928, 0x44aaaa44, 0x66666600,
+    0xaa444444, 0x54a854a8, 0x95809580, 0x96969600, 0xa85454a8, 0x80959580, 0xaa141414, 0x96960000,
+    0xaaaa1414, 0xa05050a0, 0xa0a5a5a0, 0x96000000, 0x40804080, 0xa9a8a9a8, 0xaaaaaa44, 0x2a4a5254,
+];
+
+/// Anchor texel of the second subset of two subset partitions.
+const BC7_ANCHORS_2: [usize; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15, 2, 8, 2, 2, 8, 8, 15, 2, 8, 2, 2, 8, 8, 2, 2,
+    15, 15, 6, 8, 2, 8, 15, 15, 2, 8, 2, 2, 2, 15, 15, 6,
+    6, 2, 6, 8, 15, 15, 2, 2, 15, 15, 15, 15, 15, 2, 2, 15,
+];
+
+/// Anchor texels of the second and third subsets of three subset partitions.
+const BC7_ANCHORS_3: [[usize; 2]; 64] = [
+    [3, 15], [3, 8], [15, 8], [15, 3], [8, 15], [3, 15], [15, 3], [15, 8],
+    [8, 15], [8, 15], [6, 15], [6, 15], [6, 15], [5, 15], [3, 15], [3, 8],
+    [3, 15], [3, 8], [8, 15], [15, 3], [3, 15], [3, 8], [6, 15], [10, 8],
+    [5, 3], [8, 15], [8, 6], [6, 10], [8, 15], [5, 15], [15, 10], [15, 8],
+    [8, 15], [15, 3], [3, 15], [5, 10], [6, 10], [10, 8], [8, 9], [15, 10],
+    [15, 6], [3, 15], [15, 8], [5, 15], [15, 3], [15, 6], [15, 6], [15, 8],
+    [3, 15], [15, 3], [5, 15], [5, 15], [5, 15], [8, 15], [5, 15], [10, 15],
+    [5, 15], [10, 15], [8, 15], [13, 15], [15, 3], [12, 15], [3, 15], [3, 8],
+];
+
+fn bc7_weights(bits: u32) -> &'static [u32] {
+    match bits {
+        2 => &[0, 21, 43, 64],
+        3 => &[0, 9, 18, 27, 37, 46, 55, 64],
+        _ => &[0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64],
+    }
+}
+
+fn bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut bits = Bits { bits: u128::from_le_bytes(block[..16].try_into().unwrap()) };
+
+    // the mode is the number of zeros before the first set bit, blocks without one are reserved
+    let Some(mode) = (0..8).find(|_| bits.read(1) == 1) else { return [[0; 4]; 16] };
+    let mode = &BC7_MODES[mode];
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    let endpoint_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0..3 {
+        for endpoint in &mut endpoints[..endpoint_count] {
+            endpoint[channel] = bits.read(mode.colour_bits);
+        }
+    }
+    for endpoint in &mut endpoints[..endpoint_count] {
+        endpoint[3] = bits.read(mode.alpha_bits);
+    }
+
+    let mut p_bits = [None; 6];
+    if mode.endpoint_p_bits {
+        for p_bit in &mut p_bits[..endpoint_count] {
+            *p_bit = Some(bits.read(1));
+        }
+    } else if mode.shared_p_bits {
+        for subset in 0..mode.subsets {
+            let p_bit = Some(bits.read(1));
+            p_bits[subset * 2] = p_bit;
+            p_bits[subset * 2 + 1] = p_bit;
+        }
+    }
+
+    // endpoints are expanded to 8 bits by replicating their high bits
+    let endpoints: [[u8; 4]; 6] = std::array::from_fn(|e| std::array::from_fn(|channel| {
+        let precision = if channel < 3 { mode.colour_bits } else { mode.alpha_bits };
+        if precision == 0 {
+            return 255;
+        }
+        let (value, precision) = match p_bits[e] {
+            Some(p_bit) => ((endpoints[e][channel] << 1) | p_bit, precision + 1),
+            None => (endpoints[e][channel], precision),
+        };
+        let value = value << (8 - precision);
+        (value | (value >> precision)) as u8
+    }));
+
+    let subset = |i: usize| match mode.subsets {
+        2 => ((BC7_PARTITIONS_2[partition] >> i) & 1) as usize,
+        3 => ((BC7_PARTITIONS_3[partition] >> (2 * i)) & 3) as usize,
+        _ => 0,
+    };
+    // anchor texels store their index with one less bit, its top bit is always zero
+    let anchor = |i: usize| i == 0 || match mode.subsets {
+        2 => i == BC7_ANCHORS_2[partition],
+        3 => BC7_ANCHORS_3[partition].contains(&i),
+        _ => false,
+    };
+
+    let mut indices = [0; 16];
+    for (i, index) in indices.iter_mut().enumerate() {
+        *index = bits.read(mode.index_bits - anchor(i) as u32);
+    }
+    let mut secondary_indices = [0; 16];
+    if mode.secondary_index_bits > 0 {
+        for (i, index) in secondary_indices.iter_mut().enumerate() {
+            *index = bits.read(mode.secondary_index_bits - (i == 0) as u32);
+        }
+    }
+
+    let interpolate = |e0: u8, e1: u8, weight: u32| ((e0 as u32 * (64 - weight) + e1 as u32 * weight + 32) >> 6) as u8;
+
+    std::array::from_fn(|i| {
+        let subset = subset(i);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+
+        let (colour_weight, alpha_weight) = if mode.secondary_index_bits == 0 {
+            let weight = bc7_weights(mode.index_bits)[indices[i] as usize];
+            (weight, weight)
+        } else {
+            let primary = bc7_weights(mode.index_bits)[indices[i] as usize];
+            let secondary = bc7_weights(mode.secondary_index_bits)[secondary_indices[i] as usize];
+            if index_selection == 0 { (primary, secondary) } else { (secondary, primary) }
+        };
+
+        let mut texel: [u8; 4] = std::array::from_fn(|channel| {
+            let weight = if channel < 3 { colour_weight } else { alpha_weight };
+            interpolate(e0[channel], e1[channel], weight)
+        });
+        match rotation {
+            1 => texel.swap(0, 3),
+            2 => texel.swap(1, 3),
+            3 => texel.swap(2, 3),
+            _ => {},
+        }
+        texel
+    })
+}
+
+/// ETC1 intensity modifiers of each table, for texel indices 0 and 1 (negated for 2 and 3).
+const ETC_MODIFIERS: [[i32; 2]; 8] = [[2, 8], [5, 17], [9, 29], [13, 42], [18, 60], [24, 80], [33, 106], [47, 183]];
+/// Distances of the T and H modes.
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+fn extend(value: u64, bits: u32) -> i32 {
+    let value = value as i32;
+    (value << (8 - bits)) | (value >> (2 * bits - 8))
+}
+
+fn add(colour: [i32; 3], offset: i32) -> [u8; 4] {
+    let [r, g, b] = colour.map(|channel| (channel + offset).clamp(0, 255) as u8);
+    [r, g, b, 255]
+}
+
+/// ETC2 RGB, or with punch-through alpha where the 'opaque' bit replaces the 'differential' bit.
+fn etc2(block: &[u8], punch_through: bool) -> [[u8; 4]; 16] {
+    let bits = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let field = |high: u32, count: u32| (bits >> (high + 1 - count)) & ((1 << count) - 1);
+
+    // texel indices are stored column by column, most significant bits in the upper half
+    let index = |i: usize| {
+        let bit = (i % 4) * 4 + i / 4;
+        (((bits >> (bit + 16)) & 1) << 1 | ((bits >> bit) & 1)) as usize
+    };
+
+    let differential = punch_through || field(33, 1) == 1;
+    let opaque = !punch_through || field(33, 1) == 1;
+    let transparent = [0; 4];
+
+    let (base, second) = if differential {
+        let base = [field(63, 5), field(55, 5), field(47, 5)].map(|channel| channel as i32);
+        let delta = [field(58, 3), field(50, 3), field(42, 3)].map(|delta| ((delta as i32) << 29) >> 29);
+        let second: [i32; 3] = std::array::from_fn(|c| base[c] + delta[c]);
+
+        if !(0..32).contains(&second[0]) {
+            // T mode
+            let c0 = [(field(60, 2) << 2) | field(57, 2), field(55, 4), field(51, 4)].map(|channel| extend(channel, 4));
+            let c1 = [field(47, 4), field(43, 4), field(39, 4)].map(|channel| extend(channel, 4));
+            let distance = ETC_DISTANCES[((field(35, 2) << 1) | field(32, 1)) as usize];
+            let palette = [add(c0, 0), add(c1, distance), add(c1, 0), add(c1, -distance)];
+            return std::array::from_fn(|i| if !opaque && index(i) == 2 { transparent } else { palette[index(i)] });
+        }
+        if !(0..32).contains(&second[1]) {
+            // H mode
+            let c0 = [field(62, 4), (field(58, 3) << 1) | field(52, 1), (field(51, 1) << 3) | field(49, 3)];
+            let c1 = [field(46, 4), field(42, 4), field(38, 4)];
+            let order = ((c0[0] << 8) | (c0[1] << 4) | c0[2]) >= ((c1[0] << 8) | (c1[1] << 4) | c1[2]);
+            let distance = ETC_DISTANCES[((field(34, 1) << 2) | (field(32, 1) << 1) | order as u64) as usize];
+            let (c0, c1) = (c0.map(|channel| extend(channel, 4)), c1.map(|channel| extend(channel, 4)));
+            let palette = [add(c0, distance), add(c0, -distance), add(c1, distance), add(c1, -distance)];
+            return std::array::from_fn(|i| if !opaque && index(i) == 2 { transparent } else { palette[index(i)] });
+        }
+        if !(0..32).contains(&second[2]) {
+            // planar mode, always opaque
+            let origin = [extend(field(62, 6), 6), extend((field(56, 1) << 6) | field(54, 6), 7), extend((field(48, 1) << 5) | (field(44, 2) << 3) | field(41, 3), 6)];
+            let horizontal = [extend((field(38, 5) << 1) | field(32, 1), 6), extend(field(31, 7), 7), extend(field(24, 6), 6)];
+            let vertical = [extend(field(18, 6), 6), extend(field(12, 7), 7), extend(field(5, 6), 6)];
+            return std::array::from_fn(|i| {
+                let (x, y) = ((i % 4) as i32, (i / 4) as i32);
+                let [r, g, b] = std::array::from_fn(|c| {
+                    ((x * (horizontal[c] - origin[c]) + y * (vertical[c] - origin[c]) + 4 * origin[c] + 2) >> 2).clamp(0, 255) as u8
+                });
+                [r, g, b, 255]
+            });
+        }
+
+        (base.map(|channel| extend(channel as u64, 5)), second.map(|channel| extend(channel as u64, 5)))
+    } else {
+        let base = [field(63, 4), field(55, 4), field(47, 4)].map(|channel| extend(channel, 4));
+        let second = [field(59, 4), field(51, 4), field(43, 4)].map(|channel| extend(channel, 4));
+        (base, second)
+    };
+
+    let flip = field(32, 1) == 1;
+    let tables = [field(39, 3), field(36, 3)].map(|table| ETC_MODIFIERS[table as usize]);
+
+    std::array::from_fn(|i| {
+        let (x, y) = (i % 4, i / 4);
+        let sub_block = if flip { y >= 2 } else { x >= 2 } as usize;
+        let colour = if sub_block == 0 { base } else { second };
+        let [small, large] = tables[sub_block];
+
+        match index(i) {
+            // without the opaque bit, the small modifiers are zero and index 2 is transparent
+            0 if !opaque => add(colour, 0),
+            2 if !opaque => transparent,
+            0 => add(colour, small),
+            1 => add(colour, large),
+            2 => add(colour, -small),
+            _ => add(colour, -large),
+        }
+    })
+}
+
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/// Base codeword, multiplier and the modifier of each texel (row by row) of an EAC block.
+fn eac(block: &[u8]) -> (u8, i32, [i32; 16]) {
+    let bits = u64::from_be_bytes(block[..8].try_into().unwrap());
+    let modifiers = EAC_MODIFIERS[((bits >> 48) & 0xf) as usize];
+
+    // indices are stored column by column
+    let modifiers = std::array::from_fn(|i| {
+        let texel = (i % 4) * 4 + i / 4;
+        modifiers[((bits >> (45 - 3 * texel)) & 7) as usize]
+    });
+
+    ((bits >> 56) as u8, ((bits >> 52) & 0xf) as i32, modifiers)
+}
+
+fn eac_alpha(block: &[u8]) -> [u8; 16] {
+    let (base, multiplier, modifiers) = eac(block);
+    modifiers.map(|modifier| (base as i32 + modifier * multiplier).clamp(0, 255) as u8)
+}
+
+fn eac_r11(block: &[u8], signed: bool) -> [f32; 16] {
+    let (base, multiplier, modifiers) = eac(block);
+
+    // a zero multiplier leaves the modifiers unscaled
+    let scale = |modifier: i32| if multiplier == 0 { modifier } else { modifier * multiplier * 8 };
+
+    if signed {
+        let base = (base as i8).max(-127) as i32 * 8;
+        modifiers.map(|modifier| (base + scale(modifier)).clamp(-1023, 1023) as f32 / 1023.0)
+    } else {
+        let base = base as i32 * 8 + 4;
+        modifiers.map(|modifier| (base + scale(modifier)).clamp(0, 2047) as f32 / 2047.0)
+    }
+}
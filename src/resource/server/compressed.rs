@@ -0,0 +1,323 @@
+use std::io::Read;
+
+use super::block::decode_block;
+
+/// Texture data as stored in a KTX2 or DDS container: possibly block compressed, with its mip levels from largest to smallest.
+#[derive(Debug, Clone)]
+pub struct CompressedImage {
+    pub format: wgpu::TextureFormat,
+    pub width: u32,
+    pub height: u32,
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    /// Parses `bytes` if it starts with a KTX2 or DDS identifier, `None` for other images.
+    pub fn from_container(bytes: &[u8]) -> Option<Result<CompressedImage, CompressedImageError>> {
+        if bytes.starts_with(&KTX2_IDENTIFIER) {
+            Some(CompressedImage::from_ktx2(bytes))
+        } else if bytes.starts_with(b"DDS ") {
+            Some(CompressedImage::from_dds(bytes))
+        } else {
+            None
+        }
+    }
+
+    pub fn from_ktx2(bytes: &[u8]) -> Result<CompressedImage, CompressedImageError> {
+        let reader = ktx2::Reader::new(bytes).map_err(CompressedImageError::Ktx2Error)?;
+        let header = reader.header();
+
+        if header.face_count != 1 || header.layer_count > 1 || header.pixel_depth > 1 {
+            return Err(CompressedImageError::UnsupportedLayout);
+        }
+
+        // ktx2 panics on descriptors shorter than their own length field
+        let dfd_length = u32::from_le_bytes(bytes[52..56].try_into().expect("unreachable"));
+        let basis = header.supercompression_scheme == Some(ktx2::SupercompressionScheme::BasisLZ) || dfd_length >= 4 && reader.data_format_descriptors()
+            .filter_map(|descriptor| ktx2::BasicDataFormatDescriptor::parse(descriptor.data).ok())
+            .any(|descriptor| descriptor.color_model == Some(ktx2::ColorModel::UASTC));
+        if basis {
+            return Err(CompressedImageError::BasisUniversal);
+        }
+
+        let format = header.format.ok_or_else(|| CompressedImageError::UnsupportedFormat("undefined".to_owned()))?;
+        let format = ktx2_format(format).ok_or_else(|| CompressedImageError::UnsupportedFormat(format!("{format:?}")))?;
+
+        let levels = reader.levels().map(|level| match header.supercompression_scheme {
+            None => Ok(level.to_vec()),
+            Some(ktx2::SupercompressionScheme::Zstandard) => {
+                let mut decoded = vec![];
+                ruzstd::StreamingDecoder::new(level)
+                    .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+                    .and_then(|mut decoder| decoder.read_to_end(&mut decoded))
+                    .map_err(CompressedImageError::ZstdError)?;
+                Ok(decoded)
+            },
+            Some(scheme) => Err(CompressedImageError::UnsupportedSupercompression(format!("{scheme:?}"))),
+        }).collect::<Result<Vec<_>, _>>()?;
+
+        CompressedImage::new(format, header.pixel_width, header.pixel_height.max(1), levels)
+    }
+
+    pub fn from_dds(bytes: &[u8]) -> Result<CompressedImage, CompressedImageError> {
+        let dds = ddsfile::Dds::read(bytes).map_err(CompressedImageError::DdsError)?;
+
+        if dds.get_num_array_layers() > 1 || dds.get_depth() > 1 || dds.header.caps2.contains(ddsfile::Caps2::CUBEMAP) {
+            return Err(CompressedImageError::UnsupportedLayout);
+        }
+
+        let format = match dds.get_dxgi_format() {
+            Some(format) => dxgi_format(format).ok_or_else(|| CompressedImageError::UnsupportedFormat(format!("{format:?}")))?,
+            None => match dds.get_d3d_format() {
+                Some(format) => d3d_format(format).ok_or_else(|| CompressedImageError::UnsupportedFormat(format!("{format:?}")))?,
+                // BC4 and BC5 have no D3D format, only FourCCs
+                None => match dds.header.spf.fourcc.as_ref().map(|fourcc| fourcc.0.to_le_bytes()).as_ref() {
+                    Some(b"ATI1" | b"BC4U") => wgpu::TextureFormat::Bc4RUnorm,
+                    Some(b"BC4S") => wgpu::TextureFormat::Bc4RSnorm,
+                    Some(b"ATI2" | b"BC5U") => wgpu::TextureFormat::Bc5RgUnorm,
+                    Some(b"BC5S") => wgpu::TextureFormat::Bc5RgSnorm,
+                    _ => return Err(CompressedImageError::UnsupportedFormat(format!("{:?}", dds.header.spf))),
+                },
+            },
+        };
+
+        // levels are stored one after the other
+        let (width, height) = (dds.get_width(), dds.get_height());
+        let mut data = dds.get_data(0).map_err(CompressedImageError::DdsError)?;
+        let mut levels = vec![];
+        for level in 0..dds.get_num_mipmap_levels().max(1) {
+            let size = level_size(format, width, height, level);
+            if data.len() < size {
+                break;
+            }
+            levels.push(data[..size].to_vec());
+            data = &data[size..];
+        }
+
+        CompressedImage::new(format, width, height, levels)
+    }
+
+    fn new(format: wgpu::TextureFormat, width: u32, height: u32, levels: Vec<Vec<u8>>) -> Result<CompressedImage, CompressedImageError> {
+        let complete = !levels.is_empty() && levels.iter().enumerate().all(|(level, data)| data.len() >= level_size(format, width, height, level as u32));
+        if !complete {
+            return Err(CompressedImageError::MissingData);
+        }
+
+        Ok(CompressedImage { format, width, height, levels })
+    }
+
+    pub fn is_block_compressed(&self) -> bool {
+        self.format.describe().block_dimensions != (1, 1)
+    }
+
+    /// Size of mip level `level` in texels.
+    pub fn level_dimensions(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+
+    /// Decodes block compressed levels on the CPU, into `Rgba8Unorm` or `Rgba16Float` for signed formats.
+    /// Uncompressed images are returned as they are.
+    pub fn decompress(&self) -> Result<CompressedImage, CompressedImageError> {
+        if !self.is_block_compressed() {
+            return Ok(self.clone());
+        }
+
+        let format_desc = self.format.describe();
+        let block_size = format_desc.block_size as usize;
+        let signed = matches!(self.format, wgpu::TextureFormat::Bc4RSnorm | wgpu::TextureFormat::Bc5RgSnorm | wgpu::TextureFormat::EacR11Snorm | wgpu::TextureFormat::EacRg11Snorm);
+        if format_desc.block_dimensions != (4, 4) || decode_block(self.format, &vec![0; block_size]).is_none() {
+            return Err(CompressedImageError::NoDecoder(self.format));
+        }
+
+        let levels = self.levels.iter().enumerate().map(|(level, data)| {
+            let (width, height) = self.level_dimensions(level as u32);
+            let blocks_x = ((width + 3) / 4) as usize;
+
+            let mut texels = vec![[0.0; 4]; (width * height) as usize];
+            for (i, block) in data.chunks_exact(block_size).take(blocks_x * ((height + 3) / 4) as usize).enumerate() {
+                let decoded = decode_block(self.format, block).expect("unreachable");
+                let (block_x, block_y) = (i % blocks_x * 4, i / blocks_x * 4);
+
+                for (j, texel) in decoded.into_iter().enumerate() {
+                    let (x, y) = (block_x + j % 4, block_y + j / 4);
+                    if x < width as usize && y < height as usize {
+                        texels[y * width as usize + x] = texel;
+                    }
+                }
+            }
+
+            if signed {
+                texels.into_iter().flatten().flat_map(|channel| half::f16::from_f32(channel).to_ne_bytes()).collect()
+            } else {
+                texels.into_iter().flatten().map(|channel| (channel * 255.0).round() as u8).collect()
+            }
+        }).collect();
+
+        let format = if signed { wgpu::TextureFormat::Rgba16Float } else { wgpu::TextureFormat::Rgba8Unorm };
+
+        Ok(CompressedImage { format, width: self.width, height: self.height, levels })
+    }
+}
+
+const KTX2_IDENTIFIER: [u8; 12] = [0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Bytes of mip level `level` of a `width`x`height` image.
+fn level_size(format: wgpu::TextureFormat, width: u32, height: u32, level: u32) -> usize {
+    let format_desc = format.describe();
+    let (block_width, block_height) = format_desc.block_dimensions;
+    let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+
+    let blocks_x = (width + block_width as u32 - 1) / block_width as u32;
+    let blocks_y = (height + block_height as u32 - 1) / block_height as u32;
+    (blocks_x * blocks_y) as usize * format_desc.block_size as usize
+}
+
+fn ktx2_format(format: ktx2::Format) -> Option<wgpu::TextureFormat> {
+    use wgpu::{TextureFormat::*, AstcBlock, AstcChannel};
+
+    let astc = |block, srgb| Astc { block, channel: if srgb { AstcChannel::UnormSrgb } else { AstcChannel::Unorm } };
+
+    Some(match format {
+        ktx2::Format::R8_UNORM => R8Unorm,
+        ktx2::Format::R8G8_UNORM => Rg8Unorm,
+        ktx2::Format::R8G8B8A8_UNORM => Rgba8Unorm,
+        ktx2::Format::R8G8B8A8_SRGB => Rgba8UnormSrgb,
+        ktx2::Format::B8G8R8A8_UNORM => Bgra8Unorm,
+        ktx2::Format::B8G8R8A8_SRGB => Bgra8UnormSrgb,
+        ktx2::Format::R16G16B16A16_SFLOAT => Rgba16Float,
+        ktx2::Format::BC1_RGB_UNORM_BLOCK | ktx2::Format::BC1_RGBA_UNORM_BLOCK => Bc1RgbaUnorm,
+        ktx2::Format::BC1_RGB_SRGB_BLOCK | ktx2::Format::BC1_RGBA_SRGB_BLOCK => Bc1RgbaUnormSrgb,
+        ktx2::Format::BC2_UNORM_BLOCK => Bc2RgbaUnorm,
+        ktx2::Format::BC2_SRGB_BLOCK => Bc2RgbaUnormSrgb,
+        ktx2::Format::BC3_UNORM_BLOCK => Bc3RgbaUnorm,
+        ktx2::Format::BC3_SRGB_BLOCK => Bc3RgbaUnormSrgb,
+        ktx2::Format::BC4_UNORM_BLOCK => Bc4RUnorm,
+        ktx2::Format::BC4_SNORM_BLOCK => Bc4RSnorm,
+        ktx2::Format::BC5_UNORM_BLOCK => Bc5RgUnorm,
+        ktx2::Format::BC5_SNORM_BLOCK => Bc5RgSnorm,
+        ktx2::Format::BC6H_UFLOAT_BLOCK => Bc6hRgbUfloat,
+        ktx2::Format::BC6H_SFLOAT_BLOCK => Bc6hRgbSfloat,
+        ktx2::Format::BC7_UNORM_BLOCK => Bc7RgbaUnorm,
+        ktx2::Format::BC7_SRGB_BLOCK => Bc7RgbaUnormSrgb,
+        ktx2::Format::ETC2_R8G8B8_UNORM_BLOCK => Etc2Rgb8Unorm,
+        ktx2::Format::ETC2_R8G8B8_SRGB_BLOCK => Etc2Rgb8UnormSrgb,
+        ktx2::Format::ETC2_R8G8B8A1_UNORM_BLOCK => Etc2Rgb8A1Unorm,
+        ktx2::Format::ETC2_R8G8B8A1_SRGB_BLOCK => Etc2Rgb8A1UnormSrgb,
+        ktx2::Format::ETC2_R8G8B8A8_UNORM_BLOCK => Etc2Rgba8Unorm,
+        ktx2::Format::ETC2_R8G8B8A8_SRGB_BLOCK => Etc2Rgba8UnormSrgb,
+        ktx2::Format::EAC_R11_UNORM_BLOCK => EacR11Unorm,
+        ktx2::Format::EAC_R11_SNORM_BLOCK => EacR11Snorm,
+        ktx2::Format::EAC_R11G11_UNORM_BLOCK => EacRg11Unorm,
+        ktx2::Format::EAC_R11G11_SNORM_BLOCK => EacRg11Snorm,
+        ktx2::Format::ASTC_4x4_UNORM_BLOCK => astc(AstcBlock::B4x4, false),
+        ktx2::Format::ASTC_4x4_SRGB_BLOCK => astc(AstcBlock::B4x4, true),
+        ktx2::Format::ASTC_5x4_UNORM_BLOCK => astc(AstcBlock::B5x4, false),
+        ktx2::Format::ASTC_5x4_SRGB_BLOCK => astc(AstcBlock::B5x4, true),
+        ktx2::Format::ASTC_5x5_UNORM_BLOCK => astc(AstcBlock::B5x5, false),
+        ktx2::Format::ASTC_5x5_SRGB_BLOCK => astc(AstcBlock::B5x5, true),
+        ktx2::Format::ASTC_6x5_UNORM_BLOCK => astc(AstcBlock::B6x5, false),
+        ktx2::Format::ASTC_6x5_SRGB_BLOCK => astc(AstcBlock::B6x5, true),
+        ktx2::Format::ASTC_6x6_UNORM_BLOCK => astc(AstcBlock::B6x6, false),
+        ktx2::Format::ASTC_6x6_SRGB_BLOCK => astc(AstcBlock::B6x6, true),
+        ktx2::Format::ASTC_8x5_UNORM_BLOCK => astc(AstcBlock::B8x5, false),
+        ktx2::Format::ASTC_8x5_SRGB_BLOCK => astc(AstcBlock::B8x5, true),
+        ktx2::Format::ASTC_8x6_UNORM_BLOCK => astc(AstcBlock::B8x6, false),
+        ktx2::Format::ASTC_8x6_SRGB_BLOCK => astc(AstcBlock::B8x6, true),
+        ktx2::Format::ASTC_8x8_UNORM_BLOCK => astc(AstcBlock::B8x8, false),
+        ktx2::Format::ASTC_8x8_SRGB_BLOCK => astc(AstcBlock::B8x8, true),
+        ktx2::Format::ASTC_10x5_UNORM_BLOCK => astc(AstcBlock::B10x5, false),
+        ktx2::Format::ASTC_10x5_SRGB_BLOCK => astc(AstcBlock::B10x5, true),
+        ktx2::Format::ASTC_10x6_UNORM_BLOCK => astc(AstcBlock::B10x6, false),
+        ktx2::Format::ASTC_10x6_SRGB_BLOCK => astc(AstcBlock::B10x6, true),
+        ktx2::Format::ASTC_10x8_UNORM_BLOCK => astc(AstcBlock::B10x8, false),
+        ktx2::Format::ASTC_10x8_SRGB_BLOCK => astc(AstcBlock::B10x8, true),
+        ktx2::Format::ASTC_10x10_UNORM_BLOCK => astc(AstcBlock::B10x10, false),
+        ktx2::Format::ASTC_10x10_SRGB_BLOCK => astc(AstcBlock::B10x10, true),
+        ktx2::Format::ASTC_12x10_UNORM_BLOCK => astc(AstcBlock::B12x10, false),
+        ktx2::Format::ASTC_12x10_SRGB_BLOCK => astc(AstcBlock::B12x10, true),
+        ktx2::Format::ASTC_12x12_UNORM_BLOCK => astc(AstcBlock::B12x12, false),
+        ktx2::Format::ASTC_12x12_SRGB_BLOCK => astc(AstcBlock::B12x12, true),
+        _ => return None,
+    })
+}
+
+fn dxgi_format(format: ddsfile::DxgiFormat) -> Option<wgpu::TextureFormat> {
+    use ddsfile::DxgiFormat;
+    use wgpu::TextureFormat::*;
+
+    Some(match format {
+        DxgiFormat::R8_UNorm => R8Unorm,
+        DxgiFormat::R8G8_UNorm => Rg8Unorm,
+        DxgiFormat::R8G8B8A8_UNorm => Rgba8Unorm,
+        DxgiFormat::R8G8B8A8_UNorm_sRGB => Rgba8UnormSrgb,
+        DxgiFormat::B8G8R8A8_UNorm => Bgra8Unorm,
+        DxgiFormat::B8G8R8A8_UNorm_sRGB => Bgra8UnormSrgb,
+        DxgiFormat::R16G16B16A16_Float => Rgba16Float,
+        DxgiFormat::BC1_Typeless | DxgiFormat::BC1_UNorm => Bc1RgbaUnorm,
+        DxgiFormat::BC1_UNorm_sRGB => Bc1RgbaUnormSrgb,
+        DxgiFormat::BC2_Typeless | DxgiFormat::BC2_UNorm => Bc2RgbaUnorm,
+        DxgiFormat::BC2_UNorm_sRGB => Bc2RgbaUnormSrgb,
+        DxgiFormat::BC3_Typeless | DxgiFormat::BC3_UNorm => Bc3RgbaUnorm,
+        DxgiFormat::BC3_UNorm_sRGB => Bc3RgbaUnormSrgb,
+        DxgiFormat::BC4_Typeless | DxgiFormat::BC4_UNorm => Bc4RUnorm,
+        DxgiFormat::BC4_SNorm => Bc4RSnorm,
+        DxgiFormat::BC5_Typeless | DxgiFormat::BC5_UNorm => Bc5RgUnorm,
+        DxgiFormat::BC5_SNorm => Bc5RgSnorm,
+        DxgiFormat::BC6H_Typeless | DxgiFormat::BC6H_UF16 => Bc6hRgbUfloat,
+        DxgiFormat::BC6H_SF16 => Bc6hRgbSfloat,
+        DxgiFormat::BC7_Typeless | DxgiFormat::BC7_UNorm => Bc7RgbaUnorm,
+        DxgiFormat::BC7_UNorm_sRGB => Bc7RgbaUnormSrgb,
+        _ => return None,
+    })
+}
+
+fn d3d_format(format: ddsfile::D3DFormat) -> Option<wgpu::TextureFormat> {
+    use ddsfile::D3DFormat;
+    use wgpu::TextureFormat::*;
+
+    Some(match format {
+        D3DFormat::A8B8G8R8 => Rgba8Unorm,
+        D3DFormat::A8R8G8B8 => Bgra8Unorm,
+        D3DFormat::L8 | D3DFormat::A8 => R8Unorm,
+        D3DFormat::A16B16G16R16F => Rgba16Float,
+        D3DFormat::DXT1 => Bc1RgbaUnorm,
+        D3DFormat::DXT2 | D3DFormat::DXT3 => Bc2RgbaUnorm,
+        D3DFormat::DXT4 | D3DFormat::DXT5 => Bc3RgbaUnorm,
+        _ => return None,
+    })
+}
+
+#[derive(Debug)]
+pub enum CompressedImageError {
+    Ktx2Error(ktx2::ParseError),
+    DdsError(ddsfile::Error),
+    ZstdError(std::io::Error),
+    UnsupportedFormat(String),
+    UnsupportedSupercompression(String),
+    /// Cube maps, arrays and 3D textures
+    UnsupportedLayout,
+    /// Basis Universal (ETC1S or UASTC) data, which needs transcoding. Not supported yet
+    BasisUniversal,
+    /// Levels are shorter than their dimensions require
+    MissingData,
+    /// The device can't sample the format and there's no CPU decoder for it, i.e. ASTC and BC6H
+    NoDecoder(wgpu::TextureFormat),
+}
+
+impl std::fmt::Display for CompressedImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompressedImageError::Ktx2Error(err) => err.fmt(f),
+            CompressedImageError::DdsError(err) => err.fmt(f),
+            CompressedImageError::ZstdError(err) => write!(f, "Zstandard decompression failed: {err}"),
+            CompressedImageError::UnsupportedFormat(format) => write!(f, "Unsupported texture format {format}."),
+            CompressedImageError::UnsupportedSupercompression(scheme) => write!(f, "Unsupported supercompression scheme {scheme}."),
+            CompressedImageError::UnsupportedLayout => write!(f, "Only 2D textures with a single layer are supported."),
+            CompressedImageError::BasisUniversal => write!(f, "Basis Universal (ETC1S/UASTC) textures aren't supported yet, glTF textures need a fallback `source` image."),
+            CompressedImageError::MissingData => write!(f, "Texture data is shorter than its dimensions require."),
+            CompressedImageError::NoDecoder(format) => write!(f, "{format:?} isn't supported by the device and can't be decoded on the CPU."),
+        }
+    }
+}
+
+impl std::error::Error for CompressedImageError {}
@@ -4,7 +4,7 @@ use crate::{util::{Uid, AsAny}, node::{Component, NodeDescriptor}, renderer::{Re
 
 use super::Resources;
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct HandleId(Uid);
 
 // todo: separate into separate Handle and WeakHandle structs
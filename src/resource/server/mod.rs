@@ -1,13 +1,24 @@
-use std::{path::Path, io::{Cursor, BufReader}, collections::HashMap, any::{TypeId, Any}};
+use std::{path::{Path, PathBuf}, io::{Cursor, BufReader}, collections::{HashMap, HashSet, hash_map::Entry}, any::{TypeId, Any}, cell::RefCell};
 
 use wgpu::util::DeviceExt;
 
-use crate::{resource::{Material, Mesh, AlphaMode, Sampler}, engine::Rgba, util::AsAny, renderer::{ModelVertex, Renderer}, node::Node, components::Transform, camera::Camera};
+use crate::{resource::{Material, MaterialBuilder, MaterialTexture, TextureTransform, Mesh, MorphTarget, AlphaMode, Sampler, AnimationClip, AnimationChannel, Keyframes, Interpolation}, engine::{Rgba, Aabb}, util::AsAny, renderer::{ModelVertex, SkinnedVertex, Renderer}, node::{Node, NodeId}, components::{Transform, PointLight, DirectionalLight, SpotLight, Skeleton, AnimationPlayer, MorphWeights}, camera::Camera};
 
 use super::{Texture, Model};
 
 mod handle;
+mod accessor;
+mod block;
+mod compressed;
+mod asset;
+mod reload;
+mod registry;
 pub use handle::*;
+pub use accessor::*;
+pub use compressed::*;
+pub use asset::*;
+use reload::AssetWatcher;
+pub use registry::*;
 
 pub trait ManagesResources: AsAny {
     fn drop_invalid(&mut self);
@@ -81,6 +92,10 @@ impl<T: 'static> ResourceManager<T> {
     pub fn set<H: HandlesResource<T>>(&mut self, handle: &H, resource: T) {
         self.resources.insert(handle.downgrade(), resource);
     }
+
+    pub(crate) fn remove<H: HandlesResource<T>>(&mut self, handle: &H) -> Option<T> {
+        self.resources.remove(&handle.downgrade())
+    }
 }
 
 // wrapper that can only be accessed in-engine, used to store resources the game dev shouldn't have access to
@@ -104,6 +119,9 @@ pub struct Resources {
     managers: HashMap<TypeId, Box<dyn ManagesResources>>,
     global_handles: HashMap<String, Box<dyn Any>>,
     engine_global_handles: HashMap<String, Box<dyn Any>>,
+    assets: AssetServer,
+    watcher: AssetWatcher,
+    registry: AssetRegistry,
 }
 
 impl Resources {
@@ -112,6 +130,9 @@ impl Resources {
             managers: hashmap!{},
             global_handles: hashmap!{},
             engine_global_handles: hashmap!{},
+            assets: AssetServer::new(),
+            watcher: AssetWatcher::new(),
+            registry: AssetRegistry::new(),
         }
     }
 
@@ -154,6 +175,10 @@ impl Resources {
         for manager in self.managers.values_mut() {
             manager.drop_invalid();
         }
+
+        self.assets.drop_invalid();
+        self.watcher.drop_invalid();
+        self.registry.drop_invalid();
     }
 
     pub fn store<T: 'static>(&mut self, resource: T) -> Handle<T> {
@@ -190,6 +215,11 @@ impl Resources {
         manager.set(handle, resource);
     }
 
+    /// Takes the resource out, leaving the handle empty.
+    pub(crate) fn remove<T: 'static>(&mut self, handle: &Handle<T>) -> Option<T> {
+        self.get_manager_mut::<T>()?.remove(handle)
+    }
+
     pub fn set_global<T: 'static>(&mut self, key: &str, resource: T) {
         let resource: Box<dyn Any> = Box::new(resource);
         self.global_handles.insert(key.to_owned(), resource);
@@ -223,191 +253,331 @@ pub fn load_texture<P: AsRef<Path>>(
     path: P,
     // srgb: bool,
     flip_y: bool,
-) -> Result<Handle<Texture>, image::ImageError> {
+) -> Result<Handle<Texture>, TextureLoadError> {
     let path = path.as_ref();
 
-    log::debug!("Loading texture {path:?}");
+    if let Some(texture) = resources.cached_asset(path, &texture_options(flip_y)) {
+        return Ok(texture);
+    }
 
     let path_str = &path.to_string_lossy().to_string();
 
-    let image= image::open(path)?;
-
-    let texture = Texture::from_image(renderer, resources, &image, Some(path_str), flip_y);
+    let texture = DecodedTexture::read(path)?.upload(renderer, resources, Some(path_str), flip_y)?;
     let texture = resources.store(texture);
+    register_texture(resources, &texture, path, flip_y);
 
     Ok(texture)
 }
 
+/// Options textures loaded from files are registered with, see `Resources::cached_asset`.
+fn texture_options(flip_y: bool) -> String {
+    format!("flip_y: {flip_y}")
+}
+
+fn register_texture<H: HandlesResource<Texture>>(resources: &mut Resources, texture: &H, path: &Path, flip_y: bool) {
+    let gpu_memory = resources.get(texture).map(Texture::gpu_memory).unwrap_or_default();
+    resources.register_asset(texture, path, &texture_options(flip_y), file_size(path), gpu_memory);
+}
+
+/// Greyscale images are expanded, textures and cube maps only take colour.
+pub(crate) fn expand_greyscale(image: image::DynamicImage) -> image::DynamicImage {
+    match image {
+        image @ (image::DynamicImage::ImageLuma8(_) | image::DynamicImage::ImageLumaA8(_)) => image::DynamicImage::ImageRgba8(image.to_rgba8()),
+        image @ (image::DynamicImage::ImageLuma16(_) | image::DynamicImage::ImageLumaA16(_)) => image::DynamicImage::ImageRgba16(image.to_rgba16()),
+        image => image,
+    }
+}
+
+/// A texture decoded on the CPU, waiting to be uploaded.
+pub(crate) enum DecodedTexture {
+    Compressed(CompressedImage),
+    Image(image::DynamicImage),
+}
+
+impl DecodedTexture {
+    pub(crate) fn read(path: &Path) -> Result<DecodedTexture, TextureLoadError> {
+        log::debug!("Loading texture {path:?}");
+
+        let bytes = std::fs::read(path).map_err(TextureLoadError::IoError)?;
+
+        DecodedTexture::decode(&bytes, image::ImageFormat::from_path(path).ok())
+    }
+
+    /// Loads KTX2 and DDS containers, or decodes an image of `format` (guessed if `None`).
+    pub(crate) fn decode(bytes: &[u8], format: Option<image::ImageFormat>) -> Result<DecodedTexture, TextureLoadError> {
+        if let Some(image) = CompressedImage::from_container(bytes) {
+            return Ok(DecodedTexture::Compressed(image?));
+        }
+
+        let format = match format {
+            Some(format) => format,
+            None => image::guess_format(bytes)?,
+        };
+
+        let image = image::load_from_memory_with_format(bytes, format)?;
+
+        Ok(DecodedTexture::Image(expand_greyscale(image)))
+    }
+
+    pub(crate) fn upload(&self, renderer: &Renderer, resources: &mut Resources, label: Option<&str>, flip_y: bool) -> Result<Texture, TextureLoadError> {
+        match self {
+            DecodedTexture::Compressed(image) => Ok(Texture::from_compressed(renderer, resources, image, label, flip_y)?),
+            DecodedTexture::Image(image) => Ok(Texture::from_image(renderer, resources, image, label, flip_y)),
+        }
+    }
+}
+
 pub fn load_obj<P: AsRef<Path>>(
     renderer: &crate::renderer::Renderer,
     resources: &mut Resources,
     path: P,
 ) -> Result<Model, ModelLoadError> {
-    let path = path.as_ref();
-    let path_str = &path.to_string_lossy().to_string();
-    let parent = path.parent().unwrap_or(Path::new("res")).to_owned();
+    ObjData::read(path.as_ref())?.build(renderer, resources, true)
+}
 
-    log::debug!("Loading obj model {path:?}");
-    
-    let obj_text = std::fs::read_to_string(path.clone()).map_err(|err| ModelLoadError::IoError(err))?;
+/// An obj model and its materials, with textures decoded.
+pub(crate) struct ObjData {
+    path: PathBuf,
+    models: Vec<tobj::Model>,
+    materials: Vec<tobj::Material>,
+    mtl_paths: Vec<PathBuf>,
+    /// Keyed by path
+    textures: HashMap<PathBuf, DecodedTexture>,
+}
 
-    log::trace!("Read obj file {path:?}");
-    
-    let obj_cursor = Cursor::new(obj_text);
-    let mut obj_reader = BufReader::new(obj_cursor);
-
-    let (models, obj_materials) = tobj::load_obj_buf(
-        &mut obj_reader,
-        &tobj::LoadOptions {
-            single_index: true,
-            triangulate: true,
-            ..Default::default()
-        },
-        |p| {
-            let material_path = parent.join(p).to_owned();
-            
-            log::trace!("Read material file {material_path:?}");
-            
-            let material_text = std::fs::read_to_string(material_path).unwrap();
-            tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(material_text)))
-        },
-    ).map_err(|err| ModelLoadError::TobjError(err))?;
+impl ObjData {
+    pub(crate) fn read(path: &Path) -> Result<ObjData, ModelLoadError> {
+        let mut obj = ObjData::parse(path)?;
+
+        for texture_path in obj.texture_paths() {
+            if let Entry::Vacant(entry) = obj.textures.entry(texture_path) {
+                let texture = DecodedTexture::read(entry.key()).map_err(ModelLoadError::TextureLoadError)?;
+                entry.insert(texture);
+            }
+        }
+
+        Ok(obj)
+    }
+
+    /// Reads the obj and mtl files, without decoding textures.
+    fn parse(path: &Path) -> Result<ObjData, ModelLoadError> {
+        let parent = path.parent().unwrap_or(Path::new("res")).to_owned();
+
+        log::debug!("Loading obj model {path:?}");
+        
+        let obj_text = std::fs::read_to_string(path).map_err(ModelLoadError::IoError)?;
+
+        log::trace!("Read obj file {path:?}");
+        
+        let obj_cursor = Cursor::new(obj_text);
+        let mut obj_reader = BufReader::new(obj_cursor);
+
+        let mtl_paths = RefCell::new(vec![]);
+
+        let (models, materials) = tobj::load_obj_buf(
+            &mut obj_reader,
+            &tobj::LoadOptions {
+                single_index: true,
+                triangulate: true,
+                ..Default::default()
+            },
+            |p| {
+                let material_path = parent.join(p).to_owned();
+                
+                log::trace!("Read material file {material_path:?}");
+                
+                let material_text = std::fs::read_to_string(&material_path).unwrap();
+                mtl_paths.borrow_mut().push(material_path);
+                tobj::load_mtl_buf(&mut BufReader::new(Cursor::new(material_text)))
+            },
+        ).map_err(ModelLoadError::TobjError)?;
+
+        Ok(ObjData {
+            path: path.to_owned(),
+            models,
+            materials: materials.map_err(ModelLoadError::TobjError)?,
+            mtl_paths: mtl_paths.into_inner(),
+            textures: hashmap!{},
+        })
+    }
 
-    let mut materials = vec![];
+    fn texture_paths(&self) -> Vec<PathBuf> {
+        let parent = self.path.parent().unwrap_or(Path::new("res"));
 
-    for material in obj_materials.map_err(|err| ModelLoadError::TobjError(err))? {
-        let diffuse_texture = if material.diffuse_texture.len() > 0 {
-            load_texture(renderer, resources, parent.join(material.diffuse_texture), true).map_err(|err| ModelLoadError::ImageError(err))?
-        } else {
-            let texture = Texture::from_pixel(renderer, resources, &[255, 255, 255, 255], Some(&format!("{} diffuse pixel texture", material.name)));
-            resources.store(texture)
+        self.materials.iter().flat_map(|material| {
+            // displacement maps are used as normal maps if there isn't one
+            let normal_texture = match material.normal_texture.as_str() {
+                "" => material.unknown_param.get("map_Disp").map(String::as_str).unwrap_or_default(),
+                normal_texture => normal_texture,
+            };
+
+            [material.diffuse_texture.as_str(), normal_texture]
+        }).filter(|file| !file.is_empty()).map(|file| parent.join(file)).collect()
+    }
+
+    /// The obj, mtl and texture files of the model.
+    fn sources(&self) -> Vec<PathBuf> {
+        [vec![self.path.clone()], self.mtl_paths.clone(), self.texture_paths()].concat()
+    }
+
+    /// Textures already loaded from the same files are reused if `reuse` is set, otherwise they're uploaded again.
+    pub(crate) fn build(self, renderer: &Renderer, resources: &mut Resources, reuse: bool) -> Result<Model, ModelLoadError> {
+        let ObjData { path, models, materials: obj_materials, textures, .. } = self;
+        let path_str = &path.to_string_lossy().to_string();
+        let parent = path.parent().unwrap_or(Path::new("res")).to_owned();
+
+        let upload_texture = |resources: &mut Resources, file: &str| -> Result<Handle<Texture>, ModelLoadError> {
+            let path = parent.join(file);
+
+            if let Some(texture) = resources.cached_asset(&path, &texture_options(true)).filter(|_| reuse) {
+                return Ok(texture);
+            }
+
+            let texture = textures[&path].upload(renderer, resources, Some(&path.to_string_lossy()), true).map_err(ModelLoadError::TextureLoadError)?;
+            let texture = resources.store(texture);
+            register_texture(resources, &texture, &path, true);
+            Ok(texture)
         };
+
+        let mut materials = vec![];
+
+        for material in obj_materials {
+            let diffuse_texture = if material.diffuse_texture.len() > 0 {
+                upload_texture(resources, &material.diffuse_texture)?
+            } else {
+                let texture = Texture::from_pixel(renderer, resources, &[255, 255, 255, 255], Some(&format!("{} diffuse pixel texture", material.name)));
+                resources.store(texture)
+            };
         
-        let diffuse_sampler = Sampler::new_default(renderer, resources, diffuse_texture);
-        let diffuse_sampler = resources.store(diffuse_sampler);
+            let diffuse_sampler = Sampler::new_default(renderer, resources, diffuse_texture);
+            let diffuse_sampler = resources.store(diffuse_sampler);
 
-        let diffuse_colour = Rgba::new(material.diffuse[0], material.diffuse[1], material.diffuse[2], 1.0);
+            let diffuse_colour = Rgba::new(material.diffuse[0], material.diffuse[1], material.diffuse[2], 1.0);
 
-        let normal_texture = if material.normal_texture.len() > 0 {
-            load_texture(renderer, resources, parent.join(material.normal_texture), true).map_err(|err| ModelLoadError::ImageError(err))?
-        } else if let Some(disp_map) = material.unknown_param.get("map_Disp") {
-            if disp_map.len() > 0 {
-                load_texture(renderer, resources, parent.join(&material.unknown_param["map_Disp"]), true).map_err(|err| ModelLoadError::ImageError(err))?
+            let normal_texture = if material.normal_texture.len() > 0 {
+                upload_texture(resources, &material.normal_texture)?
+            } else if let Some(disp_map) = material.unknown_param.get("map_Disp") {
+                if disp_map.len() > 0 {
+                    upload_texture(resources, &material.unknown_param["map_Disp"])?
+                } else {
+                    let texture = Texture::from_pixel(renderer, resources, &[128, 128, 255, 255], Some(&format!("{} normal pixel texture", material.name)));
+                    resources.store(texture)
+                }
             } else {
                 let texture = Texture::from_pixel(renderer, resources, &[128, 128, 255, 255], Some(&format!("{} normal pixel texture", material.name)));
                 resources.store(texture)
-            }
-        } else {
-            let texture = Texture::from_pixel(renderer, resources, &[128, 128, 255, 255], Some(&format!("{} normal pixel texture", material.name)));
-            resources.store(texture)
-        };
+            };
         
-        let normal_sampler = Sampler::new_default(renderer, resources, normal_texture);
-        let normal_sampler = resources.store(normal_sampler);
-
-        let normal_scale = 1.0;
-
-        // let material = Material::new(
-        //     &renderer,
-        //     resources,
-        //     name,
-        //     false,
-        //     AlphaMode::Mask { cutoff: 0.1 },
-        //     Some(diffuse_texture),
-        //     diffuse_colour,
-        //     None,
-        //     0.0,
-        //     0.5,
-        //     Some(normal_texture),
-        //     normal_scale,
-        //     None,
-        //     1.0,
-        //     None,
-        //     Rgba::BLACK,
-        // );
-
-        let mut builder = Material::builder()
-            .albedo_sampler(diffuse_sampler)
-            .albedo(diffuse_colour)
-            .metallic_factor(0.0)
-            .roughness_factor(0.5)
-            .normal_sampler(normal_sampler)
-            .normal_scale(normal_scale);
+            let normal_sampler = Sampler::new_default(renderer, resources, normal_texture);
+            let normal_sampler = resources.store(normal_sampler);
+
+            let normal_scale = 1.0;
+
+            // let material = Material::new(
+            //     &renderer,
+            //     resources,
+            //     name,
+            //     false,
+            //     AlphaMode::Mask { cutoff: 0.1 },
+            //     Some(diffuse_texture),
+            //     diffuse_colour,
+            //     None,
+            //     0.0,
+            //     0.5,
+            //     Some(normal_texture),
+            //     normal_scale,
+            //     None,
+            //     1.0,
+            //     None,
+            //     Rgba::BLACK,
+            // );
+
+            let mut builder = Material::builder()
+                .albedo_sampler(diffuse_sampler)
+                .albedo(diffuse_colour)
+                .metallic_factor(0.0)
+                .roughness_factor(0.5)
+                .normal_sampler(normal_sampler)
+                .normal_scale(normal_scale);
         
-        if material.name.len() > 0 {
-            builder = builder.name(material.name.as_str())
-        };
+            if material.name.len() > 0 {
+                builder = builder.name(material.name.as_str())
+            };
 
-        let material = builder.build(renderer, resources);
-
-        materials.push(resources.store(material));
-    }
-
-    let meshes: Vec<Mesh> = models.into_iter()
-        .map(|model| {
-            let mut vertices: Vec<ModelVertex> = (0..model.mesh.positions.len() / 3)
-                .map(|i| ModelVertex {
-                    position: glam::vec3(
-                        model.mesh.positions[i*3],
-                        model.mesh.positions[i*3+1],
-                        model.mesh.positions[i*3+2],
-                    ),
-                    tex_coords: glam::vec2(
-                        model.mesh.texcoords[i*2],
-                        model.mesh.texcoords[i*2+1],
-                    ),
-                    normal: glam::vec3(
-                        model.mesh.normals[i*3],
-                        model.mesh.normals[i*3+1],
-                        model.mesh.normals[i*3+2],
-                    ),
-                    tangent: glam::Vec3::ZERO,
-                    bitangent: glam::Vec3::ZERO,
-                }).collect();
+            let material = builder.build(renderer, resources);
+
+            materials.push(resources.store(material));
+        }
+
+        let meshes: Vec<Mesh> = models.into_iter()
+            .map(|model| {
+                let mut vertices: Vec<ModelVertex> = (0..model.mesh.positions.len() / 3)
+                    .map(|i| ModelVertex {
+                        position: glam::vec3(
+                            model.mesh.positions[i*3],
+                            model.mesh.positions[i*3+1],
+                            model.mesh.positions[i*3+2],
+                        ),
+                        tex_coords: glam::vec2(
+                            model.mesh.texcoords[i*2],
+                            model.mesh.texcoords[i*2+1],
+                        ),
+                        normal: glam::vec3(
+                            model.mesh.normals[i*3],
+                            model.mesh.normals[i*3+1],
+                            model.mesh.normals[i*3+2],
+                        ),
+                        ..Default::default()
+                    }).collect();
             
-            let indices = &model.mesh.indices;
+                let indices = &model.mesh.indices;
                 
-            compute_tangents(&mut vertices, indices);
+                compute_tangents(&mut vertices, indices);
             
-            let vertex_buffer = resources.store(renderer.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{} Vertex Buffer", path_str)),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
-            }));
+                let vertex_buffer = resources.store(renderer.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("{} Vertex Buffer", path_str)),
+                    contents: bytemuck::cast_slice(&vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }));
             
-            let index_buffer = resources.store(renderer.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("{} Index Buffer", path_str)),
-                contents: bytemuck::cast_slice(indices),
-                usage: wgpu::BufferUsages::INDEX,
-            }));
+                let index_buffer = resources.store(renderer.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(&format!("{} Index Buffer", path_str)),
+                    contents: bytemuck::cast_slice(indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                }));
 
-            let material = model.mesh.material_id.map(|material_id| materials[material_id].clone());
+                let material = model.mesh.material_id.map(|material_id| materials[material_id].clone());
 
-            let name = if model.name.len() > 0 {
-                Some(model.name)
-            } else {
-                None
-            };
+                let name = if model.name.len() > 0 {
+                    Some(model.name)
+                } else {
+                    None
+                };
 
-            let mesh = Mesh {
-                name,
-                vertex_buffer,
-                index_buffer,
-                material,
-                num_elements: model.mesh.indices.len() as u32,
-                // material: model.mesh.material_id,
-            };
+                let mesh = Mesh {
+                    name,
+                    vertex_buffer,
+                    index_buffer,
+                    material,
+                    num_elements: model.mesh.indices.len() as u32,
+                    bounds: Aabb::from_points(vertices.iter().map(|vertex| vertex.position)),
+                    morph_targets: None,
+                    // material: model.mesh.material_id,
+                };
 
-            mesh
-        }).collect();
+                mesh
+            }).collect();
     
-    Ok(Model {
-        meshes,
-        // materials,
-    })
+        Ok(Model {
+            meshes,
+            // materials,
+        })
+    }
 }
 
 // If scene is None, use default scene
+/// Basis Universal payloads aren't transcoded yet, textures with a `KHR_texture_basisu` image fall back on their `source`,
+/// without one loading fails with `CompressedImageError::BasisUniversal`.
 pub fn load_gltf<P: AsRef<Path>>(
     renderer: &crate::renderer::Renderer,
     resources: &mut Resources,
@@ -415,87 +585,143 @@ pub fn load_gltf<P: AsRef<Path>>(
     scene: Option<usize>,
 ) -> Result<Node, SceneLoadError> {
     // let gltf = Gltf::open(path).map_err(|err| SceneLoadError::GltfError(err))?;
+    let cached_images = cached_gltf_images(resources, path.as_ref());
+    let skip_images = cached_images.iter().map(|(index, _)| *index).collect();
+
+    build_gltf(renderer, resources, GltfData::read(path.as_ref(), &skip_images)?, scene, true)
+}
+
+/// A glTF document with its buffers, and images decoded.
+pub(crate) struct GltfData {
+    path: PathBuf,
+    document: gltf::Document,
+    buffers: Vec<gltf::buffer::Data>,
+    json: serde_json::Value,
+    /// Failures are resolved when building, textures may fall back on another image.
+    /// `None` for skipped images, which are already uploaded
+    images: Vec<Option<Result<DecodedTexture, TextureLoadError>>>,
+    /// Encoded bytes of each image
+    image_sizes: Vec<u64>,
+}
+
+impl GltfData {
+    /// Images with an index in `skip` aren't decoded, they have to be cached when building.
+    pub(crate) fn read(path: &Path, skip: &HashSet<usize>) -> Result<GltfData, SceneLoadError> {
+        log::debug!("Loading gltf {path:?}");
+
+        // todo: don't load all scenes, if only one is needed
+        let base = path.parent().unwrap_or(Path::new("./"));
+        let (document, blob, json) = gltf_document(path)?;
+        let buffers = gltf_buffers(&document, base, blob).map_err(SceneLoadError::GltfError)?;
+
+        let (images, image_sizes) = document.images().map(|image| {
+            log::trace!("Loading gltf image #{}", image.index());
+
+            let bytes = gltf_image(&image, base, &buffers).map_err(SceneLoadError::GltfError)?;
+            let decoded = (!skip.contains(&image.index())).then(|| DecodedTexture::decode(&bytes, None));
+
+            Ok((decoded, bytes.len() as u64))
+        }).collect::<Result<(Vec<_>, Vec<_>), SceneLoadError>>()?;
+
+        Ok(GltfData {
+            path: path.to_owned(),
+            document,
+            buffers,
+            json,
+            images,
+            image_sizes,
+        })
+    }
+}
+
+/// Options images of glTF files are registered with, see `Resources::cached_asset`.
+fn gltf_image_options(index: usize) -> String {
+    format!("image {index}")
+}
+
+/// Images of the glTF file at `path` that are still cached, so they don't have to be decoded again.
+fn cached_gltf_images(resources: &Resources, path: &Path) -> Vec<(usize, Handle<Texture>)> {
+    resources.cached_assets_from::<Texture>(path).into_iter()
+        .filter_map(|(options, texture)| Some((options.strip_prefix("image ")?.parse().ok()?, texture)))
+        .collect()
+}
 
+/// Uploads the images of `gltf` and builds the node tree of `scene`.
+/// Images already uploaded from the same file are reused if `reuse` is set, otherwise they're uploaded again.
+fn build_gltf(
+    renderer: &Renderer,
+    resources: &mut Resources,
+    gltf: GltfData,
+    scene: Option<usize>,
+    reuse: bool,
+) -> Result<Node, SceneLoadError> {
     struct ImportData {
         buffers: Vec<gltf::buffer::Data>,
+        /// Indexed by texture, not image
         textures: Vec<Handle<Texture>>,
+        /// JSON of each material, for extensions `gltf` doesn't parse
+        materials_json: Vec<serde_json::Value>,
     }
 
-    log::debug!("Loading gltf {:?}", path.as_ref());
-
-    // todo: don't load all scenes, if only one is needed
-    let (document, import_data) = {
-        let (document, buffers, images) = gltf::import(&path).map_err(|err| SceneLoadError::GltfError(err))?;
+    let GltfData { path, document, buffers, json, images, image_sizes } = gltf;
 
-        let mut i = 0;
+    let import_data = {
+        // KHR_texture_basisu images are preferred, textures fall back on their `source` if one can't be loaded
+        let basisu_sources: Vec<Option<usize>> = json["textures"].as_array().into_iter().flatten()
+            .map(|texture| texture["extensions"]["KHR_texture_basisu"]["source"].as_u64().map(|image| image as usize))
+            .collect();
+        // i.e. Basis Universal payloads, which aren't transcoded
+        let mut basisu_errors = hashmap!{};
 
-        let textures = images.into_iter().map(|data| {
-            let gltf::image::Data {
-                pixels,
-                format,
-                width,
-                height,
-            } = data;
+        let images = document.images().zip(images).map(|(image, decoded)| {
+            let options = gltf_image_options(image.index());
 
-            log::trace!("Loading gltf texture #{i}: (format: {format:?}, data: {:?})", &pixels[0..3]);
+            if let Some(texture) = resources.cached_asset(&path, &options).filter(|_| reuse) {
+                return Ok(Some(texture));
+            }
 
-            i += 1;
+            let decoded = decoded.expect("Skipped glTF image is no longer cached, this is a bug.");
 
-            // convert `gltf` Image to `image` DynamicImage
-            let image = match format {
-                // Luma8
-                gltf::image::Format::R8 => image::DynamicImage::ImageLuma8(image::GrayImage::from_raw(width, height, pixels).unwrap()),
-                // LumaA8
-                gltf::image::Format::R8G8 => {
-                    let pixels = pixels.chunks(2).flat_map(|chunk| [chunk[0], chunk[0], chunk[0], chunk[1]]).collect();
-                    image::DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, pixels).unwrap())
-                },
-                gltf::image::Format::R8G8B8 => image::DynamicImage::ImageRgb8(image::RgbImage::from_raw(width, height, pixels).unwrap()),
-                gltf::image::Format::R8G8B8A8 => image::DynamicImage::ImageRgba8(image::RgbaImage::from_raw(width, height, pixels).unwrap()),
-                gltf::image::Format::B8G8R8 => {
-                    let pixels = pixels.chunks(3).flat_map(|chunk| {
-                        [chunk[2], chunk[1], chunk[0]]
-                    }).collect();
-                    image::DynamicImage::ImageRgb8(image::RgbImage::from_raw(width, height, pixels).unwrap())
-                },
-                gltf::image::Format::B8G8R8A8 => {
-                    let pixels = pixels.chunks(4).flat_map(|chunk| {
-                        [chunk[2], chunk[1], chunk[0], chunk[3]]
-                    }).collect();
-                    image::DynamicImage::ImageRgb8(image::RgbImage::from_raw(width, height, pixels).unwrap())
+            match decoded.and_then(|decoded| decoded.upload(renderer, resources, image.name(), false)) {
+                Ok(texture) => {
+                    let gpu_memory = texture.gpu_memory;
+                    let texture = resources.store(texture);
+                    resources.register_asset(&texture, &path, &options, image_sizes[image.index()], gpu_memory);
+                    Ok(Some(texture))
                 },
-                // Luma16
-                gltf::image::Format::R16 => {
-                    let pixels = pixels.chunks(2).map(|chunk| bytemuck::cast_slice(chunk)[0]).collect();
-                    image::DynamicImage::ImageLuma16(image::ImageBuffer::<image::Luma<u16>, Vec<u16>>::from_raw(width, height, pixels).unwrap())
+                Err(err) if basisu_sources.contains(&Some(image.index())) => {
+                    basisu_errors.insert(image.index(), err);
+                    Ok(None)
                 },
-                // LumaA16
-                gltf::image::Format::R16G16 => {
-                    let pixels = pixels.chunks(4).flat_map(|chunk| {
-                        let slice = bytemuck::cast_slice(chunk);
-                        [slice[0], slice[0], slice[0], slice[1]]
-                    }).collect();
-                    image::DynamicImage::ImageRgba16(image::ImageBuffer::<image::Rgba<u16>, Vec<u16>>::from_raw(width, height, pixels).unwrap())
-                },
-                gltf::image::Format::R16G16B16 => {
-                    let pixels = pixels.chunks(2).map(|chunk| bytemuck::cast_slice(chunk)[0]).collect();
-                    image::DynamicImage::ImageRgb16(image::ImageBuffer::<image::Rgb<u16>, Vec<u16>>::from_raw(width, height, pixels).unwrap())
+                Err(err) => Err(SceneLoadError::TextureLoadError(err)),
+            }
+        }).collect::<Result<Vec<_>, SceneLoadError>>()?;
+
+        let textures = document.textures().map(|texture| {
+            let basisu_source = basisu_sources.get(texture.index()).copied().flatten();
+            let basisu = basisu_source.and_then(|image| images.get(image).cloned().flatten());
+
+            // textures with only a KHR_texture_basisu image have it as their `source` too, see `gltf_document`
+            match basisu.or_else(|| images[texture.source().index()].clone()) {
+                Some(image) => {
+                    if let Some(err) = basisu_source.and_then(|image| basisu_errors.get(&image)) {
+                        log::warn!("Failed to load the KHR_texture_basisu image of texture #{}, using its source: {err}", texture.index());
+                    }
+                    Ok(image)
                 },
-                gltf::image::Format::R16G16B16A16 => {
-                    let pixels = pixels.chunks(2).map(|chunk| bytemuck::cast_slice(chunk)[0]).collect();
-                    image::DynamicImage::ImageRgba16(image::ImageBuffer::<image::Rgba<u16>, Vec<u16>>::from_raw(width, height, pixels).unwrap())
+                // only KHR_texture_basisu images can be missing, collecting stops at the first error
+                None => {
+                    let image = basisu_source.unwrap_or_else(|| texture.source().index());
+                    Err(SceneLoadError::TextureLoadError(basisu_errors.remove(&image).expect("Missing KHR_texture_basisu image without an error, this is a bug.")))
                 },
-            };
-
-            let texture = Texture::from_image(renderer, resources, &image, None, false);
+            }
+        }).collect::<Result<Vec<_>, SceneLoadError>>()?;
 
-            resources.store(texture)
-        }).collect();
-        
-        (document, ImportData {
+        ImportData {
             buffers,
             textures,
-        })
+            materials_json: json["materials"].as_array().cloned().unwrap_or_default(),
+        }
     };
 
     let scene = if let Some(scene) = scene {
@@ -504,8 +730,30 @@ pub fn load_gltf<P: AsRef<Path>>(
         document.default_scene().ok_or(SceneLoadError::SceneNotFound)?
     };
 
-    fn visit(renderer: &Renderer, resources: &mut Resources, node: gltf::Node, import_data: &ImportData) -> Node {
+    // joints and animation targets are nodes anywhere in the scene, so they're resolved once it's built
+    struct NodeImport<'a> {
+        /// Skins with joints outside of the scene are ignored, as are animation channels targeting them
+        scene_nodes: HashSet<usize>,
+        node_ids: HashMap<usize, NodeId>,
+        skinned_nodes: Vec<(NodeId, gltf::Skin<'a>)>,
+        /// Resolves textures of material extensions `gltf` doesn't parse
+        document: &'a gltf::Document,
+    }
+
+    fn visit<'a>(renderer: &Renderer, resources: &mut Resources, node: gltf::Node<'a>, import_data: &ImportData, node_import: &mut NodeImport<'a>) -> Result<Node, SceneLoadError> {
         let mut builder = Node::builder(node.name().unwrap_or("#"));
+        node_import.node_ids.insert(node.index(), builder.id());
+
+        let skin = node.skin().filter(|skin| {
+            let complete = skin.joints().all(|joint| node_import.scene_nodes.contains(&joint.index()));
+            if !complete {
+                log::warn!("Skin {:?} has joints outside of the scene, node {:?} will not be skinned.", skin.name(), node.name());
+            }
+            complete
+        });
+        if let Some(skin) = &skin {
+            node_import.skinned_nodes.push((builder.id(), skin.clone()));
+        }
         
         let (scale, rotation, translation) = match node.transform() {
             gltf::scene::Transform::Matrix { matrix } => glam::Mat4::from_cols_array_2d(&matrix).to_scale_rotation_translation(),
@@ -526,10 +774,42 @@ pub fn load_gltf<P: AsRef<Path>>(
 
             builder = builder.add_component(camera);
         }
+        if let Some(light) = node.light() {
+            log::trace!("Loading light, name: {:?}", light.name());
+
+            // intensity is used as is, lux for directional lights and candela for point and spot lights
+            let [r, g, b] = light.color();
+            let colour = Rgba::new(r, g, b, light.intensity());
+
+            // gltf lights point along -Z, directional and spot lights point along -Y, so they are rotated in a child node
+            let rotated = |name: &str| Node::builder(name).add_component(Transform::from_rotation(glam::Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)));
+            let name = light.name().unwrap_or("light");
+
+            match light.kind() {
+                gltf::khr_lights_punctual::Kind::Point => {
+                    builder = builder.add_component(PointLight {
+                        colour,
+                        range: light.range(),
+                    });
+                },
+                gltf::khr_lights_punctual::Kind::Directional => {
+                    builder = builder.add_child(rotated(name).add_component(DirectionalLight::new(colour)).build());
+                },
+                gltf::khr_lights_punctual::Kind::Spot { inner_cone_angle, outer_cone_angle } => {
+                    builder = builder.add_child(rotated(name).add_component(SpotLight {
+                        colour,
+                        inner_cone_angle,
+                        outer_cone_angle,
+                        range: light.range(),
+                    }).build());
+                },
+            }
+        }
         if let Some(mesh) = node.mesh() {
             log::trace!("Loading mesh, name: {:?}", mesh.name());
             
             let mut meshes = vec![];
+            let mut num_morph_targets = 0;
             
             for primitive in mesh.primitives() {
                 // todo: handle topology
@@ -544,112 +824,73 @@ pub fn load_gltf<P: AsRef<Path>>(
                 // };
 
                 // log::trace!("Loading mesh");
-                let mut positions = vec![];
-                let mut normals = vec![];
-                // let mut tangents = vec![];
-                let mut tex_coords = vec![];
-
-                for (semantic, accessor) in primitive.attributes() {
-                    match &semantic {
-                        // todo: sparse accessors
-                        gltf::Semantic::Positions | gltf::Semantic::Normals | gltf::Semantic::Tangents | gltf::Semantic::TexCoords(0) => {
-                            let view = accessor.view().expect("Sparse accessors not supported.");
-                            let buffer = &import_data.buffers[view.buffer().index()].0;
-
-                            let dimensions = accessor.dimensions();
-                            let data_type = accessor.data_type();
-                            
-                            let start_index = view.offset() + accessor.offset();
-                            let item_size = data_type.size() * dimensions.multiplicity();
-                            let stride = view.stride().unwrap_or(item_size);
-
-                            for i in 0..accessor.count() {
-                                let index = start_index + i * stride;
-
-                                let data = buffer[index..(index + item_size)].chunks(data_type.size()).map(|chunk| {
-                                    match data_type {
-                                        gltf::accessor::DataType::U8 => chunk[0] as f32 / 255.0,
-                                        gltf::accessor::DataType::U16 => bytemuck::cast_slice::<_, i8>(chunk)[0] as f32 / std::u16::MAX as f32,
-                                        gltf::accessor::DataType::F32 => bytemuck::cast_slice::<_, f32>(chunk)[0],
-                                        _ => todo!(),
-                                    }
-                                }).collect::<Vec<_>>();
-
-                                match semantic {
-                                    gltf::Semantic::Positions => {
-                                        // position should be vec3
-                                        positions.push(glam::vec3(data[0], data[1], data[2]));
-                                    },
-                                    gltf::Semantic::Normals => {
-                                        // normal should be vec3
-                                        normals.push(glam::vec3(data[0], data[1], data[2]));
-                                    },
-                                    // Tangents will be calculated from UV's
-                                    // gltf::Semantic::Tangents => {
-                                    //     // tangent are vec4, w is handedness
-                                    //     tangents.push(glam::vec4(data[0], data[1], data[2], data[3]));
-                                    // },
-                                    gltf::Semantic::TexCoords(0) => {
-                                        // tex coord should be vec2
-                                        tex_coords.push(glam::vec2(data[0], data[1]));
-                                    },
-                                    _ => {},
-                                }
-                            }
-                        },
-                        _ => {},
-                    }
-                }
-
-                let indices = if let Some(accessor) = primitive.indices() {
-                    let view = accessor.view().expect("Sparse accessors not supported.");
-                    let buffer = &import_data.buffers[view.buffer().index()].0;
+                let read_f32 = |semantic| primitive.get(&semantic).map(|accessor| read_accessor_f32(&accessor, &import_data.buffers)).transpose();
 
-                    let dimensions = accessor.dimensions();
-                    let data_type = accessor.data_type();
-                    
-                    let start_index = view.offset() + accessor.offset();
-                    let item_size = data_type.size() * dimensions.multiplicity();
-                    let stride = view.stride().unwrap_or(item_size);
+                let positions: Vec<glam::Vec3> = read_f32(gltf::Semantic::Positions)?.unwrap_or_default().into_iter().map(glam::Vec3::from).collect();
+                let normals: Vec<glam::Vec3> = read_f32(gltf::Semantic::Normals)?.unwrap_or_default().into_iter().map(glam::Vec3::from).collect();
+                let tangents: Vec<[f32; 4]> = primitive.get(&gltf::Semantic::Tangents).map(|accessor| read_accessor_f32(&accessor, &import_data.buffers)).transpose()?.unwrap_or_default();
 
-                    let mut indices = vec![];
-                    
-                    for i in 0..accessor.count() {
-                        let index = start_index + i * stride;
-
-                        let vertex_index = buffer[index..(index + item_size)].chunks(data_type.size()).map(|chunk| {
-                            match data_type {
-                                gltf::accessor::DataType::U8 => chunk[0] as u32,
-                                gltf::accessor::DataType::U16 => bytemuck::cast_slice::<_, u16>(chunk)[0] as u32,
-                                gltf::accessor::DataType::U32 => chunk[0] as u32,
-                                _ => todo!(),
-                                
-                            }
-                        }).next().unwrap();
-                        
-                        indices.push(vertex_index);
-                    }
+                let read_tex_coords = |set| -> Result<Vec<glam::Vec2>, AccessorError> {
+                    Ok(primitive.get(&gltf::Semantic::TexCoords(set))
+                        .map(|accessor| read_accessor_f32(&accessor, &import_data.buffers)).transpose()?
+                        .unwrap_or_default().into_iter().map(glam::Vec2::from).collect())
+                };
+                let tex_coords = read_tex_coords(0)?;
+                let tex_coords_1 = read_tex_coords(1)?;
+
+                // RGB or RGBA
+                let colours: Vec<[f32; 4]> = match primitive.get(&gltf::Semantic::Colors(0)) {
+                    Some(accessor) if accessor.dimensions() == gltf::accessor::Dimensions::Vec3 => {
+                        read_accessor_f32::<3>(&accessor, &import_data.buffers)?.into_iter().map(|[r, g, b]| [r, g, b, 1.0]).collect()
+                    },
+                    Some(accessor) => read_accessor_f32(&accessor, &import_data.buffers)?,
+                    None => vec![],
+                };
 
-                    indices
+                let indices: Vec<u32> = if let Some(accessor) = primitive.indices() {
+                    read_accessor_u32::<1>(&accessor, &import_data.buffers)?.into_iter().map(|[index]| index).collect()
                 } else {
                     (0..positions.len()).map(|i| i as u32).collect()
                 };
 
-                let mut vertices = vec![];
-                // let has_tangents = tangents.len() > 0;
-
-                for i in 0..positions.len() {
-                    vertices.push(ModelVertex {
-                        position: positions[i],
-                        tex_coords: *tex_coords.get(i).unwrap_or(&glam::Vec2::ZERO),
-                        normal: *normals.get(i).unwrap_or(&glam::Vec3::ZERO),
-                        // tangent: if has_tangents { tangents[i].xyz() } else { glam::Vec3::ZERO },
-                        tangent: glam::Vec3::ZERO,
-                        bitangent: glam::Vec3::ZERO,
-                    });
+                let mut vertices: Vec<ModelVertex> = (0..positions.len()).map(|i| ModelVertex {
+                    position: positions[i],
+                    tex_coords: tex_coords.get(i).copied().unwrap_or_default(),
+                    normal: normals.get(i).copied().unwrap_or_default(),
+                    tangent: tangents.get(i).copied().unwrap_or([1.0, 0.0, 0.0, 1.0]),
+                    tex_coords_1: tex_coords_1.get(i).copied().unwrap_or_default(),
+                    colour: colours.get(i).copied().unwrap_or([1.0; 4]),
+                }).collect();
+
+                if tangents.len() < vertices.len() {
+                    compute_tangents(&mut vertices, &indices);
                 }
-                
-                compute_tangents(&mut vertices, &indices);
+
+                // up to four joints of the node's skin per vertex
+                let skin_weights = match skin {
+                    Some(_) => {
+                        let joints: Vec<[u32; 4]> = primitive.get(&gltf::Semantic::Joints(0)).map(|accessor| read_accessor_u32(&accessor, &import_data.buffers)).transpose()?.unwrap_or_default();
+                        let weights: Vec<[f32; 4]> = primitive.get(&gltf::Semantic::Weights(0)).map(|accessor| read_accessor_f32(&accessor, &import_data.buffers)).transpose()?.unwrap_or_default();
+
+                        if joints.len() < vertices.len() || weights.len() < vertices.len() {
+                            log::warn!("Skinned mesh {:?} is missing joints or weights, vertices without them follow the first joint.", mesh.name());
+                        }
+
+                        Some((joints, weights))
+                    },
+                    None => None,
+                };
+
+                let read_vec3 = |accessor: Option<gltf::Accessor>| -> Result<Vec<glam::Vec3>, AccessorError> {
+                    Ok(accessor.map(|accessor| read_accessor_f32(&accessor, &import_data.buffers)).transpose()?.unwrap_or_default().into_iter().map(glam::Vec3::from).collect())
+                };
+
+                let morph_targets = primitive.morph_targets().map(|target| Ok(MorphTarget {
+                    positions: read_vec3(target.positions())?,
+                    normals: read_vec3(target.normals())?,
+                    tangents: read_vec3(target.tangents())?,
+                })).collect::<Result<Vec<_>, AccessorError>>()?;
+                num_morph_targets = num_morph_targets.max(morph_targets.len());
 
                 // primitive.bounding_box(), todo: use this
 
@@ -693,14 +934,35 @@ pub fn load_gltf<P: AsRef<Path>>(
                 }
 
                 fn gltf_texture_to_wgpu(texture: gltf::texture::Texture, textures: &Vec<Handle<Texture>>) -> Handle<Texture> {
-                    let image_index = texture.source().index();
-                    let texture_handle = textures[image_index].clone();
+                    textures[texture.index()].clone()
+                }
 
-                    texture_handle
+                // sets the sampler, UV set and transform of a material texture
+                fn set_texture(renderer: &Renderer, resources: &mut Resources, textures: &Vec<Handle<Texture>>, builder: MaterialBuilder, slot: MaterialTexture, texture: MaterialTextureImport) -> MaterialBuilder {
+                    let t = gltf_texture_to_wgpu(texture.texture.clone(), textures);
+                    let (transform, tex_coord) = texture.transform.unwrap_or_default();
+
+                    builder
+                        .sampler(slot, gltf_sampler_to_wgpu(renderer, resources, texture.texture.sampler(), t))
+                        .tex_coord(slot, tex_coord.unwrap_or(texture.tex_coord))
+                        .texture_transform(slot, transform)
+                }
+
+                // clearcoat, specular and sheen textures are sampled as one, exporters usually pack them together
+                fn packed_texture<'a>(material: Option<&str>, slot: MaterialTexture, a: Option<MaterialTextureImport<'a>>, b: Option<MaterialTextureImport<'a>>) -> Option<MaterialTextureImport<'a>> {
+                    match (a, b) {
+                        (Some(a), Some(b)) if a.texture.index() != b.texture.index() => {
+                            log::warn!("Material {:?} has separate {:?} textures, only texture {} is used.", material, slot, a.texture.index());
+                            Some(a)
+                        },
+                        (a, b) => a.or(b),
+                    }
                 }
 
                 let material = {
                     let material = primitive.material();
+                    let json = material.index().and_then(|index| import_data.materials_json.get(index)).unwrap_or(&serde_json::Value::Null);
+                    let json_texture = |json: &serde_json::Value| MaterialTextureImport::from_json(node_import.document, json);
 
                     let mut builder = Material::builder();
     
@@ -717,58 +979,107 @@ pub fn load_gltf<P: AsRef<Path>>(
                     }
                     builder = builder.double_sided(double_sided);
                     builder = builder.alpha_mode(alpha_mode);
+                    builder = builder.unlit(material.unlit());
                     
                     let pbr = material.pbr_metallic_roughness();
                     
                     if let Some(texture) = pbr.base_color_texture() {
-                        let t = gltf_texture_to_wgpu(texture.texture(), &import_data.textures);
-                        builder = builder
-                            .albedo_sampler(gltf_sampler_to_wgpu(renderer, resources, texture.texture().sampler(), t));
+                        builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Albedo, texture.into());
                     };
                     
                     builder = builder.albedo(pbr.base_color_factor().into());
                     
                     if let Some(texture) = pbr.metallic_roughness_texture() {
-                        let t = gltf_texture_to_wgpu(texture.texture(), &import_data.textures);
-                        builder = builder
-                            .metallic_sampler(gltf_sampler_to_wgpu(renderer, resources, texture.texture().sampler(), t.clone()));
-                        builder = builder
-                            .roughness_sampler(gltf_sampler_to_wgpu(renderer, resources, texture.texture().sampler(), t));
+                        builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Metallic, texture.clone().into());
+                        builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Roughness, texture.into());
                     };
 
                     builder = builder
                         .metallic_factor(pbr.metallic_factor())
                         .roughness_factor(pbr.roughness_factor());
 
+                    // the transforms of normal and occlusion textures aren't parsed by `gltf`
                     if let Some(normal_texture) = material.normal_texture() {
-                        let t = gltf_texture_to_wgpu(normal_texture.texture(), &import_data.textures);
-                        builder = builder
-                            .normal_sampler(gltf_sampler_to_wgpu(renderer, resources, normal_texture.texture().sampler(), t))
+                        let texture = json_texture(&json["normalTexture"]).unwrap_or(MaterialTextureImport { texture: normal_texture.texture(), tex_coord: normal_texture.tex_coord(), transform: None });
+                        builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Normal, texture)
                             .normal_scale(normal_texture.scale());
                     }
                     
                     if let Some(occlusion_texture) = material.occlusion_texture() {
-                        let t = gltf_texture_to_wgpu(occlusion_texture.texture(), &import_data.textures);
-                        builder = builder
-                            .occlusion_sampler(gltf_sampler_to_wgpu(renderer, resources, occlusion_texture.texture().sampler(), t))
+                        let texture = json_texture(&json["occlusionTexture"]).unwrap_or(MaterialTextureImport { texture: occlusion_texture.texture(), tex_coord: occlusion_texture.tex_coord(), transform: None });
+                        builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Occlusion, texture)
                             .occlusion_strength(occlusion_texture.strength());
                     }
                     
                     if let Some(texture) = material.emissive_texture() {
-                        let t = gltf_texture_to_wgpu(texture.texture(), &import_data.textures);
-                        builder = builder
-                            .emissive_sampler(gltf_sampler_to_wgpu(renderer, resources, texture.texture().sampler(), t));
+                        builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Emissive, texture.into());
                     };
 
-                    builder = builder.emissive_factor(material.emissive_factor().into());
+                    let emissive_strength = json["extensions"]["KHR_materials_emissive_strength"]["emissiveStrength"].as_f64().unwrap_or(1.0) as f32;
+                    builder = builder.emissive_factor(material.emissive_factor().map(|channel| channel * emissive_strength).into());
+
+                    if let Some(ior) = material.ior() {
+                        builder = builder.ior(ior);
+                    }
+
+                    if let Some(specular) = material.specular() {
+                        let texture = packed_texture(name, MaterialTexture::Specular, specular.specular_texture().map(Into::into), specular.specular_color_texture().map(Into::into));
+                        if let Some(texture) = texture {
+                            builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Specular, texture);
+                        }
+                        builder = builder
+                            .specular_factor(specular.specular_factor())
+                            .specular_colour_factor(specular.specular_color_factor().into());
+                    }
+
+                    if let Some(transmission) = material.transmission() {
+                        if let Some(texture) = transmission.transmission_texture() {
+                            builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Transmission, texture.into());
+                        }
+                        builder = builder.transmission_factor(transmission.transmission_factor());
+                    }
+
+                    let clearcoat = &json["extensions"]["KHR_materials_clearcoat"];
+                    if clearcoat.is_object() {
+                        let texture = packed_texture(name, MaterialTexture::Clearcoat, json_texture(&clearcoat["clearcoatTexture"]), json_texture(&clearcoat["clearcoatRoughnessTexture"]));
+                        if let Some(texture) = texture {
+                            builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Clearcoat, texture);
+                        }
+                        if let Some(texture) = json_texture(&clearcoat["clearcoatNormalTexture"]) {
+                            builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::ClearcoatNormal, texture);
+                        }
+                        builder = builder
+                            .clearcoat_factor(clearcoat["clearcoatFactor"].as_f64().unwrap_or(0.0) as f32)
+                            .clearcoat_roughness_factor(clearcoat["clearcoatRoughnessFactor"].as_f64().unwrap_or(0.0) as f32)
+                            .clearcoat_normal_scale(clearcoat["clearcoatNormalTexture"]["scale"].as_f64().unwrap_or(1.0) as f32);
+                    }
+
+                    let sheen = &json["extensions"]["KHR_materials_sheen"];
+                    if sheen.is_object() {
+                        let texture = packed_texture(name, MaterialTexture::Sheen, json_texture(&sheen["sheenColorTexture"]), json_texture(&sheen["sheenRoughnessTexture"]));
+                        if let Some(texture) = texture {
+                            builder = set_texture(renderer, resources, &import_data.textures, builder, MaterialTexture::Sheen, texture);
+                        }
+                        let colour = sheen["sheenColorFactor"].as_array().map(|colour| glam::Vec3::from_slice(&colour.iter().map(|channel| channel.as_f64().unwrap_or(0.0) as f32).collect::<Vec<_>>()));
+                        builder = builder
+                            .sheen_colour_factor(colour.unwrap_or(glam::Vec3::ZERO))
+                            .sheen_roughness_factor(sheen["sheenRoughnessFactor"].as_f64().unwrap_or(0.0) as f32);
+                    }
 
-                    // let material = Material::new(renderer, resources, name.as_deref(), double_sided, alpha_mode, albedo_texture, albedo, metallic_roughness_texture, metallic_factor, roughness_factor, normal_texture, normal_scale, occlusion_texture, occlusion_strength, emissive_texture, emissive_factor);
                     let material = builder.build(renderer, resources);
                     
                     resources.store(material)
                 };
 
-                meshes.push(Mesh::new(renderer, resources, mesh.name(), vertices, indices, Some(material)));
+                meshes.push(match skin_weights {
+                    Some((joints, weights)) => {
+                        let vertices = vertices.into_iter().enumerate().map(|(i, vertex)| {
+                            SkinnedVertex::new(vertex, *joints.get(i).unwrap_or(&[0; 4]), *weights.get(i).unwrap_or(&[1.0, 0.0, 0.0, 0.0]))
+                        }).collect();
+                        Mesh::new_morphed(renderer, resources, mesh.name(), vertices, indices, Some(material), &morph_targets)
+                    },
+                    None => Mesh::new_morphed(renderer, resources, mesh.name(), vertices, indices, Some(material), &morph_targets),
+                });
             }
             
             let model = resources.store(Model {
@@ -776,16 +1087,21 @@ pub fn load_gltf<P: AsRef<Path>>(
             });
 
             builder = builder.add_component(model);
+
+            if num_morph_targets > 0 {
+                // the node's weights override the mesh's, the default is zero
+                let mut weights = node.weights().or(mesh.weights()).map(|weights| weights.to_vec()).unwrap_or_default();
+                weights.resize(num_morph_targets, 0.0);
+                builder = builder.add_component(MorphWeights::new(weights));
+            }
         }
 
         for child in node.children() {
-            builder = builder.add_child(visit(renderer, resources, child, import_data));
+            builder = builder.add_child(visit(renderer, resources, child, import_data, node_import)?);
         }
 
-        builder.build()
+        Ok(builder.build())
     }
-    
-    let path = path.as_ref();
 
     let scene_name = match scene.name() {
         Some(name) => name.to_owned(),
@@ -796,19 +1112,260 @@ pub fn load_gltf<P: AsRef<Path>>(
     };
 
     let mut builder = Node::builder(&scene_name);
+
+    let mut node_import = NodeImport {
+        scene_nodes: HashSet::new(),
+        node_ids: hashmap!{},
+        skinned_nodes: vec![],
+        document: &document,
+    };
+
+    fn scene_nodes(node: gltf::Node, nodes: &mut HashSet<usize>) {
+        nodes.insert(node.index());
+        for child in node.children() {
+            scene_nodes(child, nodes);
+        }
+    }
+
+    for node in scene.nodes() {
+        scene_nodes(node, &mut node_import.scene_nodes);
+    }
     
     for node in scene.nodes() {
         // log::trace!("Node #{} has {} children", node.index(), node.children().count());
-        builder = builder.add_child(visit(renderer, resources, node, &import_data));
+        builder = builder.add_child(visit(renderer, resources, node, &import_data, &mut node_import)?);
+    }
+
+    let mut root = builder.build();
+
+    for (node_id, skin) in node_import.skinned_nodes {
+        let joints = skin.joints().map(|joint| node_import.node_ids[&joint.index()]).collect();
+        let inverse_bind_matrices = skin.inverse_bind_matrices()
+            .map(|accessor| read_accessor_f32(&accessor, &import_data.buffers)).transpose()?
+            .unwrap_or_default().into_iter().map(|matrix| glam::Mat4::from_cols_array(&matrix)).collect();
+
+        root.find_by_id_mut(&node_id).unwrap().add_component(Skeleton::new(joints, inverse_bind_matrices));
     }
 
-    Ok(builder.build())
+    let mut clips = vec![];
+
+    for animation in document.animations() {
+        let mut channels = vec![];
+
+        for channel in animation.channels() {
+            let Some(target) = node_import.node_ids.get(&channel.target().node().index()) else { continue };
+
+            let sampler = channel.sampler();
+            let times = read_accessor_f32::<1>(&sampler.input(), &import_data.buffers)?;
+            let outputs = sampler.output();
+
+            let keyframes = match channel.target().property() {
+                gltf::animation::Property::Translation => Keyframes::Translation(read_accessor_f32(&outputs, &import_data.buffers)?.into_iter().map(glam::Vec3::from).collect()),
+                gltf::animation::Property::Rotation => Keyframes::Rotation(read_accessor_f32(&outputs, &import_data.buffers)?.into_iter().map(glam::Quat::from_array).collect()),
+                gltf::animation::Property::Scale => Keyframes::Scale(read_accessor_f32(&outputs, &import_data.buffers)?.into_iter().map(glam::Vec3::from).collect()),
+                gltf::animation::Property::MorphTargetWeights => Keyframes::Weights(read_accessor_f32::<1>(&outputs, &import_data.buffers)?.into_iter().map(|[weight]| weight).collect()),
+            };
+
+            channels.push(AnimationChannel {
+                target: *target,
+                interpolation: match sampler.interpolation() {
+                    gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                    gltf::animation::Interpolation::Step => Interpolation::Step,
+                    gltf::animation::Interpolation::CubicSpline => Interpolation::CubicSpline,
+                },
+                times: times.into_iter().map(|[time]| time).collect(),
+                keyframes,
+            });
+        }
+
+        clips.push(resources.store(AnimationClip::new(animation.name(), channels)));
+    }
+
+    if !clips.is_empty() {
+        root.add_component(AnimationPlayer::new(clips));
+    }
+
+    Ok(root)
+}
+
+/// `KHR_materials_emissive_strength` of each material, which the `gltf` crate doesn't support yet.
+/// A texture of a material, with its `KHR_texture_transform` and the UV set that overrides.
+struct MaterialTextureImport<'a> {
+    texture: gltf::texture::Texture<'a>,
+    tex_coord: u32,
+    transform: Option<(TextureTransform, Option<u32>)>,
+}
+
+impl<'a> From<gltf::texture::Info<'a>> for MaterialTextureImport<'a> {
+    fn from(info: gltf::texture::Info<'a>) -> Self {
+        MaterialTextureImport {
+            texture: info.texture(),
+            tex_coord: info.tex_coord(),
+            transform: info.texture_transform().map(|transform| (TextureTransform {
+                offset: transform.offset().into(),
+                rotation: transform.rotation(),
+                scale: transform.scale().into(),
+            }, transform.tex_coord())),
+        }
+    }
+}
+
+impl<'a> MaterialTextureImport<'a> {
+    /// `json` is a textureInfo object.
+    fn from_json(document: &'a gltf::Document, json: &serde_json::Value) -> Option<MaterialTextureImport<'a>> {
+        let texture = document.textures().nth(json["index"].as_u64()? as usize)?;
+        let transform = &json["extensions"]["KHR_texture_transform"];
+        let vec2 = |value: &serde_json::Value, default: f32| glam::vec2(
+            value[0].as_f64().unwrap_or(default as f64) as f32,
+            value[1].as_f64().unwrap_or(default as f64) as f32,
+        );
+
+        Some(MaterialTextureImport {
+            texture,
+            tex_coord: json["texCoord"].as_u64().unwrap_or(0) as u32,
+            transform: transform.is_object().then(|| (TextureTransform {
+                offset: vec2(&transform["offset"], 0.0),
+                rotation: transform["rotation"].as_f64().unwrap_or(0.0) as f32,
+                scale: vec2(&transform["scale"], 1.0),
+            }, transform["texCoord"].as_u64().map(|tex_coord| tex_coord as u32))),
+        })
+    }
+}
+
+/// Reads a glTF or GLB file, returning its binary chunk and JSON (for extensions `gltf` doesn't parse).
+/// Textures with only a `KHR_texture_basisu` image get it as their `source`, which `gltf` requires.
+fn gltf_document(path: &Path) -> Result<(gltf::Document, Option<Vec<u8>>, serde_json::Value), SceneLoadError> {
+    let bytes = std::fs::read(path).map_err(|err| SceneLoadError::GltfError(gltf::Error::Io(err)))?;
+
+    let (json, blob) = if bytes.starts_with(b"glTF") {
+        let glb = gltf::Glb::from_slice(&bytes).map_err(SceneLoadError::GltfError)?;
+        (glb.json.into_owned(), glb.bin.map(|bin| bin.into_owned()))
+    } else {
+        (bytes, None)
+    };
+
+    let mut json: serde_json::Value = serde_json::from_slice(&json).map_err(|err| SceneLoadError::GltfError(gltf::Error::Deserialize(err)))?;
+
+    for texture in json.get_mut("textures").and_then(|textures| textures.as_array_mut()).into_iter().flatten() {
+        if texture.get("source").is_none() {
+            if let Some(source) = texture["extensions"]["KHR_texture_basisu"].get("source").cloned() {
+                texture["source"] = source;
+            }
+        }
+    }
+
+    let root = serde_json::from_value(json.clone()).map_err(|err| SceneLoadError::GltfError(gltf::Error::Deserialize(err)))?;
+    let document = gltf::Document::from_json(root).map_err(SceneLoadError::GltfError)?;
+
+    Ok((document, blob, json))
+}
+
+fn gltf_buffers(document: &gltf::Document, base: &Path, mut blob: Option<Vec<u8>>) -> Result<Vec<gltf::buffer::Data>, gltf::Error> {
+    document.buffers().map(|buffer| {
+        let mut data = match buffer.source() {
+            gltf::buffer::Source::Uri(uri) => gltf_uri(base, uri)?,
+            gltf::buffer::Source::Bin => blob.take().ok_or(gltf::Error::MissingBlob)?,
+        };
+
+        if data.len() < buffer.length() {
+            return Err(gltf::Error::BufferLength { buffer: buffer.index(), expected: buffer.length(), actual: data.len() });
+        }
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+
+        Ok(gltf::buffer::Data(data))
+    }).collect()
+}
+
+/// Encoded image data, its format is recognised from the data itself.
+fn gltf_image(image: &gltf::Image, base: &Path, buffers: &[gltf::buffer::Data]) -> Result<Vec<u8>, gltf::Error> {
+    match image.source() {
+        gltf::image::Source::Uri { uri, .. } => gltf_uri(base, uri),
+        gltf::image::Source::View { view, .. } => {
+            let buffer = &buffers[view.buffer().index()].0;
+            Ok(buffer[view.offset()..view.offset() + view.length()].to_vec())
+        },
+    }
+}
+
+/// Contents of a base64 data URI, or of a file relative to `base`.
+fn gltf_uri(base: &Path, uri: &str) -> Result<Vec<u8>, gltf::Error> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, data) = data.split_once(";base64,").ok_or(gltf::Error::UnsupportedScheme)?;
+        base64::decode(data).map_err(gltf::Error::Base64)
+    } else if let Some(path) = gltf_uri_path(base, uri) {
+        std::fs::read(path).map_err(gltf::Error::Io)
+    } else {
+        Err(gltf::Error::UnsupportedScheme)
+    }
+}
+
+/// The file a URI refers to, `None` for data URIs and other schemes.
+fn gltf_uri_path(base: &Path, uri: &str) -> Option<PathBuf> {
+    if let Some(path) = uri.strip_prefix("file://").or_else(|| uri.strip_prefix("file:")) {
+        Some(PathBuf::from(path))
+    } else if uri.contains(':') {
+        None
+    } else {
+        Some(base.join(uri))
+    }
+}
+
+/// The glTF file and the files its buffers and images are read from.
+fn gltf_sources(path: &Path) -> Result<Vec<PathBuf>, SceneLoadError> {
+    let base = path.parent().unwrap_or(Path::new("./"));
+    let (document, _, _) = gltf_document(path)?;
+
+    let buffer_uris = document.buffers().filter_map(|buffer| match buffer.source() {
+        gltf::buffer::Source::Uri(uri) => Some(uri),
+        gltf::buffer::Source::Bin => None,
+    });
+    let image_uris = document.images().filter_map(|image| match image.source() {
+        gltf::image::Source::Uri { uri, .. } => Some(uri),
+        gltf::image::Source::View { .. } => None,
+    });
+
+    let files = buffer_uris.chain(image_uris).filter_map(|uri| gltf_uri_path(base, uri));
+
+    Ok(std::iter::once(path.to_owned()).chain(files).collect())
 }
 
 #[derive(Debug)]
-pub enum ModelLoadError {
+pub enum TextureLoadError {
     IoError(std::io::Error),
     ImageError(image::ImageError),
+    CompressedImageError(CompressedImageError),
+}
+
+impl From<image::ImageError> for TextureLoadError {
+    fn from(err: image::ImageError) -> Self {
+        TextureLoadError::ImageError(err)
+    }
+}
+
+impl From<CompressedImageError> for TextureLoadError {
+    fn from(err: CompressedImageError) -> Self {
+        TextureLoadError::CompressedImageError(err)
+    }
+}
+
+impl std::fmt::Display for TextureLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TextureLoadError::IoError(err) => err.fmt(f),
+            TextureLoadError::ImageError(err) => err.fmt(f),
+            TextureLoadError::CompressedImageError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TextureLoadError {}
+
+#[derive(Debug)]
+pub enum ModelLoadError {
+    IoError(std::io::Error),
+    TextureLoadError(TextureLoadError),
     TobjError(tobj::LoadError),
 }
 
@@ -816,13 +1373,21 @@ pub enum ModelLoadError {
 pub enum SceneLoadError {
     SceneNotFound,
     GltfError(gltf::Error),
+    AccessorError(AccessorError),
+    TextureLoadError(TextureLoadError),
+}
+
+impl From<AccessorError> for SceneLoadError {
+    fn from(err: AccessorError) -> Self {
+        SceneLoadError::AccessorError(err)
+    }
 }
 
 impl std::fmt::Display for ModelLoadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ModelLoadError::IoError(err) => err.fmt(f),
-            ModelLoadError::ImageError(err) => err.fmt(f),
+            ModelLoadError::TextureLoadError(err) => err.fmt(f),
             ModelLoadError::TobjError(err) => err.fmt(f),
             // ModelLoadError::GltfError(err) => err.fmt(f),
         }
@@ -831,60 +1396,61 @@ impl std::fmt::Display for ModelLoadError {
 
 impl std::error::Error for ModelLoadError {}
 
-pub(crate) fn compute_tangents(vertices: &mut Vec<ModelVertex>, indices: &[u32]) {
-    let mut triangles_included = vec![0; vertices.len()];
-
-    for c in indices.chunks(3) {
-        let v0 = vertices[c[0] as usize];
-        let v1 = vertices[c[1] as usize];
-        let v2 = vertices[c[2] as usize];
-
-        let pos0: glam::Vec3 = v0.position.into();
-        let pos1: glam::Vec3 = v1.position.into();
-        let pos2: glam::Vec3 = v2.position.into();
-
-        let uv0: glam::Vec2 = v0.tex_coords.into();
-        let uv1: glam::Vec2 = v1.tex_coords.into();
-        let uv2: glam::Vec2 = v2.tex_coords.into();
-
-        let delta_pos1 = pos1 - pos0;
-        let delta_pos2 = pos2 - pos0;
-
-        let delta_uv1 = uv1 - uv0;
-        let delta_uv2 = uv2 - uv0;
-
-        // Solve:
-        // delta_pos1 = delta_uv1.x * T + delta_uv1.y * B;
-        // delta_pos2 = delta_uv2.x * T + delta_uv2.y * B;
-
-        // Solution:
-        let r = 1.0 / (delta_uv1.x * delta_uv2.y - delta_uv1.y * delta_uv2.x);
-        let tangent = (delta_pos1 * delta_uv2.y - delta_pos2 * delta_uv1.y) * r;
-        let bitangent = (delta_pos2 * delta_uv1.x - delta_pos1 * delta_uv2.x) * -r;
-
-        vertices[c[0] as usize].tangent = 
-            (tangent + glam::Vec3::from(vertices[c[0] as usize].tangent)).into();
-        vertices[c[1] as usize].tangent = 
-            (tangent + glam::Vec3::from(vertices[c[1] as usize].tangent)).into();
-        vertices[c[2] as usize].tangent = 
-            (tangent + glam::Vec3::from(vertices[c[2] as usize].tangent)).into();
-        vertices[c[0] as usize].bitangent = 
-            (bitangent + glam::Vec3::from(vertices[c[0] as usize].bitangent)).into();
-        vertices[c[1] as usize].bitangent = 
-            (bitangent + glam::Vec3::from(vertices[c[1] as usize].bitangent)).into();
-        vertices[c[2] as usize].bitangent = 
-            (bitangent + glam::Vec3::from(vertices[c[2] as usize].bitangent)).into();
-        
-        // Used to average tangents/bitangents for vertices that touch multiple triangles.
-        triangles_included[c[0] as usize] += 1;
-        triangles_included[c[1] as usize] += 1;
-        triangles_included[c[2] as usize] += 1;
+impl std::fmt::Display for SceneLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SceneLoadError::SceneNotFound => write!(f, "Scene not found."),
+            SceneLoadError::GltfError(err) => err.fmt(f),
+            SceneLoadError::AccessorError(err) => err.fmt(f),
+            SceneLoadError::TextureLoadError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SceneLoadError {}
+
+/// Generates MikkTSpace tangents, the tangent space normal maps are baked in. Needs normals and tex coords.
+pub(crate) fn compute_tangents(vertices: &mut [ModelVertex], indices: &[u32]) {
+    struct Geometry<'a> {
+        vertices: &'a mut [ModelVertex],
+        indices: &'a [u32],
+    }
+
+    impl Geometry<'_> {
+        fn vertex(&self, face: usize, vert: usize) -> &ModelVertex {
+            &self.vertices[self.indices[face * 3 + vert] as usize]
+        }
+    }
+
+    impl bevy_mikktspace::Geometry for Geometry<'_> {
+        fn num_faces(&self) -> usize {
+            self.indices.len() / 3
+        }
+
+        fn num_vertices_of_face(&self, _face: usize) -> usize {
+            3
+        }
+
+        fn position(&self, face: usize, vert: usize) -> [f32; 3] {
+            self.vertex(face, vert).position.into()
+        }
+
+        fn normal(&self, face: usize, vert: usize) -> [f32; 3] {
+            self.vertex(face, vert).normal.into()
+        }
+
+        // v points down in glTF, so the generated bitangents point up the texture
+        fn tex_coord(&self, face: usize, vert: usize) -> [f32; 2] {
+            let tex_coords = self.vertex(face, vert).tex_coords;
+            [tex_coords.x, 1.0 - tex_coords.y]
+        }
+
+        fn set_tangent_encoded(&mut self, tangent: [f32; 4], face: usize, vert: usize) {
+            self.vertices[self.indices[face * 3 + vert] as usize].tangent = tangent;
+        }
     }
 
-    for (i, n) in triangles_included.into_iter().enumerate() {
-        let denom = 1.0 / n as f32;
-        let mut v = &mut vertices[i];
-        v.tangent = (glam::Vec3::from(v.tangent) * denom).into();
-        v.bitangent = (glam::Vec3::from(v.bitangent) * denom).into();
+    if !bevy_mikktspace::generate_tangents(&mut Geometry { vertices, indices }) {
+        log::warn!("Failed to generate tangents.");
     }
 }
@@ -0,0 +1,179 @@
+use std::{any::{Any, TypeId}, collections::HashMap, path::{Path, PathBuf}};
+
+use super::{Resources, Handle, WeakHandle, HandlesResource, HandleId};
+
+/// Where a loaded asset came from.
+#[derive(Debug, Clone)]
+pub struct AssetInfo {
+    /// Canonical path of the file it was loaded from
+    pub path: PathBuf,
+    /// What else it was loaded with, i.e. `flip_y: true`, or the index of an image in a glTF file
+    pub options: String,
+    pub type_name: &'static str,
+    /// Size of the encoded data
+    pub byte_size: u64,
+    /// Estimate of the GPU memory used by the asset itself, i.e. a model's buffers but not its textures
+    pub gpu_memory: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct AssetKey {
+    type_id: TypeId,
+    path: PathBuf,
+    options: String,
+}
+
+struct RegisteredAsset {
+    id: HandleId,
+    /// `WeakHandle` of the asset's type
+    handle: Box<dyn Any>,
+    alive: Box<dyn Fn() -> bool>,
+    info: AssetInfo,
+}
+
+struct LoadingAsset {
+    id: HandleId,
+    /// `WeakHandle` of the asset's type
+    handle: Box<dyn Any>,
+    alive: Box<dyn Fn() -> bool>,
+}
+
+/// Weak handles of loaded assets, keyed by path and options so loading an asset again reuses it while it's alive.
+pub(crate) struct AssetRegistry {
+    assets: HashMap<AssetKey, RegisteredAsset>,
+    /// Assets still loading in the background, so loading them again shares the pending handle
+    loading: HashMap<AssetKey, LoadingAsset>,
+    /// Key of every registered or loading asset, by its handle
+    keys: HashMap<HandleId, AssetKey>,
+}
+
+impl AssetRegistry {
+    pub(crate) fn new() -> AssetRegistry {
+        AssetRegistry {
+            assets: hashmap!{},
+            loading: hashmap!{},
+            keys: hashmap!{},
+        }
+    }
+
+    pub(crate) fn drop_invalid(&mut self) {
+        self.assets.retain(|_, asset| (asset.alive)());
+        self.loading.retain(|_, asset| (asset.alive)());
+
+        let AssetRegistry { assets, loading, keys } = self;
+        keys.retain(|id, key| assets.get(key).map_or(false, |asset| asset.id == *id) || loading.get(key).map_or(false, |asset| asset.id == *id));
+    }
+
+    /// Stops sharing the load of `id`, it's registered by then if it succeeded.
+    pub(crate) fn finish_loading(&mut self, id: HandleId) {
+        let Some(key) = self.keys.get(&id) else { return };
+
+        if self.loading.get(key).map_or(false, |asset| asset.id == id) {
+            self.loading.remove(key);
+        }
+        if !self.assets.get(key).map_or(false, |asset| asset.id == id) {
+            self.keys.remove(&id);
+        }
+    }
+}
+
+fn asset_key<T: 'static>(path: &Path, options: &str) -> std::io::Result<AssetKey> {
+    Ok(AssetKey {
+        type_id: TypeId::of::<T>(),
+        path: path.canonicalize()?,
+        options: options.to_owned(),
+    })
+}
+
+impl Resources {
+    /// The asset of type `T` loaded from `path` with `options`, if any of its handles are still alive.
+    pub fn cached_asset<T: 'static, P: AsRef<Path>>(&self, path: P, options: &str) -> Option<Handle<T>> {
+        let key = asset_key::<T>(path.as_ref(), options).ok()?;
+
+        self.registry.assets.get(&key)?.handle.downcast_ref::<WeakHandle<T>>()?.upgrade()
+    }
+
+    /// Like `cached_asset`, but also returns the handle of an asset that's still loading in the background.
+    pub(crate) fn cached_or_loading_asset<T: 'static>(&self, path: &Path, options: &str) -> Option<Handle<T>> {
+        let key = asset_key::<T>(path, options).ok()?;
+
+        let upgrade = |handle: &dyn Any| handle.downcast_ref::<WeakHandle<T>>()?.upgrade();
+
+        self.registry.assets.get(&key).and_then(|asset| upgrade(asset.handle.as_ref()))
+            .or_else(|| self.registry.loading.get(&key).and_then(|asset| upgrade(asset.handle.as_ref())))
+    }
+
+    /// Every alive asset of type `T` loaded from `path`, with the options it was loaded with.
+    pub(crate) fn cached_assets_from<T: 'static>(&self, path: &Path) -> Vec<(&str, Handle<T>)> {
+        let Ok(path) = path.canonicalize() else { return vec![] };
+
+        self.registry.assets.iter()
+            .filter(|(key, _)| key.type_id == TypeId::of::<T>() && key.path == path)
+            .filter_map(|(key, asset)| Some((key.options.as_str(), asset.handle.downcast_ref::<WeakHandle<T>>()?.upgrade()?)))
+            .collect()
+    }
+
+    /// Records that `handle` is loading from `path` with `options`, until `AssetRegistry::finish_loading`.
+    pub(crate) fn register_loading<T: 'static>(&mut self, handle: &Handle<T>, path: &Path, options: &str) {
+        let Ok(key) = asset_key::<T>(path, options) else { return };
+
+        let weak = handle.downgrade();
+        let alive = weak.clone();
+
+        self.registry.keys.insert(handle.id(), key.clone());
+        self.registry.loading.insert(key, LoadingAsset {
+            id: handle.id(),
+            handle: Box::new(weak),
+            alive: Box::new(move || alive.valid()),
+        });
+    }
+
+    /// Records where the asset in `handle` was loaded from, so `cached_asset` returns it.
+    /// Replaces an asset registered with the same path and options.
+    pub fn register_asset<T: 'static, H: HandlesResource<T>, P: AsRef<Path>>(&mut self, handle: &H, path: P, options: &str, byte_size: u64, gpu_memory: u64) {
+        let path = match path.as_ref().canonicalize() {
+            Ok(path) => path,
+            Err(err) => {
+                log::debug!("Not registering {:?}: {err}", path.as_ref());
+                return;
+            },
+        };
+
+        let weak = handle.downgrade();
+        let alive = weak.clone();
+        let key = AssetKey { type_id: TypeId::of::<T>(), path: path.clone(), options: options.to_owned() };
+
+        self.registry.keys.insert(handle.id(), key.clone());
+        let replaced = self.registry.assets.insert(key, RegisteredAsset {
+            id: handle.id(),
+            handle: Box::new(weak),
+            alive: Box::new(move || alive.valid()),
+            info: AssetInfo {
+                path,
+                options: options.to_owned(),
+                type_name: std::any::type_name::<T>(),
+                byte_size,
+                gpu_memory,
+            },
+        });
+
+        if let Some(replaced) = replaced.filter(|replaced| replaced.id != handle.id()) {
+            self.registry.keys.remove(&replaced.id);
+        }
+    }
+
+    pub fn asset_info<T: 'static>(&self, handle: &Handle<T>) -> Option<&AssetInfo> {
+        let key = self.registry.keys.get(&handle.id())?;
+        self.registry.assets.get(key).filter(|asset| asset.id == handle.id()).map(|asset| &asset.info)
+    }
+
+    /// Every registered asset that's still alive, i.e. for debugging memory use.
+    pub fn loaded_assets(&self) -> impl Iterator<Item = &AssetInfo> {
+        self.registry.assets.values().filter(|asset| (asset.alive)()).map(|asset| &asset.info)
+    }
+}
+
+/// Size of the file at `path`, 0 if it can't be read.
+pub(crate) fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0)
+}
@@ -0,0 +1,266 @@
+use std::{collections::HashSet, error::Error, path::{Path, PathBuf}, sync::mpsc};
+
+use notify::Watcher;
+
+use crate::{renderer::{Renderer, Shader}, node::Node, components::AnimationPlayer, resource::{Texture, Model, AnimationClip}};
+
+use super::{Resources, Handle, WeakHandle, HandlesResource, DecodedTexture, ObjData, GltfData, build_gltf, gltf_sources, register_texture};
+
+/// Reloads an asset into its handle, returning the files it was loaded from.
+type Reload = Box<dyn FnMut(&Renderer, &mut Resources) -> Result<Vec<PathBuf>, Box<dyn Error>>>;
+
+struct WatchedAsset {
+    label: String,
+    /// Canonical paths
+    sources: HashSet<PathBuf>,
+    reload: Reload,
+    alive: Box<dyn Fn() -> bool>,
+}
+
+/// Watches the files of assets, which `Resources::reload_assets` loads again when they change.
+pub(crate) struct AssetWatcher {
+    /// Created on the first watch
+    watcher: Option<notify::RecommendedWatcher>,
+    sender: mpsc::Sender<notify::Result<notify::Event>>,
+    receiver: mpsc::Receiver<notify::Result<notify::Event>>,
+    /// Editors often replace files instead of writing them, so their directories are watched
+    directories: HashSet<PathBuf>,
+    assets: Vec<WatchedAsset>,
+}
+
+impl AssetWatcher {
+    pub(crate) fn new() -> AssetWatcher {
+        let (sender, receiver) = mpsc::channel();
+
+        AssetWatcher {
+            watcher: None,
+            sender,
+            receiver,
+            directories: hashset!{},
+            assets: vec![],
+        }
+    }
+
+    /// Starts watching the directories of `paths`, returns the canonical paths.
+    fn watch_paths(&mut self, paths: &[PathBuf]) -> HashSet<PathBuf> {
+        let mut canonical = hashset!{};
+
+        for path in paths {
+            let path = match path.canonicalize() {
+                Ok(path) => path,
+                Err(err) => {
+                    log::warn!("Can't watch {path:?}: {err}");
+                    continue;
+                },
+            };
+
+            let directory = path.parent().unwrap_or(Path::new("/")).to_owned();
+            canonical.insert(path);
+
+            if self.directories.contains(&directory) {
+                continue;
+            }
+
+            let watcher = match &mut self.watcher {
+                Some(watcher) => watcher,
+                None => match notify::recommended_watcher(self.sender.clone()) {
+                    Ok(watcher) => self.watcher.insert(watcher),
+                    Err(err) => {
+                        log::warn!("Failed to create file watcher: {err}");
+                        continue;
+                    },
+                },
+            };
+
+            match watcher.watch(&directory, notify::RecursiveMode::NonRecursive) {
+                Ok(()) => { self.directories.insert(directory); },
+                Err(err) => log::warn!("Can't watch {directory:?}: {err}"),
+            }
+        }
+
+        canonical
+    }
+
+    /// Files created or written since the last call.
+    fn changed(&self) -> HashSet<PathBuf> {
+        self.receiver.try_iter().filter_map(|event| match event {
+            Ok(event) if event.kind.is_create() || event.kind.is_modify() => Some(event.paths),
+            Ok(_) => None,
+            Err(err) => {
+                log::warn!("File watcher error: {err}");
+                None
+            },
+        }).flatten().collect()
+    }
+
+    /// Stops reloading assets of dropped handles.
+    pub(crate) fn drop_invalid(&mut self) {
+        self.assets.retain(|asset| (asset.alive)());
+    }
+}
+
+impl Resources {
+    /// Calls `reload` whenever one of `paths` changes, its asset replaces the contents of `handle`.
+    pub fn watch<T, E, F>(&mut self, handle: &Handle<T>, paths: Vec<PathBuf>, mut reload: F)
+    where
+        T: 'static,
+        E: Error + 'static,
+        F: FnMut(&Renderer, &mut Resources) -> Result<T, E> + 'static,
+    {
+        let label = paths.first().map(|path| path.to_string_lossy().to_string()).unwrap_or_else(|| std::any::type_name::<T>().to_owned());
+        let weak = handle.downgrade();
+        let sources = paths.clone();
+
+        self.watch_sources(label, &paths, Box::new(move |renderer, resources| {
+            let asset = reload(renderer, resources)?;
+            weak.set(resources, asset);
+            Ok(sources.clone())
+        }), alive(handle));
+    }
+
+    /// Reloads a shader loaded by `Shader::from_resource` when its resource, shader or included files change.
+    /// Pipelines are compiled again, if any of them fail the current shader is kept.
+    pub fn watch_shader<P: AsRef<Path>>(&mut self, handle: &Handle<Shader>, path: P) {
+        let path = path.as_ref().to_owned();
+        let sources = self.get(handle).map(|shader: &Shader| shader.sources.clone()).unwrap_or_else(|| vec![path.clone()]);
+        let weak = handle.downgrade();
+
+        self.watch_sources(path.to_string_lossy().to_string(), &sources, Box::new(move |renderer, resources| {
+            let shader = match weak.get(resources) {
+                Some(current) => current.reload(renderer, &path)?,
+                None => Shader::load(renderer, &path, Some(vec![]))?,
+            };
+            let sources = shader.sources.clone();
+            weak.set(resources, shader);
+            Ok(sources)
+        }), alive(handle));
+    }
+
+    /// Reloads a texture loaded by `load_texture`.
+    pub fn watch_texture<P: AsRef<Path>>(&mut self, handle: &Handle<Texture>, path: P, flip_y: bool) {
+        let path = path.as_ref().to_owned();
+        let label = path.to_string_lossy().to_string();
+        let sources = [path.clone()];
+        let weak = handle.downgrade();
+
+        self.watch_sources(label.clone(), &sources, Box::new(move |renderer, resources| {
+            let texture = DecodedTexture::read(&path)?.upload(renderer, resources, Some(&label), flip_y)?;
+            weak.set(resources, texture);
+            register_texture(resources, &weak, &path, flip_y);
+            Ok(vec![path.clone()])
+        }), alive(handle));
+    }
+
+    /// Reloads a model loaded by `load_obj` when its obj, mtl or texture files change.
+    pub fn watch_obj<P: AsRef<Path>>(&mut self, handle: &Handle<Model>, path: P) {
+        let path = path.as_ref().to_owned();
+        let sources = ObjData::parse(&path).map(|obj| obj.sources()).unwrap_or_else(|_| vec![path.clone()]);
+        let weak = handle.downgrade();
+
+        self.watch_sources(path.to_string_lossy().to_string(), &sources, Box::new(move |renderer, resources| {
+            let obj = ObjData::read(&path)?;
+            let sources = obj.sources();
+            let model = obj.build(renderer, resources, false)?;
+            weak.set(resources, model);
+            Ok(sources)
+        }), alive(handle));
+    }
+
+    /// Reloads the models and animation clips of a scene loaded by `load_gltf`, matched by their order in the file.
+    /// Nodes, lights and cameras aren't reloaded.
+    pub fn watch_gltf<P: AsRef<Path>>(&mut self, node: &Node, path: P, scene: Option<usize>) {
+        let path = path.as_ref().to_owned();
+        let sources = gltf_sources(&path).unwrap_or_else(|_| vec![path.clone()]);
+
+        let (models, clips) = scene_assets(node);
+        let models: Vec<_> = models.iter().map(Handle::downgrade).collect();
+        let clips: Vec<_> = clips.iter().map(Handle::downgrade).collect();
+        let alive_models = models.clone();
+
+        self.watch_sources(path.to_string_lossy().to_string(), &sources, Box::new(move |renderer, resources| {
+            let reloaded = build_gltf(renderer, resources, GltfData::read(&path, &HashSet::new())?, scene, false)?;
+            let (new_models, new_clips) = scene_assets(&reloaded);
+
+            if new_models.len() != models.len() || new_clips.len() != clips.len() {
+                log::warn!("The scene in {path:?} changed, only models and animations that still match are reloaded.");
+            }
+
+            replace(resources, &models, &new_models);
+            replace(resources, &clips, &new_clips);
+
+            Ok(gltf_sources(&path)?)
+        }), Box::new(move || alive_models.iter().any(HandlesResource::valid)));
+    }
+
+    fn watch_sources(&mut self, label: String, sources: &[PathBuf], reload: Reload, alive: Box<dyn Fn() -> bool>) {
+        log::debug!("Watching {label:?}");
+
+        let sources = self.watcher.watch_paths(sources);
+
+        self.watcher.assets.push(WatchedAsset {
+            label,
+            sources,
+            reload,
+            alive,
+        });
+    }
+
+    /// Reloads assets whose files changed, returns whether any were.
+    pub(crate) fn reload_assets(&mut self, renderer: &Renderer) -> bool {
+        let changed = self.watcher.changed();
+
+        if changed.is_empty() {
+            return false;
+        }
+
+        // reloading may watch more assets
+        let mut assets = std::mem::take(&mut self.watcher.assets);
+        let mut reloaded = false;
+
+        for asset in &mut assets {
+            if asset.sources.is_disjoint(&changed) || !(asset.alive)() {
+                continue;
+            }
+
+            log::info!("Reloading {:?}", asset.label);
+
+            match (asset.reload)(renderer, self) {
+                Ok(sources) => {
+                    asset.sources = self.watcher.watch_paths(&sources);
+                    reloaded = true;
+                },
+                Err(err) => log::error!("Failed to reload {:?}: {err}", asset.label),
+            }
+        }
+
+        assets.append(&mut self.watcher.assets);
+        self.watcher.assets = assets;
+
+        reloaded
+    }
+}
+
+fn alive<T: 'static>(handle: &Handle<T>) -> Box<dyn Fn() -> bool> {
+    let weak = handle.downgrade();
+    Box::new(move || weak.valid())
+}
+
+/// Models in a consistent order, and the animation clips of a loaded glTF scene.
+fn scene_assets(node: &Node) -> (Vec<Handle<Model>>, Vec<Handle<AnimationClip>>) {
+    let models = node.find_all(|node| node.has_component::<Handle<Model>>()).into_iter()
+        .filter_map(|node| node.get_component::<Handle<Model>>().cloned())
+        .collect();
+
+    let clips = node.get_component::<AnimationPlayer>().map(|player| player.clips.clone()).unwrap_or_default();
+
+    (models, clips)
+}
+
+/// Moves the resources of `new` into `old`, pairwise.
+fn replace<T: 'static>(resources: &mut Resources, old: &[WeakHandle<T>], new: &[Handle<T>]) {
+    for (old, new) in old.iter().zip(new) {
+        if let Some(resource) = resources.remove(new) {
+            old.set(resources, resource);
+        }
+    }
+}
@@ -4,29 +4,32 @@ use image::GenericImageView;
 
 use crate::renderer::{Renderer, BindingHolder, Shader, PipelineProperties};
 
-use super::{Handle, Resources};
+use super::{Handle, Resources, CompressedImage, CompressedImageError};
 
 pub struct Texture {
     pub(crate) texture: wgpu::Texture,
     pub(crate) size: glam::UVec2,
     pub(crate) view: Handle<wgpu::TextureView>,
     // pub(crate) sampler: Handle<wgpu::Sampler>,
+    /// Estimated bytes of every mip level
+    pub(crate) gpu_memory: u64,
 }
 
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+    pub const BRDF_LUT_SIZE: u32 = 256;
 
-    pub(crate) fn create_depth_texture(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration, label: &str) -> wgpu::TextureView {
+    pub(crate) fn create_depth_texture(device: &wgpu::Device, width: u32, height: u32, sample_count: u32, label: &str) -> wgpu::TextureView {
         let size = wgpu::Extent3d {
-            width: config.width,
-            height: config.height,
+            width,
+            height,
             depth_or_array_layers: 1,
         };
         let desc = wgpu::TextureDescriptor {
             label: Some(label),
             size,
             mip_level_count: 1,
-            sample_count: 1,
+            sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: Texture::DEPTH_FORMAT,
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
@@ -49,12 +52,13 @@ impl Texture {
         flip_y: bool,
     ) -> Texture {
         let format_desc = format.describe();
+        if format_desc.block_dimensions != (1,1) {
+            let image = CompressedImage { format, width: dimensions.0, height: dimensions.1, levels: vec![bytes.to_vec()] };
+            return Texture::from_compressed(renderer, resources, &image, label, flip_y).expect("Failed to load compressed texture.");
+        }
         if !format_desc.required_features.is_empty() {
             panic!("Given image format has feature requirements not met by renderer.");
         }
-        if format_desc.block_dimensions != (1,1) {
-            panic!("Compressed textures not supported.");
-        }
 
         // srgb will be calculated in the shader
         let format = match format {
@@ -142,15 +146,123 @@ impl Texture {
         let dest_texture = Texture::generate_mipmaps(renderer, resources, source_texture, format, size);
         let dest_view = dest_texture.create_view(&wgpu::TextureViewDescriptor::default());
         let dest_view = resources.store(dest_view);
+
+        let gpu_memory = (0..Texture::mip_level_count(size)).map(|level| {
+            (size.width >> level).max(1) as u64 * (size.height >> level).max(1) as u64 * bytes_per_pixel as u64
+        }).sum();
         
         Texture {
             texture: dest_texture,
             size: dimensions.into(),
             view: dest_view,
             // sampler,
+            gpu_memory,
         }
     }
 
+    /// Uploads `image` with its mip levels, or generates them if it only has one.
+    /// Block compressed formats the device doesn't support, and ones that need flipping, are decoded on the CPU first.
+    pub fn from_compressed(
+        renderer: &Renderer,
+        resources: &mut Resources,
+        image: &CompressedImage,
+        label: Option<&str>,
+        flip_y: bool,
+    ) -> Result<Texture, CompressedImageError> {
+        let format_desc = image.format.describe();
+        let (block_width, block_height) = (format_desc.block_dimensions.0 as u32, format_desc.block_dimensions.1 as u32);
+
+        if image.is_block_compressed() {
+            // compressed textures must be a whole number of blocks
+            let supported = renderer.features().contains(format_desc.required_features)
+                && image.width % block_width == 0 && image.height % block_height == 0
+                // wgpu's GL backend uploads Etc2Rgba8Unorm as its srgb variant
+                && !(renderer.backend == wgpu::Backend::Gl
+                    && matches!(image.format, wgpu::TextureFormat::Etc2Rgba8Unorm | wgpu::TextureFormat::Etc2Rgba8UnormSrgb));
+
+            if !supported || flip_y {
+                log::debug!("Decoding {:?} texture {label:?} on the CPU", image.format);
+                return Texture::from_compressed(renderer, resources, &image.decompress()?, label, flip_y);
+            }
+        } else if image.levels.len() == 1 {
+            return Ok(Texture::from_bytes(renderer, resources, &image.levels[0], (image.width, image.height), image.format, label, flip_y));
+        }
+
+        // srgb will be calculated in the shader
+        let format = match image.format {
+            wgpu::TextureFormat::Rgba8UnormSrgb => wgpu::TextureFormat::Rgba8Unorm,
+            wgpu::TextureFormat::Bgra8UnormSrgb => wgpu::TextureFormat::Bgra8Unorm,
+            wgpu::TextureFormat::Bc1RgbaUnormSrgb => wgpu::TextureFormat::Bc1RgbaUnorm,
+            wgpu::TextureFormat::Bc2RgbaUnormSrgb => wgpu::TextureFormat::Bc2RgbaUnorm,
+            wgpu::TextureFormat::Bc3RgbaUnormSrgb => wgpu::TextureFormat::Bc3RgbaUnorm,
+            wgpu::TextureFormat::Bc7RgbaUnormSrgb => wgpu::TextureFormat::Bc7RgbaUnorm,
+            wgpu::TextureFormat::Etc2Rgb8UnormSrgb => wgpu::TextureFormat::Etc2Rgb8Unorm,
+            wgpu::TextureFormat::Etc2Rgb8A1UnormSrgb => wgpu::TextureFormat::Etc2Rgb8A1Unorm,
+            wgpu::TextureFormat::Etc2Rgba8UnormSrgb => wgpu::TextureFormat::Etc2Rgba8Unorm,
+            wgpu::TextureFormat::Astc { block, channel: wgpu::AstcChannel::UnormSrgb } => wgpu::TextureFormat::Astc { block, channel: wgpu::AstcChannel::Unorm },
+            format => format,
+        };
+
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label,
+            size: wgpu::Extent3d {
+                width: image.width,
+                height: image.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: image.levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+
+        for (level, data) in image.levels.iter().enumerate() {
+            let (width, height) = image.level_dimensions(level as u32);
+            let (blocks_x, blocks_y) = ((width + block_width - 1) / block_width, (height + block_height - 1) / block_height);
+            let bytes_per_row = blocks_x * format_desc.block_size as u32;
+
+            let flipped;
+            let data = if flip_y {
+                flipped = data.chunks(bytes_per_row as usize).take(blocks_y as usize).rev().flatten().copied().collect::<Vec<_>>();
+                &flipped
+            } else {
+                data
+            };
+
+            // levels smaller than a block are copied at their physical (whole block) size
+            renderer.queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(bytes_per_row),
+                    rows_per_image: NonZeroU32::new(blocks_y),
+                },
+                wgpu::Extent3d {
+                    width: blocks_x * block_width,
+                    height: blocks_y * block_height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let view = resources.store(view);
+
+        Ok(Texture {
+            texture,
+            size: glam::uvec2(image.width, image.height),
+            view,
+            gpu_memory: image.levels.iter().map(|level| level.len() as u64).sum(),
+        })
+    }
+
     pub fn from_image(
         renderer: &Renderer,
         resources: &mut Resources,
@@ -262,9 +374,15 @@ impl Texture {
             texture,
             size: glam::UVec2::ONE,
             view,
+            gpu_memory: 4,
         }
     }
 
+    /// Estimated bytes of the texture and its mip levels.
+    pub fn gpu_memory(&self) -> u64 {
+        self.gpu_memory
+    }
+
     pub(crate) fn binding_types() -> Vec<wgpu::BindingType> {
         vec![
             wgpu::BindingType::Texture {
@@ -280,6 +398,96 @@ impl Texture {
         BindingHolder::Texture(self.view.clone())
     }
 
+    /// Split sum BRDF integration lookup table for specular image based lighting, indexed by (n.v, roughness).
+    pub fn brdf_lut(renderer: &Renderer, resources: &mut Resources) -> Texture {
+        let format = wgpu::TextureFormat::Rg16Float;
+        let size = Texture::BRDF_LUT_SIZE;
+
+        let index = PipelineProperties {
+            transparent: false, double_sided: false,
+            colour_format: Some(format), depth_format: None, sample_count: 1, weight_format: None,
+        };
+
+        let mut shader = Shader::from_resource(renderer, "pipelines/builtin/brdf_lut.ron").expect("BRDF LUT shader not present.");
+        shader.prepare_pipeline(renderer, index);
+
+        let texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("BRDF LUT"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = renderer.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &shader.bind_group_layout,
+            entries: &[],
+        });
+
+        let mut encoder = renderer.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            render_pass.set_pipeline(shader.get_pipeline(index).unwrap());
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        renderer.queue.submit(std::iter::once(encoder.finish()));
+
+        let view = resources.store(view);
+
+        Texture {
+            texture,
+            size: glam::uvec2(size, size),
+            view,
+            gpu_memory: size as u64 * size as u64 * format.describe().block_size as u64,
+        }
+    }
+
+    /// Colour grading lookup table which leaves colours unchanged, `size` slices of `size`x`size` laid out horizontally.
+    /// Red increases left to right within a slice, green top to bottom and blue from slice to slice.
+    pub fn identity_lut(renderer: &Renderer, resources: &mut Resources, size: u32) -> Texture {
+        let max = (size - 1) as f32;
+        let mut bytes = vec![];
+
+        for g in 0..size {
+            for b in 0..size {
+                for r in 0..size {
+                    bytes.extend([r, g, b].map(|channel| (channel as f32 / max * 255.0).round() as u8));
+                    bytes.push(255);
+                }
+            }
+        }
+
+        Texture::from_bytes(renderer, resources, &bytes, (size * size, size), wgpu::TextureFormat::Rgba8Unorm, Some("Identity LUT"), false)
+    }
+
+    /// Levels of the chain `generate_mipmaps` creates.
+    fn mip_level_count(size: wgpu::Extent3d) -> u32 {
+        ((size.width as f32).log2().max((size.height as f32).log2()).ceil() as u32).max(1)
+    }
+
     pub(crate) fn generate_mipmaps(renderer: &Renderer, resources: &mut Resources, source_texture: wgpu::Texture, format: wgpu::TextureFormat, size: wgpu::Extent3d) -> wgpu::Texture {
         let source_view = source_texture.create_view(&wgpu::TextureViewDescriptor::default());
         let source_sampler = renderer.device.create_sampler(
@@ -292,20 +500,20 @@ impl Texture {
         let shader = if let Some(shader) = resources.get_engine_global_mut::<Shader>("texture::mipmap_pipeline") {
             shader.prepare_pipeline(renderer, PipelineProperties {
                 transparent: false, double_sided: false,
-                colour_format: format, depth_format: None,
+                colour_format: Some(format), depth_format: None, sample_count: 1, weight_format: None,
             });
             &*shader
         } else {
             let mut shader = Shader::from_resource(renderer, "pipelines/builtin/texture_mipmaps.ron").expect("Mipmap shader not present.");
             shader.prepare_pipeline(renderer, PipelineProperties {
                 transparent: false, double_sided: false,
-                colour_format: format, depth_format: None,
+                colour_format: Some(format), depth_format: None, sample_count: 1, weight_format: None,
             });
             resources.set_engine_global("texture::mipmap_pipeline", shader);
             resources.get_engine_global::<Shader>("texture::mipmap_pipeline").expect("unreachable")
         };
 
-        let mip_level_count = ((size.width as f32).log2().max((size.height as f32).log2()).ceil() as u32).max(1);
+        let mip_level_count = Texture::mip_level_count(size);
 
         let dest_texture = renderer.device.create_texture(&wgpu::TextureDescriptor {
             label: None,
@@ -363,7 +571,7 @@ impl Texture {
                 });
                 render_pass.set_pipeline(shader.get_pipeline(PipelineProperties {
                     transparent: false, double_sided: false,
-                    colour_format: format, depth_format: None,
+                    colour_format: Some(format), depth_format: None, sample_count: 1, weight_format: None,
                 }).unwrap());
                 render_pass.set_bind_group(0, &bind_group, &[]);
                 render_pass.draw(0..3, 0..1);
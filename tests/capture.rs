@@ -0,0 +1,23 @@
+use std::path::Path;
+
+use tritium::renderer::save_image;
+
+/// Float captures are linear, 8 and 16 bit files are srgb encoded like captures of srgb targets.
+#[test]
+fn float_images_are_srgb_encoded() {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capture");
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let linear = image::DynamicImage::ImageRgba32F(image::Rgba32FImage::from_pixel(2, 2, image::Rgba([0.5, 0.5, 0.5, 1.0])));
+
+    save_image(&linear, directory.join("grey.png")).unwrap();
+    let png = image::open(directory.join("grey.png")).unwrap().to_rgba32f();
+    // 0.5 is 0.7354 in srgb
+    assert!((png.get_pixel(0, 0).0[0] - 0.7354).abs() < 0.001);
+    assert_eq!(png.get_pixel(0, 0).0[3], 1.0);
+
+    save_image(&linear, directory.join("grey.hdr")).unwrap();
+    let file = std::io::BufReader::new(std::fs::File::open(directory.join("grey.hdr")).unwrap());
+    let hdr = image::codecs::hdr::HdrDecoder::new(file).unwrap().read_image_hdr().unwrap();
+    assert!((hdr[0].0[0] - 0.5).abs() < 0.01);
+}
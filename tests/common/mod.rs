@@ -0,0 +1,207 @@
+//! Golden image harness: renders scenes offscreen and compares them against the references in `tests/golden`.
+//!
+//! Set `TRITIUM_BLESS=1` to (re)write the references from the current output.
+//! On failure the expected, actual and diff images are written to `target/tmp/golden/<name>/`.
+
+// each test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+use tritium::{HeadlessApp, node::Node, renderer::Renderer, resource::{self, Resources, CubeMap, CubeSampler}};
+
+pub const WIDTH: u32 = 256;
+pub const HEIGHT: u32 = 192;
+
+/// Maximum YIQ colour delta between two 8-bit pixels, see "Measuring perceived color difference using YIQ NTSC transmission color space in mobile applications" (Kotsarenko & Ramos).
+const MAX_DELTA: f32 = 35215.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Tolerance {
+    /// Perceptual difference (0..1) above which a pixel counts as different.
+    pub threshold: f32,
+    /// Fraction of pixels (0..1) allowed to differ.
+    pub max_differing: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Tolerance {
+        Tolerance {
+            threshold: 0.1,
+            max_differing: 0.001,
+        }
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn diff_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden").join(name)
+}
+
+fn blessing() -> bool {
+    std::env::var("TRITIUM_BLESS").map(|value| value != "0").unwrap_or(false)
+}
+
+/// Renders `frames` frames of a scene, returns `None` if GPU tests are skipped, see `headless_app`.
+pub fn render<F: FnMut(&Renderer, &mut Resources) -> Node>(frames: usize, scene_builder: F) -> Option<image::RgbaImage> {
+    render_internal(frames, None, 1, scene_builder)
+}
+
+/// Like `render`, drawing with the render graph at `graph` instead of the default.
+pub fn render_with_graph<F: FnMut(&Renderer, &mut Resources) -> Node>(frames: usize, graph: &Path, scene_builder: F) -> Option<image::RgbaImage> {
+    render_internal(frames, Some(graph), 1, scene_builder)
+}
+
+/// Like `render`, with `sample_count` MSAA samples.
+pub fn render_multisampled<F: FnMut(&Renderer, &mut Resources) -> Node>(frames: usize, sample_count: u32, scene_builder: F) -> Option<image::RgbaImage> {
+    render_internal(frames, None, sample_count, scene_builder)
+}
+
+/// Creates a `width`x`height` headless app, golden images are `WIDTH`x`HEIGHT`.
+/// Panics if no adapter is available, unless `TRITIUM_SKIP_GPU_TESTS` is set, then it returns `None`.
+pub fn headless_app<F: FnMut(&Renderer, &mut Resources) -> Node>(width: u32, height: u32, scene_builder: F) -> Option<HeadlessApp> {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+    match runtime.block_on(HeadlessApp::new(width, height, scene_builder)) {
+        Ok(app) => Some(app),
+        Err(err) if skip_gpu_tests() => {
+            eprintln!("Skipping, failed to create headless renderer: {err}");
+            None
+        },
+        Err(err) => panic!("Failed to create headless renderer: {err}. Set TRITIUM_SKIP_GPU_TESTS=1 to skip tests that need a GPU."),
+    }
+}
+
+fn skip_gpu_tests() -> bool {
+    std::env::var("TRITIUM_SKIP_GPU_TESTS").map(|value| value != "0").unwrap_or(false)
+}
+
+fn render_internal<F: FnMut(&Renderer, &mut Resources) -> Node>(frames: usize, graph: Option<&Path>, sample_count: u32, scene_builder: F) -> Option<image::RgbaImage> {
+    let mut app = headless_app(WIDTH, HEIGHT, scene_builder)?;
+
+    if let Some(graph) = graph {
+        app.load_render_graph(graph).unwrap();
+    }
+
+    app.set_sample_count(sample_count);
+
+    app.run_frames(frames);
+
+    Some(app.capture_frame().expect("Failed to capture frame.").to_rgba8())
+}
+
+/// Sets `tritium::irradiance` and `tritium::reflections` to a small procedural sky, so scenes don't depend on `res/`.
+pub fn procedural_environment(renderer: &Renderer, resources: &mut Resources) {
+    let sky = image::Rgb32FImage::from_fn(64, 32, |_, y| {
+        let t = y as f32 / 31.0;
+        if t < 0.5 {
+            image::Rgb([0.5 - 0.3 * t, 0.6 - 0.3 * t, 0.9 - 0.4 * t])
+        } else {
+            image::Rgb([0.25, 0.2, 0.15])
+        }
+    });
+
+    let cubemap = CubeMap::from_image_equirectangular(renderer, resources, &image::DynamicImage::ImageRgb32F(sky), Some("Procedural Sky")).unwrap();
+    let cubemap = resources.store(cubemap);
+
+    let reflections = CubeMap::prefilter_specular(renderer, resources, &cubemap, Some("Procedural Sky Reflections"));
+    let reflections = resources.store(reflections);
+    let reflections = CubeSampler::new(renderer, resources, reflections, wgpu::AddressMode::ClampToEdge, wgpu::AddressMode::ClampToEdge, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear, wgpu::FilterMode::Linear);
+
+    let irradiance = CubeMap::convolve_irradiance(renderer, resources, &cubemap, Some("Procedural Sky Irradiance"));
+    let irradiance = resources.store(irradiance);
+    let irradiance = CubeSampler::new_default(renderer, resources, irradiance);
+
+    resources.set_global("tritium::irradiance", irradiance);
+    resources.set_global("tritium::reflections", reflections);
+}
+
+pub fn main_pipeline(renderer: &Renderer, resources: &mut Resources) -> resource::Handle<tritium::renderer::Shader> {
+    let shader = tritium::renderer::Shader::from_resource(renderer, Path::new(env!("CARGO_MANIFEST_DIR")).join("pipelines/main.ron")).unwrap();
+    resources.store(shader)
+}
+
+pub fn skinned_pipeline(renderer: &Renderer, resources: &mut Resources) -> resource::Handle<tritium::renderer::Shader> {
+    let shader = tritium::renderer::Shader::from_resource(renderer, Path::new(env!("CARGO_MANIFEST_DIR")).join("pipelines/skinned.ron")).unwrap();
+    resources.store(shader)
+}
+
+pub fn morph_pipeline(renderer: &Renderer, resources: &mut Resources) -> resource::Handle<tritium::renderer::Shader> {
+    let shader = tritium::renderer::Shader::from_resource(renderer, Path::new(env!("CARGO_MANIFEST_DIR")).join("pipelines/morph.ron")).unwrap();
+    resources.store(shader)
+}
+
+fn delta(a: &image::Rgba<u8>, b: &image::Rgba<u8>) -> f32 {
+    // blend over white, so differences in alpha are visible
+    let blend = |pixel: &image::Rgba<u8>| {
+        let alpha = pixel[3] as f32 / 255.0;
+        [0, 1, 2].map(|i| 255.0 + (pixel[i] as f32 - 255.0) * alpha)
+    };
+
+    let [r1, g1, b1] = blend(a);
+    let [r2, g2, b2] = blend(b);
+
+    let y = |r: f32, g: f32, b: f32| r * 0.29889531 + g * 0.58662247 + b * 0.11448223;
+    let i = |r: f32, g: f32, b: f32| r * 0.59597799 - g * 0.27417610 - b * 0.32180189;
+    let q = |r: f32, g: f32, b: f32| r * 0.21147017 - g * 0.52261711 + b * 0.31114694;
+
+    let dy = y(r1, g1, b1) - y(r2, g2, b2);
+    let di = i(r1, g1, b1) - i(r2, g2, b2);
+    let dq = q(r1, g1, b1) - q(r2, g2, b2);
+
+    0.5053 * dy * dy + 0.299 * di * di + 0.1957 * dq * dq
+}
+
+/// Compares `actual` against the reference image `tests/golden/<name>.png`.
+pub fn assert_golden(name: &str, actual: &image::RgbaImage, tolerance: Tolerance) {
+    let reference_path = golden_dir().join(format!("{name}.png"));
+
+    if blessing() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&reference_path).unwrap();
+        eprintln!("Blessed {reference_path:?}");
+        return;
+    }
+
+    let expected = match image::open(&reference_path) {
+        Ok(image) => image.to_rgba8(),
+        Err(err) => panic!("Failed to open reference image {reference_path:?} ({err}), run with TRITIUM_BLESS=1 to create it."),
+    };
+
+    assert_eq!(expected.dimensions(), actual.dimensions(), "Reference image {reference_path:?} has different dimensions.");
+
+    let max_delta = MAX_DELTA * tolerance.threshold * tolerance.threshold;
+    let mut diff = image::RgbaImage::new(actual.width(), actual.height());
+    let mut differing = 0;
+
+    for ((expected, actual), diff) in expected.pixels().zip(actual.pixels()).zip(diff.pixels_mut()) {
+        if delta(expected, actual) > max_delta {
+            differing += 1;
+            *diff = image::Rgba([255, 0, 0, 255]);
+        } else {
+            // faded greyscale of the reference, for context
+            let luma = (expected[0] as f32 * 0.299 + expected[1] as f32 * 0.587 + expected[2] as f32 * 0.114) as u8;
+            let faded = 255 - (255 - luma) / 4;
+            *diff = image::Rgba([faded, faded, faded, 255]);
+        }
+    }
+
+    let fraction = differing as f32 / (actual.width() * actual.height()) as f32;
+
+    if fraction > tolerance.max_differing {
+        let dir = diff_dir(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        expected.save(dir.join("expected.png")).unwrap();
+        actual.save(dir.join("actual.png")).unwrap();
+        diff.save(dir.join("diff.png")).unwrap();
+
+        panic!(
+            "Golden image '{name}' differs: {differing} pixels ({:.3}%, allowed {:.3}%). Images written to {dir:?}.",
+            fraction * 100.0,
+            tolerance.max_differing * 100.0,
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use tritium::resource::{CompressedImage, CompressedImageError};
+use wgpu::TextureFormat;
+
+const BC1_RGBA_UNORM: u32 = 133;
+const BC6H_UFLOAT: u32 = 143;
+const R8G8B8A8_UNORM: u32 = 37;
+const UNDEFINED: u32 = 0;
+
+const BASIS_LZ: u32 = 1;
+const ZSTANDARD: u32 = 2;
+
+/// A 2D KTX2 file: header, level index, data format descriptor `dfd` (without its length) and `levels`.
+fn ktx2(format: u32, (width, height): (u32, u32), supercompression: u32, dfd: &[u8], levels: &[&[u8]]) -> Vec<u8> {
+    let dfd_offset = 80 + 24 * levels.len();
+    let mut level_offset = dfd_offset + 4 + dfd.len();
+
+    let mut bytes = vec![0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a];
+    for value in [format, 1, width, height, 0, 0, 1, levels.len() as u32, supercompression, dfd_offset as u32, 4 + dfd.len() as u32, 0, 0] {
+        bytes.extend(value.to_le_bytes());
+    }
+    bytes.extend([0; 16]);
+
+    for level in levels {
+        bytes.extend((level_offset as u64).to_le_bytes());
+        bytes.extend((level.len() as u64).to_le_bytes());
+        bytes.extend((level.len() as u64).to_le_bytes());
+        level_offset += level.len();
+    }
+
+    bytes.extend((4 + dfd.len() as u32).to_le_bytes());
+    bytes.extend(dfd);
+    for level in levels {
+        bytes.extend(*level);
+    }
+    bytes
+}
+
+/// A basic data format descriptor block with colour model `model` and no samples.
+fn basic_dfd(model: u8) -> Vec<u8> {
+    let mut dfd = vec![];
+    dfd.extend(0u32.to_le_bytes());
+    dfd.extend((2u32 | 24 << 16).to_le_bytes());
+    dfd.extend([model, 1, 2, 0]);
+    dfd.extend([0; 12]);
+    dfd
+}
+
+/// `data` as a single raw (uncompressed) block zstd frame.
+fn zstd_raw(data: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0x20, data.len() as u8];
+    frame.extend(&(1 | (data.len() as u32) << 3).to_le_bytes()[..3]);
+    frame.extend(data);
+    frame
+}
+
+fn image(format: TextureFormat, width: u32, height: u32, block: &[u8]) -> CompressedImage {
+    CompressedImage { format, width, height, levels: vec![block.to_vec()] }
+}
+
+/// Texels of the first level, `Rgba8Unorm`.
+fn texels(image: &CompressedImage) -> Vec<[u8; 4]> {
+    let decoded = image.decompress().unwrap();
+    assert_eq!(decoded.format, TextureFormat::Rgba8Unorm);
+    decoded.levels[0].chunks_exact(4).map(|texel| texel.try_into().unwrap()).collect()
+}
+
+/// Texels of the first level, `Rgba16Float`.
+fn signed_texels(image: &CompressedImage) -> Vec<[f32; 4]> {
+    let decoded = image.decompress().unwrap();
+    assert_eq!(decoded.format, TextureFormat::Rgba16Float);
+    let channels: Vec<f32> = decoded.levels[0].chunks_exact(2).map(|channel| half::f16::from_ne_bytes([channel[0], channel[1]]).to_f32()).collect();
+    channels.chunks_exact(4).map(|texel| texel.try_into().unwrap()).collect()
+}
+
+#[test]
+fn bc1() {
+    // red and blue endpoints, the first row uses each palette entry
+    let opaque = texels(&image(TextureFormat::Bc1RgbaUnorm, 4, 4, &[0x00, 0xf8, 0x1f, 0x00, 0xe4, 0, 0, 0]));
+    assert_eq!(opaque[..5], [[255, 0, 0, 255], [0, 0, 255, 255], [170, 0, 85, 255], [85, 0, 170, 255], [255, 0, 0, 255]]);
+
+    // endpoints in increasing order switch to three colours and transparent black
+    let punch_through = texels(&image(TextureFormat::Bc1RgbaUnorm, 4, 4, &[0x1f, 0x00, 0x00, 0xf8, 0x03, 0, 0, 0]));
+    assert_eq!(punch_through[..2], [[0, 0, 0, 0], [0, 0, 255, 255]]);
+
+    // partial blocks are cropped
+    let cropped = texels(&image(TextureFormat::Bc1RgbaUnormSrgb, 2, 1, &[0x00, 0xf8, 0x1f, 0x00, 0xe4, 0, 0, 0]));
+    assert_eq!(cropped, [[255, 0, 0, 255], [0, 0, 255, 255]]);
+}
+
+#[test]
+fn bc4_unsigned_and_signed() {
+    let unsigned = texels(&image(TextureFormat::Bc4RUnorm, 4, 4, &[255, 0, 0x08, 0, 0, 0, 0, 0]));
+    assert_eq!(unsigned[..3], [[255, 0, 0, 255], [0, 0, 0, 255], [255, 0, 0, 255]]);
+
+    // -128 is clamped to -127
+    let signed = signed_texels(&image(TextureFormat::Bc4RSnorm, 4, 4, &[0x7f, 0x80, 0x08, 0, 0, 0, 0, 0]));
+    assert_eq!(signed[..2], [[1.0, 0.0, 0.0, 1.0], [-1.0, 0.0, 0.0, 1.0]]);
+}
+
+#[test]
+fn bc7_mode_6() {
+    // all endpoint and p bits set, every index zero
+    let white = texels(&image(TextureFormat::Bc7RgbaUnorm, 4, 4, &[0xc0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0, 0, 0, 0, 0, 0, 0]));
+    assert!(white.iter().all(|&texel| texel == [255; 4]));
+}
+
+#[test]
+fn etc2_and_eac() {
+    // individual mode, base colour 0x88 plus the first modifier of table 0
+    let colour = [0x88, 0x88, 0x88, 0, 0, 0, 0, 0];
+    let rgb = texels(&image(TextureFormat::Etc2Rgb8Unorm, 4, 4, &colour));
+    assert!(rgb.iter().all(|&texel| texel == [138, 138, 138, 255]));
+
+    // base 128 with multiplier 1 and the first modifier of table 0
+    let alpha = [128, 0x10, 0, 0, 0, 0, 0, 0];
+    let rgba = texels(&image(TextureFormat::Etc2Rgba8Unorm, 4, 4, &[alpha, colour].concat()));
+    assert!(rgba.iter().all(|&texel| texel == [138, 138, 138, 125]));
+
+    // (128 * 8 + 4 - 3 * 8) / 2047
+    let red = texels(&image(TextureFormat::EacR11Unorm, 4, 4, &alpha));
+    assert!(red.iter().all(|&texel| texel == [125, 0, 0, 255]));
+}
+
+#[test]
+fn no_decoder() {
+    let astc = TextureFormat::Astc { block: wgpu::AstcBlock::B4x4, channel: wgpu::AstcChannel::Unorm };
+    assert!(matches!(image(astc, 4, 4, &[0; 16]).decompress(), Err(CompressedImageError::NoDecoder(_))));
+    assert!(matches!(image(TextureFormat::Bc6hRgbUfloat, 4, 4, &[0; 16]).decompress(), Err(CompressedImageError::NoDecoder(_))));
+
+    // uncompressed images pass through
+    let rgba = image(TextureFormat::Rgba8Unorm, 1, 1, &[1, 2, 3, 4]);
+    assert_eq!(rgba.decompress().unwrap().levels, [[1, 2, 3, 4]]);
+}
+
+#[test]
+fn ktx2_levels() {
+    let bytes = ktx2(BC1_RGBA_UNORM, (8, 6), 0, &[], &[&[1; 32], &[2; 8], &[3; 8], &[4; 8]]);
+    let image = CompressedImage::from_container(&bytes).unwrap().unwrap();
+
+    assert_eq!(image.format, TextureFormat::Bc1RgbaUnorm);
+    assert_eq!((image.width, image.height), (8, 6));
+    assert_eq!(image.levels.len(), 4);
+    assert_eq!(image.level_dimensions(2), (2, 1));
+    assert_eq!(image.levels[1], [2; 8]);
+
+    let decoded = image.decompress().unwrap();
+    assert_eq!(decoded.levels.iter().map(Vec::len).collect::<Vec<_>>(), [8 * 6 * 4, 4 * 3 * 4, 2 * 4, 4]);
+}
+
+#[test]
+fn ktx2_zstandard() {
+    let texel = [10, 20, 30, 40];
+    let bytes = ktx2(R8G8B8A8_UNORM, (1, 1), ZSTANDARD, &basic_dfd(1), &[&zstd_raw(&texel)]);
+    let image = CompressedImage::from_ktx2(&bytes).unwrap();
+
+    assert_eq!(image.format, TextureFormat::Rgba8Unorm);
+    assert_eq!(image.levels, [texel]);
+}
+
+#[test]
+fn ktx2_errors() {
+    // BasisLZ (ETC1S) and UASTC
+    let etc1s = ktx2(UNDEFINED, (4, 4), BASIS_LZ, &basic_dfd(163), &[&[0; 16]]);
+    assert!(matches!(CompressedImage::from_ktx2(&etc1s), Err(CompressedImageError::BasisUniversal)));
+    let uastc = ktx2(UNDEFINED, (4, 4), 0, &basic_dfd(166), &[&[0; 16]]);
+    assert!(matches!(CompressedImage::from_ktx2(&uastc), Err(CompressedImageError::BasisUniversal)));
+
+    let undefined = ktx2(UNDEFINED, (4, 4), 0, &basic_dfd(1), &[&[0; 16]]);
+    assert!(matches!(CompressedImage::from_ktx2(&undefined), Err(CompressedImageError::UnsupportedFormat(_))));
+
+    let truncated = ktx2(BC6H_UFLOAT, (8, 8), 0, &[], &[&[0; 16]]);
+    assert!(matches!(CompressedImage::from_ktx2(&truncated), Err(CompressedImageError::MissingData)));
+
+    assert!(matches!(CompressedImage::from_ktx2(&uastc[..40]), Err(CompressedImageError::Ktx2Error(_))));
+    assert!(CompressedImage::from_container(b"\x89PNG\r\n\x1a\n").is_none());
+}
+
+#[test]
+fn dds_levels() {
+    let mut dds = ddsfile::Dds::new_dxgi(ddsfile::NewDxgiParams {
+        height: 8,
+        width: 8,
+        depth: None,
+        format: ddsfile::DxgiFormat::BC3_UNorm_sRGB,
+        mipmap_levels: Some(4),
+        array_layers: None,
+        caps2: None,
+        is_cubemap: false,
+        resource_dimension: ddsfile::D3D10ResourceDimension::Texture2D,
+        alpha_mode: ddsfile::AlphaMode::Unknown,
+    }).unwrap();
+    dds.data = (0..dds.data.len()).map(|i| i as u8).collect();
+
+    let mut bytes = vec![];
+    dds.write(&mut bytes).unwrap();
+    let image = CompressedImage::from_container(&bytes).unwrap().unwrap();
+
+    assert_eq!(image.format, TextureFormat::Bc3RgbaUnormSrgb);
+    assert_eq!(image.levels.iter().map(Vec::len).collect::<Vec<_>>(), [64, 16, 16, 16]);
+    assert_eq!(image.levels[1][0], 64);
+
+    assert!(matches!(CompressedImage::from_dds(&bytes[..bytes.len() - 100]), Err(CompressedImageError::DdsError(_))));
+}
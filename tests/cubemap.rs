@@ -0,0 +1,66 @@
+use std::f32::consts::PI;
+
+use tritium::{node::Node, resource::{Cube, CubeMap, SphericalHarmonics}};
+
+mod common;
+
+/// A constant environment only has a DC term, which evaluates to its colour in every direction.
+#[test]
+fn constant_spherical_harmonics() {
+    let colour = glam::vec3(0.2, 0.4, 0.8);
+    let mut harmonics = None;
+
+    let app = common::headless_app(64, 64, |renderer, resources| {
+        let face = image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(16, 16, image::Rgba([51, 102, 204, 255])));
+        let cubemap = CubeMap::from_images(renderer, resources, Cube::from([&face; 6]), None, false).unwrap();
+        let cubemap = resources.store(cubemap);
+
+        harmonics = Some(CubeMap::spherical_harmonics(renderer, resources, &cubemap).unwrap());
+
+        Node::builder("root").build()
+    });
+
+    if app.is_none() {
+        return;
+    }
+
+    let harmonics: SphericalHarmonics = harmonics.unwrap();
+
+    // the DC basis is 1 / sqrt(4 PI)
+    assert!(harmonics.coefficients[0].abs_diff_eq(colour * (4.0 * PI).sqrt(), 0.01), "{:?}", harmonics.coefficients);
+    for coefficient in &harmonics.coefficients[1..] {
+        assert!(coefficient.abs_diff_eq(glam::Vec3::ZERO, 0.01), "{:?}", harmonics.coefficients);
+    }
+
+    for direction in [glam::Vec3::X, glam::Vec3::NEG_Y, glam::vec3(1.0, 2.0, -3.0)] {
+        assert!(harmonics.evaluate(direction).abs_diff_eq(colour, 0.01), "{direction}");
+    }
+}
+
+/// Bands are scaled by the clamped cosine's A_l = PI, 2 PI / 3 and PI / 4, divided by PI.
+#[test]
+fn convolve_cosine_bands() {
+    let harmonics = SphericalHarmonics {
+        coefficients: [glam::Vec3::ONE; 9],
+    };
+
+    let convolved = harmonics.convolve_cosine();
+
+    let bands = [PI, 2.0 * PI / 3.0, PI / 4.0];
+    for (i, coefficient) in convolved.coefficients.iter().enumerate() {
+        let band = match i {
+            0 => bands[0],
+            1..=3 => bands[1],
+            _ => bands[2],
+        };
+        assert!((*coefficient * PI).abs_diff_eq(glam::Vec3::splat(band), 1e-5), "{i}: {coefficient}");
+    }
+
+    // convolving a constant environment gives irradiance / PI, i.e. the same constant
+    let colour = glam::vec3(0.2, 0.4, 0.8);
+    let mut constant = SphericalHarmonics {
+        coefficients: [glam::Vec3::ZERO; 9],
+    };
+    constant.coefficients[0] = colour * (4.0 * PI).sqrt();
+    assert!(constant.convolve_cosine().evaluate(glam::Vec3::Z).abs_diff_eq(colour, 1e-5));
+}
@@ -0,0 +1,967 @@
+mod common;
+
+use common::Tolerance;
+use tritium::{camera::Camera, components::{AmbientLight, AnimationPlayer, MorphWeights, DirectionalLight, DirectionalShadows, InstancedModel, PointLight, PointShadows, PostProcessing, Skeleton, SortBias, SpotLight, Tonemapper, Transform}, engine::Rgba, node::Node, renderer::Renderer, resource::{self, AlphaMode, Handle, Material, Model, Resources, SpawnScene, Transparency}};
+
+fn camera(translation: glam::Vec3) -> Node {
+    Node::builder("camera")
+        .add_component(Transform::from_translation(translation))
+        .add_component(Camera::Perspective {
+            fovy: std::f32::consts::FRAC_PI_3,
+            znear: 0.1,
+            zfar: Some(100.0),
+        })
+        .build()
+}
+
+fn sun() -> Node {
+    Node::builder("sun")
+        .add_component(Transform::from_rotation(glam::Quat::from_axis_angle(glam::vec3(-2.0, 0.0, -1.0).normalize(), -std::f32::consts::PI / 2.9)))
+        .add_component(DirectionalLight::new(Rgba::new(1.0, 1.0, 1.0, 5.0)))
+        .build()
+}
+
+/// Wraps a scene with a sun and a camera, setting `current_camera`.
+fn scene(resources: &mut Resources, content: Node, camera_translation: glam::Vec3) -> Node {
+    let scene = Node::builder("scene")
+        .add_child(content)
+        .add_child(sun())
+        .add_child(camera(camera_translation))
+        .build();
+
+    resources.set_global("current_camera", scene.find_by_name("camera").unwrap().id());
+
+    scene
+}
+
+fn load_gltf_scene(renderer: &Renderer, resources: &mut Resources, path: &std::path::Path, camera_translation: glam::Vec3) -> Node {
+    common::procedural_environment(renderer, resources);
+    let pipeline = common::main_pipeline(renderer, resources);
+
+    let mut node = resource::load_gltf(renderer, resources, path, None).unwrap();
+    node.traverse_if_mut(&mut |node| node.has_component::<Handle<Model>>(), &mut |node| {
+        node.add_component(pipeline.clone());
+    });
+
+    scene(resources, node, camera_translation)
+}
+
+fn pbr_spheres(renderer: &Renderer, resources: &mut Resources) -> Node {
+    common::procedural_environment(renderer, resources);
+    let pipeline = common::main_pipeline(renderer, resources);
+
+    let mut spheres = Node::builder("spheres");
+
+    for y in 0..3 {
+        for x in 0..4 {
+            let material = Material::builder()
+                .albedo(Rgba::new(0.8, 0.2, 0.1, 1.0))
+                .metallic_factor(y as f32 / 2.0)
+                .roughness_factor((x as f32 + 1.0) / 4.0)
+                .build(renderer, resources);
+            let material = resources.store(material);
+
+            let sphere = Model::new_sphere(renderer, resources, Some(material), 32, resource::SphereUV::Cube);
+            let sphere = resources.store(sphere);
+
+            spheres = spheres.add_child(
+                Node::builder(&format!("sphere ({x}, {y})"))
+                .add_component(Transform::from_translation_scale(glam::vec3(x as f32 - 1.5, y as f32 - 1.0, 0.0) * 1.1, glam::Vec3::splat(0.5)))
+                .add_component(sphere)
+                .add_component(pipeline.clone())
+                .build()
+            );
+        }
+    }
+
+    let lights = Node::builder("lights")
+        .add_child(
+            Node::builder("point")
+            .add_component(Transform::from_translation(glam::vec3(1.0, 1.5, 2.0)))
+            .add_component(PointLight::new(Rgba::new(0.2, 0.4, 1.0, 4.0)))
+            .build()
+        )
+        .add_child(
+            Node::builder("ambient")
+            .add_component(AmbientLight::new(Rgba::new(1.0, 1.0, 1.0, 0.05)))
+            .build()
+        )
+        .build();
+
+    let content = Node::builder("content")
+        .add_child(spheres.build())
+        .add_child(lights)
+        .build();
+
+    scene(resources, content, glam::vec3(0.0, 0.0, 5.0))
+}
+
+#[test]
+fn procedural_pbr_spheres() {
+    let image = common::render(2, pbr_spheres);
+
+    if let Some(image) = image {
+        common::assert_golden("procedural_pbr_spheres", &image, Tolerance::default());
+    }
+}
+
+/// The default graph with a depth prepass, declared out of order, should match the default graph.
+#[test]
+fn render_graph_depth_prepass() {
+    let graph = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/depth_prepass.ron");
+    let image = common::render_with_graph(2, &graph, pbr_spheres);
+
+    if let Some(image) = image {
+        common::assert_golden("procedural_pbr_spheres", &image, Tolerance::default());
+    }
+}
+
+/// Thin bars at different angles, which alias without anti-aliasing.
+fn thin_bars(renderer: &Renderer, resources: &mut Resources, post_processing: PostProcessing) -> Node {
+    common::procedural_environment(renderer, resources);
+    let pipeline = common::main_pipeline(renderer, resources);
+
+    let material = Material::builder()
+        .albedo(Rgba::new(0.9, 0.85, 0.8, 1.0))
+        .roughness_factor(0.6)
+        .build(renderer, resources);
+    let material = resources.store(material);
+
+    let model = Model::new_plane(renderer, resources, Some(material));
+    let model = resources.store(model);
+
+    let mut content = Node::builder("content");
+    for i in 0..6 {
+        let angle = i as f32 * std::f32::consts::PI / 6.0 + 0.1;
+        let rotation = glam::Quat::from_rotation_z(angle) * glam::Quat::from_rotation_x(std::f32::consts::FRAC_PI_2);
+
+        content = content.add_child(
+            Node::builder(&format!("bar {i}"))
+            .add_component(Transform::new(glam::Vec3::ZERO, rotation, glam::vec3(1.5, 1.0, 0.01)))
+            .add_component(model.clone())
+            .add_component(pipeline.clone())
+            .build()
+        );
+    }
+
+    let mut scene = scene(resources, content.build(), glam::vec3(0.0, 0.0, 3.0));
+    scene.find_by_name_mut("camera").unwrap().add_component(post_processing);
+
+    scene
+}
+
+#[test]
+fn procedural_thin_bars_msaa() {
+    let image = common::render_multisampled(2, 4, |renderer, resources| thin_bars(renderer, resources, PostProcessing::default()));
+
+    if let Some(image) = image {
+        common::assert_golden("procedural_thin_bars_msaa", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn procedural_thin_bars_fxaa() {
+    let image = common::render(2, |renderer, resources| thin_bars(renderer, resources, PostProcessing::default().with_fxaa()));
+
+    if let Some(image) = image {
+        common::assert_golden("procedural_thin_bars_fxaa", &image, Tolerance::default());
+    }
+}
+
+/// Objects outside the camera frustum are culled without changing the image.
+#[test]
+fn frustum_culling() {
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, |renderer, resources| {
+        let pipeline = common::main_pipeline(renderer, resources);
+        let material = Material::builder().build(renderer, resources);
+        let material = resources.store(material);
+        let sphere = Model::new_sphere(renderer, resources, Some(material), 8, resource::SphereUV::Cube);
+        let sphere = resources.store(sphere);
+
+        let mut outside = Node::builder("outside");
+        // behind the camera, far to the side and below the scene, where they can't shadow it
+        for (i, translation) in [glam::vec3(0.0, -20.0, 10.0), glam::vec3(60.0, -20.0, 0.0), glam::vec3(0.0, -60.0, -5.0)].into_iter().enumerate() {
+            outside = outside.add_child(
+                Node::builder(&format!("sphere {i}"))
+                .add_component(Transform::from_translation(translation))
+                .add_component(sphere.clone())
+                .add_component(pipeline.clone())
+                .build()
+            );
+        }
+
+        Node::builder("root")
+            .add_child(pbr_spheres(renderer, resources))
+            .add_child(outside.build())
+            .build()
+    });
+
+    if let Some(mut app) = app {
+        app.run_frames(2);
+
+        let stats = app.render_stats();
+        assert_eq!(stats.objects, 15);
+        assert_eq!(stats.culled, 3);
+
+        let image = app.capture_frame().expect("Failed to capture frame.").to_rgba8();
+        common::assert_golden("procedural_pbr_spheres", &image, Tolerance::default());
+    }
+}
+
+/// Buffers and bind groups are reused between frames.
+#[test]
+fn persistent_bind_groups() {
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, pbr_spheres);
+
+    if let Some(mut app) = app {
+        app.run_frames(2);
+        app.run_frames(1);
+
+        let stats = app.render_stats();
+        assert_eq!(stats.bind_groups_created, 0);
+
+        let image = app.capture_frame().expect("Failed to capture frame.").to_rgba8();
+        common::assert_golden("procedural_pbr_spheres", &image, Tolerance::default());
+    }
+}
+
+/// Nodes sharing a model and shader are batched, as are the instances of an `InstancedModel`.
+#[test]
+fn procedural_instancing() {
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let sphere = |resources: &mut Resources, albedo| {
+            let material = Material::builder().albedo(albedo).roughness_factor(0.4).build(renderer, resources);
+            let material = resources.store(material);
+            let sphere = Model::new_sphere(renderer, resources, Some(material), 16, resource::SphereUV::Cube);
+            resources.store(sphere)
+        };
+
+        let mut row = Node::builder("row");
+        let red = sphere(resources, Rgba::new(0.8, 0.1, 0.1, 1.0));
+        for i in 0..10 {
+            row = row.add_child(
+                Node::builder(&format!("sphere {i}"))
+                .add_component(Transform::from_translation_scale(glam::vec3(i as f32 - 4.5, 1.0, -2.0), glam::Vec3::splat(0.4)))
+                .add_component(red.clone())
+                .add_component(pipeline.clone())
+                .build()
+            );
+        }
+
+        let instances = (0..64).map(|i| glam::Mat4::from_scale_rotation_translation(
+            glam::Vec3::splat(0.3),
+            glam::Quat::IDENTITY,
+            glam::vec3((i % 8) as f32 - 3.5, 0.0, -(i / 8) as f32),
+        )).collect();
+        let grid = Node::builder("grid")
+            .add_component(Transform::from_translation(glam::vec3(0.0, -1.0, 0.0)))
+            .add_component(InstancedModel::new(sphere(resources, Rgba::new(0.1, 0.3, 0.8, 1.0)), instances))
+            .add_component(pipeline)
+            .build();
+
+        let content = Node::builder("content")
+            .add_child(row.build())
+            .add_child(grid)
+            .build();
+
+        scene(resources, content, glam::vec3(0.0, 1.0, 5.0))
+    });
+
+    if let Some(mut app) = app {
+        app.run_frames(2);
+
+        let stats = app.render_stats();
+        assert_eq!(stats.objects, 74);
+        assert_eq!(stats.draws, 2);
+
+        let image = app.capture_frame().expect("Failed to capture frame.").to_rgba8();
+        common::assert_golden("procedural_instancing", &image, Tolerance::default());
+    }
+}
+
+/// An `InstancedModel` without instances draws nothing.
+#[test]
+fn empty_instanced_model() {
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let sphere = Model::new_sphere(renderer, resources, None, 16, resource::SphereUV::Cube);
+        let sphere = resources.store(sphere);
+
+        let content = Node::builder("empty")
+            .add_component(InstancedModel::new(sphere, vec![]))
+            .add_component(pipeline)
+            .build();
+
+        scene(resources, content, glam::vec3(0.0, 0.0, 5.0))
+    });
+
+    if let Some(mut app) = app {
+        app.run_frames(2);
+
+        let stats = app.render_stats();
+        assert_eq!(stats.objects, 0);
+        assert_eq!(stats.draws, 0);
+    }
+}
+
+#[test]
+fn procedural_alpha_modes() {
+    let image = common::render(2, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let alpha_modes = [
+            AlphaMode::Opaque,
+            AlphaMode::Mask { cutoff: 0.5 },
+            AlphaMode::Blend,
+        ];
+
+        let backdrop = {
+            let material = Material::builder()
+                .albedo(Rgba::new(0.1, 0.6, 0.2, 1.0))
+                .roughness_factor(0.8)
+                .build(renderer, resources);
+            let material = resources.store(material);
+
+            let model = Model::new_plane(renderer, resources, Some(material));
+            let model = resources.store(model);
+
+            Node::builder("backdrop")
+                .add_component(Transform::new(glam::vec3(0.0, 0.0, -1.0), glam::Quat::from_rotation_x(std::f32::consts::FRAC_PI_2), glam::vec3(2.5, 1.0, 0.5)))
+                .add_component(model)
+                .add_component(pipeline.clone())
+                .build()
+        };
+
+        let mut content = Node::builder("content").add_child(backdrop);
+
+        for (i, alpha_mode) in alpha_modes.into_iter().enumerate() {
+            for (j, alpha) in [0.25, 0.75].into_iter().enumerate() {
+                let material = Material::builder()
+                    .albedo(Rgba::new(0.9, 0.9, 0.2, alpha))
+                    .alpha_mode(alpha_mode)
+                    .roughness_factor(0.5)
+                    .build(renderer, resources);
+                let material = resources.store(material);
+
+                let model = Model::new_sphere(renderer, resources, Some(material), 32, resource::SphereUV::Cube);
+                let model = resources.store(model);
+
+                content = content.add_child(
+                    Node::builder(&format!("sphere ({i}, {j})"))
+                    .add_component(Transform::from_translation_scale(glam::vec3(i as f32 - 1.0, j as f32 - 0.5, 0.0) * 1.1, glam::Vec3::splat(0.45)))
+                    .add_component(model)
+                    .add_component(pipeline.clone())
+                    .build()
+                );
+            }
+        }
+
+        scene(resources, content.build(), glam::vec3(0.0, 0.0, 3.5))
+    });
+
+    if let Some(image) = image {
+        common::assert_golden("procedural_alpha_modes", &image, Tolerance::default());
+    }
+}
+
+/// Overlapping translucent quads, declared in no particular order, blend back to front.
+/// The last pair share a depth and are ordered by `SortBias`.
+#[test]
+fn procedural_transparent_sorting() {
+    let image = common::render(2, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let quads = [
+            (Rgba::new(0.1, 0.3, 0.9, 0.6), glam::vec3(0.4, 0.3, -0.5), 0.0),
+            (Rgba::new(0.9, 0.2, 0.1, 0.6), glam::vec3(-0.4, 0.3, 0.5), 0.0),
+            (Rgba::new(0.1, 0.9, 0.2, 0.6), glam::vec3(0.0, 0.0, 0.0), 0.0),
+            (Rgba::new(0.9, 0.9, 0.1, 0.6), glam::vec3(-0.5, -0.6, 0.8), -1.0),
+            (Rgba::new(0.8, 0.1, 0.9, 0.6), glam::vec3(0.0, -0.8, 0.8), 0.0),
+        ];
+
+        let mut content = Node::builder("content");
+        for (i, (albedo, translation, bias)) in quads.into_iter().enumerate() {
+            let material = Material::builder()
+                .albedo(albedo)
+                .alpha_mode(AlphaMode::Blend)
+                .roughness_factor(0.7)
+                .build(renderer, resources);
+            let material = resources.store(material);
+
+            let model = Model::new_plane(renderer, resources, Some(material));
+            let model = resources.store(model);
+
+            content = content.add_child(
+                Node::builder(&format!("quad {i}"))
+                .add_component(Transform::new(translation, glam::Quat::from_rotation_x(std::f32::consts::FRAC_PI_2), glam::Vec3::splat(0.6)))
+                .add_component(model)
+                .add_component(pipeline.clone())
+                .add_component(SortBias(bias))
+                .build()
+            );
+        }
+
+        scene(resources, content.build(), glam::vec3(0.0, 0.0, 3.0))
+    });
+
+    if let Some(image) = image {
+        common::assert_golden("procedural_transparent_sorting", &image, Tolerance::default());
+    }
+}
+
+/// Intersecting translucent quads, which can't be sorted, composited with weighted blended OIT.
+#[test]
+fn procedural_order_independent_transparency() {
+    let image = common::render(2, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let quads = [
+            (Rgba::new(0.9, 0.2, 0.1, 0.5), glam::Quat::from_rotation_y(0.6)),
+            (Rgba::new(0.1, 0.3, 0.9, 0.5), glam::Quat::from_rotation_y(-0.6)),
+            (Rgba::new(0.1, 0.9, 0.2, 0.5), glam::Quat::from_rotation_x(0.8)),
+        ];
+
+        let mut content = Node::builder("content");
+        for (i, (albedo, rotation)) in quads.into_iter().enumerate() {
+            let material = Material::builder()
+                .albedo(albedo)
+                .alpha_mode(AlphaMode::Blend)
+                .transparency(Transparency::WeightedBlended)
+                .double_sided(true)
+                .roughness_factor(0.7)
+                .build(renderer, resources);
+            let material = resources.store(material);
+
+            let model = Model::new_plane(renderer, resources, Some(material));
+            let model = resources.store(model);
+
+            content = content.add_child(
+                Node::builder(&format!("quad {i}"))
+                .add_component(Transform::new(glam::Vec3::ZERO, rotation * glam::Quat::from_rotation_x(std::f32::consts::FRAC_PI_2), glam::Vec3::splat(0.8)))
+                .add_component(model)
+                .add_component(pipeline.clone())
+                .build()
+            );
+        }
+
+        scene(resources, content.build(), glam::vec3(0.0, 0.0, 3.0))
+    });
+
+    if let Some(image) = image {
+        common::assert_golden("procedural_order_independent_transparency", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn procedural_shadows() {
+    let image = common::render(2, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let ground = {
+            let material = Material::builder()
+                .albedo(Rgba::new(0.8, 0.8, 0.8, 1.0))
+                .roughness_factor(0.9)
+                .build(renderer, resources);
+            let material = resources.store(material);
+
+            let model = Model::new_plane(renderer, resources, Some(material));
+            let model = resources.store(model);
+
+            Node::builder("ground")
+                .add_component(Transform::from_translation_scale(glam::vec3(0.0, -0.5, 0.0), glam::Vec3::splat(4.0)))
+                .add_component(model)
+                .add_component(pipeline.clone())
+                .build()
+        };
+
+        let material = Material::builder()
+            .albedo(Rgba::new(0.8, 0.3, 0.1, 1.0))
+            .roughness_factor(0.6)
+            .build(renderer, resources);
+        let material = resources.store(material);
+
+        let sphere = Model::new_sphere(renderer, resources, Some(material), 32, resource::SphereUV::Cube);
+        let sphere = resources.store(sphere);
+
+        let content = Node::builder("content")
+            .add_child(ground)
+            .add_child(
+                Node::builder("pillar")
+                .add_component(Transform::from_translation_scale(glam::vec3(-0.8, 0.3, -0.2), glam::vec3(0.25, 0.8, 0.25)))
+                .add_component(sphere.clone())
+                .add_component(pipeline.clone())
+                .build()
+            )
+            .add_child(
+                Node::builder("sphere")
+                .add_component(Transform::from_translation_scale(glam::vec3(0.9, 0.0, 0.3), glam::Vec3::splat(0.5)))
+                .add_component(sphere)
+                .add_component(pipeline.clone())
+                .build()
+            )
+            .add_child(
+                Node::builder("point")
+                .add_component(Transform::from_translation(glam::vec3(0.2, 1.2, 1.2)))
+                .add_component(PointLight::new(Rgba::new(1.0, 0.6, 0.3, 6.0)))
+                .add_component(PointShadows::new(0.05, 20.0))
+                .build()
+            )
+            .build();
+
+        let mut scene = scene(resources, content, glam::vec3(0.0, 1.5, 4.5));
+
+        let sun = scene.find_by_name_mut("sun").unwrap();
+        sun.add_component(DirectionalShadows::new(2, 20.0));
+
+        let camera = scene.find_by_name_mut("camera").unwrap();
+        camera.get_component_mut::<Transform>().unwrap().rotation = glam::Quat::from_rotation_x(-0.35);
+
+        scene
+    });
+
+    if let Some(image) = image {
+        common::assert_golden("procedural_shadows", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn gltf_lights_punctual() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/lights_punctual.gltf");
+
+    let image = common::render(2, |renderer, resources| {
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let material = Material::builder()
+            .albedo(Rgba::new(0.8, 0.8, 0.8, 1.0))
+            .roughness_factor(0.7)
+            .build(renderer, resources);
+        let material = resources.store(material);
+
+        let ground = Model::new_plane(renderer, resources, Some(material));
+        let ground = resources.store(ground);
+
+        let lights = resource::load_gltf(renderer, resources, &path, None).unwrap();
+
+        let spot = lights.find_by_name("spot").expect("Spot light should be imported into a rotated child node.");
+        assert!(spot.has_component::<SpotLight>());
+        assert!(lights.find_by_name("sun").unwrap().has_component::<DirectionalLight>());
+        let point = lights.find_by_name("point_node").unwrap().get_component::<PointLight>().unwrap();
+        assert_eq!(point.range, Some(1.5));
+        assert_eq!(point.intensity(), 4.0);
+
+        let content = Node::builder("content")
+            .add_child(
+                Node::builder("ground")
+                .add_component(Transform::from_scale(glam::Vec3::splat(3.0)))
+                .add_component(ground)
+                .add_component(pipeline.clone())
+                .build()
+            )
+            .add_child(lights)
+            .build();
+
+        let scene = Node::builder("scene")
+            .add_child(content)
+            .add_child(
+                Node::builder("camera")
+                .add_component(Transform::from_translation_rotation(glam::vec3(0.0, 3.0, 3.5), glam::Quat::from_rotation_x(-0.7)))
+                .add_component(Camera::Perspective {
+                    fovy: std::f32::consts::FRAC_PI_3,
+                    znear: 0.1,
+                    zfar: Some(100.0),
+                })
+                .build()
+            )
+            .build();
+
+        resources.set_global("current_camera", scene.find_by_name("camera").unwrap().id());
+
+        common::procedural_environment(renderer, resources);
+
+        scene
+    });
+
+    if let Some(image) = image {
+        common::assert_golden("gltf_lights_punctual", &image, Tolerance::default());
+    }
+}
+
+/// `KHR_materials_emissive_strength` scales the emissive factor past 1, the brighter quad should bloom.
+#[test]
+fn gltf_emissive_strength_bloom() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/emissive_strength.gltf");
+
+    let image = common::render(2, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let mut quads = resource::load_gltf(renderer, resources, &path, None).unwrap();
+        quads.traverse_if_mut(&mut |node| node.has_component::<Handle<Model>>(), &mut |node| {
+            node.add_component(pipeline.clone());
+        });
+
+        let scene = Node::builder("scene")
+            .add_child(quads)
+            .add_child(
+                Node::builder("camera")
+                .add_component(Transform::from_translation(glam::vec3(0.0, 0.0, 2.5)))
+                .add_component(Camera::Perspective {
+                    fovy: std::f32::consts::FRAC_PI_3,
+                    znear: 0.1,
+                    zfar: Some(100.0),
+                })
+                .add_component(PostProcessing::new(Tonemapper::AgX).with_bloom(0.15, 1.0).with_exposure(-0.5))
+                .build()
+            )
+            .build();
+
+        resources.set_global("current_camera", scene.find_by_name("camera").unwrap().id());
+
+        scene
+    });
+
+    if let Some(image) = image {
+        common::assert_golden("gltf_emissive_strength_bloom", &image, Tolerance::default());
+    }
+}
+
+/// The second joint of the skin bends the bar, and the root joint is turned after loading. The bar's shadow is skinned too.
+#[test]
+fn gltf_skinning() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/skinned_bar.gltf");
+
+    let image = common::render(2, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+        let skinned_pipeline = common::skinned_pipeline(renderer, resources);
+
+        let mut bar = resource::load_gltf(renderer, resources, &path, None).unwrap();
+
+        let skeleton = bar.find_by_name("bar").unwrap().get_component::<Skeleton>().expect("Skinned nodes should have a Skeleton.");
+        assert_eq!(skeleton.joints, [bar.find_by_name("root").unwrap().id(), bar.find_by_name("tip").unwrap().id()]);
+
+        bar.traverse_if_mut(&mut |node| node.has_component::<Handle<Model>>(), &mut |node| {
+            if node.has_component::<Skeleton>() {
+                node.add_component(skinned_pipeline.clone());
+            } else {
+                node.add_component(pipeline.clone());
+            }
+        });
+        bar.find_by_name_mut("root").unwrap().get_component_mut::<Transform>().unwrap().rotation = glam::Quat::from_rotation_y(-0.6);
+
+        let material = Material::builder()
+            .albedo(Rgba::new(0.8, 0.8, 0.8, 1.0))
+            .roughness_factor(0.9)
+            .build(renderer, resources);
+        let material = resources.store(material);
+
+        let ground = Model::new_plane(renderer, resources, Some(material));
+        let ground = resources.store(ground);
+
+        let content = Node::builder("content")
+            .add_child(
+                Node::builder("ground")
+                .add_component(Transform::from_translation_scale(glam::vec3(0.0, -1.0, 0.0), glam::Vec3::splat(4.0)))
+                .add_component(ground)
+                .add_component(pipeline.clone())
+                .build()
+            )
+            .add_child(bar)
+            .build();
+
+        let mut scene = scene(resources, content, glam::vec3(0.0, 0.8, 4.0));
+
+        let sun = scene.find_by_name_mut("sun").unwrap();
+        sun.add_component(DirectionalShadows::new(2, 20.0));
+
+        let camera = scene.find_by_name_mut("camera").unwrap();
+        camera.get_component_mut::<Transform>().unwrap().rotation = glam::Quat::from_rotation_x(-0.3);
+
+        scene
+    });
+
+    if let Some(image) = image {
+        common::assert_golden("gltf_skinning", &image, Tolerance::default());
+    }
+}
+
+fn animated_boxes(renderer: &Renderer, resources: &mut Resources) -> Node {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/animated_boxes.gltf");
+    load_gltf_scene(renderer, resources, &path, glam::vec3(0.0, 0.0, 4.0))
+}
+
+fn translation_x(app: &tritium::HeadlessApp, name: &str) -> f32 {
+    app.root().find_by_name(name).unwrap().get_component::<Transform>().unwrap().translation.x
+}
+
+/// Linear, step and cubic spline channels of an imported clip, paused and looped.
+#[test]
+fn gltf_animation_playback() {
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, animated_boxes);
+
+    if let Some(mut app) = app {
+        let boxes = app.root().find_by_name("animated_boxes").unwrap();
+        let player = boxes.get_component::<AnimationPlayer>().expect("Scenes with animations should have an AnimationPlayer.");
+        assert_eq!(player.clips.len(), 2);
+        let slide = player.clip(app.resources(), "slide").unwrap();
+        assert_eq!(slide.get(app.resources()).duration, 1.0);
+
+        app.root_mut().find_by_name_mut("animated_boxes").unwrap().get_component_mut::<AnimationPlayer>().unwrap().play(slide);
+
+        // a quarter of the way through
+        app.run_frames(15);
+        assert!((translation_x(&app, "linear") + 0.5).abs() < 1e-3);
+        assert_eq!(translation_x(&app, "step"), -1.0);
+        assert!((translation_x(&app, "cubic") + 0.6875).abs() < 1e-3);
+
+        app.root_mut().find_by_name_mut("animated_boxes").unwrap().get_component_mut::<AnimationPlayer>().unwrap().pause();
+        app.run_frames(15);
+        assert!((translation_x(&app, "linear") + 0.5).abs() < 1e-3);
+
+        let player = app.root_mut().find_by_name_mut("animated_boxes").unwrap().get_component_mut::<AnimationPlayer>().unwrap();
+        player.resume();
+        player.speed = 2.0;
+        // 0.25 + 45 / 60 * 2 wraps around to 0.75
+        app.run_frames(45);
+        assert!((translation_x(&app, "linear") - 0.5).abs() < 1e-3);
+        assert_eq!(translation_x(&app, "step"), -1.0);
+
+        let player = app.root().find_by_name("animated_boxes").unwrap().get_component::<AnimationPlayer>().unwrap();
+        assert!((player.time().unwrap() - 0.75).abs() < 1e-3);
+    }
+}
+
+/// Cross-fading blends the poses of both clips, or a clip's with the pose the fade started in, then plays the new clip alone.
+#[test]
+fn gltf_animation_cross_fade() {
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, animated_boxes);
+
+    if let Some(mut app) = app {
+        let player = app.root().find_by_name("animated_boxes").unwrap().get_component::<AnimationPlayer>().unwrap();
+        let slide = player.clip(app.resources(), "slide").unwrap();
+        let spin = player.clip(app.resources(), "spin").unwrap();
+
+        let player = app.root_mut().find_by_name_mut("animated_boxes").unwrap().get_component_mut::<AnimationPlayer>().unwrap();
+        player.looping = false;
+        player.play(slide);
+        // holds the last keyframe
+        app.run_frames(70);
+        assert!((translation_x(&app, "linear") - 1.0).abs() < 1e-3);
+
+        let player = app.root_mut().find_by_name_mut("animated_boxes").unwrap().get_component_mut::<AnimationPlayer>().unwrap();
+        player.cross_fade(spin, 1.0);
+        app.run_frames(30);
+
+        // half way through the fade, both clips move linear
+        assert!((translation_x(&app, "linear") - 0.5).abs() < 1e-3);
+        // properties only spin animates are blended from the pose the fade started in, half way to 0.75 and 45 degrees
+        let step = app.root().find_by_name("step").unwrap().get_component::<Transform>().unwrap();
+        assert!((step.scale - glam::Vec3::splat(0.875)).abs().max_element() < 1e-3);
+        assert!(step.rotation.angle_between(glam::Quat::from_rotation_y(std::f32::consts::FRAC_PI_8)) < 1e-3);
+        // and properties only slide animates stay where it left them
+        assert!((step.translation.x - 1.0).abs() < 1e-3);
+
+        let image = app.capture_frame().expect("Failed to capture frame.").to_rgba8();
+        common::assert_golden("gltf_animation_cross_fade", &image, Tolerance::default());
+
+        app.run_frames(31);
+        let player = app.root().find_by_name("animated_boxes").unwrap().get_component::<AnimationPlayer>().unwrap();
+        assert_eq!(player.current_clip().unwrap().get(app.resources()).name.as_deref(), Some("spin"));
+        assert!(translation_x(&app, "linear").abs() < 1e-3);
+    }
+}
+
+/// Morph targets blended by per node weights, one node's driven by an animation.
+#[test]
+fn gltf_morph_targets() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/morph_grids.gltf");
+
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+        let morph_pipeline = common::morph_pipeline(renderer, resources);
+
+        let mut grids = resource::load_gltf(renderer, resources, &path, None).unwrap();
+
+        // the mesh's weights, overridden by the node
+        assert_eq!(grids.find_by_name("left").unwrap().get_component::<MorphWeights>().unwrap().weights, [0.5, 0.0]);
+        assert_eq!(grids.find_by_name("right").unwrap().get_component::<MorphWeights>().unwrap().weights, [0.0, 1.0]);
+
+        grids.traverse_if_mut(&mut |node| node.has_component::<Handle<Model>>(), &mut |node| {
+            node.add_component(morph_pipeline.clone());
+        });
+
+        let player = grids.get_component_mut::<AnimationPlayer>().unwrap();
+        let bulge = player.clips[0].clone();
+        player.play(bulge);
+        player.looping = false;
+
+        let material = Material::builder()
+            .albedo(Rgba::new(0.8, 0.8, 0.8, 1.0))
+            .roughness_factor(0.9)
+            .build(renderer, resources);
+        let material = resources.store(material);
+
+        let ground = Model::new_plane(renderer, resources, Some(material));
+        let ground = resources.store(ground);
+
+        let content = Node::builder("content")
+            .add_child(
+                Node::builder("ground")
+                .add_component(Transform::from_translation_scale(glam::vec3(0.0, -0.5, 0.0), glam::Vec3::splat(4.0)))
+                .add_component(ground)
+                .add_component(pipeline.clone())
+                .build()
+            )
+            .add_child(grids)
+            .build();
+
+        let mut scene = scene(resources, content, glam::vec3(-1.2, 0.5, 2.2));
+
+        let sun = scene.find_by_name_mut("sun").unwrap();
+        sun.add_component(DirectionalShadows::new(2, 20.0));
+
+        let camera = scene.find_by_name_mut("camera").unwrap();
+        camera.get_component_mut::<Transform>().unwrap().rotation = glam::Quat::from_rotation_y(-0.45) * glam::Quat::from_rotation_x(-0.2);
+
+        scene
+    });
+
+    if let Some(mut app) = app {
+        // three quarters of the way through
+        app.run_frames(45);
+
+        let weights = &app.root().find_by_name("left").unwrap().get_component::<MorphWeights>().unwrap().weights;
+        assert!((weights[0] - 0.75).abs() < 1e-3);
+        assert_eq!(weights[1], 0.0);
+        assert_eq!(app.root().find_by_name("right").unwrap().get_component::<MorphWeights>().unwrap().weights, [0.0, 1.0]);
+
+        let image = app.capture_frame().expect("Failed to capture frame.").to_rgba8();
+        common::assert_golden("gltf_morph_targets", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn gltf_vertex_attributes() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/vertex_attributes.gltf");
+
+    // vertex colours, a texture on the second UV set, and the same normal map with provided and generated tangents
+    let image = common::render(2, |renderer, resources| load_gltf_scene(renderer, resources, &path, glam::vec3(0.0, 0.0, 2.5)));
+
+    if let Some(image) = image {
+        common::assert_golden("gltf_vertex_attributes", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn gltf_material_extensions() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/material_extensions.gltf");
+
+    // clearcoat, transmission, specular, sheen / unlit, emissive strength, texture transform and the clearcoat sphere without its coat
+    let image = common::render(2, |renderer, resources| load_gltf_scene(renderer, resources, &path, glam::vec3(0.0, 0.0, 3.2)));
+
+    if let Some(image) = image {
+        common::assert_golden("gltf_material_extensions", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn gltf_compressed_textures() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/compressed_textures.gltf");
+
+    // KTX2 BC1 with tinted mips, DDS DXT5, KTX2 BC7 / KTX2 ETC2 RGB, ETC2 RGBA and a KHR_texture_basisu image that falls back on its png
+    let image = common::render(2, |renderer, resources| load_gltf_scene(renderer, resources, &path, glam::vec3(0.0, 0.0, 2.6)));
+
+    if let Some(image) = image {
+        common::assert_golden("gltf_compressed_textures", &image, Tolerance::default());
+    }
+}
+
+/// Basis Universal isn't transcoded, so a `KHR_texture_basisu` texture without a fallback `source` can't be loaded.
+#[test]
+fn gltf_basisu_without_fallback() {
+    let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/compressed_textures.gltf");
+    let assets: serde_json::Value = serde_json::from_slice(&std::fs::read(assets).unwrap()).unwrap();
+
+    let gltf = serde_json::json!({
+        "asset": {"version": "2.0"},
+        "extensionsUsed": ["KHR_texture_basisu"],
+        "extensionsRequired": ["KHR_texture_basisu"],
+        "scenes": [{"nodes": []}],
+        "images": [assets["images"][5]],
+        "textures": [{"extensions": {"KHR_texture_basisu": {"source": 0}}}],
+    });
+    let path = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join("basisu_without_fallback.gltf");
+    std::fs::write(&path, gltf.to_string()).unwrap();
+
+    let mut result = None;
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, |renderer, resources| {
+        result = Some(resource::load_gltf(renderer, resources, &path, None));
+        Node::builder("root").build()
+    });
+
+    if app.is_some() {
+        let Some(Err(resource::SceneLoadError::TextureLoadError(resource::TextureLoadError::CompressedImageError(err)))) = result else { panic!("Basis Universal texture loaded.") };
+        assert!(matches!(err, resource::CompressedImageError::BasisUniversal));
+    }
+}
+
+#[test]
+fn gltf_compressed_textures_async() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/compressed_textures.gltf");
+
+    let app = common::headless_app(common::WIDTH, common::HEIGHT, |renderer, resources| {
+        common::procedural_environment(renderer, resources);
+        let pipeline = common::main_pipeline(renderer, resources);
+
+        let gltf = resource::load_gltf_async(resources, &path, None);
+        let spawn_scene = SpawnScene::new(gltf).on_spawn(move |node, _| {
+            node.traverse_if_mut(&mut |node| node.has_component::<Handle<Model>>(), &mut |node| {
+                node.add_component(pipeline.clone());
+            });
+        });
+
+        scene(resources, Node::builder("gltf").add_script(spawn_scene).build(), glam::vec3(0.0, 0.0, 2.6))
+    });
+    let Some(mut app) = app else { return };
+
+    app.finish_loading();
+    app.run_frames(2);
+
+    // matches loading synchronously
+    let image = app.capture_frame().unwrap().to_rgba8();
+    common::assert_golden("gltf_compressed_textures", &image, Tolerance::default());
+}
+
+#[test]
+fn gltf_metal_rough_spheres() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/metal_rough_spheres.gltf");
+
+    // metallic increases upwards, roughness to the right
+    let image = common::render(2, |renderer, resources| load_gltf_scene(renderer, resources, &path, glam::vec3(0.0, 0.0, 6.5)));
+
+    if let Some(image) = image {
+        common::assert_golden("gltf_metal_rough_spheres", &image, Tolerance::default());
+    }
+}
+
+#[test]
+fn gltf_alpha_blend_mode() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/alpha_blend_mode.gltf");
+
+    // opaque, masked and blended quads with alpha increasing to the right
+    let image = common::render(2, |renderer, resources| load_gltf_scene(renderer, resources, &path, glam::vec3(0.0, 0.0, 4.0)));
+
+    if let Some(image) = image {
+        common::assert_golden("gltf_alpha_blend_mode", &image, Tolerance::default());
+    }
+}
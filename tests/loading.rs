@@ -0,0 +1,186 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use tritium::{HeadlessApp, node::Node, resource::{self, AssetLoadError, Handle, LoadState, Resources, TextureLoadError}};
+
+/// Writes a `width`x`height` png to the test's temporary directory.
+fn png(name: &str, width: u32, height: u32) -> PathBuf {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("loading").join(name);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    image::RgbaImage::from_pixel(width, height, image::Rgba([255, 0, 0, 255])).save(&path).unwrap();
+    path
+}
+
+/// Starts loading with `load` while building the scene.
+fn load<T: 'static, F: FnOnce(&mut Resources) -> Handle<T>>(load: F) -> Option<(HeadlessApp, Handle<T>)> {
+    let mut load = Some(load);
+    let mut handle = None;
+
+    let app = common::headless_app(64, 64, |_, resources| {
+        handle = Some(load.take().unwrap()(resources));
+        Node::builder("root").build()
+    })?;
+
+    Some((app, handle.unwrap()))
+}
+
+#[test]
+fn texture() {
+    let path = png("texture.png", 8, 4);
+    let Some((mut app, texture)) = load(|resources| resource::load_texture_async(resources, &path, false)) else { return };
+
+    app.finish_loading();
+
+    assert!(matches!(app.resources().load_state(&texture), LoadState::Loaded));
+    assert_eq!(app.resources().assets_loading(), 0);
+    assert!(texture.downgrade().get(app.resources()).is_some());
+}
+
+#[test]
+fn missing_file() {
+    let Some((mut app, texture)) = load(|resources| resource::load_texture_async(resources, "missing.png", false)) else { return };
+
+    app.finish_loading();
+
+    let LoadState::Failed(err) = app.resources().load_state(&texture) else { panic!("Missing texture loaded.") };
+    assert!(matches!(*err, AssetLoadError::TextureLoadError(TextureLoadError::IoError(_))));
+    assert!(texture.downgrade().get(app.resources()).is_none());
+}
+
+#[test]
+fn uploads_between_frames() {
+    let Some((mut app, handle)) = load(|resources| resources.load_async("value", || Ok(21), |_, _, value: u32| Ok(value * 2))) else { return };
+
+    // decoded on a worker thread, so it may take a few frames
+    for _ in 0..1000 {
+        if !app.resources().load_state(&handle).is_loading() {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+        app.step();
+    }
+
+    assert!(matches!(app.resources().load_state(&handle), LoadState::Loaded));
+    assert_eq!(*handle.get(app.resources()), 42);
+}
+
+#[test]
+fn panicking_decode() {
+    let Some((mut app, handle)) = load(|resources| resources.load_async("panic", || -> Result<u32, AssetLoadError> { panic!("Decoding failed.") }, |_, _, value| Ok(value))) else { return };
+
+    app.finish_loading();
+
+    let LoadState::Failed(err) = app.resources().load_state(&handle) else { panic!("Panicking decode loaded.") };
+    assert!(matches!(*err, AssetLoadError::Panicked));
+}
+
+#[test]
+fn awaiting() {
+    let path = png("awaiting.png", 2, 2);
+    let Some((mut app, texture)) = load(|resources| resource::load_texture_async(resources, &path, false)) else { return };
+
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let loaded = runtime.spawn(app.resources().loaded(&texture));
+
+    app.finish_loading();
+
+    assert!(matches!(runtime.block_on(loaded).unwrap(), LoadState::Loaded));
+
+    // stored resources are already loaded
+    let stored = app.resources_mut().store(0u32);
+    assert!(matches!(runtime.block_on(app.resources().loaded(&stored)), LoadState::Loaded));
+}
+
+#[test]
+fn cached_texture() {
+    let path = png("cached.png", 4, 4);
+
+    let mut handles = None;
+    let Some(mut app) = common::headless_app(64, 64, |renderer, resources| {
+        let first = resource::load_texture(renderer, resources, &path, false).unwrap();
+        let second = resource::load_texture(renderer, resources, &path, false).unwrap();
+        let flipped = resource::load_texture(renderer, resources, &path, true).unwrap();
+        let background = resource::load_texture_async(resources, &path, false);
+        handles = Some((first, second, flipped, background));
+
+        Node::builder("root").build()
+    }) else { return };
+    let (first, second, flipped, background) = handles.unwrap();
+
+    assert_eq!(first, second);
+    assert_eq!(first, background);
+    assert_ne!(first, flipped);
+
+    let info = app.resources().asset_info(&first).unwrap();
+    assert_eq!(info.path, path.canonicalize().unwrap());
+    assert_eq!(info.byte_size, std::fs::metadata(&path).unwrap().len());
+    // 4x4 and 2x2 mip levels
+    assert_eq!(info.gpu_memory, (16 + 4) * 4);
+    assert_eq!(app.resources().loaded_assets().filter(|info| info.path == path.canonicalize().unwrap()).count(), 2);
+
+    drop((first, second, background));
+    app.step();
+
+    assert_eq!(app.resources().loaded_assets().filter(|info| info.path == path.canonicalize().unwrap()).count(), 1);
+    assert!(app.resources().asset_info(&flipped).is_some());
+}
+
+#[test]
+fn cached_after_background_load() {
+    let path = png("cached_async.png", 2, 2);
+    let Some((mut app, texture)) = load(|resources| resource::load_texture_async(resources, &path, false)) else { return };
+
+    app.finish_loading();
+
+    assert_eq!(app.resources().cached_asset(&path, "flip_y: false"), Some(texture.clone()));
+    assert_eq!(resource::load_texture_async(app.resources_mut(), &path, false), texture);
+}
+
+#[test]
+fn concurrent_loads_share_handle() {
+    let path = png("concurrent.png", 2, 2);
+    let Some((mut app, first)) = load(|resources| resource::load_texture_async(resources, &path, false)) else { return };
+    let second = resource::load_texture_async(app.resources_mut(), &path, false);
+
+    assert_eq!(first, second);
+    assert_eq!(app.resources().assets_loading(), 1);
+
+    app.finish_loading();
+
+    assert!(matches!(app.resources().load_state(&second), LoadState::Loaded));
+    assert_eq!(app.resources().asset_info(&first).unwrap().path, path.canonicalize().unwrap());
+}
+
+#[test]
+fn failed_load_is_not_shared() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("loading").join("corrupt.png");
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, b"not a png").unwrap();
+
+    let Some((mut app, texture)) = load(|resources| resource::load_texture_async(resources, &path, false)) else { return };
+
+    app.finish_loading();
+
+    assert!(matches!(app.resources().load_state(&texture), LoadState::Failed(_)));
+    assert_ne!(resource::load_texture_async(app.resources_mut(), &path, false), texture);
+}
+
+#[test]
+fn gltf_reuses_cached_images() {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/assets/alpha_blend_mode.gltf");
+
+    let mut scene = None;
+    let Some(mut app) = common::headless_app(64, 64, |renderer, resources| {
+        scene = Some(resource::load_gltf(renderer, resources, &path, None).unwrap());
+        Node::builder("root").build()
+    }) else { return };
+    assert_eq!(app.resources().loaded_assets().filter(|info| info.path == path.canonicalize().unwrap()).count(), 1);
+
+    let handle = resource::load_gltf_async(app.resources_mut(), &path, None);
+    app.finish_loading();
+
+    assert!(matches!(app.resources().load_state(&handle), LoadState::Loaded));
+    assert_eq!(app.resources().loaded_assets().filter(|info| info.path == path.canonicalize().unwrap()).count(), 1);
+    drop(scene);
+}
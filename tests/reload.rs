@@ -0,0 +1,179 @@
+mod common;
+
+use std::{path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use tritium::{HeadlessApp, camera::Camera, components::Transform, node::Node, renderer::{Renderer, Shader}, resource::{self, Handle, Model, Resources, Sampler}};
+
+/// An empty directory for the test's files.
+fn directory(name: &str) -> PathBuf {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("reload").join(name);
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn png(path: &Path, colour: [u8; 4]) {
+    image::RgbaImage::from_pixel(4, 4, image::Rgba(colour)).save(path).unwrap();
+}
+
+/// A shader drawing a cube over the whole screen, `colour.wgsl` is included for its fragment colour.
+fn shader(directory: &Path, inputs: &str, colour: &str) -> PathBuf {
+    std::fs::write(directory.join("shader.ron"), format!("ShaderResource(name: \"reload\", inputs: [{inputs}], vertex_type: Position, shader_file: \"shader.wgsl\")")).unwrap();
+    std::fs::write(directory.join("shader.wgsl"), "
+        //!include(\"colour.wgsl\")
+
+        @vertex
+        fn vs_main(@location(0) position: vec3<f32>) -> @builtin(position) vec4<f32> {
+            return vec4(position.xy * 4.0, 0.5, 1.0);
+        }
+
+        @fragment
+        fn fs_main() -> @location(0) vec4<f32> {
+            return colour();
+        }
+    ").unwrap();
+    std::fs::write(directory.join("colour.wgsl"), colour).unwrap();
+
+    directory.join("shader.ron")
+}
+
+fn solid(colour: &str) -> String {
+    format!("fn colour() -> vec4<f32> {{ return vec4({colour}, 1.0); }}")
+}
+
+/// A cube drawn with the shader at `path`, which is watched.
+fn scene(renderer: &Renderer, resources: &mut Resources, path: &Path) -> Node {
+    let shader = Shader::from_resource(renderer, path).unwrap();
+    let shader = resources.store(shader);
+    resources.watch_shader(&shader, path);
+
+    let cube = Model::new_cube(renderer, resources, None);
+    let cube = resources.store(cube);
+
+    let scene = Node::builder("scene")
+        .add_child(Node::builder("cube").add_component(cube).add_component(shader).build())
+        .add_child(
+            Node::builder("camera")
+            .add_component(Transform::from_translation(glam::vec3(0.0, 0.0, 3.0)))
+            .add_component(Camera::Perspective { fovy: std::f32::consts::FRAC_PI_3, znear: 0.1, zfar: Some(100.0) })
+            .build()
+        )
+        .build();
+
+    resources.set_global("current_camera", scene.find_by_name("camera").unwrap().id());
+
+    scene
+}
+
+/// The brightest channel of the centre pixel.
+fn centre(app: &HeadlessApp) -> usize {
+    let frame = app.capture_frame().unwrap().to_rgba8();
+    let pixel = frame.get_pixel(32, 32).0;
+    (0..3).max_by_key(|&channel| pixel[channel]).unwrap()
+}
+
+/// Steps until `done`, files are watched on another thread so it may take a few frames.
+fn step_until<F: FnMut(&mut HeadlessApp) -> bool>(app: &mut HeadlessApp, mut done: F) -> bool {
+    let start = Instant::now();
+
+    while start.elapsed() < Duration::from_secs(5) {
+        app.step();
+        if done(app) {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    false
+}
+
+#[test]
+fn shader_include() {
+    let directory = directory("shader_include");
+    let path = shader(&directory, "", &solid("1.0, 0.0, 0.0"));
+    let Some(mut app) = common::headless_app(64, 64, |renderer, resources| scene(renderer, resources, &path)) else { return };
+
+    app.run_frames(2);
+    assert_eq!(centre(&app), 0);
+
+    std::fs::write(directory.join("colour.wgsl"), solid("0.0, 1.0, 0.0")).unwrap();
+
+    assert!(step_until(&mut app, |app| centre(app) == 1), "Shader wasn't reloaded.");
+}
+
+#[test]
+fn shader_compile_error() {
+    let directory = directory("shader_compile_error");
+    let path = shader(&directory, "", &solid("1.0, 0.0, 0.0"));
+    let Some(mut app) = common::headless_app(64, 64, |renderer, resources| scene(renderer, resources, &path)) else { return };
+
+    std::fs::write(directory.join("colour.wgsl"), "fn colour() -> vec4<f32> { return missing; }").unwrap();
+
+    // the current shader is kept
+    let start = Instant::now();
+    step_until(&mut app, |_| start.elapsed() > Duration::from_secs(1));
+    assert_eq!(centre(&app), 0);
+
+    // and fixing the file reloads it
+    std::fs::write(directory.join("colour.wgsl"), solid("0.0, 0.0, 1.0")).unwrap();
+
+    assert!(step_until(&mut app, |app| centre(app) == 2), "Shader wasn't reloaded after an error.");
+}
+
+#[test]
+fn texture() {
+    let directory = directory("texture");
+    let texture_path = directory.join("texture.png");
+    png(&texture_path, [255, 0, 0, 255]);
+
+    let colour = "
+        //!binding()
+        var colour_texture: texture_2d<f32>;
+        //!binding()
+        var colour_sampler: sampler;
+
+        fn colour() -> vec4<f32> { return textureSample(colour_texture, colour_sampler, vec2(0.5)); }
+    ";
+    let path = shader(&directory, "Resource(ty: Sampler, res: \"reload::texture\")", colour);
+
+    let mut texture = None;
+    let Some(mut app) = common::headless_app(64, 64, |renderer, resources| {
+        let handle = resource::load_texture(renderer, resources, &texture_path, false).unwrap();
+        resources.watch_texture(&handle, &texture_path, false);
+
+        let sampler = Sampler::new_default(renderer, resources, handle.clone());
+        resources.set_global("reload::texture", sampler);
+        texture = Some(handle);
+
+        scene(renderer, resources, &path)
+    }) else { return };
+
+    app.run_frames(2);
+    assert_eq!(centre(&app), 0);
+
+    png(&texture_path, [0, 0, 255, 255]);
+
+    assert!(step_until(&mut app, |app| centre(app) == 2), "Texture wasn't reloaded.");
+}
+
+#[test]
+fn watch() {
+    let directory = directory("watch");
+    let path = directory.join("value.txt");
+    std::fs::write(&path, "first").unwrap();
+
+    let mut handle: Option<Handle<String>> = None;
+    let Some(mut app) = common::headless_app(64, 64, |_, resources| {
+        let value = resources.store(std::fs::read_to_string(&path).unwrap());
+        let reload_path = path.clone();
+        resources.watch(&value, vec![path.clone()], move |_, _| std::fs::read_to_string(&reload_path));
+        handle = Some(value);
+
+        Node::builder("root").build()
+    }) else { return };
+    let handle = handle.unwrap();
+
+    std::fs::write(&path, "second").unwrap();
+
+    assert!(step_until(&mut app, |app| handle.get(app.resources()) == "second"), "Value wasn't reloaded.");
+}
@@ -0,0 +1,155 @@
+use tritium::renderer::{PassCondition, RenderGraphError, RenderGraphResource};
+
+fn schedule(source: &str) -> Result<Vec<String>, RenderGraphError> {
+    let resource: RenderGraphResource = source.parse().unwrap();
+    let order = resource.schedule()?;
+
+    Ok(order.into_iter().map(|i| resource.passes[i].name.clone()).collect())
+}
+
+#[test]
+fn default_graph() {
+    let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/pipelines/graphs/default.ron")).unwrap();
+
+    let order = schedule(&source).unwrap();
+
+    assert_eq!(order.first().unwrap(), "main");
+    assert_eq!(order[order.len() - 2..], ["tonemap", "fxaa"]);
+}
+
+/// Disabled effects are skipped per frame, so every variant is scheduled.
+#[test]
+fn default_graph_conditions() {
+    let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/pipelines/graphs/default.ron")).unwrap();
+    let resource: RenderGraphResource = source.parse().unwrap();
+    let condition = |name: &str| resource.passes.iter().find(|pass| pass.name == name).unwrap().condition.clone();
+
+    assert_eq!(condition("main"), None);
+    assert_eq!(condition("transparent"), Some(PassCondition::OrderIndependent));
+    assert_eq!(condition("bloom_upsample_0"), Some(PassCondition::Bloom));
+    assert_eq!(condition("tonemap_output"), Some(PassCondition::Not(Box::new(PassCondition::Fxaa))));
+    assert_eq!(condition("fxaa"), Some(PassCondition::Fxaa));
+
+    let order = schedule(&source).unwrap();
+    assert!(order.iter().any(|pass| pass == "tonemap_output"));
+    assert!(order.iter().any(|pass| pass == "bloom_prefilter"));
+}
+
+#[test]
+fn orders_by_dependency_and_culls() {
+    let source = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/assets/depth_prepass.ron")).unwrap();
+    let order = schedule(&source).unwrap();
+
+    assert_eq!(order[..5], ["depth_prepass", "main", "transparent", "oit_composite", "bloom_prefilter"]);
+    assert_eq!(order.last().unwrap(), "fxaa");
+    assert!(!order.iter().any(|pass| pass == "unused"));
+}
+
+#[test]
+fn cycle() {
+    let result = schedule(r#"(
+        name: "cycle",
+        targets: [(name: "a", format: Rgba16Float), (name: "b", format: Rgba16Float)],
+        passes: [
+            (name: "first", kind: Fullscreen(shader: "blit.ron"), inputs: ["b"], colour: Some("a")),
+            (name: "second", kind: Fullscreen(shader: "blit.ron"), inputs: ["a"], colour: Some("b")),
+            (name: "present", kind: Fullscreen(shader: "blit.ron"), inputs: ["b"], colour: Some("output")),
+        ],
+    )"#);
+
+    assert!(matches!(result, Err(RenderGraphError::Cycle(_))));
+}
+
+#[test]
+fn invalid_graphs() {
+    let result = schedule(r#"(
+        name: "feedback",
+        targets: [(name: "a", format: Rgba16Float)],
+        passes: [(name: "pass", kind: Fullscreen(shader: "blit.ron"), inputs: ["a"], colour: Some("a"))],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::Feedback { .. })));
+
+    let result = schedule(r#"(
+        name: "unknown",
+        passes: [(name: "pass", kind: Fullscreen(shader: "blit.ron"), inputs: ["missing"], colour: Some("output"))],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::UnknownTarget { .. })));
+
+    let result = schedule(r#"(
+        name: "depth_as_colour",
+        passes: [(name: "pass", kind: Scene(All), colour: Some("depth"))],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::InvalidAttachment { .. })));
+
+    let result = schedule(r#"(
+        name: "reserved",
+        targets: [(name: "output", format: Rgba8Unorm)],
+        passes: [(name: "pass", kind: Scene(All), colour: Some("output"))],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::DuplicateTarget(_))));
+
+    let result = schedule(r#"(
+        name: "no_output",
+        passes: [(name: "pass", kind: Scene(All), depth: Some("depth"))],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::NoOutput)));
+}
+
+#[test]
+fn multisampled_targets() {
+    let result = schedule(r#"(
+        name: "sampled_msaa",
+        targets: [(name: "msaa", format: Rgba16Float, multisampled: true)],
+        passes: [
+            (name: "main", kind: Scene(All), colour: Some("msaa"), depth: Some("depth")),
+            (name: "present", kind: Fullscreen(shader: "blit.ron"), inputs: ["msaa"], colour: Some("output")),
+        ],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::InvalidAttachment { .. })));
+
+    let result = schedule(r#"(
+        name: "resolve_format",
+        targets: [(name: "msaa", format: Rgba16Float, multisampled: true), (name: "resolved", format: Rgba8Unorm)],
+        passes: [
+            (name: "main", kind: Scene(All), colour: Some("msaa"), depth: Some("depth"), resolve: Some("resolved")),
+            (name: "present", kind: Fullscreen(shader: "blit.ron"), inputs: ["resolved"], colour: Some("output")),
+        ],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::InvalidAttachment { .. })));
+
+    let result = schedule(r#"(
+        name: "mismatch",
+        passes: [(name: "main", kind: Scene(All), colour: Some("output"), depth: Some("depth"))],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::SampleCountMismatch(_))));
+}
+
+#[test]
+fn weighted_blended_targets() {
+    let result = schedule(r#"(
+        name: "unresolved_weight",
+        targets: [
+            (name: "accumulation_msaa", format: Rgba16Float, multisampled: true),
+            (name: "accumulation", format: Rgba16Float),
+            (name: "weight_msaa", format: R16Float, multisampled: true),
+        ],
+        passes: [
+            (name: "transparent", kind: WeightedBlended(weight: "weight_msaa"), colour: Some("accumulation_msaa"), depth: Some("depth"), resolve: Some("accumulation")),
+            (name: "present", kind: Fullscreen(shader: "blit.ron"), inputs: ["accumulation"], colour: Some("output")),
+        ],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::InvalidAttachment { .. })));
+
+    let result = schedule(r#"(
+        name: "single_sample_weight",
+        targets: [
+            (name: "accumulation", format: Rgba16Float, multisampled: true),
+            (name: "weight", format: R16Float),
+        ],
+        passes: [
+            (name: "transparent", kind: WeightedBlended(weight: "weight"), colour: Some("accumulation"), depth: Some("depth")),
+            (name: "present", kind: Fullscreen(shader: "blit.ron"), inputs: ["weight"], colour: Some("output")),
+        ],
+    )"#);
+    assert!(matches!(result, Err(RenderGraphError::SampleCountMismatch(_))));
+}